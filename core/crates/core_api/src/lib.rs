@@ -1,20 +1,25 @@
+use std::fs::File;
+use std::io::Read;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use core_types::{BlameLine, FileNode, GitFileDiff, GitStatus, TokenSpan};
+pub use core_types::CoreError;
+use core_types::{
+    BlameLine, BlameNavigation, BlameOptions, BranchInfo, CommitDetails, CommitGraphRow,
+    CommitOptions, CommitResult, DiffFile, DiffLineRef, FileHistoryEntry, FileNode, FsEvent,
+    FuzzyFileMatch, GitBackendKind, GitFileDiff, GitRepositoryStatus, GitStatus, HighlightDelta,
+    LanguageDetection, LineChangeMarker, ProjectTreeDiff, ProjectTreeNode, SearchMatch,
+    SearchOptions, SearchSummary, SplitDiffOptions, SplitDiffRow, StashApplyResult,
+    StashCreateOptions, StashEntry, TextEdit, TokenSpan,
+};
 
 uniffi::setup_scaffolding!();
 
-#[derive(Debug, thiserror::Error, uniffi::Error)]
-#[uniffi(flat_error)]
-pub enum CoreError {
-    #[error("{reason}")]
-    Message { reason: String },
-}
-
-fn core_error(reason: impl Into<String>) -> CoreError {
-    CoreError::Message {
-        reason: reason.into(),
-    }
+/// エラーの安定したコード番号を返す（ログ・サポート用）
+#[uniffi::export]
+pub fn core_error_code(error: CoreError) -> u32 {
+    error.code()
 }
 
 /// プロジェクトを開く（MVPではルートパスをそのまま返す）
@@ -22,12 +27,10 @@ fn core_error(reason: impl Into<String>) -> CoreError {
 pub fn open_project(root_path: String) -> Result<String, CoreError> {
     let path = Path::new(&root_path);
     if !path.exists() {
-        return Err(core_error(format!("パスが存在しません: {root_path}")));
+        return Err(CoreError::NotFound { path: root_path });
     }
     if !path.is_dir() {
-        return Err(core_error(format!(
-            "パスがディレクトリではありません: {root_path}"
-        )));
+        return Err(CoreError::NotADirectory { path: root_path });
     }
     Ok(root_path)
 }
@@ -35,7 +38,223 @@ pub fn open_project(root_path: String) -> Result<String, CoreError> {
 /// ディレクトリ内のファイル一覧を返す
 #[uniffi::export]
 pub fn list_dir(root_path: String, dir_path: String) -> Result<Vec<FileNode>, CoreError> {
-    core_fs::list_dir(&root_path, &dir_path).map_err(core_error)
+    core_fs::list_dir(&root_path, &dir_path)
+}
+
+/// プロジェクトツリー（遅延読み込み・展開状態・Git 状態を Rust 側で保持する）
+/// ノード ID はルートからの相対パスで、ルート自身は空文字
+#[derive(uniffi::Object)]
+pub struct ProjectTree {
+    inner: Mutex<core_fs::ProjectTree>,
+}
+
+#[uniffi::export]
+impl ProjectTree {
+    /// root_path を開き、直下の子を読み込む
+    #[uniffi::constructor]
+    pub fn open(root_path: String) -> Result<Arc<Self>, CoreError> {
+        let tree = core_fs::ProjectTree::open(&root_path)?;
+        Ok(Arc::new(Self {
+            inner: Mutex::new(tree),
+        }))
+    }
+
+    /// 表示中のノードを深さ優先の並びで返す
+    pub fn visible_nodes(&self) -> Result<Vec<ProjectTreeNode>, CoreError> {
+        Ok(self.lock()?.visible_nodes())
+    }
+
+    /// 指定ディレクトリの子ノードを返す（未読み込みなら列挙する）
+    pub fn children(&self, id: String) -> Result<Vec<ProjectTreeNode>, CoreError> {
+        self.lock()?.children(&id)
+    }
+
+    /// ディレクトリの展開・折りたたみを切り替え、更新後の表示ノードを返す
+    pub fn toggle(&self, id: String) -> Result<Vec<ProjectTreeNode>, CoreError> {
+        self.lock()?.toggle(&id)
+    }
+
+    pub fn set_expanded(
+        &self,
+        id: String,
+        expanded: bool,
+    ) -> Result<Vec<ProjectTreeNode>, CoreError> {
+        self.lock()?.set_expanded(&id, expanded)
+    }
+
+    /// ディレクトリを再列挙し、前回との差分を返す
+    pub fn refresh(&self, id: String) -> Result<ProjectTreeDiff, CoreError> {
+        self.lock()?.refresh(&id)
+    }
+
+    /// ProjectWatcher のイベントを反映し、変化のあったディレクトリの差分を返す
+    pub fn apply_fs_events(&self, events: Vec<FsEvent>) -> Result<Vec<ProjectTreeDiff>, CoreError> {
+        Ok(self.lock()?.apply_fs_events(&events))
+    }
+
+    /// git status を取り直して装飾を更新し、装飾が変わったノードを返す
+    /// Git リポジトリでなければ装飾をすべて外す
+    pub fn refresh_git_status(&self) -> Result<Vec<ProjectTreeNode>, CoreError> {
+        let root = self.lock()?.root().to_string_lossy().to_string();
+        let entries = match core_git::git_status(&root) {
+            Ok(status) => status
+                .staged
+                .iter()
+                .chain(&status.unstaged)
+                .chain(&status.untracked)
+                .filter_map(|entry| {
+                    core_git::status_decoration(&entry.status)
+                        .map(|decoration| (entry.path.clone(), decoration))
+                })
+                .collect(),
+            Err(CoreError::NotAGitRepo { .. }) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(self.lock()?.set_git_decorations(&entries))
+    }
+}
+
+impl ProjectTree {
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, core_fs::ProjectTree>, CoreError> {
+        self.inner
+            .lock()
+            .map_err(|e| CoreError::internal(format!("project tree lock 失敗: {e}")))
+    }
+}
+
+/// プロジェクト検索結果の通知先（Swift 側で実装する）
+#[uniffi::export(with_foreign)]
+pub trait SearchListener: Send + Sync {
+    /// ヒットした行をまとめて受け取る（検索中に複数回呼ばれる）
+    fn on_matches(&self, matches: Vec<SearchMatch>);
+    /// true を返すと検索を中断する
+    fn is_cancelled(&self) -> bool;
+}
+
+struct ListenerSink(Arc<dyn SearchListener>);
+
+impl core_fs::SearchSink for ListenerSink {
+    fn on_matches(&mut self, matches: Vec<SearchMatch>) {
+        self.0.on_matches(matches);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.0.is_cancelled()
+    }
+}
+
+/// プロジェクト全体を検索し、結果を listener にバッチで通知する
+/// 完了まで戻らないため、UI スレッド以外から呼び出すこと
+#[uniffi::export]
+pub fn search_project(
+    root_path: String,
+    options: SearchOptions,
+    listener: Arc<dyn SearchListener>,
+) -> Result<SearchSummary, CoreError> {
+    core_fs::search_project(&root_path, &options, &mut ListenerSink(listener))
+}
+
+/// ファイル変更の通知先（Swift 側で実装する）
+/// 監視スレッドから呼び出されるため、UI 更新はメインスレッドへ移して行うこと
+#[uniffi::export(with_foreign)]
+pub trait FsWatchListener: Send + Sync {
+    /// デバウンス済みの変更イベントをまとめて受け取る
+    fn on_events(&self, events: Vec<FsEvent>);
+    /// 監視中のエラーを受け取る（監視は継続する）
+    fn on_error(&self, error: CoreError);
+}
+
+struct ListenerHandler(Arc<dyn FsWatchListener>);
+
+impl core_fs::WatchHandler for ListenerHandler {
+    fn on_events(&self, events: Vec<FsEvent>) {
+        self.0.on_events(events);
+    }
+
+    fn on_error(&self, error: CoreError) {
+        self.0.on_error(error);
+    }
+}
+
+/// プロジェクトルート配下のファイル変更監視
+/// .gitignore と隠しファイルの除外ルールは list_dir と同じ
+#[derive(uniffi::Object)]
+pub struct ProjectWatcher {
+    inner: Mutex<Option<core_fs::ProjectWatcher>>,
+}
+
+#[uniffi::export]
+impl ProjectWatcher {
+    /// root_path の監視を開始する
+    /// debounce_ms の間変更が途切れたらまとめて listener に通知する
+    #[uniffi::constructor]
+    pub fn start(
+        root_path: String,
+        debounce_ms: u32,
+        listener: Arc<dyn FsWatchListener>,
+    ) -> Result<Arc<Self>, CoreError> {
+        let watcher = core_fs::ProjectWatcher::start(
+            &root_path,
+            Duration::from_millis(u64::from(debounce_ms)),
+            ListenerHandler(listener),
+        )?;
+        Ok(Arc::new(Self {
+            inner: Mutex::new(Some(watcher)),
+        }))
+    }
+
+    /// 監視を終了する（以降は通知されない）
+    pub fn stop(&self) -> Result<(), CoreError> {
+        let watcher = self
+            .inner
+            .lock()
+            .map_err(|e| CoreError::internal(format!("watcher lock 失敗: {e}")))?
+            .take();
+        drop(watcher);
+        Ok(())
+    }
+}
+
+/// Go to File 用のファイルパスインデックス
+/// ProjectWatcher のイベントを apply_events で反映すると再構築せずに最新の状態を保てる
+#[derive(uniffi::Object)]
+pub struct FileIndex {
+    inner: Mutex<core_fs::FileIndex>,
+}
+
+#[uniffi::export]
+impl FileIndex {
+    /// root_path 配下の全ファイルを走査してインデックスを作る
+    #[uniffi::constructor]
+    pub fn build(root_path: String) -> Result<Arc<Self>, CoreError> {
+        let index = core_fs::FileIndex::build(&root_path)?;
+        Ok(Arc::new(Self {
+            inner: Mutex::new(index),
+        }))
+    }
+
+    /// あいまい検索してスコアの高い順に最大 limit 件を返す
+    pub fn search(&self, query: String, limit: u32) -> Result<Vec<FuzzyFileMatch>, CoreError> {
+        Ok(self.lock()?.search(&query, limit as usize))
+    }
+
+    /// ファイル変更イベントをインデックスに反映する
+    pub fn apply_events(&self, events: Vec<FsEvent>) -> Result<(), CoreError> {
+        self.lock()?.apply_events(&events);
+        Ok(())
+    }
+
+    pub fn file_count(&self) -> Result<u32, CoreError> {
+        Ok(self.lock()?.len() as u32)
+    }
+}
+
+impl FileIndex {
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, core_fs::FileIndex>, CoreError> {
+        self.inner
+            .lock()
+            .map_err(|e| CoreError::internal(format!("file index lock 失敗: {e}")))
+    }
 }
 
 /// ファイルの内容を文字列として読み込む
@@ -43,28 +262,42 @@ pub fn list_dir(root_path: String, dir_path: String) -> Result<Vec<FileNode>, Co
 pub fn read_file(path: String) -> Result<String, CoreError> {
     let p = Path::new(&path);
     if !p.exists() {
-        return Err(core_error(format!("ファイルが存在しません: {path}")));
+        return Err(CoreError::NotFound { path });
     }
     if !p.is_file() {
-        return Err(core_error(format!("パスがファイルではありません: {path}")));
+        return Err(CoreError::NotAFile { path });
     }
-    std::fs::read_to_string(&path).map_err(|e| core_error(format!("ファイル読み取りエラー: {e}")))
+    std::fs::read_to_string(&path).map_err(|e| CoreError::from_io(path.as_str(), &e))
 }
 
+/// パスとファイル先頭の内容から言語を判定する
+/// .gitattributes・モードライン・shebang・既知のファイル名・拡張子の順に確度が高い
+#[uniffi::export]
+pub fn detect_language(path: String, content_head: String) -> Option<LanguageDetection> {
+    core_highlight::detect_language_with_content(&path, &content_head)
+}
+
+/// パスで言語が決まらないときに shebang・モードラインを探す先頭部分の長さ（byte）
+const DETECT_HEAD_BYTES: u64 = 4096;
+
 /// シンタックスハイライト: ファイルを読み込み、指定範囲のトークンを返す
+/// 言語を判定できないファイルは読み込まずに空を返す
 #[uniffi::export]
 pub fn highlight_range(
     path: String,
     start_line: u32,
     end_line: u32,
 ) -> Result<Vec<TokenSpan>, CoreError> {
-    let language = match core_highlight::detect_language(&path) {
-        Some(lang) => lang,
+    if !has_detectable_language(&path)? {
+        return Ok(vec![]);
+    }
+    let content = read_file(path.clone())?;
+    let language = match core_highlight::detect_language_with_content(&path, &content) {
+        Some(detection) => detection.language,
         None => return Ok(vec![]),
     };
 
-    let content = read_file(path)?;
-    let tokens = core_highlight::tokenize(&content, language).map_err(core_error)?;
+    let tokens = core_highlight::tokenize(&content, &language)?;
 
     Ok(tokens
         .into_iter()
@@ -72,60 +305,385 @@ pub fn highlight_range(
         .collect())
 }
 
-/// Git Blame: 指定範囲の行に対する blame 情報を返す
-/// 取得不能時は理由付きエラーを返す
+/// パスだけで言語が決まらなければ、先頭だけを（UTF-8 でなくても）読んで判定できるか調べる
+/// バイナリや巨大な未対応ファイルを全体読み込みしないため
+fn has_detectable_language(path: &str) -> Result<bool, CoreError> {
+    if core_highlight::detect_language_with_content(path, "").is_some() {
+        return Ok(true);
+    }
+    let p = Path::new(path);
+    if !p.exists() {
+        return Err(CoreError::NotFound {
+            path: path.to_string(),
+        });
+    }
+    if !p.is_file() {
+        return Err(CoreError::NotAFile {
+            path: path.to_string(),
+        });
+    }
+    let mut head = Vec::new();
+    File::open(p)
+        .and_then(|file| file.take(DETECT_HEAD_BYTES).read_to_end(&mut head))
+        .map_err(|e| CoreError::from_io(path, &e))?;
+    let head = String::from_utf8_lossy(&head);
+    Ok(core_highlight::detect_language_with_content(path, &head).is_some())
+}
+
+/// 構文木を保持するハイライト用ドキュメント
+/// 編集ごとにインクリメンタルに再パースし、スクロール時は再パースせずにトークンを返す
+#[derive(uniffi::Object)]
+pub struct HighlightDocument {
+    inner: Mutex<core_highlight::Document>,
+}
+
 #[uniffi::export]
+impl HighlightDocument {
+    /// テキストと言語名からドキュメントを生成する
+    #[uniffi::constructor]
+    pub fn new(text: String, language: String) -> Result<Arc<Self>, CoreError> {
+        let document = core_highlight::Document::new(&text, &language)?;
+        Ok(Arc::new(Self {
+            inner: Mutex::new(document),
+        }))
+    }
+
+    /// ファイルを読み込み、パスと内容から言語を判定してドキュメントを生成する
+    #[uniffi::constructor]
+    pub fn open(path: String) -> Result<Arc<Self>, CoreError> {
+        let content = read_file(path.clone())?;
+        let detection =
+            core_highlight::detect_language_with_content(&path, &content).ok_or_else(|| {
+                // language にはパスではなく、判定に使った拡張子を入れる
+                let extension = Path::new(&path)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .unwrap_or("unknown");
+                CoreError::UnsupportedLanguage {
+                    language: extension.to_string(),
+                }
+            })?;
+        Self::new(content, detection.language)
+    }
+
+    pub fn language(&self) -> Result<String, CoreError> {
+        Ok(self.lock()?.language().to_string())
+    }
+
+    /// 指定範囲（1始まり、両端含む）のトークンを返す
+    pub fn highlight_range(
+        &self,
+        start_line: u32,
+        end_line: u32,
+    ) -> Result<Vec<TokenSpan>, CoreError> {
+        Ok(self.lock()?.highlight_range(start_line, end_line))
+    }
+
+    /// 編集を適用し、再ハイライトが必要な行範囲のトークンを返す
+    pub fn apply_edit(&self, edit: TextEdit) -> Result<Vec<HighlightDelta>, CoreError> {
+        self.lock()?.apply_edit(&edit)
+    }
+
+    /// テキスト全体を置き換える（外部でファイルが変更された場合など）
+    pub fn set_text(&self, text: String) -> Result<(), CoreError> {
+        self.lock()?.set_text(&text)
+    }
+}
+
+impl HighlightDocument {
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, core_highlight::Document>, CoreError> {
+        self.inner
+            .lock()
+            .map_err(|e| CoreError::internal(format!("document lock 失敗: {e}")))
+    }
+}
+
+/// Git Blame: 指定範囲の行に対する blame 情報を返す
+/// 取得不能時は理由付きエラーを返す（options 省略時は既定の設定）
+#[uniffi::export(default(options = None))]
 pub fn blame_range(
     path: String,
     start_line: u32,
     end_line: u32,
+    options: Option<BlameOptions>,
 ) -> Result<Vec<BlameLine>, CoreError> {
-    match core_git::blame_file(&path) {
-        Ok(lines) => {
-            let raw_count = lines.len();
-            let first_line = lines.first().map(|l| l.line).unwrap_or(0);
-            let last_line = lines.last().map(|l| l.line).unwrap_or(0);
-            let filtered: Vec<BlameLine> = lines
-                .into_iter()
-                .filter(|bl| bl.line >= start_line && bl.line <= end_line)
-                .collect();
-
-            if filtered.is_empty() {
-                return Err(core_error(format!(
-                    "blame_range empty: path={path}, range={start_line}-{end_line}, raw_count={raw_count}, first_line={first_line}, last_line={last_line}"
-                )));
-            }
-
-            Ok(filtered)
-        }
-        Err(reason) => Err(core_error(format!(
-            "blame_range error: path={path}, range={start_line}-{end_line}, reason={reason}"
-        ))),
+    let lines = core_git::blame_file(&path, &options.unwrap_or_default())?;
+    let raw_count = lines.len();
+    let first_line = lines.first().map(|l| l.line).unwrap_or(0);
+    let last_line = lines.last().map(|l| l.line).unwrap_or(0);
+    let filtered: Vec<BlameLine> = lines
+        .into_iter()
+        .filter(|bl| bl.line >= start_line && bl.line <= end_line)
+        .collect();
+
+    if filtered.is_empty() {
+        return Err(CoreError::invalid_argument(format!(
+            "blame_range empty: path={path}, range={start_line}-{end_line}, raw_count={raw_count}, first_line={first_line}, last_line={last_line}"
+        )));
     }
+
+    Ok(filtered)
+}
+
+/// 指定リビジョン時点のファイルを blame する（path はそのリビジョンでのリポジトリ相対パス）
+#[uniffi::export]
+pub fn blame_revision(
+    root_path: String,
+    revision: String,
+    path: String,
+) -> Result<Vec<BlameLine>, CoreError> {
+    core_git::blame_revision(&root_path, &revision, &path)
+}
+
+/// blame 行の変更コミットの親で blame し直す（対応する行番号付き）
+#[uniffi::export]
+pub fn blame_parent(root_path: String, line: BlameLine) -> Result<BlameNavigation, CoreError> {
+    core_git::blame_parent(&root_path, &line)
 }
 
 /// Blame 行で選択したコミットの差分を返す
 #[uniffi::export]
 pub fn blame_commit_diff(path: String, commit: String) -> Result<GitFileDiff, CoreError> {
-    core_git::blame_commit_diff(&path, &commit).map_err(core_error)
+    core_git::blame_commit_diff(&path, &commit)
 }
 
 /// 対象ファイルの現在差分（staged/unstaged/untracked）を返す
 #[uniffi::export]
 pub fn git_file_diff(path: String) -> Result<GitFileDiff, CoreError> {
-    core_git::git_file_diff(&path).map_err(core_error)
+    core_git::git_file_diff(&path)
+}
+
+/// unified diff 文字列をファイル・ハンク・行に分解する
+#[uniffi::export]
+pub fn parse_unified_diff(diff_text: String) -> Vec<DiffFile> {
+    core_git::parse_unified_diff(&diff_text)
+}
+
+/// 差分1ファイル分を左右分割表示の行に並べる
+#[uniffi::export]
+pub fn split_diff(file: DiffFile, options: SplitDiffOptions) -> Vec<SplitDiffRow> {
+    core_git::split_diff(&file, &options)
+}
+
+/// ファイルに触れたコミットを新しい順に返す（リネームを遡り、offset/limit でページ分割）
+#[uniffi::export]
+pub fn file_history(
+    path: String,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<FileHistoryEntry>, CoreError> {
+    core_git::file_history(&path, limit, offset)
+}
+
+/// リポジトリ全体のコミットログをグラフのレーン・参照付きで返す（offset/limit でページ分割）
+#[uniffi::export]
+pub fn commit_log(
+    root_path: String,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<CommitGraphRow>, CoreError> {
+    core_git::commit_log(&root_path, limit, offset)
+}
+
+/// コミットの詳細（メッセージ全文・トレーラー・変更ファイル一覧）を返す
+#[uniffi::export]
+pub fn commit_details(root_path: String, commit: String) -> Result<CommitDetails, CoreError> {
+    core_git::commit_details(&root_path, &commit)
+}
+
+/// コミット詳細で選んだファイルの差分を返す（リネームは old_path も渡す）
+#[uniffi::export]
+pub fn commit_file_diff(
+    root_path: String,
+    commit: String,
+    path: String,
+    old_path: Option<String>,
+) -> Result<GitFileDiff, CoreError> {
+    core_git::commit_file_diff(&root_path, &commit, &path, old_path.as_deref())
+}
+
+/// ガター用の行変更マーカーを返す（text には未保存のバッファ内容を渡せる）
+#[uniffi::export]
+pub fn line_change_markers(
+    path: String,
+    text: Option<String>,
+) -> Result<Vec<LineChangeMarker>, CoreError> {
+    core_git::line_change_markers(&path, text.as_deref())
 }
 
 /// リポジトリの変更状態（staged / unstaged / untracked）を返す
 #[uniffi::export]
 pub fn git_status(root_path: String) -> Result<GitStatus, CoreError> {
-    core_git::git_status(&root_path).map_err(core_error)
+    core_git::git_status(&root_path)
+}
+
+/// リポジトリの状態（ブランチ・上流との差分・型付きの変更エントリ）を返す
+#[uniffi::export]
+pub fn repository_status(root_path: String) -> Result<GitRepositoryStatus, CoreError> {
+    core_git::repository_status(&root_path)
 }
 
 /// 現在のブランチ名を返す
 #[uniffi::export]
 pub fn git_current_branch(root_path: String) -> Result<String, CoreError> {
-    core_git::git_current_branch(&root_path).map_err(core_error)
+    core_git::git_current_branch(&root_path)
+}
+
+/// ローカルブランチとリモート追跡ブランチ（上流・ahead/behind・先端コミット付き）を返す
+#[uniffi::export]
+pub fn list_branches(root_path: String) -> Result<Vec<BranchInfo>, CoreError> {
+    core_git::list_branches(&root_path)
+}
+
+/// start_point（省略時は HEAD）からブランチを作る
+#[uniffi::export]
+pub fn create_branch(
+    root_path: String,
+    name: String,
+    start_point: Option<String>,
+) -> Result<(), CoreError> {
+    core_git::create_branch(&root_path, &name, start_point.as_deref())
+}
+
+/// ブランチを切り替える（ローカルの変更が上書きされる場合は CheckoutConflict）
+#[uniffi::export]
+pub fn switch_branch(root_path: String, name: String) -> Result<(), CoreError> {
+    core_git::switch_branch(&root_path, &name)
+}
+
+#[uniffi::export]
+pub fn rename_branch(
+    root_path: String,
+    old_name: String,
+    new_name: String,
+) -> Result<(), CoreError> {
+    core_git::rename_branch(&root_path, &old_name, &new_name)
+}
+
+/// ブランチを削除する（force でなければマージ済みのものだけ）
+#[uniffi::export]
+pub fn delete_branch(root_path: String, name: String, force: bool) -> Result<(), CoreError> {
+    core_git::delete_branch(&root_path, &name, force)
+}
+
+/// ファイル全体をステージする（削除・未追跡ファイルも含む）
+#[uniffi::export]
+pub fn stage_file(file_path: String) -> Result<(), CoreError> {
+    core_git::stage_file(&file_path)
+}
+
+/// ファイル全体のステージを解除する
+#[uniffi::export]
+pub fn unstage_file(file_path: String) -> Result<(), CoreError> {
+    core_git::unstage_file(&file_path)
+}
+
+/// 未ステージの差分の hunk_index 番目のハンクをステージする
+#[uniffi::export]
+pub fn stage_hunk(file_path: String, hunk_index: u32) -> Result<(), CoreError> {
+    core_git::stage_hunk(&file_path, hunk_index)
+}
+
+/// ステージ済みの差分の hunk_index 番目のハンクを外す
+#[uniffi::export]
+pub fn unstage_hunk(file_path: String, hunk_index: u32) -> Result<(), CoreError> {
+    core_git::unstage_hunk(&file_path, hunk_index)
+}
+
+/// 未ステージの差分の hunk_index 番目のハンクを作業ツリーから破棄する
+#[uniffi::export]
+pub fn discard_hunk(file_path: String, hunk_index: u32) -> Result<(), CoreError> {
+    core_git::discard_hunk(&file_path, hunk_index)
+}
+
+/// 未ステージの差分のうち選んだ行だけをステージする
+#[uniffi::export]
+pub fn stage_lines(file_path: String, lines: Vec<DiffLineRef>) -> Result<(), CoreError> {
+    core_git::stage_lines(&file_path, &lines)
+}
+
+/// ステージ済みの差分のうち選んだ行だけステージを解除する
+#[uniffi::export]
+pub fn unstage_lines(file_path: String, lines: Vec<DiffLineRef>) -> Result<(), CoreError> {
+    core_git::unstage_lines(&file_path, &lines)
+}
+
+/// 未ステージの差分のうち選んだ行だけを作業ツリーから破棄する
+#[uniffi::export]
+pub fn discard_lines(file_path: String, lines: Vec<DiffLineRef>) -> Result<(), CoreError> {
+    core_git::discard_lines(&file_path, &lines)
+}
+
+/// 最後に破棄した変更を戻し、そのファイルのパスを返す（戻せるものが無ければ None）
+#[uniffi::export]
+pub fn undo_discard(root_path: String) -> Result<Option<String>, CoreError> {
+    core_git::undo_discard(&root_path)
+}
+
+/// ステージ済みの変更をコミットする（フックが失敗した場合は HookFailed）
+#[uniffi::export(default(options = None))]
+pub fn create_commit(
+    root_path: String,
+    message: String,
+    options: Option<CommitOptions>,
+) -> Result<CommitResult, CoreError> {
+    core_git::create_commit(&root_path, &message, &options.unwrap_or_default())
+}
+
+/// commit.template の内容（コミットメッセージ欄の初期値）
+#[uniffi::export]
+pub fn commit_template(root_path: String) -> Result<Option<String>, CoreError> {
+    core_git::commit_template(&root_path)
+}
+
+/// stash の一覧（新しい順）
+#[uniffi::export]
+pub fn list_stashes(root_path: String) -> Result<Vec<StashEntry>, CoreError> {
+    core_git::list_stashes(&root_path)
+}
+
+/// 作業ツリーの変更を stash する（変更が無ければ NoChanges）
+#[uniffi::export(default(options = None))]
+pub fn create_stash(
+    root_path: String,
+    options: Option<StashCreateOptions>,
+) -> Result<StashEntry, CoreError> {
+    core_git::create_stash(&root_path, &options.unwrap_or_default())
+}
+
+/// stash を適用する（コンフリクトしたファイルは結果で返す）
+#[uniffi::export]
+pub fn apply_stash(root_path: String, index: u32) -> Result<StashApplyResult, CoreError> {
+    core_git::apply_stash(&root_path, index)
+}
+
+/// stash を適用して削除する（コンフリクトした場合は残す）
+#[uniffi::export]
+pub fn pop_stash(root_path: String, index: u32) -> Result<StashApplyResult, CoreError> {
+    core_git::pop_stash(&root_path, index)
+}
+
+/// stash を削除する
+#[uniffi::export]
+pub fn drop_stash(root_path: String, index: u32) -> Result<(), CoreError> {
+    core_git::drop_stash(&root_path, index)
+}
+
+/// stash の内容をファイルごとの差分で返す
+#[uniffi::export]
+pub fn stash_diff(root_path: String, index: u32) -> Result<Vec<DiffFile>, CoreError> {
+    core_git::stash_diff(&root_path, index)
+}
+
+/// Git 実装を切り替える（既定は Auto: libgit2 を使い、扱えない操作だけ git CLI）
+#[uniffi::export]
+pub fn set_git_backend(kind: GitBackendKind) {
+    core_git::set_git_backend(kind)
+}
+
+#[uniffi::export]
+pub fn git_backend() -> GitBackendKind {
+    core_git::git_backend()
 }
 
 #[cfg(test)]
@@ -146,11 +704,9 @@ mod tests {
     #[test]
     fn open_project_nonexistent() {
         let result = open_project("/nonexistent/path".to_string());
-        assert!(result.is_err());
-        assert!(result
-            .unwrap_err()
-            .to_string()
-            .contains("パスが存在しません"));
+        let error = result.unwrap_err();
+        assert!(matches!(error, CoreError::NotFound { .. }));
+        assert!(error.to_string().contains("パスが存在しません"));
     }
 
     #[test]
@@ -160,11 +716,9 @@ mod tests {
         fs::write(&file_path, "hello").unwrap();
 
         let result = open_project(file_path.to_str().unwrap().to_string());
-        assert!(result.is_err());
-        assert!(result
-            .unwrap_err()
-            .to_string()
-            .contains("ディレクトリではありません"));
+        let error = result.unwrap_err();
+        assert!(matches!(error, CoreError::NotADirectory { .. }));
+        assert!(error.to_string().contains("ディレクトリではありません"));
     }
 
     #[test]
@@ -185,6 +739,120 @@ mod tests {
         assert!(names.contains(&"main.rs"));
     }
 
+    struct ChannelListener(Mutex<std::sync::mpsc::Sender<Vec<FsEvent>>>);
+
+    impl FsWatchListener for ChannelListener {
+        fn on_events(&self, events: Vec<FsEvent>) {
+            let _ = self.0.lock().unwrap().send(events);
+        }
+
+        fn on_error(&self, _error: CoreError) {}
+    }
+
+    #[test]
+    fn project_watcher_notifies_listener_until_stopped() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let watcher = ProjectWatcher::start(
+            root.to_str().unwrap().to_string(),
+            50,
+            Arc::new(ChannelListener(Mutex::new(sender))),
+        )
+        .unwrap();
+
+        fs::write(root.join("a.txt"), "a").unwrap();
+        let events = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(events.iter().any(|e| e.path.ends_with("a.txt")));
+
+        watcher.stop().unwrap();
+        fs::write(root.join("b.txt"), "b").unwrap();
+        assert!(receiver.recv_timeout(Duration::from_millis(300)).is_err());
+    }
+
+    #[test]
+    fn project_watcher_nonexistent_root_returns_error() {
+        let (sender, _receiver) = std::sync::mpsc::channel();
+        let result = ProjectWatcher::start(
+            "/nonexistent/blink-watch".to_string(),
+            50,
+            Arc::new(ChannelListener(Mutex::new(sender))),
+        );
+        assert!(matches!(result, Err(CoreError::NotFound { .. })));
+    }
+
+    struct CollectingSearchListener(Mutex<Vec<SearchMatch>>);
+
+    impl SearchListener for CollectingSearchListener {
+        fn on_matches(&self, matches: Vec<SearchMatch>) {
+            self.0.lock().unwrap().extend(matches);
+        }
+
+        fn is_cancelled(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn search_project_delegates_to_core_fs() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("a.txt"), "hello\nworld\n").unwrap();
+
+        let listener = Arc::new(CollectingSearchListener(Mutex::new(Vec::new())));
+        let summary = search_project(
+            tmp.path().to_str().unwrap().to_string(),
+            SearchOptions {
+                query: "world".into(),
+                is_regex: false,
+                case_sensitive: true,
+                whole_word: false,
+                include_globs: vec![],
+                exclude_globs: vec![],
+                max_results: 0,
+            },
+            listener.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(summary.match_count, 1);
+        let matches = listener.0.lock().unwrap();
+        assert_eq!(matches[0].line, 2);
+    }
+
+    #[test]
+    fn file_index_search_returns_file_nodes() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("src/foo")).unwrap();
+        fs::write(tmp.path().join("src/foo/bar_service.rs"), "").unwrap();
+        fs::write(tmp.path().join("README.md"), "").unwrap();
+
+        let index = FileIndex::build(tmp.path().to_str().unwrap().to_string()).unwrap();
+        assert_eq!(index.file_count().unwrap(), 2);
+
+        let results = index.search("barserv".into(), 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].node.name, "bar_service.rs");
+        assert_eq!(results[0].relative_path, "src/foo/bar_service.rs");
+    }
+
+    #[test]
+    fn project_tree_toggle_and_git_status() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("src")).unwrap();
+        fs::write(tmp.path().join("src/main.rs"), "").unwrap();
+        fs::write(tmp.path().join("README.md"), "").unwrap();
+
+        let tree = ProjectTree::open(tmp.path().to_str().unwrap().to_string()).unwrap();
+        assert_eq!(tree.visible_nodes().unwrap().len(), 2);
+
+        let nodes = tree.toggle("src".into()).unwrap();
+        let ids: Vec<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["src", "src/main.rs", "README.md"]);
+
+        // Git 管理外なら装飾なしで成功する
+        assert!(tree.refresh_git_status().unwrap().is_empty());
+    }
+
     #[test]
     fn read_file_success() {
         let tmp = tempfile::tempdir().unwrap();
@@ -200,10 +868,7 @@ mod tests {
     fn read_file_nonexistent() {
         let result = read_file("/nonexistent/file.txt".to_string());
         assert!(result.is_err());
-        assert!(result
-            .unwrap_err()
-            .to_string()
-            .contains("ファイルが存在しません"));
+        assert!(matches!(result, Err(CoreError::NotFound { .. })));
     }
 
     #[test]
@@ -211,10 +876,9 @@ mod tests {
         let tmp = tempfile::tempdir().unwrap();
         let result = read_file(tmp.path().to_str().unwrap().to_string());
         assert!(result.is_err());
-        assert!(result
-            .unwrap_err()
-            .to_string()
-            .contains("ファイルではありません"));
+        let error = result.unwrap_err();
+        assert!(matches!(error, CoreError::NotAFile { .. }));
+        assert!(error.to_string().contains("ファイルではありません"));
     }
 
     #[test]
@@ -228,6 +892,16 @@ mod tests {
         assert!(result.unwrap().is_empty());
     }
 
+    #[test]
+    fn highlight_range_binary_file_without_extension_returns_empty() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file_path = tmp.path().join("blob");
+        fs::write(&file_path, b"\xff\xfe\x00\x01binary\x80").unwrap();
+
+        let result = highlight_range(file_path.to_str().unwrap().to_string(), 1, 10);
+        assert_eq!(result, Ok(vec![]));
+    }
+
     #[test]
     fn highlight_range_javascript_file() {
         let tmp = tempfile::tempdir().unwrap();
@@ -254,9 +928,99 @@ mod tests {
         assert!(tokens.iter().all(|t| t.line == 2));
     }
 
+    #[test]
+    fn highlight_document_edit_and_range() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file_path = tmp.path().join("test.js");
+        fs::write(&file_path, "const a = 1;\nconst b = 2;").unwrap();
+
+        let doc = HighlightDocument::open(file_path.to_str().unwrap().to_string()).unwrap();
+        assert_eq!(doc.language().unwrap(), "javascript");
+        assert!(doc
+            .highlight_range(2, 2)
+            .unwrap()
+            .iter()
+            .all(|t| t.line == 2));
+
+        let deltas = doc
+            .apply_edit(TextEdit {
+                start_byte: 13,
+                old_end_byte: 13,
+                start_row: 1,
+                start_column: 0,
+                old_end_row: 1,
+                old_end_column: 0,
+                new_text: "// note\n".into(),
+            })
+            .unwrap();
+        assert!(deltas.iter().any(|d| d.start_line == 2));
+        let line3 = doc.highlight_range(3, 3).unwrap();
+        assert!(!line3.is_empty());
+        assert!(line3.iter().all(|t| t.line == 3));
+    }
+
+    #[test]
+    fn highlight_document_unsupported_language_returns_error() {
+        assert!(matches!(
+            HighlightDocument::new("text".into(), "cobol".into()),
+            Err(CoreError::UnsupportedLanguage { .. })
+        ));
+    }
+
+    #[test]
+    fn highlight_document_open_reports_extension_as_language() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file_path = tmp.path().join("data.cob");
+        fs::write(&file_path, "IDENTIFICATION DIVISION.").unwrap();
+
+        match HighlightDocument::open(file_path.to_str().unwrap().to_string()) {
+            Err(CoreError::UnsupportedLanguage { language }) => assert_eq!(language, "cob"),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn highlight_range_detects_language_from_shebang() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file_path = tmp.path().join("deploy");
+        fs::write(
+            &file_path,
+            "#!/usr/bin/env python3\ndef main():\n    pass\n",
+        )
+        .unwrap();
+
+        let tokens = highlight_range(file_path.to_str().unwrap().to_string(), 2, 2).unwrap();
+        assert!(!tokens.is_empty());
+    }
+
+    #[test]
+    fn detect_language_uses_content_head() {
+        let detection = detect_language("bin/tool".into(), "#!/usr/bin/env node\n".into()).unwrap();
+        assert_eq!(detection.language, "javascript");
+        assert!(detect_language("notes".into(), "plain".into()).is_none());
+    }
+
+    #[test]
+    fn read_file_invalid_utf8_returns_encoding_error() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file_path = tmp.path().join("binary.bin");
+        fs::write(&file_path, [0xff, 0xfe, 0x00]).unwrap();
+
+        let result = read_file(file_path.to_str().unwrap().to_string());
+        assert!(matches!(result, Err(CoreError::Encoding { .. })));
+    }
+
+    #[test]
+    fn core_error_code_is_exported() {
+        assert_eq!(
+            core_error_code(CoreError::NotADirectory { path: "/a".into() }),
+            1002
+        );
+    }
+
     #[test]
     fn blame_range_non_git_returns_error() {
-        let result = blame_range("/tmp/nonexistent_file.rs".to_string(), 1, 10);
+        let result = blame_range("/tmp/nonexistent_file.rs".to_string(), 1, 10, None);
         assert!(result.is_err());
     }
 
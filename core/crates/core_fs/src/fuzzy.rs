@@ -0,0 +1,590 @@
+use std::path::{Path, PathBuf};
+
+use core_types::{CoreError, FileNode, FsEvent, FsEventKind, FuzzyFileMatch, NodeKind};
+
+// スコアは fzf（v1 アルゴリズム）の重み付けに合わせている
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+const BONUS_BOUNDARY: i32 = SCORE_MATCH / 2;
+const BONUS_BOUNDARY_WHITE: i32 = BONUS_BOUNDARY + 2;
+const BONUS_BOUNDARY_DELIMITER: i32 = BONUS_BOUNDARY + 1;
+const BONUS_NON_WORD: i32 = SCORE_MATCH / 2;
+const BONUS_CAMEL_123: i32 = BONUS_BOUNDARY + SCORE_GAP_EXTENSION;
+const BONUS_CONSECUTIVE: i32 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+/// クエリ全体がファイル名の中で一致した場合の加点
+const BONUS_FILE_NAME: i32 = SCORE_MATCH * 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum CharClass {
+    White,
+    NonWord,
+    Delimiter,
+    Lower,
+    Upper,
+    Letter,
+    Number,
+}
+
+fn char_class(c: char) -> CharClass {
+    match c {
+        'a'..='z' => CharClass::Lower,
+        'A'..='Z' => CharClass::Upper,
+        '0'..='9' => CharClass::Number,
+        '/' | '\\' | ',' | ':' | ';' | '|' => CharClass::Delimiter,
+        c if c.is_whitespace() => CharClass::White,
+        c if c.is_lowercase() => CharClass::Lower,
+        c if c.is_uppercase() => CharClass::Upper,
+        c if c.is_numeric() => CharClass::Number,
+        c if c.is_alphabetic() => CharClass::Letter,
+        _ => CharClass::NonWord,
+    }
+}
+
+fn bonus_for(prev: CharClass, class: CharClass) -> i32 {
+    if class > CharClass::Delimiter {
+        match prev {
+            CharClass::White => return BONUS_BOUNDARY_WHITE,
+            CharClass::Delimiter => return BONUS_BOUNDARY_DELIMITER,
+            CharClass::NonWord => return BONUS_BOUNDARY,
+            _ => {}
+        }
+    }
+    if (prev == CharClass::Lower && class == CharClass::Upper)
+        || (prev != CharClass::Number && class == CharClass::Number)
+    {
+        return BONUS_CAMEL_123;
+    }
+    match class {
+        CharClass::NonWord | CharClass::Delimiter => BONUS_NON_WORD,
+        CharClass::White => BONUS_BOUNDARY_WHITE,
+        _ => 0,
+    }
+}
+
+/// 文字数を変えずに小文字化する（位置をそのまま元のパスに対応させるため）
+fn fold_char(c: char) -> char {
+    if c.is_ascii() {
+        c.to_ascii_lowercase()
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+struct IndexedPath {
+    path: String,
+    relative: String,
+    /// relative を fold_char で小文字化したもの
+    folded: String,
+    /// relative 内でファイル名が始まる文字位置
+    name_start: usize,
+}
+
+impl IndexedPath {
+    fn new(path: &Path, relative: &Path) -> Self {
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let name_start = relative
+            .rfind('/')
+            .map(|i| relative[..=i].chars().count())
+            .unwrap_or(0);
+        Self {
+            path: path.to_string_lossy().to_string(),
+            folded: relative.chars().map(fold_char).collect(),
+            relative,
+            name_start,
+        }
+    }
+
+    fn haystack(&self, case_sensitive: bool) -> &str {
+        if case_sensitive {
+            &self.relative
+        } else {
+            &self.folded
+        }
+    }
+
+    fn to_match(&self, score: i32, positions: Vec<u32>) -> FuzzyFileMatch {
+        let name = self
+            .relative
+            .rsplit('/')
+            .next()
+            .unwrap_or(&self.relative)
+            .to_string();
+        FuzzyFileMatch {
+            node: FileNode {
+                id: self.relative.clone(),
+                path: self.path.clone(),
+                name,
+                kind: NodeKind::File,
+            },
+            relative_path: self.relative.clone(),
+            score,
+            positions,
+        }
+    }
+}
+
+/// Go to File 用に、ルート配下の全ファイルパスを保持するインデックス
+/// 対象は list_dir と同じ（隠しファイル・.gitignore 対象を除外）
+pub struct FileIndex {
+    root: PathBuf,
+    entries: Vec<IndexedPath>,
+}
+
+impl FileIndex {
+    /// root_path 配下を走査してインデックスを作る
+    pub fn build(root_path: &str) -> Result<Self, CoreError> {
+        let root = Path::new(root_path);
+        if !root.exists() {
+            return Err(CoreError::NotFound {
+                path: root_path.to_string(),
+            });
+        }
+        if !root.is_dir() {
+            return Err(CoreError::NotADirectory {
+                path: root_path.to_string(),
+            });
+        }
+
+        let mut index = Self {
+            root: root.to_path_buf(),
+            entries: Vec::new(),
+        };
+        index.add_tree(root);
+        Ok(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// ファイル監視のイベントをインデックスに反映する
+    pub fn apply_events(&mut self, events: &[FsEvent]) {
+        for event in events {
+            let path = Path::new(&event.path);
+            match event.kind {
+                FsEventKind::Created => self.add_tree(path),
+                FsEventKind::Removed => self.remove_tree(path),
+                FsEventKind::Renamed => {
+                    if let Some(old_path) = &event.old_path {
+                        self.remove_tree(Path::new(old_path));
+                    }
+                    self.add_tree(path);
+                }
+                FsEventKind::Modified => {}
+                FsEventKind::Rescan => {
+                    self.entries.clear();
+                    let root = self.root.clone();
+                    self.add_tree(&root);
+                }
+            }
+        }
+    }
+
+    /// あいまい検索して上位 limit 件を返す
+    /// クエリに大文字が含まれる場合のみ大文字小文字を区別する
+    pub fn search(&self, query: &str, limit: usize) -> Vec<FuzzyFileMatch> {
+        let pattern: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+        if pattern.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+        let pattern = Pattern::new(pattern);
+
+        // 採点だけを先に行い、一致位置は返却する上位 limit 件についてのみ求める
+        let mut scored: Vec<(i32, &IndexedPath)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                if !pattern.is_subsequence_of(entry.haystack(pattern.case_sensitive)) {
+                    return None;
+                }
+                let score = score_path(entry, &pattern, None)?;
+                Some((score, entry))
+            })
+            .collect();
+
+        let ranking = |a: &(i32, &IndexedPath), b: &(i32, &IndexedPath)| {
+            b.0.cmp(&a.0)
+                .then_with(|| a.1.relative.len().cmp(&b.1.relative.len()))
+                .then_with(|| a.1.relative.cmp(&b.1.relative))
+        };
+        if scored.len() > limit {
+            scored.select_nth_unstable_by(limit - 1, ranking);
+            scored.truncate(limit);
+        }
+        scored.sort_unstable_by(ranking);
+
+        scored
+            .into_iter()
+            .map(|(score, entry)| {
+                let mut positions = Vec::with_capacity(pattern.chars.len());
+                score_path(entry, &pattern, Some(&mut positions));
+                entry.to_match(score, positions)
+            })
+            .collect()
+    }
+
+    /// path（ファイルまたはディレクトリ）配下のファイルを追加する
+    fn add_tree(&mut self, path: &Path) {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return;
+        };
+        if relative
+            .components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+        {
+            return;
+        }
+        // 既に登録済みのものと重複しないよう、いったん取り除いてから追加する
+        self.remove_tree(path);
+        if path.is_file() {
+            self.entries.push(IndexedPath::new(path, relative));
+            return;
+        }
+
+        for entry in crate::walk_builder(path).build().flatten() {
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            if let Ok(relative) = entry.path().strip_prefix(&self.root) {
+                self.entries.push(IndexedPath::new(entry.path(), relative));
+            }
+        }
+    }
+
+    /// path 自身と、path をディレクトリとみなした配下のエントリを削除する
+    fn remove_tree(&mut self, path: &Path) {
+        let path_str = path.to_string_lossy();
+        let dir_prefix = format!("{}/", path_str.trim_end_matches('/'));
+        self.entries
+            .retain(|e| e.path != path_str && !e.path.starts_with(&dir_prefix));
+    }
+}
+
+/// 検索クエリ（ASCII のみの場合は byte 比較用の表現も持つ）
+struct Pattern {
+    chars: Vec<char>,
+    bytes: Option<Vec<u8>>,
+    case_sensitive: bool,
+}
+
+impl Pattern {
+    /// クエリに大文字が含まれる場合のみ大文字小文字を区別する（smart case）
+    fn new(chars: Vec<char>) -> Self {
+        let case_sensitive = chars.iter().any(|c| c.is_uppercase());
+        let chars: Vec<char> = if case_sensitive {
+            chars
+        } else {
+            chars.into_iter().map(fold_char).collect()
+        };
+        let bytes = chars
+            .iter()
+            .all(char::is_ascii)
+            .then(|| chars.iter().map(|c| *c as u8).collect());
+        Self {
+            chars,
+            bytes,
+            case_sensitive,
+        }
+    }
+
+    /// 採点前の絞り込み（クエリの文字が順番どおりに含まれるか）
+    fn is_subsequence_of(&self, haystack: &str) -> bool {
+        match &self.bytes {
+            Some(bytes) => {
+                let mut rest = haystack.as_bytes().iter();
+                bytes.iter().all(|p| rest.any(|c| c == p))
+            }
+            None => {
+                let mut rest = haystack.chars();
+                self.chars.iter().all(|p| rest.any(|c| c == *p))
+            }
+        }
+    }
+}
+
+/// 比較単位（ASCII のパスは byte、それ以外は char で扱う）
+trait MatchUnit: Copy + PartialEq {
+    fn class(self) -> CharClass;
+}
+
+impl MatchUnit for u8 {
+    fn class(self) -> CharClass {
+        char_class(self as char)
+    }
+}
+
+impl MatchUnit for char {
+    fn class(self) -> CharClass {
+        char_class(self)
+    }
+}
+
+/// パスを採点する。positions が渡された場合は一致位置も記録する
+fn score_path(
+    entry: &IndexedPath,
+    pattern: &Pattern,
+    positions: Option<&mut Vec<u32>>,
+) -> Option<i32> {
+    let text = entry.haystack(pattern.case_sensitive);
+    match &pattern.bytes {
+        // 大半のパスは ASCII なので、文字配列を作らずに byte のまま評価する
+        Some(bytes) if entry.relative.is_ascii() => score_units(
+            text.as_bytes(),
+            entry.relative.as_bytes(),
+            bytes,
+            entry.name_start,
+            positions,
+        ),
+        _ => {
+            let text: Vec<char> = text.chars().collect();
+            let original: Vec<char> = entry.relative.chars().collect();
+            score_units(
+                &text,
+                &original,
+                &pattern.chars,
+                entry.name_start,
+                positions,
+            )
+        }
+    }
+}
+
+/// ファイル名だけで一致すればそちらを優先し、だめならパス全体で評価する
+/// text は比較用（小文字化済みの場合あり）、original は文字種判定用の元の文字列
+fn score_units<C: MatchUnit>(
+    text: &[C],
+    original: &[C],
+    pattern: &[C],
+    name_start: usize,
+    positions: Option<&mut Vec<u32>>,
+) -> Option<i32> {
+    if let Some((start, end)) = match_window(text, pattern, name_start) {
+        let score = calculate_score(text, original, pattern, start, end, positions);
+        return Some(score + BONUS_FILE_NAME);
+    }
+    let (start, end) = match_window(text, pattern, 0)?;
+    Some(calculate_score(
+        text, original, pattern, start, end, positions,
+    ))
+}
+
+/// fzf v1: 前方走査で最初の一致を見つけ、後方走査で最短の一致区間に縮める
+fn match_window<C: MatchUnit>(text: &[C], pattern: &[C], from: usize) -> Option<(usize, usize)> {
+    let mut pattern_index = 0;
+    let mut end = None;
+    for (index, c) in text.iter().enumerate().skip(from) {
+        if *c == pattern[pattern_index] {
+            pattern_index += 1;
+            if pattern_index == pattern.len() {
+                end = Some(index + 1);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    let mut start = end;
+    let mut pattern_index = pattern.len();
+    while pattern_index > 0 {
+        start -= 1;
+        if text[start] == pattern[pattern_index - 1] {
+            pattern_index -= 1;
+        }
+    }
+    Some((start, end))
+}
+
+fn calculate_score<C: MatchUnit>(
+    text: &[C],
+    original: &[C],
+    pattern: &[C],
+    start: usize,
+    end: usize,
+    mut positions: Option<&mut Vec<u32>>,
+) -> i32 {
+    let mut pattern_index = 0;
+    let mut score = 0;
+    let mut in_gap = false;
+    let mut consecutive = 0;
+    let mut first_bonus = 0;
+    let mut prev_class = if start > 0 {
+        original[start - 1].class()
+    } else {
+        CharClass::Delimiter
+    };
+
+    for index in start..end {
+        let class = original[index].class();
+        if pattern_index < pattern.len() && text[index] == pattern[pattern_index] {
+            if let Some(positions) = positions.as_deref_mut() {
+                positions.push(index as u32);
+            }
+            score += SCORE_MATCH;
+            let mut bonus = bonus_for(prev_class, class);
+            if consecutive == 0 {
+                first_bonus = bonus;
+            } else {
+                // 連続一致は区切りの直後から始まった一致のボーナスを引き継ぐ
+                if bonus >= BONUS_BOUNDARY && bonus > first_bonus {
+                    first_bonus = bonus;
+                }
+                bonus = bonus.max(first_bonus).max(BONUS_CONSECUTIVE);
+            }
+            score += if pattern_index == 0 {
+                bonus * BONUS_FIRST_CHAR_MULTIPLIER
+            } else {
+                bonus
+            };
+            in_gap = false;
+            consecutive += 1;
+            pattern_index += 1;
+        } else {
+            score += if in_gap {
+                SCORE_GAP_EXTENSION
+            } else {
+                SCORE_GAP_START
+            };
+            in_gap = true;
+            consecutive = 0;
+            first_bonus = 0;
+        }
+        prev_class = class;
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn index_from(root: &Path, relatives: &[&str]) -> FileIndex {
+        FileIndex {
+            root: root.to_path_buf(),
+            entries: relatives
+                .iter()
+                .map(|r| IndexedPath::new(&root.join(r), Path::new(r)))
+                .collect(),
+        }
+    }
+
+    fn top(index: &FileIndex, query: &str) -> Vec<String> {
+        index
+            .search(query, 10)
+            .into_iter()
+            .map(|m| m.relative_path)
+            .collect()
+    }
+
+    #[test]
+    fn matches_subsequence_across_word_boundaries() {
+        let index = index_from(
+            Path::new("/repo"),
+            &[
+                "src/foo/bar_service.rs",
+                "src/foo/bar.rs",
+                "docs/observer.md",
+                "Cargo.toml",
+            ],
+        );
+        let results = index.search("barserv", 10);
+        assert_eq!(results[0].relative_path, "src/foo/bar_service.rs");
+        assert_eq!(results[0].node.name, "bar_service.rs");
+        assert_eq!(results[0].node.path, "/repo/src/foo/bar_service.rs");
+        assert_eq!(results[0].positions, vec![8, 9, 10, 12, 13, 14, 15]);
+        assert!(!top(&index, "barserv").contains(&"Cargo.toml".to_string()));
+    }
+
+    #[test]
+    fn prefers_file_name_and_boundary_matches() {
+        let index = index_from(
+            Path::new("/repo"),
+            &["main/other.rs", "src/main.rs", "lib/domain_info.rs"],
+        );
+        assert_eq!(top(&index, "main")[0], "src/main.rs");
+    }
+
+    #[test]
+    fn smart_case_only_when_query_has_uppercase() {
+        let index = index_from(
+            Path::new("/repo"),
+            &["src/ViewModel.swift", "src/viewmodel.rs"],
+        );
+        assert_eq!(top(&index, "viewmodel").len(), 2);
+        assert_eq!(top(&index, "VM"), vec!["src/ViewModel.swift"]);
+    }
+
+    #[test]
+    fn limit_returns_top_results_in_order() {
+        let relatives: Vec<String> = (0..50).map(|i| format!("src/file_{i}.rs")).collect();
+        let refs: Vec<&str> = relatives.iter().map(String::as_str).collect();
+        let index = index_from(Path::new("/repo"), &refs);
+
+        let results = index.search("file", 5);
+        assert_eq!(results.len(), 5);
+        assert!(results.windows(2).all(|w| w[0].score >= w[1].score));
+        assert!(index.search("", 5).is_empty());
+        assert!(index.search("zzz", 5).is_empty());
+    }
+
+    #[test]
+    fn build_skips_ignored_files_and_applies_events() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("src/nested/util.rs"), "").unwrap();
+        fs::write(root.join("target/build.rs"), "").unwrap();
+
+        let mut index = FileIndex::build(root.to_str().unwrap()).unwrap();
+        assert_eq!(index.len(), 2);
+        assert!(top(&index, "build").is_empty());
+
+        fs::write(root.join("src/added.rs"), "").unwrap();
+        fs::rename(root.join("src/nested"), root.join("src/moved")).unwrap();
+        index.apply_events(&[
+            FsEvent {
+                kind: FsEventKind::Created,
+                path: root.join("src/added.rs").to_string_lossy().to_string(),
+                old_path: None,
+                is_dir: false,
+            },
+            FsEvent {
+                kind: FsEventKind::Renamed,
+                path: root.join("src/moved").to_string_lossy().to_string(),
+                old_path: Some(root.join("src/nested").to_string_lossy().to_string()),
+                is_dir: true,
+            },
+        ]);
+
+        assert_eq!(top(&index, "added"), vec!["src/added.rs"]);
+        assert_eq!(top(&index, "util"), vec!["src/moved/util.rs"]);
+        assert_eq!(index.len(), 3);
+
+        index.apply_events(&[FsEvent {
+            kind: FsEventKind::Removed,
+            path: root.join("src/moved").to_string_lossy().to_string(),
+            old_path: None,
+            is_dir: true,
+        }]);
+        assert!(top(&index, "util").is_empty());
+    }
+
+    #[test]
+    fn build_nonexistent_root_returns_error() {
+        assert!(matches!(
+            FileIndex::build("/nonexistent/blink-index"),
+            Err(CoreError::NotFound { .. })
+        ));
+    }
+}
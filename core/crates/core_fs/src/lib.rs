@@ -1,15 +1,37 @@
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
 use std::path::Path;
 
-use core_types::{FileNode, NodeKind};
+use core_types::{CoreError, FileNode, NodeKind};
 use ignore::WalkBuilder;
 
-/// パス文字列からIDを生成（ハッシュの先頭8文字）
-fn path_to_id(path: &str) -> String {
-    let mut hasher = DefaultHasher::new();
-    path.hash(&mut hasher);
-    format!("{:016x}", hasher.finish())[..8].to_string()
+mod fuzzy;
+mod search;
+mod tree;
+mod watcher;
+
+pub use fuzzy::FileIndex;
+pub use search::{search_project, SearchSink};
+pub use tree::ProjectTree;
+pub use watcher::{ProjectWatcher, WatchHandler};
+
+/// ノードIDを生成する（プロジェクトルートからの相対パス、区切りは `/`、ルート自身は空文字）
+/// パスそのものなので衝突せず、Rust のバージョンや起動をまたいでも変わらない
+/// ルート外のパスは絶対パスをそのまま使う
+pub(crate) fn node_id(root: &Path, path: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+        Err(_) => path.to_string_lossy().to_string(),
+    }
+}
+
+/// list_dir・検索・監視で共通の走査設定（隠しファイルと .gitignore 対象を除外）
+fn walk_builder(dir: &Path) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(dir);
+    builder
+        .hidden(true) // ドットファイルを除外
+        .git_ignore(true)
+        .git_global(false)
+        .git_exclude(true);
+    builder
 }
 
 /// 指定ディレクトリ直下のファイル・ディレクトリ一覧を返す。
@@ -18,33 +40,35 @@ fn path_to_id(path: &str) -> String {
 /// # Arguments
 /// * `root_path` - プロジェクトルート（.gitignore 探索の起点）
 /// * `dir_path` - 列挙対象ディレクトリの絶対パス
-pub fn list_dir(root_path: &str, dir_path: &str) -> Result<Vec<FileNode>, String> {
+pub fn list_dir(root_path: &str, dir_path: &str) -> Result<Vec<FileNode>, CoreError> {
     let root = Path::new(root_path);
     let dir = Path::new(dir_path);
 
     if !root.exists() {
-        return Err(format!("root_path が存在しません: {root_path}"));
+        return Err(CoreError::NotFound {
+            path: root_path.to_string(),
+        });
     }
     if !dir.exists() {
-        return Err(format!("dir_path が存在しません: {dir_path}"));
+        return Err(CoreError::NotFound {
+            path: dir_path.to_string(),
+        });
     }
     if !dir.is_dir() {
-        return Err(format!("dir_path がディレクトリではありません: {dir_path}"));
+        return Err(CoreError::NotADirectory {
+            path: dir_path.to_string(),
+        });
     }
 
-    let walker = WalkBuilder::new(dir)
+    let walker = walk_builder(dir)
         .max_depth(Some(1))
-        .hidden(true) // ドットファイルを除外
-        .git_ignore(true)
-        .git_global(false)
-        .git_exclude(true)
         .sort_by_file_path(|a, b| a.cmp(b))
         .build();
 
     let mut nodes: Vec<FileNode> = Vec::new();
 
     for entry in walker {
-        let entry = entry.map_err(|e| format!("ディレクトリ読み取りエラー: {e}"))?;
+        let entry = entry.map_err(|e| walk_error(dir_path, e))?;
         let entry_path = entry.path();
 
         // ルートエントリ自身はスキップ
@@ -65,7 +89,7 @@ pub fn list_dir(root_path: &str, dir_path: &str) -> Result<Vec<FileNode>, String
         };
 
         nodes.push(FileNode {
-            id: path_to_id(&path_str),
+            id: node_id(root, entry_path),
             path: path_str,
             name,
             kind,
@@ -88,6 +112,17 @@ pub fn list_dir(root_path: &str, dir_path: &str) -> Result<Vec<FileNode>, String
     Ok(nodes)
 }
 
+/// ignore クレートの走査エラーを CoreError に変換する
+fn walk_error(dir_path: &str, error: ignore::Error) -> CoreError {
+    match error.io_error() {
+        Some(io_error) => CoreError::from_io(dir_path, io_error),
+        None => CoreError::Io {
+            path: dir_path.to_string(),
+            reason: error.to_string(),
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -168,13 +203,19 @@ mod tests {
         assert!(names.contains(&"main.rs"));
         assert!(names.contains(&"lib.rs"));
         assert_eq!(result.len(), 2);
+        assert!(result.iter().any(|n| n.id == "src/main.rs"));
     }
 
     #[test]
     fn list_dir_nonexistent_root() {
         let result = list_dir("/nonexistent/path", "/nonexistent/path");
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("root_path が存在しません"));
+        assert_eq!(
+            result.unwrap_err(),
+            CoreError::NotFound {
+                path: "/nonexistent/path".into()
+            }
+        );
     }
 
     #[test]
@@ -184,7 +225,12 @@ mod tests {
 
         let result = list_dir(root, "/nonexistent/dir");
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("dir_path が存在しません"));
+        assert_eq!(
+            result.unwrap_err(),
+            CoreError::NotFound {
+                path: "/nonexistent/dir".into()
+            }
+        );
     }
 
     #[test]
@@ -196,23 +242,30 @@ mod tests {
 
         let result = list_dir(root, file_str);
         assert!(result.is_err());
-        assert!(result
-            .unwrap_err()
-            .contains("dir_path がディレクトリではありません"));
+        assert_eq!(
+            result.unwrap_err(),
+            CoreError::NotADirectory {
+                path: file_str.into()
+            }
+        );
     }
 
     #[test]
-    fn path_to_id_deterministic() {
-        let id1 = path_to_id("/some/path");
-        let id2 = path_to_id("/some/path");
-        assert_eq!(id1, id2);
-        assert_eq!(id1.len(), 8);
+    fn node_id_is_relative_path() {
+        let root = Path::new("/some/root");
+        assert_eq!(
+            node_id(root, Path::new("/some/root/src/main.rs")),
+            "src/main.rs"
+        );
+        assert_eq!(node_id(root, root), "");
+        assert_eq!(node_id(root, Path::new("/other/file")), "/other/file");
     }
 
     #[test]
-    fn path_to_id_different_paths() {
-        let id1 = path_to_id("/path/a");
-        let id2 = path_to_id("/path/b");
+    fn node_id_different_paths() {
+        let root = Path::new("/path");
+        let id1 = node_id(root, Path::new("/path/a"));
+        let id2 = node_id(root, Path::new("/path/b"));
         assert_ne!(id1, id2);
     }
 
@@ -0,0 +1,480 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use core_types::{CoreError, MatchRange, SearchMatch, SearchOptions, SearchSummary};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::bytes::{Regex, RegexBuilder};
+
+/// この件数たまったら途中結果を通知する
+const BATCH_SIZE: usize = 200;
+/// 件数が少なくても、この間隔で途中結果を通知する
+const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+/// これより大きいファイルは検索しない（生成物・ダンプなど）
+const MAX_FILE_BYTES: u64 = 8 * 1024 * 1024;
+/// 先頭のこの範囲に NUL を含むファイルはバイナリとみなす
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+/// preview の最大長（byte）
+const MAX_PREVIEW_BYTES: usize = 400;
+/// 長い行を切り出すとき、最初の一致の前に残す長さ（byte）
+const PREVIEW_CONTEXT_BYTES: usize = 80;
+
+/// 検索結果の受け取り先
+pub trait SearchSink {
+    /// ヒットした行をまとめて受け取る
+    fn on_matches(&mut self, matches: Vec<SearchMatch>);
+    /// true を返すと検索を中断し、Cancelled エラーを返す
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+impl<F: FnMut(Vec<SearchMatch>)> SearchSink for F {
+    fn on_matches(&mut self, matches: Vec<SearchMatch>) {
+        self(matches)
+    }
+}
+
+/// root_path 配下のファイルを全文検索し、ヒットした行をバッチで sink に通知する
+/// 走査対象は list_dir と同じ（隠しファイル・.gitignore 対象を除外）
+/// バイナリファイルと巨大なファイルは対象外
+pub fn search_project(
+    root_path: &str,
+    options: &SearchOptions,
+    sink: &mut impl SearchSink,
+) -> Result<SearchSummary, CoreError> {
+    let root = Path::new(root_path);
+    if !root.exists() {
+        return Err(CoreError::NotFound {
+            path: root_path.to_string(),
+        });
+    }
+    if !root.is_dir() {
+        return Err(CoreError::NotADirectory {
+            path: root_path.to_string(),
+        });
+    }
+    if options.query.is_empty() {
+        return Err(CoreError::invalid_argument("query が空です"));
+    }
+
+    let matcher = build_regex(options)?;
+    let include = build_globset(&options.include_globs)?;
+    let exclude = build_globset(&options.exclude_globs)?;
+
+    // 除外 glob に一致したディレクトリは中に入らない
+    let prune_root = root.to_path_buf();
+    let prune = exclude.clone();
+    let walker = crate::walk_builder(root)
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            match (&prune, entry.path().strip_prefix(&prune_root)) {
+                (Some(prune), Ok(relative)) if is_dir && !relative.as_os_str().is_empty() => {
+                    !prune.is_match(relative)
+                }
+                _ => true,
+            }
+        })
+        .build();
+
+    let max_results = options.max_results as usize;
+    let mut summary = SearchSummary {
+        searched_files: 0,
+        matched_files: 0,
+        match_count: 0,
+        truncated: false,
+    };
+    let mut batch: Vec<SearchMatch> = Vec::new();
+    let mut last_flush = Instant::now();
+
+    'files: for entry in walker {
+        if sink.is_cancelled() {
+            return Err(CoreError::Cancelled);
+        }
+        // 読めないディレクトリがあっても検索全体は続ける
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let path = entry.path();
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        if include.as_ref().is_some_and(|set| !set.is_match(relative))
+            || exclude.as_ref().is_some_and(|set| set.is_match(relative))
+        {
+            continue;
+        }
+        let Some(bytes) = read_searchable(path) else {
+            continue;
+        };
+
+        summary.searched_files += 1;
+        let path_str = path.to_string_lossy().to_string();
+        let mut file_matched = false;
+
+        for (index, line) in lines(&bytes).enumerate() {
+            let ranges = find_ranges(&matcher, line);
+            if ranges.is_empty() {
+                continue;
+            }
+            if max_results > 0 && summary.match_count as usize >= max_results {
+                summary.truncated = true;
+                break 'files;
+            }
+
+            let (preview, preview_start_col) = preview_for(line, ranges[0].start_col as usize);
+            batch.push(SearchMatch {
+                path: path_str.clone(),
+                line: index as u32 + 1,
+                ranges,
+                preview,
+                preview_start_col: preview_start_col as u32,
+            });
+            summary.match_count += 1;
+            file_matched = true;
+
+            if batch.len() >= BATCH_SIZE || last_flush.elapsed() >= BATCH_INTERVAL {
+                sink.on_matches(std::mem::take(&mut batch));
+                last_flush = Instant::now();
+            }
+        }
+
+        if file_matched {
+            summary.matched_files += 1;
+        }
+    }
+
+    if !batch.is_empty() {
+        sink.on_matches(batch);
+    }
+    Ok(summary)
+}
+
+/// 検索条件から正規表現を組み立てる（リテラル検索はエスケープする）
+/// whole_word は前後が単語構成文字に接していない位置でだけ一致させる。一致を後から
+/// 除外すると、重なる位置にある正しい一致（"aab ab" の2つ目の "ab"）を取りこぼすため
+fn build_regex(options: &SearchOptions) -> Result<Regex, CoreError> {
+    let pattern = if options.is_regex {
+        options.query.clone()
+    } else {
+        regex::escape(&options.query)
+    };
+    let pattern = if options.whole_word {
+        format!(r"\b{{start-half}}(?:{pattern})\b{{end-half}}")
+    } else {
+        pattern
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| CoreError::invalid_argument(format!("正規表現が不正です: {e}")))
+}
+
+fn build_globset(globs: &[String]) -> Result<Option<GlobSet>, CoreError> {
+    if globs.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        let glob = Glob::new(glob)
+            .map_err(|e| CoreError::invalid_argument(format!("glob が不正です: {glob}: {e}")))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| CoreError::invalid_argument(format!("glob が不正です: {e}")))
+}
+
+/// 検索対象のファイルをバイト列のまま読み込む（巨大・バイナリ・読めないファイルは None）
+/// UTF-8 として不正なバイトを置き換えると以降の列がずれるので、変換せずに検索する
+fn read_searchable(path: &Path) -> Option<Vec<u8>> {
+    let metadata = fs::metadata(path).ok()?;
+    if metadata.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    if bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+        return None;
+    }
+    Some(bytes)
+}
+
+/// str::lines と同じく "\n" で分け、行末の "\r" を除く（空のファイルは空の1行になるが一致しない）
+fn lines(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    bytes
+        .strip_suffix(b"\n")
+        .unwrap_or(bytes)
+        .split(|b| *b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+}
+
+/// 行内の一致範囲（行頭からの byte 位置）を返す
+fn find_ranges(matcher: &Regex, line: &[u8]) -> Vec<MatchRange> {
+    matcher
+        .find_iter(line)
+        .filter(|m| !m.is_empty())
+        .map(|m| MatchRange {
+            start_col: m.start() as u32,
+            end_col: m.end() as u32,
+        })
+        .collect()
+}
+
+/// 表示用の行を返す。長い行は最初の一致の少し前から MAX_PREVIEW_BYTES だけ切り出す
+/// 不正なバイトは1バイトずつ "?" にして、preview 内の位置と一致範囲の列を揃える
+fn preview_for(line: &[u8], first_match: usize) -> (String, usize) {
+    if line.len() <= MAX_PREVIEW_BYTES {
+        return (same_width_lossy(line), 0);
+    }
+    let start = floor_char_boundary(line, first_match.saturating_sub(PREVIEW_CONTEXT_BYTES));
+    let end = floor_char_boundary(line, (start + MAX_PREVIEW_BYTES).min(line.len()));
+    (same_width_lossy(&line[start..end]), start)
+}
+
+/// UTF-8 の継続バイトの途中なら、その文字の先頭まで戻る
+fn floor_char_boundary(bytes: &[u8], mut index: usize) -> usize {
+    let start = index.saturating_sub(3);
+    while index > start && index < bytes.len() && bytes[index] & 0xC0 == 0x80 {
+        index -= 1;
+    }
+    index
+}
+
+fn same_width_lossy(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        text.extend(std::iter::repeat_n('?', chunk.invalid().len()));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(query: &str) -> SearchOptions {
+        SearchOptions {
+            query: query.to_string(),
+            is_regex: false,
+            case_sensitive: true,
+            whole_word: false,
+            include_globs: Vec::new(),
+            exclude_globs: Vec::new(),
+            max_results: 0,
+        }
+    }
+
+    fn setup_search_dir() -> tempfile::TempDir {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::create_dir_all(root.join("vendor")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(
+            root.join("src/main.rs"),
+            "fn main() {\n    let total = add(1, 2);\n    println!(\"{total}\");\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/lib.rs"),
+            "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n// Add more\n",
+        )
+        .unwrap();
+        fs::write(root.join("vendor/dep.rs"), "fn add() {}\n").unwrap();
+        fs::write(root.join("target/out.rs"), "fn add() {}\n").unwrap();
+        fs::write(root.join("image.bin"), b"add\0\x01\x02").unwrap();
+        tmp
+    }
+
+    fn collect(root: &Path, options: &SearchOptions) -> (Vec<SearchMatch>, SearchSummary) {
+        let mut matches = Vec::new();
+        let summary = search_project(root.to_str().unwrap(), options, &mut |batch: Vec<
+            SearchMatch,
+        >| {
+            matches.extend(batch)
+        })
+        .unwrap();
+        (matches, summary)
+    }
+
+    fn relative_paths(root: &Path, matches: &[SearchMatch]) -> Vec<String> {
+        matches
+            .iter()
+            .map(|m| {
+                Path::new(&m.path)
+                    .strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn literal_search_skips_ignored_and_binary_files() {
+        let tmp = setup_search_dir();
+        let (matches, summary) = collect(tmp.path(), &options("add"));
+        let paths = relative_paths(tmp.path(), &matches);
+
+        assert!(paths.contains(&"src/main.rs".to_string()));
+        assert!(paths.contains(&"src/lib.rs".to_string()));
+        assert!(paths.contains(&"vendor/dep.rs".to_string()));
+        assert!(!paths.iter().any(|p| p.starts_with("target")));
+        assert!(!paths.contains(&"image.bin".to_string()));
+        assert_eq!(summary.match_count as usize, matches.len());
+
+        let main = matches
+            .iter()
+            .find(|m| m.path.ends_with("main.rs"))
+            .unwrap();
+        assert_eq!(main.line, 2);
+        assert_eq!(
+            main.ranges,
+            vec![MatchRange {
+                start_col: 16,
+                end_col: 19
+            }]
+        );
+        assert_eq!(main.preview, "    let total = add(1, 2);");
+    }
+
+    #[test]
+    fn case_insensitive_and_whole_word() {
+        let tmp = setup_search_dir();
+        let mut opts = options("add");
+        opts.case_sensitive = false;
+        let (matches, _) = collect(tmp.path(), &opts);
+        assert!(matches.iter().any(|m| m.preview == "// Add more"));
+
+        let mut opts = options("a");
+        opts.whole_word = true;
+        opts.include_globs = vec!["src/lib.rs".into()];
+        let (matches, _) = collect(tmp.path(), &opts);
+        let lines: Vec<u32> = matches.iter().map(|m| m.line).collect();
+        assert_eq!(lines, vec![1, 2]);
+        assert_eq!(matches[0].ranges.len(), 1);
+    }
+
+    #[test]
+    fn regex_search_and_invalid_regex() {
+        let tmp = setup_search_dir();
+        let mut opts = options(r"fn \w+\(");
+        opts.is_regex = true;
+        let (matches, _) = collect(tmp.path(), &opts);
+        assert!(matches.iter().any(|m| m.preview.starts_with("pub fn add(")));
+
+        opts.query = "fn (".into();
+        let result = search_project(tmp.path().to_str().unwrap(), &opts, &mut |_| {});
+        assert!(matches!(result, Err(CoreError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn include_and_exclude_globs() {
+        let tmp = setup_search_dir();
+        let mut opts = options("add");
+        opts.include_globs = vec!["*.rs".into()];
+        opts.exclude_globs = vec!["vendor".into()];
+        let (matches, _) = collect(tmp.path(), &opts);
+        let paths = relative_paths(tmp.path(), &matches);
+        assert!(!paths.iter().any(|p| p.starts_with("vendor")));
+        assert!(paths.iter().all(|p| p.starts_with("src/")));
+    }
+
+    #[test]
+    fn max_results_truncates() {
+        let tmp = setup_search_dir();
+        let mut opts = options("a");
+        opts.max_results = 2;
+        let (matches, summary) = collect(tmp.path(), &opts);
+        assert_eq!(matches.len(), 2);
+        assert!(summary.truncated);
+    }
+
+    #[test]
+    fn cancelled_sink_stops_search() {
+        struct Cancelled;
+        impl SearchSink for Cancelled {
+            fn on_matches(&mut self, _matches: Vec<SearchMatch>) {}
+            fn is_cancelled(&self) -> bool {
+                true
+            }
+        }
+
+        let tmp = setup_search_dir();
+        let result = search_project(
+            tmp.path().to_str().unwrap(),
+            &options("add"),
+            &mut Cancelled,
+        );
+        assert_eq!(result, Err(CoreError::Cancelled));
+    }
+
+    #[test]
+    fn empty_query_and_missing_root_return_errors() {
+        let tmp = setup_search_dir();
+        let result = search_project(tmp.path().to_str().unwrap(), &options(""), &mut |_| {});
+        assert!(matches!(result, Err(CoreError::InvalidArgument { .. })));
+
+        let result = search_project("/nonexistent/blink-search", &options("a"), &mut |_| {});
+        assert!(matches!(result, Err(CoreError::NotFound { .. })));
+    }
+
+    #[test]
+    fn whole_word_finds_match_after_rejected_overlap() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("a.txt"), "aab ab\n").unwrap();
+        let mut opts = options("ab");
+        opts.whole_word = true;
+        let (matches, _) = collect(tmp.path(), &opts);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            matches[0].ranges,
+            vec![MatchRange {
+                start_col: 4,
+                end_col: 6
+            }]
+        );
+
+        // 記号で始まる検索語も前後の単語構成文字だけを見る
+        opts.query = "(x)".into();
+        fs::write(tmp.path().join("a.txt"), "f (x) g(x)\n").unwrap();
+        let (matches, _) = collect(tmp.path(), &opts);
+        assert_eq!(matches[0].ranges.len(), 1);
+        assert_eq!(matches[0].ranges[0].start_col, 2);
+    }
+
+    #[test]
+    fn invalid_utf8_keeps_byte_columns() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("a.txt"), b"\xff\xfe name = 1\r\nname\n").unwrap();
+        let (matches, _) = collect(tmp.path(), &options("name"));
+        assert_eq!(matches.len(), 2);
+        assert_eq!(
+            matches[0].ranges,
+            vec![MatchRange {
+                start_col: 3,
+                end_col: 7
+            }]
+        );
+        assert_eq!(matches[0].preview, "?? name = 1");
+        assert_eq!(matches[1].line, 2);
+        assert_eq!(matches[1].preview, "name");
+    }
+
+    #[test]
+    fn long_line_preview_is_windowed_around_match() {
+        let line = format!("{}needle{}", "x".repeat(1000), "y".repeat(1000));
+        let (preview, start) = preview_for(line.as_bytes(), 1000);
+        assert_eq!(start, 1000 - PREVIEW_CONTEXT_BYTES);
+        assert_eq!(preview.len(), MAX_PREVIEW_BYTES);
+        assert!(preview.contains("needle"));
+    }
+}
@@ -0,0 +1,603 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use core_types::{
+    CoreError, FileNode, FsEvent, FsEventKind, GitDecoration, NodeKind, ProjectTreeDiff,
+    ProjectTreeNode,
+};
+
+use crate::{list_dir, node_id, walk_builder};
+
+/// ルートノードの ID（相対パスが空）
+const ROOT_ID: &str = "";
+
+/// 読み込み済みノードの状態
+struct NodeState {
+    node: ProjectTreeNode,
+    /// 子ノード ID の並び（未読み込みなら None）
+    children: Option<Vec<String>>,
+}
+
+/// 遅延読み込みのプロジェクトツリー
+///
+/// ディレクトリの子は展開・参照されたときに初めて列挙する。
+/// ノード ID はルートからの相対パスなので、再読み込みや再起動をまたいでも変わらない。
+pub struct ProjectTree {
+    root: PathBuf,
+    nodes: HashMap<String, NodeState>,
+    /// ID → Git 状態（ディレクトリは配下の集約結果）
+    decorations: HashMap<String, GitDecoration>,
+}
+
+impl ProjectTree {
+    /// ルートを開き、直下の子を読み込む
+    pub fn open(root_path: &str) -> Result<Self, CoreError> {
+        let root = fs::canonicalize(root_path).map_err(|e| CoreError::from_io(root_path, &e))?;
+        if !root.is_dir() {
+            return Err(CoreError::NotADirectory {
+                path: root_path.to_string(),
+            });
+        }
+
+        let root_node = ProjectTreeNode {
+            id: ROOT_ID.to_string(),
+            parent_id: None,
+            path: root.to_string_lossy().to_string(),
+            name: root
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            kind: NodeKind::Dir,
+            depth: 0,
+            has_children: true,
+            is_expanded: true,
+            size: 0,
+            modified_at: 0,
+            symlink_target: None,
+            git_decoration: None,
+        };
+
+        let mut tree = Self {
+            root,
+            nodes: HashMap::new(),
+            decorations: HashMap::new(),
+        };
+        tree.nodes.insert(
+            ROOT_ID.to_string(),
+            NodeState {
+                node: root_node,
+                children: None,
+            },
+        );
+        tree.load_children(ROOT_ID)?;
+        Ok(tree)
+    }
+
+    /// 正規化済みのルートパス
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 表示中のノードを深さ優先で平坦化して返す（展開済みディレクトリの子のみ含む）
+    pub fn visible_nodes(&self) -> Vec<ProjectTreeNode> {
+        let mut result = Vec::new();
+        self.collect_visible(ROOT_ID, &mut result);
+        result
+    }
+
+    /// 子ノード一覧を返す（未読み込みならこの時点で列挙する）
+    pub fn children(&mut self, id: &str) -> Result<Vec<ProjectTreeNode>, CoreError> {
+        self.ensure_dir(id)?;
+        if self.nodes[id].children.is_none() {
+            self.load_children(id)?;
+        }
+        Ok(self.child_nodes(id))
+    }
+
+    /// ディレクトリの展開状態を設定し、更新後の表示ノードを返す
+    pub fn set_expanded(
+        &mut self,
+        id: &str,
+        expanded: bool,
+    ) -> Result<Vec<ProjectTreeNode>, CoreError> {
+        self.ensure_dir(id)?;
+        if expanded && self.nodes[id].children.is_none() {
+            self.load_children(id)?;
+        }
+        if let Some(state) = self.nodes.get_mut(id) {
+            state.node.is_expanded = expanded || id == ROOT_ID;
+        }
+        Ok(self.visible_nodes())
+    }
+
+    /// ディレクトリの展開状態を反転し、更新後の表示ノードを返す
+    pub fn toggle(&mut self, id: &str) -> Result<Vec<ProjectTreeNode>, CoreError> {
+        let expanded = self
+            .nodes
+            .get(id)
+            .map(|state| state.node.is_expanded)
+            .unwrap_or(false);
+        self.set_expanded(id, !expanded)
+    }
+
+    /// ディレクトリを再列挙し、前回との差分を返す
+    /// 消えた子ノードは配下ごと破棄し、残った子の展開状態は維持する
+    pub fn refresh(&mut self, id: &str) -> Result<ProjectTreeDiff, CoreError> {
+        self.ensure_dir(id)?;
+        let old_children = self.nodes[id].children.clone().unwrap_or_default();
+        let entries = list_dir(&self.root.to_string_lossy(), &self.nodes[id].node.path)?;
+
+        let mut diff = ProjectTreeDiff {
+            parent_id: id.to_string(),
+            inserted: Vec::new(),
+            removed_ids: Vec::new(),
+            updated: Vec::new(),
+            child_ids: Vec::with_capacity(entries.len()),
+        };
+
+        for entry in &entries {
+            let mut node = self.build_node(id, entry);
+            let previous = self
+                .nodes
+                .get(&node.id)
+                .map(|state| (state.node.kind, state.node.is_expanded));
+            match previous {
+                Some((kind, is_expanded)) => {
+                    node.is_expanded = is_expanded;
+                    // ファイル⇔ディレクトリが入れ替わった場合は子の情報を捨てる
+                    if kind != node.kind {
+                        node.is_expanded = false;
+                        let stale = self
+                            .nodes
+                            .get_mut(&node.id)
+                            .and_then(|state| state.children.take())
+                            .unwrap_or_default();
+                        for child_id in stale {
+                            self.remove_subtree(&child_id);
+                        }
+                    }
+                    if let Some(state) = self.nodes.get_mut(&node.id) {
+                        if state.node != node {
+                            state.node = node.clone();
+                            diff.updated.push(node.clone());
+                        }
+                    }
+                }
+                None => {
+                    self.nodes.insert(
+                        node.id.clone(),
+                        NodeState {
+                            node: node.clone(),
+                            children: None,
+                        },
+                    );
+                    diff.inserted.push(node.clone());
+                }
+            }
+            diff.child_ids.push(node.id);
+        }
+
+        let current: HashSet<&str> = diff.child_ids.iter().map(String::as_str).collect();
+        for old_id in old_children {
+            if !current.contains(old_id.as_str()) {
+                self.remove_subtree(&old_id);
+                diff.removed_ids.push(old_id);
+            }
+        }
+
+        if let Some(state) = self.nodes.get_mut(id) {
+            state.children = Some(diff.child_ids.clone());
+        }
+        Ok(diff)
+    }
+
+    /// ファイル監視イベントを反映し、変化のあったディレクトリごとの差分を返す
+    /// 読み込み済みのディレクトリだけを再列挙する
+    pub fn apply_fs_events(&mut self, events: &[FsEvent]) -> Vec<ProjectTreeDiff> {
+        let mut targets: HashSet<String> = HashSet::new();
+
+        for event in events {
+            if event.kind == FsEventKind::Rescan {
+                targets.extend(
+                    self.nodes
+                        .iter()
+                        .filter(|(_, state)| state.children.is_some())
+                        .map(|(id, _)| id.clone()),
+                );
+                continue;
+            }
+            for path in std::iter::once(&event.path).chain(event.old_path.iter()) {
+                if let Some(target) = self.refresh_target(Path::new(path)) {
+                    targets.insert(target);
+                }
+            }
+        }
+
+        // 親から順に処理する（親の再列挙で消えたディレクトリは飛ばす）
+        let mut targets: Vec<String> = targets.into_iter().collect();
+        targets.sort_by_key(|id| (depth_of(id), id.clone()));
+
+        let mut diffs = Vec::new();
+        for id in targets {
+            if !self.nodes.contains_key(&id) {
+                continue;
+            }
+            if let Ok(diff) = self.refresh(&id) {
+                if !(diff.inserted.is_empty()
+                    && diff.removed_ids.is_empty()
+                    && diff.updated.is_empty())
+                {
+                    diffs.push(diff);
+                }
+            }
+        }
+        diffs
+    }
+
+    /// Git 状態を差し替え、装飾が変わった読み込み済みノードを返す
+    ///
+    /// # Arguments
+    /// * `entries` - (絶対パス, 状態) の一覧。ディレクトリの状態は配下から集約する
+    pub fn set_git_decorations(
+        &mut self,
+        entries: &[(String, GitDecoration)],
+    ) -> Vec<ProjectTreeNode> {
+        let mut decorations: HashMap<String, GitDecoration> = HashMap::new();
+        for (path, decoration) in entries {
+            let id = node_id(&self.root, Path::new(path));
+            if id.is_empty() || Path::new(&id).is_absolute() {
+                continue;
+            }
+            merge_decoration(&mut decorations, id.clone(), *decoration);
+
+            let mut ancestor = id.as_str();
+            while let Some(index) = ancestor.rfind('/') {
+                ancestor = &ancestor[..index];
+                merge_decoration(
+                    &mut decorations,
+                    ancestor.to_string(),
+                    dir_decoration(*decoration),
+                );
+            }
+        }
+        self.decorations = decorations;
+
+        let mut changed = Vec::new();
+        for (id, state) in self.nodes.iter_mut() {
+            if id.is_empty() {
+                continue;
+            }
+            let decoration = self.decorations.get(id).copied();
+            if state.node.git_decoration != decoration {
+                state.node.git_decoration = decoration;
+                changed.push(state.node.clone());
+            }
+        }
+        changed.sort_by(|a, b| a.id.cmp(&b.id));
+        changed
+    }
+
+    fn ensure_dir(&self, id: &str) -> Result<(), CoreError> {
+        match self.nodes.get(id) {
+            Some(state) if state.node.kind == NodeKind::Dir => Ok(()),
+            Some(state) => Err(CoreError::NotADirectory {
+                path: state.node.path.clone(),
+            }),
+            None => Err(CoreError::invalid_argument(format!(
+                "ツリーに存在しないノード ID: {id}"
+            ))),
+        }
+    }
+
+    fn load_children(&mut self, id: &str) -> Result<(), CoreError> {
+        self.refresh(id).map(|_| ())
+    }
+
+    fn child_nodes(&self, id: &str) -> Vec<ProjectTreeNode> {
+        self.nodes[id]
+            .children
+            .iter()
+            .flatten()
+            .filter_map(|child_id| self.nodes.get(child_id))
+            .map(|state| state.node.clone())
+            .collect()
+    }
+
+    fn collect_visible(&self, id: &str, result: &mut Vec<ProjectTreeNode>) {
+        let Some(children) = self.nodes.get(id).and_then(|state| state.children.as_ref()) else {
+            return;
+        };
+        for child_id in children {
+            let Some(state) = self.nodes.get(child_id) else {
+                continue;
+            };
+            result.push(state.node.clone());
+            if state.node.is_expanded {
+                self.collect_visible(child_id, result);
+            }
+        }
+    }
+
+    fn remove_subtree(&mut self, id: &str) {
+        if let Some(state) = self.nodes.remove(id) {
+            for child_id in state.children.unwrap_or_default() {
+                self.remove_subtree(&child_id);
+            }
+        }
+    }
+
+    /// イベントのパスに対して再列挙すべきディレクトリ ID を返す
+    /// 親が未読み込みでも既知のノードなら、その has_children 等を更新するため祖父を対象にする
+    fn refresh_target(&self, path: &Path) -> Option<String> {
+        let parent_id = node_id(&self.root, path.parent()?);
+        if Path::new(&parent_id).is_absolute() {
+            return None;
+        }
+        let state = self.nodes.get(&parent_id)?;
+        if state.children.is_some() {
+            return Some(parent_id);
+        }
+        state.node.parent_id.clone()
+    }
+
+    fn build_node(&self, parent_id: &str, entry: &FileNode) -> ProjectTreeNode {
+        let path = Path::new(&entry.path);
+        let link_metadata = fs::symlink_metadata(path).ok();
+        let symlink_target = link_metadata
+            .as_ref()
+            .filter(|m| m.file_type().is_symlink())
+            .and_then(|_| fs::read_link(path).ok())
+            .map(|target| target.to_string_lossy().to_string());
+        // シンボリックリンクはリンク先の情報を使う（リンク切れならリンク自身）
+        let metadata = fs::metadata(path).ok().or(link_metadata);
+
+        let size = match (&entry.kind, &metadata) {
+            (NodeKind::File, Some(m)) => m.len(),
+            _ => 0,
+        };
+        let modified_at = metadata
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let has_children = entry.kind == NodeKind::Dir && dir_has_children(path);
+
+        ProjectTreeNode {
+            id: entry.id.clone(),
+            parent_id: Some(parent_id.to_string()),
+            path: entry.path.clone(),
+            name: entry.name.clone(),
+            kind: entry.kind,
+            depth: depth_of(&entry.id),
+            has_children,
+            is_expanded: false,
+            size,
+            modified_at,
+            symlink_target,
+            git_decoration: self.decorations.get(&entry.id).copied(),
+        }
+    }
+}
+
+/// 表示対象（隠し・ignore 以外）の子が1つでもあるか
+fn dir_has_children(dir: &Path) -> bool {
+    walk_builder(dir)
+        .max_depth(Some(1))
+        .build()
+        .filter_map(Result::ok)
+        .any(|entry| entry.depth() > 0)
+}
+
+/// ルート直下を 0 とする深さ
+fn depth_of(id: &str) -> u32 {
+    id.matches('/').count() as u32
+}
+
+/// ディレクトリに伝播させる状態（変更系はまとめて Modified）
+fn dir_decoration(decoration: GitDecoration) -> GitDecoration {
+    match decoration {
+        GitDecoration::Conflicted => GitDecoration::Conflicted,
+        GitDecoration::Untracked => GitDecoration::Untracked,
+        _ => GitDecoration::Modified,
+    }
+}
+
+fn decoration_priority(decoration: GitDecoration) -> u8 {
+    match decoration {
+        GitDecoration::Conflicted => 2,
+        GitDecoration::Untracked => 0,
+        _ => 1,
+    }
+}
+
+fn merge_decoration(
+    decorations: &mut HashMap<String, GitDecoration>,
+    id: String,
+    decoration: GitDecoration,
+) {
+    decorations
+        .entry(id)
+        .and_modify(|current| {
+            if decoration_priority(decoration) > decoration_priority(*current) {
+                *current = decoration;
+            }
+        })
+        .or_insert(decoration);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_tree_dir() -> tempfile::TempDir {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::create_dir_all(root.join("empty")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("src/nested/deep.rs"), "").unwrap();
+        fs::write(root.join("README.md"), "# Test").unwrap();
+        tmp
+    }
+
+    fn ids(nodes: &[ProjectTreeNode]) -> Vec<&str> {
+        nodes.iter().map(|n| n.id.as_str()).collect()
+    }
+
+    #[test]
+    fn open_lists_root_children_with_metadata() {
+        let tmp = setup_tree_dir();
+        let tree = ProjectTree::open(tmp.path().to_str().unwrap()).unwrap();
+
+        let nodes = tree.visible_nodes();
+        assert_eq!(ids(&nodes), vec!["empty", "src", "README.md"]);
+
+        let empty = &nodes[0];
+        assert_eq!(empty.parent_id.as_deref(), Some(""));
+        assert!(!empty.has_children);
+        let src = &nodes[1];
+        assert!(src.has_children);
+        assert!(!src.is_expanded);
+        let readme = &nodes[2];
+        assert_eq!(readme.size, 6);
+        assert!(readme.modified_at > 0);
+        assert_eq!(readme.depth, 0);
+    }
+
+    #[test]
+    fn toggle_expands_and_collapses_lazily() {
+        let tmp = setup_tree_dir();
+        let mut tree = ProjectTree::open(tmp.path().to_str().unwrap()).unwrap();
+
+        let nodes = tree.toggle("src").unwrap();
+        assert_eq!(
+            ids(&nodes),
+            vec!["empty", "src", "src/nested", "src/main.rs", "README.md"]
+        );
+        assert_eq!(nodes[2].depth, 1);
+        assert!(nodes[1].is_expanded);
+
+        let nodes = tree.toggle("src/nested").unwrap();
+        assert!(ids(&nodes).contains(&"src/nested/deep.rs"));
+
+        // 親を閉じて開き直しても子の展開状態は保持される
+        tree.toggle("src").unwrap();
+        assert_eq!(tree.visible_nodes().len(), 3);
+        let nodes = tree.toggle("src").unwrap();
+        assert!(ids(&nodes).contains(&"src/nested/deep.rs"));
+    }
+
+    #[test]
+    fn toggle_rejects_files_and_unknown_ids() {
+        let tmp = setup_tree_dir();
+        let mut tree = ProjectTree::open(tmp.path().to_str().unwrap()).unwrap();
+
+        assert!(matches!(
+            tree.toggle("README.md"),
+            Err(CoreError::NotADirectory { .. })
+        ));
+        assert!(matches!(
+            tree.toggle("missing"),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn refresh_reports_incremental_diff() {
+        let tmp = setup_tree_dir();
+        let root = tmp.path();
+        let mut tree = ProjectTree::open(root.to_str().unwrap()).unwrap();
+        tree.set_expanded("src", true).unwrap();
+        tree.set_expanded("src/nested", true).unwrap();
+
+        fs::write(root.join("src/new.rs"), "").unwrap();
+        fs::remove_dir_all(root.join("src/nested")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() { println!(); }").unwrap();
+
+        let diff = tree.refresh("src").unwrap();
+        assert_eq!(diff.parent_id, "src");
+        assert_eq!(ids(&diff.inserted), vec!["src/new.rs"]);
+        assert_eq!(diff.removed_ids, vec!["src/nested".to_string()]);
+        assert_eq!(ids(&diff.updated), vec!["src/main.rs"]);
+        assert_eq!(diff.child_ids, vec!["src/main.rs", "src/new.rs"]);
+
+        // 削除されたディレクトリ配下のノードも破棄される
+        assert!(!ids(&tree.visible_nodes()).contains(&"src/nested/deep.rs"));
+    }
+
+    #[test]
+    fn apply_fs_events_refreshes_loaded_parents() {
+        let tmp = setup_tree_dir();
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        let mut tree = ProjectTree::open(root.to_str().unwrap()).unwrap();
+
+        // 未展開の空ディレクトリにファイルが増えたら、親側で has_children が更新される
+        let created = root.join("empty/file.txt");
+        fs::write(&created, "").unwrap();
+        let diffs = tree.apply_fs_events(&[FsEvent {
+            kind: FsEventKind::Created,
+            path: created.to_string_lossy().to_string(),
+            old_path: None,
+            is_dir: false,
+        }]);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].parent_id, "");
+        assert_eq!(ids(&diffs[0].updated), vec!["empty"]);
+        assert!(diffs[0].updated[0].has_children);
+
+        // 未読み込みのディレクトリ配下で変化がなければ差分は出ない
+        let diffs = tree.apply_fs_events(&[FsEvent {
+            kind: FsEventKind::Rescan,
+            path: root.to_string_lossy().to_string(),
+            old_path: None,
+            is_dir: true,
+        }]);
+        assert!(diffs.is_empty());
+    }
+
+    #[test]
+    fn git_decorations_propagate_to_directories() {
+        let tmp = setup_tree_dir();
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        let mut tree = ProjectTree::open(root.to_str().unwrap()).unwrap();
+
+        let path = |p: &str| root.join(p).to_string_lossy().to_string();
+        let changed = tree.set_git_decorations(&[
+            (path("src/main.rs"), GitDecoration::Added),
+            (path("src/nested/deep.rs"), GitDecoration::Conflicted),
+            (path("README.md"), GitDecoration::Untracked),
+        ]);
+        assert_eq!(ids(&changed), vec!["README.md", "src"]);
+        assert_eq!(changed[1].git_decoration, Some(GitDecoration::Conflicted));
+
+        // 後から読み込んだノードにも反映される
+        let nodes = tree.children("src").unwrap();
+        assert_eq!(nodes[0].git_decoration, Some(GitDecoration::Conflicted));
+        assert_eq!(nodes[1].git_decoration, Some(GitDecoration::Added));
+
+        let changed = tree.set_git_decorations(&[]);
+        assert_eq!(changed.len(), 4);
+        assert!(changed.iter().all(|n| n.git_decoration.is_none()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_target_is_reported() {
+        let tmp = setup_tree_dir();
+        let root = tmp.path();
+        std::os::unix::fs::symlink("README.md", root.join("link.md")).unwrap();
+
+        let tree = ProjectTree::open(root.to_str().unwrap()).unwrap();
+        let link = tree
+            .visible_nodes()
+            .into_iter()
+            .find(|n| n.id == "link.md")
+            .unwrap();
+        assert_eq!(link.symlink_target.as_deref(), Some("README.md"));
+        assert_eq!(link.size, 6);
+    }
+}
@@ -0,0 +1,752 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use core_types::{CoreError, FsEvent, FsEventKind};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::event::{MetadataKind, ModifyKind, RemoveKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// デバウンス中にイベントが途切れなくても、この倍率を超えたら一度通知する
+const MAX_BATCH_DELAY_FACTOR: u32 = 4;
+
+/// 監視イベントの通知先
+pub trait WatchHandler: Send + 'static {
+    /// デバウンス済みのイベントをまとめて受け取る
+    fn on_events(&self, events: Vec<FsEvent>);
+    /// 監視中に発生したエラーを受け取る（監視自体は継続する）
+    fn on_error(&self, error: CoreError);
+}
+
+enum WorkerMessage {
+    Notify(notify::Result<Event>),
+    Stop,
+}
+
+/// プロジェクトルート配下の変更を監視する
+/// .gitignore・隠しファイルの除外ルールは list_dir と同じ
+/// drop または stop() で監視を終了する
+pub struct ProjectWatcher {
+    root: PathBuf,
+    watcher: Option<RecommendedWatcher>,
+    sender: Sender<WorkerMessage>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ProjectWatcher {
+    /// root_path の再帰監視を開始する
+    ///
+    /// # Arguments
+    /// * `root_path` - 監視対象のプロジェクトルート
+    /// * `debounce` - 最後のイベントからこの時間だけ変更が無ければ通知する
+    /// * `handler` - 通知先（監視スレッドから呼び出される）
+    pub fn start(
+        root_path: &str,
+        debounce: Duration,
+        handler: impl WatchHandler,
+    ) -> Result<Self, CoreError> {
+        let root = Path::new(root_path);
+        if !root.exists() {
+            return Err(CoreError::NotFound {
+                path: root_path.to_string(),
+            });
+        }
+        if !root.is_dir() {
+            return Err(CoreError::NotADirectory {
+                path: root_path.to_string(),
+            });
+        }
+        // macOS の FSEvents は実体パスでイベントを返すため、監視前に正規化しておく
+        let root = root
+            .canonicalize()
+            .map_err(|e| CoreError::from_io(root_path, &e))?;
+
+        let (sender, receiver) = mpsc::channel();
+        let notify_sender = sender.clone();
+        let mut watcher = notify::recommended_watcher(move |result| {
+            let _ = notify_sender.send(WorkerMessage::Notify(result));
+        })
+        .map_err(|e| notify_error(root_path, e))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| notify_error(root_path, e))?;
+
+        let filter = IgnoreFilter::new(&root);
+        let worker_root = root.clone();
+        let worker = std::thread::Builder::new()
+            .name("blink-fs-watcher".to_string())
+            .spawn(move || run_worker(worker_root, receiver, filter, debounce, handler))
+            .map_err(|e| CoreError::internal(format!("監視スレッドの起動に失敗しました: {e}")))?;
+
+        Ok(Self {
+            root,
+            watcher: Some(watcher),
+            sender,
+            worker: Some(worker),
+        })
+    }
+
+    /// 監視中のルート（正規化済み）
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 監視を終了する（未通知のイベントは破棄する）
+    /// ハンドラの中から呼ばれた場合は、ハンドラが戻った後にワーカーが終了する
+    pub fn stop(&mut self) {
+        // 先に OS 側の監視を止めてから、ワーカーに終了を伝える
+        self.watcher.take();
+        let _ = self.sender.send(WorkerMessage::Stop);
+        if let Some(worker) = self.worker.take() {
+            // ワーカー自身のスレッドで join すると自分の終了を待つことになる
+            if worker.thread().id() != std::thread::current().id() {
+                let _ = worker.join();
+            }
+        }
+    }
+}
+
+impl Drop for ProjectWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn notify_error(root_path: &str, error: notify::Error) -> CoreError {
+    match error.kind {
+        notify::ErrorKind::Io(ref io_error) => CoreError::from_io(root_path, io_error),
+        notify::ErrorKind::PathNotFound => CoreError::NotFound {
+            path: root_path.to_string(),
+        },
+        _ => CoreError::Io {
+            path: root_path.to_string(),
+            reason: error.to_string(),
+        },
+    }
+}
+
+/// notify からのイベントを受け取り、デバウンスしてハンドラへ通知する
+fn run_worker(
+    root: PathBuf,
+    receiver: Receiver<WorkerMessage>,
+    mut filter: IgnoreFilter,
+    debounce: Duration,
+    handler: impl WatchHandler,
+) {
+    let max_delay = debounce * MAX_BATCH_DELAY_FACTOR;
+    let mut pending = PendingEvents::default();
+    let mut first_at = Instant::now();
+    let mut last_at = Instant::now();
+
+    loop {
+        let message = if pending.is_empty() {
+            match receiver.recv() {
+                Ok(message) => message,
+                Err(_) => return,
+            }
+        } else {
+            let deadline = (last_at + debounce).min(first_at + max_delay);
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match receiver.recv_timeout(timeout) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => {
+                    handler.on_events(pending.take());
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        };
+
+        match message {
+            WorkerMessage::Stop => return,
+            WorkerMessage::Notify(Ok(event)) => {
+                let was_empty = pending.is_empty();
+                for fs_event in convert_event(&root, &event, &mut filter) {
+                    pending.push(fs_event);
+                }
+                if !pending.is_empty() {
+                    last_at = Instant::now();
+                    if was_empty {
+                        first_at = last_at;
+                    }
+                }
+            }
+            WorkerMessage::Notify(Err(error)) => {
+                handler.on_error(notify_error(&root.to_string_lossy(), error));
+            }
+        }
+    }
+}
+
+/// notify のイベントを FsEvent に変換し、除外対象を取り除く
+fn convert_event(root: &Path, event: &Event, filter: &mut IgnoreFilter) -> Vec<FsEvent> {
+    if event.need_rescan() {
+        return vec![rescan_event(root)];
+    }
+    // 除外ルールの読み込み自体も Access イベントになるため、先に読み捨てる
+    if matches!(
+        event.kind,
+        EventKind::Access(_) | EventKind::Modify(ModifyKind::Metadata(MetadataKind::AccessTime))
+    ) {
+        return Vec::new();
+    }
+    // .gitignore・.ignore が変わると表示対象そのものが変わるため、除外ルールを読み直して再走査させる
+    if event.paths.iter().any(|p| is_ignore_file(p)) {
+        filter.invalidate();
+        return vec![rescan_event(root)];
+    }
+
+    // 削除済みのパスは種類を調べられないので、イベントが示す種類を使う
+    let removed_dir = match event.kind {
+        EventKind::Remove(RemoveKind::Folder) => Some(true),
+        EventKind::Remove(RemoveKind::File) => Some(false),
+        _ => None,
+    };
+    let kind = match event.kind {
+        EventKind::Create(_) => FsEventKind::Created,
+        EventKind::Remove(_) => FsEventKind::Removed,
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            return convert_rename(event, filter);
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => FsEventKind::Removed,
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => FsEventKind::Created,
+        // FSEvents は移動元・移動先を対にしないため、存在有無で判断する
+        EventKind::Modify(ModifyKind::Name(_)) => {
+            return event
+                .paths
+                .iter()
+                .filter_map(|path| {
+                    let kind = if path.exists() {
+                        FsEventKind::Created
+                    } else {
+                        FsEventKind::Removed
+                    };
+                    single_event(kind, path, None, filter)
+                })
+                .collect();
+        }
+        EventKind::Modify(_) | EventKind::Any | EventKind::Other => FsEventKind::Modified,
+        EventKind::Access(_) => return Vec::new(),
+    };
+
+    event
+        .paths
+        .iter()
+        .filter_map(|path| single_event(kind, path, removed_dir, filter))
+        .collect()
+}
+
+fn convert_rename(event: &Event, filter: &mut IgnoreFilter) -> Vec<FsEvent> {
+    let [from, to] = event.paths.as_slice() else {
+        return Vec::new();
+    };
+    let is_dir = to.is_dir();
+    match (
+        filter.is_ignored(from, is_dir),
+        filter.is_ignored(to, is_dir),
+    ) {
+        (true, true) => Vec::new(),
+        (false, true) => vec![fs_event(FsEventKind::Removed, from, None, is_dir)],
+        (true, false) => vec![fs_event(FsEventKind::Created, to, None, is_dir)],
+        (false, false) => vec![fs_event(FsEventKind::Renamed, to, Some(from), is_dir)],
+    }
+}
+
+/// removed_dir は削除イベントが示すディレクトリかどうか（分からなければ None）
+fn single_event(
+    kind: FsEventKind,
+    path: &Path,
+    removed_dir: Option<bool>,
+    filter: &mut IgnoreFilter,
+) -> Option<FsEvent> {
+    let exists = path.symlink_metadata().is_ok();
+    let is_dir = if exists {
+        path.is_dir()
+    } else {
+        removed_dir.unwrap_or(false)
+    };
+    // ディレクトリ自体の更新（中身の追加・削除に伴う mtime 変化）は通知しない
+    if kind == FsEventKind::Modified && is_dir {
+        return None;
+    }
+    // 種類の分からない削除済みパスは、`target/` のようなディレクトリ用の規則にも照らす
+    let ignored = if exists || removed_dir.is_some() {
+        filter.is_ignored(path, is_dir)
+    } else {
+        filter.is_ignored(path, false) || filter.is_ignored(path, true)
+    };
+    if ignored {
+        return None;
+    }
+    Some(fs_event(kind, path, None, is_dir))
+}
+
+fn fs_event(kind: FsEventKind, path: &Path, old_path: Option<&Path>, is_dir: bool) -> FsEvent {
+    FsEvent {
+        kind,
+        path: path.to_string_lossy().to_string(),
+        old_path: old_path.map(|p| p.to_string_lossy().to_string()),
+        is_dir,
+    }
+}
+
+fn rescan_event(root: &Path) -> FsEvent {
+    fs_event(FsEventKind::Rescan, root, None, true)
+}
+
+fn is_ignore_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name == ".gitignore" || name == ".ignore")
+        || path.ends_with(".git/info/exclude")
+}
+
+/// デバウンス期間中のイベントをパス単位でまとめる
+#[derive(Default)]
+struct PendingEvents {
+    events: Vec<Option<FsEvent>>,
+    index: HashMap<String, usize>,
+}
+
+impl PendingEvents {
+    fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    fn push(&mut self, event: FsEvent) {
+        if event.kind == FsEventKind::Rescan {
+            // 再走査で全体を読み直すため、それ以前の個別イベントは不要
+            self.events.clear();
+            self.index.clear();
+            self.insert(event);
+            return;
+        }
+        if let Some(old_path) = event.old_path.clone() {
+            self.push_rename(event, &old_path);
+            return;
+        }
+
+        let Some(&position) = self.index.get(&event.path) else {
+            self.insert(event);
+            return;
+        };
+        let Some(existing) = self.events[position].as_mut() else {
+            return;
+        };
+        match (existing.kind, event.kind) {
+            // 作成直後の更新は作成のまま
+            (FsEventKind::Created, FsEventKind::Modified) => {}
+            // 期間内に作成・削除されたものは何も起きなかったことにする
+            (FsEventKind::Created, FsEventKind::Removed) => self.remove(&event.path),
+            // 削除後に同じパスへ作成されたもの（アトミック保存など）は更新として扱う
+            (FsEventKind::Removed, FsEventKind::Created) => {
+                existing.kind = FsEventKind::Modified;
+                existing.is_dir = event.is_dir;
+            }
+            (FsEventKind::Rescan, _) => {}
+            _ => *existing = event,
+        }
+    }
+
+    fn push_rename(&mut self, mut event: FsEvent, old_path: &str) {
+        if let Some(&position) = self.index.get(old_path) {
+            let previous = self.events[position].as_ref().map(|e| e.kind);
+            self.remove(old_path);
+            // 期間内に作成されてから移動されたものは、移動先の作成として扱う
+            if previous == Some(FsEventKind::Created) {
+                event.kind = FsEventKind::Created;
+                event.old_path = None;
+            }
+        }
+        // inotify は移動先の作成イベントも別途通知するため、移動イベントで置き換える
+        self.remove(&event.path);
+        self.insert(event);
+    }
+
+    fn insert(&mut self, event: FsEvent) {
+        self.index.insert(event.path.clone(), self.events.len());
+        self.events.push(Some(event));
+    }
+
+    fn remove(&mut self, path: &str) {
+        if let Some(position) = self.index.remove(path) {
+            self.events[position] = None;
+        }
+    }
+
+    fn take(&mut self) -> Vec<FsEvent> {
+        self.index.clear();
+        std::mem::take(&mut self.events)
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+/// list_dir（ignore::WalkBuilder）と同じ除外ルールを監視イベントに適用する
+/// 隠しファイル・ディレクトリと、.ignore・リポジトリ内の .gitignore / .git/info/exclude が対象
+struct IgnoreFilter {
+    root: PathBuf,
+    repo_root: Option<PathBuf>,
+    exclude: Option<Gitignore>,
+    ignores: HashMap<PathBuf, Option<Gitignore>>,
+    gitignores: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl IgnoreFilter {
+    fn new(root: &Path) -> Self {
+        // ignore クレートと同様、.gitignore は Git リポジトリ内でのみ有効
+        let repo_root = root
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .map(Path::to_path_buf);
+        let mut filter = Self {
+            root: root.to_path_buf(),
+            repo_root,
+            exclude: None,
+            ignores: HashMap::new(),
+            gitignores: HashMap::new(),
+        };
+        filter.invalidate();
+        filter
+    }
+
+    /// 除外ルールのキャッシュを破棄する
+    fn invalidate(&mut self) {
+        self.ignores.clear();
+        self.gitignores.clear();
+        self.exclude = self.repo_root.as_ref().and_then(|repo_root| {
+            let mut builder = GitignoreBuilder::new(repo_root);
+            builder.add(repo_root.join(".git/info/exclude"));
+            builder.build().ok()
+        });
+    }
+
+    fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return true;
+        };
+        let hidden = relative
+            .components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+        if hidden {
+            return true;
+        }
+
+        // ignore クレートと同様、.ignore は Git リポジトリ外でも有効で、.gitignore より優先される
+        let dirs: Vec<PathBuf> = path.ancestors().skip(1).map(Path::to_path_buf).collect();
+        if let Some(ignored) = matched_in(&mut self.ignores, ".ignore", &dirs, path, is_dir) {
+            return ignored;
+        }
+        let Some(repo_root) = self.repo_root.clone() else {
+            return false;
+        };
+        let repo_dirs: Vec<PathBuf> = dirs
+            .into_iter()
+            .take_while(|dir| dir.starts_with(&repo_root))
+            .collect();
+        if let Some(ignored) =
+            matched_in(&mut self.gitignores, ".gitignore", &repo_dirs, path, is_dir)
+        {
+            return ignored;
+        }
+
+        self.exclude.as_ref().is_some_and(|exclude| {
+            exclude
+                .matched_path_or_any_parents(path, is_dir)
+                .is_ignore()
+        })
+    }
+}
+
+/// dirs（深い順）の file_name の除外ルールに照らす（どれにも当たらなければ None）
+/// 深い階層のファイルほど優先される
+fn matched_in(
+    cache: &mut HashMap<PathBuf, Option<Gitignore>>,
+    file_name: &str,
+    dirs: &[PathBuf],
+    path: &Path,
+    is_dir: bool,
+) -> Option<bool> {
+    for dir in dirs {
+        let rules = cache
+            .entry(dir.clone())
+            .or_insert_with(|| load_ignore_file(&dir.join(file_name)));
+        if let Some(rules) = rules {
+            let matched = rules.matched_path_or_any_parents(path, is_dir);
+            if matched.is_ignore() {
+                return Some(true);
+            }
+            if matched.is_whitelist() {
+                return Some(false);
+            }
+        }
+    }
+    None
+}
+
+fn load_ignore_file(path: &Path) -> Option<Gitignore> {
+    if !path.is_file() {
+        return None;
+    }
+    let (gitignore, _) = Gitignore::new(path);
+    Some(gitignore)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    struct ChannelHandler(Mutex<Sender<Vec<FsEvent>>>);
+
+    impl WatchHandler for ChannelHandler {
+        fn on_events(&self, events: Vec<FsEvent>) {
+            let _ = self.0.lock().unwrap().send(events);
+        }
+
+        fn on_error(&self, _error: CoreError) {}
+    }
+
+    fn start_watcher(root: &Path) -> (ProjectWatcher, Receiver<Vec<FsEvent>>) {
+        let (sender, receiver) = mpsc::channel();
+        let watcher = ProjectWatcher::start(
+            root.to_str().unwrap(),
+            Duration::from_millis(50),
+            ChannelHandler(Mutex::new(sender)),
+        )
+        .unwrap();
+        (watcher, receiver)
+    }
+
+    /// predicate を満たすイベントが届くまで待ち、それまでに届いたイベントを全て返す
+    fn wait_for(
+        receiver: &Receiver<Vec<FsEvent>>,
+        predicate: impl Fn(&FsEvent) -> bool,
+    ) -> Vec<FsEvent> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut received = Vec::new();
+        while Instant::now() < deadline {
+            if let Ok(batch) = receiver.recv_timeout(Duration::from_millis(100)) {
+                let found = batch.iter().any(&predicate);
+                received.extend(batch);
+                if found {
+                    return received;
+                }
+            }
+        }
+        panic!("イベントが届きませんでした: {received:?}");
+    }
+
+    fn event(kind: FsEventKind, path: &str) -> FsEvent {
+        FsEvent {
+            kind,
+            path: path.to_string(),
+            old_path: None,
+            is_dir: false,
+        }
+    }
+
+    #[test]
+    fn watcher_reports_created_and_removed_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (watcher, receiver) = start_watcher(tmp.path());
+        let file = watcher.root().join("main.rs");
+
+        fs::write(&file, "fn main() {}").unwrap();
+        let file_str = file.to_string_lossy().to_string();
+        let events = wait_for(&receiver, |e| e.path == file_str);
+        assert!(events
+            .iter()
+            .any(|e| e.path == file_str && e.kind == FsEventKind::Created));
+
+        fs::remove_file(&file).unwrap();
+        wait_for(&receiver, |e| {
+            e.path == file_str && e.kind == FsEventKind::Removed
+        });
+    }
+
+    #[test]
+    fn watcher_reports_rename_with_old_path() {
+        let tmp = tempfile::tempdir().unwrap();
+        let old = tmp.path().join("old.txt");
+        fs::write(&old, "hello").unwrap();
+        let (watcher, receiver) = start_watcher(tmp.path());
+        let old = watcher.root().join("old.txt");
+        let new = watcher.root().join("new.txt");
+
+        fs::rename(&old, &new).unwrap();
+        let new_str = new.to_string_lossy().to_string();
+        let events = wait_for(&receiver, |e| e.path == new_str);
+        let renamed = events.iter().find(|e| e.path == new_str).unwrap();
+        assert_eq!(renamed.kind, FsEventKind::Renamed);
+        assert_eq!(
+            renamed.old_path.as_deref(),
+            Some(old.to_string_lossy().as_ref())
+        );
+    }
+
+    #[test]
+    fn watcher_skips_gitignored_and_hidden_paths() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir(tmp.path().join(".git")).unwrap();
+        fs::create_dir(tmp.path().join("target")).unwrap();
+        fs::write(tmp.path().join(".gitignore"), "target/\n").unwrap();
+        let (watcher, receiver) = start_watcher(tmp.path());
+
+        fs::write(watcher.root().join("target/out.o"), "").unwrap();
+        fs::write(watcher.root().join(".env"), "").unwrap();
+        fs::write(watcher.root().join("visible.rs"), "").unwrap();
+
+        let events = wait_for(&receiver, |e| e.path.ends_with("visible.rs"));
+        assert!(events.iter().all(|e| !e.path.contains("target")));
+        assert!(events.iter().all(|e| !e.path.ends_with(".env")));
+    }
+
+    struct StopInCallback {
+        watcher: Arc<Mutex<Option<ProjectWatcher>>>,
+        stopped: Mutex<Sender<()>>,
+    }
+
+    impl WatchHandler for StopInCallback {
+        fn on_events(&self, _events: Vec<FsEvent>) {
+            if let Some(mut watcher) = self.watcher.lock().unwrap().take() {
+                watcher.stop();
+                let _ = self.stopped.lock().unwrap().send(());
+            }
+        }
+
+        fn on_error(&self, _error: CoreError) {}
+    }
+
+    #[test]
+    fn stop_from_handler_does_not_join_itself() {
+        let tmp = tempfile::tempdir().unwrap();
+        let slot = Arc::new(Mutex::new(None));
+        let (sender, receiver) = mpsc::channel();
+        let watcher = ProjectWatcher::start(
+            tmp.path().to_str().unwrap(),
+            Duration::from_millis(50),
+            StopInCallback {
+                watcher: slot.clone(),
+                stopped: Mutex::new(sender),
+            },
+        )
+        .unwrap();
+        let file = watcher.root().join("main.rs");
+        *slot.lock().unwrap() = Some(watcher);
+
+        fs::write(&file, "fn main() {}").unwrap();
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+
+    #[test]
+    fn removed_directories_match_directory_rules() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        let mut filter = IgnoreFilter::new(&root);
+
+        // 削除済みで種類を調べられないパスも、ディレクトリ用の規則で除外する
+        for kind in [RemoveKind::Folder, RemoveKind::Any] {
+            let removed = Event::new(EventKind::Remove(kind)).add_path(root.join("target"));
+            assert!(convert_event(&root, &removed, &mut filter).is_empty());
+        }
+
+        let removed = Event::new(EventKind::Remove(RemoveKind::Folder)).add_path(root.join("src"));
+        let events = convert_event(&root, &removed, &mut filter);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, FsEventKind::Removed);
+        assert!(events[0].is_dir);
+    }
+
+    #[test]
+    fn start_nonexistent_root_returns_not_found() {
+        let (sender, _receiver) = mpsc::channel();
+        let result = ProjectWatcher::start(
+            "/nonexistent/blink-watch-root",
+            Duration::from_millis(50),
+            ChannelHandler(Mutex::new(sender)),
+        );
+        assert!(matches!(result, Err(CoreError::NotFound { .. })));
+    }
+
+    #[test]
+    fn pending_events_coalesce_per_path() {
+        let mut pending = PendingEvents::default();
+        pending.push(event(FsEventKind::Created, "/r/a"));
+        pending.push(event(FsEventKind::Modified, "/r/a"));
+        pending.push(event(FsEventKind::Created, "/r/tmp"));
+        pending.push(event(FsEventKind::Removed, "/r/tmp"));
+        pending.push(event(FsEventKind::Removed, "/r/b"));
+        pending.push(event(FsEventKind::Created, "/r/b"));
+
+        let events = pending.take();
+        assert_eq!(
+            events,
+            vec![
+                event(FsEventKind::Created, "/r/a"),
+                event(FsEventKind::Modified, "/r/b"),
+            ]
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn pending_events_merge_rename_halves() {
+        let mut pending = PendingEvents::default();
+        pending.push(event(FsEventKind::Removed, "/r/old"));
+        pending.push(event(FsEventKind::Created, "/r/new"));
+        pending.push(FsEvent {
+            kind: FsEventKind::Renamed,
+            path: "/r/new".into(),
+            old_path: Some("/r/old".into()),
+            is_dir: false,
+        });
+
+        let events = pending.take();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, FsEventKind::Renamed);
+        assert_eq!(events[0].old_path.as_deref(), Some("/r/old"));
+    }
+
+    #[test]
+    fn ignore_filter_respects_nested_gitignore() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("web/dist")).unwrap();
+        fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+        fs::write(root.join("web/.gitignore"), "dist/\n!keep.log\n").unwrap();
+
+        let mut filter = IgnoreFilter::new(&root);
+        assert!(filter.is_ignored(&root.join("debug.log"), false));
+        assert!(filter.is_ignored(&root.join("web/dist/app.js"), false));
+        assert!(!filter.is_ignored(&root.join("web/keep.log"), false));
+        assert!(!filter.is_ignored(&root.join("web/index.ts"), false));
+        assert!(filter.is_ignored(&root.join(".git/index"), false));
+    }
+
+    #[test]
+    fn ignore_filter_reads_ignore_files() {
+        // .ignore は Git リポジトリの外でも効く
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        fs::write(root.join(".ignore"), "build/\n").unwrap();
+        let mut filter = IgnoreFilter::new(&root);
+        assert!(filter.is_ignored(&root.join("build/out.o"), false));
+        assert!(!filter.is_ignored(&root.join("main.rs"), false));
+
+        // 同じリポジトリ内では .gitignore より .ignore が優先される
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "*.tmp\n").unwrap();
+        fs::write(root.join(".ignore"), "build/\n!keep.tmp\n").unwrap();
+        let mut filter = IgnoreFilter::new(&root);
+        assert!(filter.is_ignored(&root.join("scratch.tmp"), false));
+        assert!(!filter.is_ignored(&root.join("keep.tmp"), false));
+        assert!(filter.is_ignored(&root.join("build/out.o"), false));
+    }
+}
@@ -0,0 +1,202 @@
+use core_types::{
+    BlameLine, BlameNavigation, BlameOptions, CoreError, DiffFile, DiffFileStatus, DiffLineOrigin,
+};
+
+use crate::{parse_unified_diff, resolve_repo_root, with_backend};
+
+/// 指定リビジョン時点のファイルを blame する（path はそのリビジョンでのリポジトリ相対パス）
+pub fn blame_revision(
+    root_path: &str,
+    revision: &str,
+    path: &str,
+) -> Result<Vec<BlameLine>, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+    if revision.trim().is_empty() {
+        return Err(CoreError::invalid_argument("revision が空です"));
+    }
+    // git コマンドのオプションとして解釈されないようにする
+    if revision.starts_with('-') {
+        return Err(CoreError::invalid_argument(format!(
+            "リビジョンが不正です: {revision}"
+        )));
+    }
+    if path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("path が空です"));
+    }
+
+    with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        backend.blame(&repo_root, path, Some(revision), &BlameOptions::default())
+    })
+}
+
+/// blame 行の変更コミットの親で blame し直す（整形だけのコミットなどを飛ばして遡る）
+///
+/// 行番号はそのコミットの差分を通して親コミット側の行に対応付ける。
+pub fn blame_parent(root_path: &str, line: &BlameLine) -> Result<BlameNavigation, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+    if line.commit.chars().all(|c| c == '0') {
+        return Err(CoreError::invalid_argument(
+            "未コミットの行は親コミットを辿れません",
+        ));
+    }
+
+    with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        let (record, _) = backend.commit_message(&repo_root, &line.commit)?;
+        let Some(parent) = record.parents.first() else {
+            return Err(CoreError::invalid_argument(format!(
+                "親コミットがありません: {}",
+                line.commit
+            )));
+        };
+
+        // リネームされていれば親コミットでは旧パスになる
+        let changed = backend.changed_files(&repo_root, &record.sha)?;
+        let change = changed.iter().find(|f| f.path == line.original_path);
+        let parent_path = match change {
+            Some(file) if file.status == DiffFileStatus::Added => {
+                return Err(CoreError::invalid_argument(format!(
+                    "このコミットで追加されたファイルです: {}",
+                    line.original_path
+                )));
+            }
+            Some(file) => file
+                .old_path
+                .clone()
+                .unwrap_or_else(|| line.original_path.clone()),
+            None => line.original_path.clone(),
+        };
+
+        let mut paths = vec![line.original_path.as_str()];
+        if parent_path != line.original_path {
+            paths.push(parent_path.as_str());
+        }
+        let diff_text = backend.commit_file_diff(&repo_root, &record.sha, &paths)?;
+        let mapped = match parse_unified_diff(&diff_text).first() {
+            Some(file) => map_line_to_parent(file, line.original_line),
+            None => line.original_line,
+        };
+
+        let lines = backend.blame(
+            &repo_root,
+            &parent_path,
+            Some(parent),
+            &BlameOptions::default(),
+        )?;
+        let last_line = lines.last().map(|l| l.line).unwrap_or(1);
+        Ok(BlameNavigation {
+            revision: parent.clone(),
+            path: parent_path,
+            line: mapped.clamp(1, last_line.max(1)),
+            lines,
+        })
+    })
+}
+
+/// 変更後の行番号を変更前の行番号に対応付ける
+///
+/// 変更された行は、同じ位置にある削除行（置き換え前の行）に対応させる。
+/// 対になる削除行が無い追加行は同じ変更ブロックの最後の削除行、それも無ければ直前の変更前の行の次を指す。
+pub(crate) fn map_line_to_parent(file: &DiffFile, new_line: u32) -> u32 {
+    let mut shift: i64 = 0;
+    for hunk in &file.hunks {
+        let new_end = hunk.new_start + hunk.new_lines;
+        if new_line < hunk.new_start || (hunk.new_lines == 0 && new_line <= hunk.new_start) {
+            break;
+        }
+        if new_line >= new_end {
+            shift += i64::from(hunk.new_lines) - i64::from(hunk.old_lines);
+            continue;
+        }
+
+        // 件数 0 の範囲は「直前の行の後ろ」を指す
+        let mut last_old = if hunk.old_lines == 0 {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let mut index = 0;
+        while index < hunk.lines.len() {
+            let current = &hunk.lines[index];
+            if current.origin == DiffLineOrigin::Context {
+                if current.new_line == Some(new_line) {
+                    return current.old_line.unwrap_or(last_old + 1);
+                }
+                last_old = current.old_line.unwrap_or(last_old);
+                index += 1;
+                continue;
+            }
+
+            let removed_start = index;
+            while index < hunk.lines.len() && hunk.lines[index].origin == DiffLineOrigin::Removed {
+                index += 1;
+            }
+            let added_start = index;
+            while index < hunk.lines.len() && hunk.lines[index].origin == DiffLineOrigin::Added {
+                index += 1;
+            }
+            let removed = &hunk.lines[removed_start..added_start];
+            let added = &hunk.lines[added_start..index];
+
+            if let Some(offset) = added.iter().position(|l| l.new_line == Some(new_line)) {
+                return match removed.get(offset).or(removed.last()) {
+                    Some(old) => old.old_line.unwrap_or(last_old + 1),
+                    None => last_old + 1,
+                };
+            }
+            if let Some(old) = removed.last().and_then(|l| l.old_line) {
+                last_old = old;
+            }
+        }
+        return last_old + 1;
+    }
+    u32::try_from(i64::from(new_line) - shift)
+        .unwrap_or(1)
+        .max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFORMAT: &str = "\
+diff --git a/a.rs b/a.rs
+--- a/a.rs
++++ b/a.rs
+@@ -2,2 +2,3 @@
+-fn f(a,b){
+-a+b }
++fn f(a, b) {
++    a + b
++}
+@@ -10,0 +12,2 @@
++extra one
++extra two
+";
+
+    #[test]
+    fn map_line_to_parent_pairs_replaced_lines() {
+        let file = &parse_unified_diff(REFORMAT)[0];
+        assert_eq!(map_line_to_parent(file, 1), 1);
+        assert_eq!(map_line_to_parent(file, 2), 2);
+        assert_eq!(map_line_to_parent(file, 3), 3);
+        // 対になる削除行が無い追加行は直前の削除行に寄せる
+        assert_eq!(map_line_to_parent(file, 4), 3);
+        // ハンクの後ろは行数の増減ぶんずらす
+        assert_eq!(map_line_to_parent(file, 8), 7);
+    }
+
+    #[test]
+    fn map_line_to_parent_handles_pure_insertions() {
+        let file = &parse_unified_diff(REFORMAT)[0];
+        assert_eq!(map_line_to_parent(file, 12), 11);
+        assert_eq!(map_line_to_parent(file, 13), 11);
+        assert_eq!(map_line_to_parent(file, 14), 11);
+        assert_eq!(map_line_to_parent(file, 15), 12);
+    }
+}
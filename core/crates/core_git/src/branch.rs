@@ -0,0 +1,226 @@
+use core_types::{BranchInfo, CoreError, RefKind};
+
+use crate::{history::parse_commit_header, resolve_repo_root, with_backend, with_backend_mut};
+
+/// `git for-each-ref` の --format（ブランチ情報の後に LOG_FORMAT と同じ並びのコミット情報）
+pub(crate) const BRANCH_FORMAT: &str = "--format=%(refname)%1f%(HEAD)%1f%(symref)%1f%(upstream:short)%1f%(upstream:track,nobracket)%1f%(objectname)%1f%(parent)%1f%(authorname)%1f%(authoremail:trim)%1f%(authordate:raw)%1f%(committername)%1f%(committeremail:trim)%1f%(committerdate:raw)%1f%(contents:subject)%1f%(contents:body)%1e";
+
+/// ローカルブランチとリモート追跡ブランチを返す（ローカルが先、それぞれ名前順）
+pub fn list_branches(root_path: &str) -> Result<Vec<BranchInfo>, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+
+    with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        backend.branches(&repo_root)
+    })
+}
+
+/// start_point（省略時は HEAD）からブランチを作る
+///
+/// start_point がリモート追跡ブランチなら上流に設定する（`git branch` の既定動作）。
+pub fn create_branch(
+    root_path: &str,
+    name: &str,
+    start_point: Option<&str>,
+) -> Result<(), CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+    validate_branch_name(name)?;
+    if start_point.is_some_and(|start| start.trim().is_empty()) {
+        return Err(CoreError::invalid_argument("start_point が空です"));
+    }
+
+    let repo_root = with_backend(|backend| resolve_repo_root(backend, root_path))?;
+    with_backend_mut(|backend| backend.create_branch(&repo_root, name, start_point))
+}
+
+/// ローカルブランチに切り替える
+///
+/// 切り替え先と食い違うローカルの変更や未追跡ファイルがあれば、何も変更せず
+/// CheckoutConflict を返す。食い違わない変更はそのまま持ち越す。
+pub fn switch_branch(root_path: &str, name: &str) -> Result<(), CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+    if name.trim().is_empty() {
+        return Err(CoreError::invalid_argument("name が空です"));
+    }
+
+    let repo_root = with_backend(|backend| resolve_repo_root(backend, root_path))?;
+    with_backend_mut(|backend| backend.switch_branch(&repo_root, name))
+}
+
+/// ローカルブランチの名前を変える（チェックアウト中なら HEAD も追従する）
+pub fn rename_branch(root_path: &str, old_name: &str, new_name: &str) -> Result<(), CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+    if old_name.trim().is_empty() {
+        return Err(CoreError::invalid_argument("old_name が空です"));
+    }
+    validate_branch_name(new_name)?;
+
+    let repo_root = with_backend(|backend| resolve_repo_root(backend, root_path))?;
+    with_backend_mut(|backend| backend.rename_branch(&repo_root, old_name, new_name))
+}
+
+/// ローカルブランチを削除する
+///
+/// force でなければ、上流（無ければ HEAD）にマージ済みのブランチだけを消す。
+/// チェックアウト中のブランチは消せない。
+pub fn delete_branch(root_path: &str, name: &str, force: bool) -> Result<(), CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+    if name.trim().is_empty() {
+        return Err(CoreError::invalid_argument("name が空です"));
+    }
+
+    let repo_root = with_backend(|backend| resolve_repo_root(backend, root_path))?;
+    with_backend_mut(|backend| backend.delete_branch(&repo_root, name, force))
+}
+
+/// git と libgit2 の両方が受け付けないブランチ名（書式そのものは各バックエンドで検証する）
+fn validate_branch_name(name: &str) -> Result<(), CoreError> {
+    if name.trim().is_empty() {
+        return Err(CoreError::invalid_argument("name が空です"));
+    }
+    if name.starts_with('-') || name == "HEAD" {
+        return Err(CoreError::invalid_argument(format!(
+            "ブランチ名が不正です: {name}"
+        )));
+    }
+    Ok(())
+}
+
+/// `%(upstream:track,nobracket)` を ahead/behind にする（"gone" は上流の参照が無い）
+fn parse_track(track: &str) -> (Option<u32>, Option<u32>) {
+    if track == "gone" {
+        return (None, None);
+    }
+    let mut ahead = 0;
+    let mut behind = 0;
+    for part in track.split(", ") {
+        match part.split_once(' ') {
+            Some(("ahead", count)) => ahead = count.parse().unwrap_or(0),
+            Some(("behind", count)) => behind = count.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    (Some(ahead), Some(behind))
+}
+
+/// `git for-each-ref <BRANCH_FORMAT> refs/heads refs/remotes` の出力をパースする
+/// （origin/HEAD などのシンボリック参照は除く）
+pub(crate) fn parse_branch_list(output: &str) -> Result<Vec<BranchInfo>, CoreError> {
+    let mut branches = Vec::new();
+    for record in output.split('\x1e') {
+        let record = record.trim_start_matches('\n');
+        if record.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = record.splitn(6, '\x1f').collect();
+        let [refname, head, symref, upstream, track, commit] = fields.as_slice() else {
+            return Err(CoreError::internal(format!(
+                "git for-each-ref の出力を解釈できません: {record}"
+            )));
+        };
+        if !symref.is_empty() {
+            continue;
+        }
+
+        let (kind, name) = if let Some(name) = refname.strip_prefix("refs/heads/") {
+            (RefKind::LocalBranch, name)
+        } else if let Some(name) = refname.strip_prefix("refs/remotes/") {
+            (RefKind::RemoteBranch, name)
+        } else {
+            continue;
+        };
+        let (ahead, behind) = if upstream.is_empty() {
+            (None, None)
+        } else {
+            parse_track(track)
+        };
+        branches.push(BranchInfo {
+            name: name.to_string(),
+            kind,
+            is_head: *head == "*",
+            upstream: (!upstream.is_empty()).then(|| upstream.to_string()),
+            ahead,
+            behind,
+            last_commit: parse_commit_header(commit)?,
+        });
+    }
+    Ok(branches)
+}
+
+/// `git switch` の "would be overwritten by checkout" エラーに列挙されたパス
+pub(crate) fn parse_overwritten_paths(stderr: &str) -> Vec<String> {
+    let mut paths: Vec<String> = stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix('\t'))
+        .map(|path| path.trim().to_string())
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(refname: &str, head: &str, symref: &str, upstream: &str, track: &str) -> String {
+        format!(
+            "{refname}\x1f{head}\x1f{symref}\x1f{upstream}\x1f{track}\x1f{sha}\x1f\x1fAlice\x1falice@example.com\x1f1700000000 +0900\x1fAlice\x1falice@example.com\x1f1700000000 +0900\x1fsubject\x1f\x1e\n",
+            sha = "a".repeat(40)
+        )
+    }
+
+    #[test]
+    fn parse_branch_list_reads_tracking_and_skips_symrefs() {
+        let output = [
+            record("refs/heads/feature", "", "", "origin/feature", "gone"),
+            record(
+                "refs/heads/main",
+                "*",
+                "",
+                "origin/main",
+                "ahead 2, behind 1",
+            ),
+            record("refs/heads/synced", "", "", "origin/synced", ""),
+            record(
+                "refs/remotes/origin/HEAD",
+                "",
+                "refs/remotes/origin/main",
+                "",
+                "",
+            ),
+            record("refs/remotes/origin/main", "", "", "", ""),
+        ]
+        .concat();
+
+        let branches = parse_branch_list(&output).unwrap();
+        let names: Vec<&str> = branches.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["feature", "main", "synced", "origin/main"]);
+
+        assert_eq!((branches[0].ahead, branches[0].behind), (None, None));
+        assert!(branches[1].is_head);
+        assert_eq!((branches[1].ahead, branches[1].behind), (Some(2), Some(1)));
+        assert_eq!((branches[2].ahead, branches[2].behind), (Some(0), Some(0)));
+        assert_eq!(branches[3].kind, RefKind::RemoteBranch);
+        assert_eq!(branches[3].upstream, None);
+        assert_eq!(branches[3].last_commit.subject, "subject");
+    }
+
+    #[test]
+    fn parse_overwritten_paths_collects_both_sections() {
+        let stderr = "error: Your local changes to the following files would be overwritten by checkout:\n\tsrc/b.swift\n\tsrc/a.swift\nPlease commit your changes or stash them before you switch branches.\nerror: The following untracked working tree files would be overwritten by checkout:\n\tnew.swift\nAborting\n";
+        assert_eq!(
+            parse_overwritten_paths(stderr),
+            vec!["new.swift", "src/a.swift", "src/b.swift"]
+        );
+    }
+}
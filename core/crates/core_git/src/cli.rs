@@ -0,0 +1,1126 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+#[cfg(target_os = "macos")]
+use std::sync::OnceLock;
+
+use core_types::{
+    BlameLine, BlameMoveDetection, BlameOptions, BranchInfo, ChangedFile, CommitAuthor,
+    CommitRecord, CoreError, FileHistoryEntry, GitRepositoryStatus, GitSignature, RefDecoration,
+    StashApplyResult, StashEntry,
+};
+
+use crate::{
+    branch::{parse_branch_list, parse_overwritten_paths, BRANCH_FORMAT},
+    commit_not_found,
+    details::{parse_commit_message, parse_raw_numstat},
+    graph::{detached_head, parse_commit_log, ref_decoration},
+    history::{parse_history_log, signature, LOG_FORMAT},
+    ignore_revs_file, parse_porcelain, parse_porcelain_entries,
+    stash::{parse_stash_conflict_paths, parse_stash_list, STASH_FORMAT},
+    status::{absolute_status_path, parse_status_v2},
+    BaseVersions, GitBackend,
+};
+
+#[cfg(target_os = "macos")]
+static GIT_BINARY_PATH: OnceLock<String> = OnceLock::new();
+
+#[cfg(target_os = "macos")]
+fn resolve_git_binary_path() -> String {
+    // App Sandbox では /usr/bin/git が xcrun 経由にフォールバックして失敗するケースがあるため、
+    // xcrun を介さない実体 git バイナリを優先する。
+    let candidates = [
+        "/Library/Developer/CommandLineTools/usr/bin/git",
+        "/Applications/Xcode.app/Contents/Developer/usr/bin/git",
+        "/usr/bin/git",
+    ];
+
+    candidates
+        .iter()
+        .find(|path| fs::metadata(path).map(|m| m.is_file()).unwrap_or(false))
+        .unwrap_or(&"/usr/bin/git")
+        .to_string()
+}
+
+pub(crate) fn git_command() -> Command {
+    #[cfg(target_os = "macos")]
+    {
+        let mut command = Command::new(
+            GIT_BINARY_PATH
+                .get_or_init(resolve_git_binary_path)
+                .as_str(),
+        );
+        // Xcode 実行環境の環境変数を引き継ぐと xcrun 解決に寄ることがあるため除去する。
+        command.env_remove("DEVELOPER_DIR");
+        command.env_remove("SDKROOT");
+        command
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        Command::new("git")
+    }
+}
+
+/// git の起動自体に失敗した場合のエラー（バイナリが見つからない場合は GitNotFound）
+pub(crate) fn git_spawn_error(error: std::io::Error) -> CoreError {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => CoreError::GitNotFound {
+            reason: error.to_string(),
+        },
+        _ => CoreError::GitFailed {
+            command: "git".to_string(),
+            stderr: error.to_string(),
+            exit_code: None,
+        },
+    }
+}
+
+/// 終了ステータスが失敗だった git コマンドの出力を GitFailed に変換する
+pub(crate) fn git_failed(command: &str, output: &Output) -> CoreError {
+    CoreError::GitFailed {
+        command: command.to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr)
+            .trim_end()
+            .to_string(),
+        exit_code: output.status.code(),
+    }
+}
+
+/// 標準入力に input を渡して git コマンドを実行する
+fn output_with_stdin(command: &mut Command, input: &[u8]) -> Result<Output, CoreError> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(git_spawn_error)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input).map_err(git_spawn_error)?;
+    }
+    child.wait_with_output().map_err(git_spawn_error)
+}
+
+/// `git cat-file blob <spec>` の内容（spec が解決できなければ None）
+fn read_blob(repo_root: &Path, spec: &str) -> Result<Option<Vec<u8>>, CoreError> {
+    let verify_output = git_command()
+        .current_dir(repo_root)
+        .args(["rev-parse", "--verify", "--quiet", spec])
+        .output()
+        .map_err(git_spawn_error)?;
+    if !verify_output.status.success() {
+        return Ok(None);
+    }
+
+    let output = git_command()
+        .current_dir(repo_root)
+        .args(["cat-file", "blob", spec])
+        .output()
+        .map_err(git_spawn_error)?;
+    if !output.status.success() {
+        return Err(git_failed("git cat-file", &output));
+    }
+    Ok(Some(output.stdout))
+}
+
+/// `git rev-parse --verify` で解決できる場合だけ完全なハッシュ・参照名を返す
+fn verify_ref(repo_root: &Path, args: &[&str]) -> Result<Option<String>, CoreError> {
+    let output = git_command()
+        .current_dir(repo_root)
+        .args(args)
+        .output()
+        .map_err(git_spawn_error)?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok((!value.is_empty()).then_some(value))
+}
+
+/// コミットを完全なハッシュに解決する（タグなどはコミットまで辿る）
+fn resolve_commit(repo_root: &Path, commit: &str, command: &str) -> Result<String, CoreError> {
+    verify_ref(
+        repo_root,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{commit}^{{commit}}"),
+        ],
+    )?
+    .ok_or_else(|| commit_not_found(command, commit))
+}
+
+/// 第一親との比較に使う diff-tree の引数（ルートコミットは --root）
+fn first_parent_range(repo_root: &Path, sha: &str) -> Result<Vec<String>, CoreError> {
+    let parent = verify_ref(
+        repo_root,
+        &["rev-parse", "--verify", "--quiet", &format!("{sha}^1")],
+    )?;
+    Ok(match parent {
+        Some(parent) => vec![parent, sha.to_string()],
+        None => vec!["--root".to_string(), sha.to_string()],
+    })
+}
+
+/// blame 行のコミットごとの、.mailmap 適用前の作者名（キーは完全なハッシュ）
+fn raw_authors<'a>(
+    repo_root: &Path,
+    commits: impl Iterator<Item = &'a str>,
+) -> Result<HashMap<String, String>, CoreError> {
+    let mut commits: Vec<&str> = commits
+        .filter(|commit| !commit.chars().all(|c| c == '0'))
+        .collect();
+    commits.sort_unstable();
+    commits.dedup();
+    if commits.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let output = git_command()
+        .current_dir(repo_root)
+        .args(["show", "-s", "--no-walk=unsorted", "--format=%H%x1f%an"])
+        .args(&commits)
+        .output()
+        .map_err(git_spawn_error)?;
+    if !output.status.success() {
+        return Err(git_failed("git show", &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once('\x1f'))
+        .map(|(sha, author)| (sha.to_string(), author.to_string()))
+        .collect())
+}
+
+/// git コマンドを起動するバックエンド（ネイティブ実装が扱えない場合のフォールバック）
+pub struct CliBackend;
+
+impl GitBackend for CliBackend {
+    fn name(&self) -> &'static str {
+        "cli"
+    }
+
+    fn repo_root(&self, search_dir: &Path) -> Result<PathBuf, CoreError> {
+        let repo_root_output = git_command()
+            .arg("-C")
+            .arg(search_dir)
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !repo_root_output.status.success() {
+            let stderr = String::from_utf8_lossy(&repo_root_output.stderr);
+            if stderr.contains("not a git repository") {
+                return Err(CoreError::NotAGitRepo {
+                    path: search_dir.to_string_lossy().to_string(),
+                });
+            }
+            return Err(git_failed("git rev-parse", &repo_root_output));
+        }
+
+        let repo_root_raw = String::from_utf8_lossy(&repo_root_output.stdout);
+        let repo_root_raw = repo_root_raw.trim();
+        fs::canonicalize(repo_root_raw).map_err(|e| CoreError::from_io(repo_root_raw, &e))
+    }
+
+    fn blame(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+        revision: Option<&str>,
+        options: &BlameOptions,
+    ) -> Result<Vec<BlameLine>, CoreError> {
+        let revision = revision
+            .map(|revision| resolve_commit(repo_root, revision, "git blame"))
+            .transpose()?;
+        let mut command = git_command();
+        command
+            .current_dir(repo_root)
+            .args(["blame", "--line-porcelain"]);
+        if options.ignore_whitespace {
+            command.arg("-w");
+        }
+        command.args(
+            match options.move_detection.unwrap_or(BlameMoveDetection::Off) {
+                BlameMoveDetection::Off => &[][..],
+                BlameMoveDetection::WithinFile => &["-M"],
+                BlameMoveDetection::ModifiedFiles => &["-C"],
+                BlameMoveDetection::FilesInCreatingCommit => &["-C", "-C"],
+                BlameMoveDetection::AnyCommit => &["-C", "-C", "-C"],
+            },
+        );
+        if !options.use_ignore_revs {
+            command.arg("--no-ignore-revs-file");
+        } else if let Some(ignore_revs) = ignore_revs_file(repo_root) {
+            command.arg("--ignore-revs-file").arg(ignore_revs);
+        }
+        let output = command
+            .args(revision)
+            .args(["--", relative_path])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git blame", &output));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if options.use_mailmap {
+            return parse_porcelain(&stdout);
+        }
+        // git blame は常に .mailmap を適用するので、無効時はコミットの作者名に戻す
+        let entries = parse_porcelain_entries(&stdout)?;
+        let authors = raw_authors(repo_root, entries.iter().map(|(commit, _)| *commit))?;
+        Ok(entries
+            .into_iter()
+            .map(|(commit, mut line)| {
+                if let Some(author) = authors.get(commit) {
+                    line.author = author.clone();
+                }
+                line
+            })
+            .collect())
+    }
+
+    fn commit_diff(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+        commit: &str,
+    ) -> Result<String, CoreError> {
+        let sha = resolve_commit(repo_root, commit, "git show")?;
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["show", "--no-color", "--format=", &sha, "--", relative_path])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git show", &output));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn working_diff(&self, repo_root: &Path, relative_path: &str) -> Result<String, CoreError> {
+        let unstaged_output = git_command()
+            .current_dir(repo_root)
+            .args(["diff", "--no-color", "--", relative_path])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !unstaged_output.status.success() {
+            return Err(git_failed("git diff", &unstaged_output));
+        }
+
+        let staged_output = git_command()
+            .current_dir(repo_root)
+            .args(["diff", "--no-color", "--cached", "--", relative_path])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !staged_output.status.success() {
+            return Err(git_failed("git diff --cached", &staged_output));
+        }
+
+        let mut sections: Vec<String> = Vec::new();
+        let unstaged_diff = String::from_utf8_lossy(&unstaged_output.stdout).to_string();
+        if !unstaged_diff.trim().is_empty() {
+            sections.push(unstaged_diff);
+        }
+
+        let staged_diff = String::from_utf8_lossy(&staged_output.stdout).to_string();
+        if !staged_diff.trim().is_empty() {
+            sections.push(staged_diff);
+        }
+
+        if sections.is_empty() {
+            let untracked_output = git_command()
+                .current_dir(repo_root)
+                .args([
+                    "ls-files",
+                    "--others",
+                    "--exclude-standard",
+                    "--",
+                    relative_path,
+                ])
+                .output()
+                .map_err(git_spawn_error)?;
+
+            if !untracked_output.status.success() {
+                return Err(git_failed("git ls-files", &untracked_output));
+            }
+
+            if !String::from_utf8_lossy(&untracked_output.stdout)
+                .trim()
+                .is_empty()
+            {
+                // リポジトリ相対パスで /dev/null と比較し、ヘッダーを通常の diff と揃える
+                let untracked_diff_output = git_command()
+                    .current_dir(repo_root)
+                    .args([
+                        "diff",
+                        "--no-color",
+                        "--no-index",
+                        "--",
+                        "/dev/null",
+                        relative_path,
+                    ])
+                    .output()
+                    .map_err(git_spawn_error)?;
+
+                let status_code = untracked_diff_output.status.code();
+                if !(untracked_diff_output.status.success() || status_code == Some(1)) {
+                    return Err(git_failed("git diff --no-index", &untracked_diff_output));
+                }
+
+                let untracked_diff =
+                    String::from_utf8_lossy(&untracked_diff_output.stdout).to_string();
+                if !untracked_diff.trim().is_empty() {
+                    sections.push(untracked_diff);
+                }
+            }
+        }
+
+        Ok(sections.join("\n"))
+    }
+
+    fn status(&self, repo_root: &Path) -> Result<GitRepositoryStatus, CoreError> {
+        let output = git_command()
+            .current_dir(repo_root)
+            .args([
+                "status",
+                "--porcelain=v2",
+                "-z",
+                "--branch",
+                "--untracked-files=all",
+            ])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git status", &output));
+        }
+
+        parse_status_v2(&output.stdout, repo_root)
+    }
+
+    fn current_branch(&self, repo_root: &Path) -> Result<String, CoreError> {
+        let branch_output = git_command()
+            .current_dir(repo_root)
+            .args(["branch", "--show-current"])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !branch_output.status.success() {
+            return Err(git_failed("git branch --show-current", &branch_output));
+        }
+
+        let branch_name = String::from_utf8_lossy(&branch_output.stdout)
+            .trim()
+            .to_string();
+        if !branch_name.is_empty() {
+            return Ok(branch_name);
+        }
+
+        let head_output = git_command()
+            .current_dir(repo_root)
+            .args(["rev-parse", "--short", "HEAD"])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !head_output.status.success() {
+            return Err(git_failed("git rev-parse --short HEAD", &head_output));
+        }
+
+        let short_sha = String::from_utf8_lossy(&head_output.stdout)
+            .trim()
+            .to_string();
+        if short_sha.is_empty() {
+            return Err(CoreError::internal("ブランチ名を取得できませんでした"));
+        }
+        Ok(format!("detached@{short_sha}"))
+    }
+
+    fn file_history(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<FileHistoryEntry>, CoreError> {
+        let output = git_command()
+            .current_dir(repo_root)
+            .args([
+                "log",
+                "--follow",
+                "--name-status",
+                "-z",
+                "--date=raw",
+                LOG_FORMAT,
+                &format!("--skip={offset}"),
+                &format!("--max-count={limit}"),
+                "--",
+                relative_path,
+            ])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            // コミットがまだ無いリポジトリは履歴なし
+            if String::from_utf8_lossy(&output.stderr).contains("does not have any commits") {
+                return Ok(Vec::new());
+            }
+            return Err(git_failed("git log", &output));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        parse_history_log(&stdout, relative_path)
+    }
+
+    fn commits(&self, repo_root: &Path, max_count: usize) -> Result<Vec<CommitRecord>, CoreError> {
+        let max_count = format!("--max-count={max_count}");
+        let mut args = vec![
+            "log",
+            "--topo-order",
+            "--date=raw",
+            LOG_FORMAT,
+            max_count.as_str(),
+            "--branches",
+            "--remotes",
+            "--tags",
+        ];
+        // コミット前の HEAD を渡すと失敗するので、解決できるときだけ起点に加える
+        if verify_ref(repo_root, &["rev-parse", "--verify", "--quiet", "HEAD"])?.is_some() {
+            args.push("HEAD");
+        }
+
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(&args)
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git log", &output));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        parse_commit_log(&stdout)
+    }
+
+    fn refs(&self, repo_root: &Path) -> Result<Vec<(String, RefDecoration)>, CoreError> {
+        let output = git_command()
+            .current_dir(repo_root)
+            .args([
+                "for-each-ref",
+                "--format=%(objectname)%00%(*objectname)%00%(refname)",
+                "refs/heads",
+                "refs/remotes",
+                "refs/tags",
+            ])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git for-each-ref", &output));
+        }
+
+        let head_ref = verify_ref(repo_root, &["symbolic-ref", "--quiet", "HEAD"])?;
+        let mut refs = Vec::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let mut fields = line.split('\0');
+            let (Some(object), Some(peeled), Some(refname)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            // 注釈付きタグはタグオブジェクトではなく指しているコミットに付ける
+            let sha = if peeled.is_empty() { object } else { peeled };
+            if let Some(decoration) = ref_decoration(refname, head_ref.as_deref()) {
+                refs.push((sha.to_string(), decoration));
+            }
+        }
+
+        if head_ref.is_none() {
+            if let Some(sha) = verify_ref(repo_root, &["rev-parse", "--verify", "--quiet", "HEAD"])?
+            {
+                refs.push((sha, detached_head()));
+            }
+        }
+        Ok(refs)
+    }
+
+    fn commit_message(
+        &self,
+        repo_root: &Path,
+        commit: &str,
+    ) -> Result<(CommitRecord, String), CoreError> {
+        let sha = resolve_commit(repo_root, commit, "git show")?;
+        let format = format!("{LOG_FORMAT}%B");
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["show", "-s", "--date=raw", &format, &sha])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git show", &output));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        parse_commit_message(&stdout)
+    }
+
+    fn changed_files(&self, repo_root: &Path, commit: &str) -> Result<Vec<ChangedFile>, CoreError> {
+        let sha = resolve_commit(repo_root, commit, "git diff-tree")?;
+        let output = git_command()
+            .current_dir(repo_root)
+            .args([
+                "diff-tree",
+                "-r",
+                "-M",
+                "-z",
+                "--raw",
+                "--numstat",
+                "--no-commit-id",
+            ])
+            .args(first_parent_range(repo_root, &sha)?)
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git diff-tree", &output));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        parse_raw_numstat(&stdout)
+    }
+
+    fn commit_file_diff(
+        &self,
+        repo_root: &Path,
+        commit: &str,
+        paths: &[&str],
+    ) -> Result<String, CoreError> {
+        let sha = resolve_commit(repo_root, commit, "git diff-tree")?;
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["diff-tree", "-p", "-M", "--no-color", "--no-commit-id"])
+            .args(first_parent_range(repo_root, &sha)?)
+            .arg("--")
+            .args(paths)
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git diff-tree", &output));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn base_versions(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+    ) -> Result<BaseVersions, CoreError> {
+        Ok(BaseVersions {
+            head: read_blob(repo_root, &format!("HEAD:{relative_path}"))?,
+            index: read_blob(repo_root, &format!(":{relative_path}"))?,
+        })
+    }
+
+    fn branches(&self, repo_root: &Path) -> Result<Vec<BranchInfo>, CoreError> {
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["for-each-ref", BRANCH_FORMAT, "refs/heads", "refs/remotes"])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git for-each-ref", &output));
+        }
+        parse_branch_list(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    fn create_branch(
+        &self,
+        repo_root: &Path,
+        name: &str,
+        start_point: Option<&str>,
+    ) -> Result<(), CoreError> {
+        check_branch_name(repo_root, name)?;
+        if local_branch(repo_root, name)?.is_some() {
+            return Err(CoreError::BranchExists {
+                name: name.to_string(),
+            });
+        }
+        let start_point = start_point.unwrap_or("HEAD");
+        resolve_commit(repo_root, start_point, "git branch")?;
+
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["branch", "--", name, start_point])
+            .output()
+            .map_err(git_spawn_error)?;
+        if !output.status.success() {
+            return Err(git_failed("git branch", &output));
+        }
+        Ok(())
+    }
+
+    fn switch_branch(&self, repo_root: &Path, name: &str) -> Result<(), CoreError> {
+        if local_branch(repo_root, name)?.is_none() {
+            return Err(CoreError::BranchNotFound {
+                name: name.to_string(),
+            });
+        }
+
+        // 上書きされるパスの一覧をエラーメッセージから読むため英語表示に固定する
+        let output = git_command()
+            .current_dir(repo_root)
+            .env("LC_ALL", "C")
+            .args(["switch", "--no-guess", name])
+            .output()
+            .map_err(git_spawn_error)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("would be overwritten by checkout") {
+                return Err(CoreError::CheckoutConflict {
+                    branch: name.to_string(),
+                    paths: parse_overwritten_paths(&stderr),
+                });
+            }
+            return Err(git_failed("git switch", &output));
+        }
+        Ok(())
+    }
+
+    fn rename_branch(
+        &self,
+        repo_root: &Path,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<(), CoreError> {
+        if local_branch(repo_root, old_name)?.is_none() {
+            return Err(CoreError::BranchNotFound {
+                name: old_name.to_string(),
+            });
+        }
+        check_branch_name(repo_root, new_name)?;
+        if local_branch(repo_root, new_name)?.is_some() {
+            return Err(CoreError::BranchExists {
+                name: new_name.to_string(),
+            });
+        }
+
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["branch", "-m", "--", old_name, new_name])
+            .output()
+            .map_err(git_spawn_error)?;
+        if !output.status.success() {
+            return Err(git_failed("git branch -m", &output));
+        }
+        Ok(())
+    }
+
+    fn delete_branch(&self, repo_root: &Path, name: &str, force: bool) -> Result<(), CoreError> {
+        let Some(tip) = local_branch(repo_root, name)? else {
+            return Err(CoreError::BranchNotFound {
+                name: name.to_string(),
+            });
+        };
+        let head = verify_ref(repo_root, &["symbolic-ref", "--quiet", "HEAD"])?;
+        if head.as_deref() == Some(format!("refs/heads/{name}").as_str()) {
+            return Err(CoreError::invalid_argument(format!(
+                "チェックアウト中のブランチは削除できません: {name}"
+            )));
+        }
+
+        if !force {
+            // `git branch -d` と同じく、上流があれば上流、無ければ HEAD に含まれているか
+            let upstream = format!("refs/heads/{name}@{{upstream}}");
+            let base =
+                match verify_ref(repo_root, &["rev-parse", "--verify", "--quiet", &upstream])? {
+                    Some(base) => Some(base),
+                    None => verify_ref(repo_root, &["rev-parse", "--verify", "--quiet", "HEAD"])?,
+                };
+            let merged = match base {
+                Some(base) => git_command()
+                    .current_dir(repo_root)
+                    .args(["merge-base", "--is-ancestor", &tip, &base])
+                    .status()
+                    .map_err(git_spawn_error)?
+                    .success(),
+                None => false,
+            };
+            if !merged {
+                return Err(CoreError::BranchNotMerged {
+                    name: name.to_string(),
+                });
+            }
+        }
+
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["branch", "-D", "--", name])
+            .output()
+            .map_err(git_spawn_error)?;
+        if !output.status.success() {
+            return Err(git_failed("git branch -D", &output));
+        }
+        Ok(())
+    }
+
+    fn index_diff(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+        cached: bool,
+    ) -> Result<Vec<u8>, CoreError> {
+        let mut command = git_command();
+        command.current_dir(repo_root).args([
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "--no-renames",
+        ]);
+        if cached {
+            command.arg("--cached");
+        }
+        let output = command
+            .args(["--", relative_path])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git diff", &output));
+        }
+        Ok(output.stdout)
+    }
+
+    fn apply_patch(&self, repo_root: &Path, patch: &[u8], to_index: bool) -> Result<(), CoreError> {
+        let mut command = git_command();
+        command
+            .current_dir(repo_root)
+            .args(["apply", "--whitespace=nowarn"]);
+        if to_index {
+            command.arg("--cached");
+        }
+        let output = output_with_stdin(command.arg("-"), patch)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git apply", &output));
+        }
+        Ok(())
+    }
+
+    fn stage_file(&self, repo_root: &Path, relative_path: &str) -> Result<(), CoreError> {
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["add", "-A", "--", relative_path])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git add", &output));
+        }
+        Ok(())
+    }
+
+    fn unstage_file(&self, repo_root: &Path, relative_path: &str) -> Result<(), CoreError> {
+        // コミットが1つもなければ reset できないので、インデックスから外すだけにする
+        let has_head = verify_ref(repo_root, &["rev-parse", "--verify", "--quiet", "HEAD"])?;
+        let (command_name, args): (&str, &[&str]) = if has_head.is_some() {
+            ("git reset", &["reset", "-q", "--"])
+        } else {
+            (
+                "git rm",
+                &["rm", "--cached", "-q", "--ignore-unmatch", "--"],
+            )
+        };
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(args)
+            .arg(relative_path)
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed(command_name, &output));
+        }
+        Ok(())
+    }
+
+    fn intent_to_add(&self, repo_root: &Path, relative_path: &str) -> Result<(), CoreError> {
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["add", "-N", "--", relative_path])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git add -N", &output));
+        }
+        Ok(())
+    }
+
+    fn config_path(&self, repo_root: &Path, key: &str) -> Result<Option<PathBuf>, CoreError> {
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["config", "--path", "--get", key])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        // 終了コード 1 は未設定
+        if output.status.code() == Some(1) {
+            return Ok(None);
+        }
+        if !output.status.success() {
+            return Err(git_failed("git config", &output));
+        }
+        let value = String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string();
+        Ok((!value.is_empty()).then(|| repo_root.join(value)))
+    }
+
+    fn git_path(&self, repo_root: &Path, name: &str) -> Result<PathBuf, CoreError> {
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["rev-parse", "--git-path", name])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git rev-parse", &output));
+        }
+        // 相対パスは作業ディレクトリ（リポジトリ直下）からの位置
+        Ok(repo_root.join(String::from_utf8_lossy(&output.stdout).trim_end()))
+    }
+
+    fn committer(&self, repo_root: &Path) -> Result<GitSignature, CoreError> {
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["var", "GIT_COMMITTER_IDENT"])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git var", &output));
+        }
+        // "Name <email> <UNIX 時刻> <+hhmm>"
+        let ident = String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string();
+        let parsed = ident.rsplit_once('>').and_then(|(person, date)| {
+            let (name, email) = person.rsplit_once('<')?;
+            Some((name.trim(), email, date))
+        });
+        let Some((name, email, date)) = parsed else {
+            return Err(CoreError::internal(format!(
+                "git var の出力を解釈できません: {ident}"
+            )));
+        };
+        signature(name, email, date)
+    }
+
+    fn create_commit(
+        &self,
+        repo_root: &Path,
+        message: &str,
+        amend: bool,
+        author: Option<&CommitAuthor>,
+    ) -> Result<String, CoreError> {
+        // フックは呼び出し側で実行済みなので、どのフックも動かさない
+        let mut command = git_command();
+        command.current_dir(repo_root).args([
+            "-c",
+            "core.hooksPath=/dev/null",
+            "commit",
+            "--quiet",
+            "--no-verify",
+            "--cleanup=verbatim",
+            "--file=-",
+        ]);
+        if amend {
+            command.arg("--amend");
+        }
+        if let Some(author) = author {
+            command.arg(format!("--author={} <{}>", author.name, author.email));
+        }
+        let output = output_with_stdin(&mut command, message.as_bytes())?;
+
+        if !output.status.success() {
+            return Err(git_failed("git commit", &output));
+        }
+        verify_ref(repo_root, &["rev-parse", "--verify", "--quiet", "HEAD"])?
+            .ok_or_else(|| CoreError::internal("コミット後の HEAD を解決できません"))
+    }
+
+    fn stashes(&self, repo_root: &Path) -> Result<Vec<StashEntry>, CoreError> {
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["stash", "list", "--date=raw", STASH_FORMAT])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git stash list", &output));
+        }
+        parse_stash_list(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    fn create_stash(
+        &self,
+        repo_root: &Path,
+        message: Option<&str>,
+        include_untracked: bool,
+        staged_only: bool,
+    ) -> Result<bool, CoreError> {
+        // 変更が無いときも終了コードは 0 なので、refs/stash が進んだかで判断する
+        let stash_ref = ["rev-parse", "--verify", "--quiet", "refs/stash"];
+        let before = verify_ref(repo_root, &stash_ref)?;
+
+        let mut command = git_command();
+        command
+            .current_dir(repo_root)
+            .args(["stash", "push", "--quiet"]);
+        if include_untracked {
+            command.arg("--include-untracked");
+        }
+        if staged_only {
+            command.arg("--staged");
+        }
+        if let Some(message) = message {
+            command.arg(format!("--message={message}"));
+        }
+        let output = command.output().map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git stash push", &output));
+        }
+        Ok(verify_ref(repo_root, &stash_ref)? != before)
+    }
+
+    fn apply_stash(
+        &self,
+        repo_root: &Path,
+        index: usize,
+        pop: bool,
+    ) -> Result<StashApplyResult, CoreError> {
+        let stash = format!("stash@{{{index}}}");
+        let subcommand = if pop { "pop" } else { "apply" };
+        let output = git_command()
+            .current_dir(repo_root)
+            .env("LC_ALL", "C")
+            .args(["stash", subcommand, &stash])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if output.status.success() {
+            return Ok(StashApplyResult {
+                conflicts: Vec::new(),
+                dropped: pop,
+            });
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("would be overwritten") || stderr.contains("already exists, no checkout")
+        {
+            return Err(CoreError::StashConflict {
+                stash,
+                paths: parse_stash_conflict_paths(&stderr),
+            });
+        }
+
+        // マージのコンフリクトでは失敗扱いになるが、適用自体は済んでいる（pop でも stash は残る）
+        let unmerged = git_command()
+            .current_dir(repo_root)
+            .args(["diff", "--name-only", "--diff-filter=U", "-z"])
+            .output()
+            .map_err(git_spawn_error)?;
+        let conflicts: Vec<String> = unmerged
+            .stdout
+            .split(|b| *b == 0)
+            .filter(|path| !path.is_empty())
+            .map(|path| absolute_status_path(repo_root, path))
+            .collect();
+        if conflicts.is_empty() {
+            return Err(git_failed(&format!("git stash {subcommand}"), &output));
+        }
+        Ok(StashApplyResult {
+            conflicts,
+            dropped: false,
+        })
+    }
+
+    fn drop_stash(&self, repo_root: &Path, index: usize) -> Result<(), CoreError> {
+        let output = git_command()
+            .current_dir(repo_root)
+            .args(["stash", "drop", "--quiet", &format!("stash@{{{index}}}")])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git stash drop", &output));
+        }
+        Ok(())
+    }
+
+    fn stash_diff(&self, repo_root: &Path, index: usize) -> Result<String, CoreError> {
+        let output = git_command()
+            .current_dir(repo_root)
+            .args([
+                "stash",
+                "show",
+                "--patch",
+                "--include-untracked",
+                "--find-renames",
+                "--no-color",
+                "--no-ext-diff",
+                &format!("stash@{{{index}}}"),
+            ])
+            .output()
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            return Err(git_failed("git stash show", &output));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+/// ローカルブランチの先端コミット（ブランチが無ければ None）
+fn local_branch(repo_root: &Path, name: &str) -> Result<Option<String>, CoreError> {
+    verify_ref(
+        repo_root,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{name}"),
+        ],
+    )
+}
+
+/// `git check-ref-format` でブランチ名として使えるか確かめる
+fn check_branch_name(repo_root: &Path, name: &str) -> Result<(), CoreError> {
+    let valid = git_command()
+        .current_dir(repo_root)
+        .args(["check-ref-format", &format!("refs/heads/{name}")])
+        .status()
+        .map_err(git_spawn_error)?
+        .success();
+    if !valid {
+        return Err(CoreError::invalid_argument(format!(
+            "ブランチ名が不正です: {name}"
+        )));
+    }
+    Ok(())
+}
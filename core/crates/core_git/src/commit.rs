@@ -0,0 +1,276 @@
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use core_types::{CommitOptions, CommitResult, CoreError, GitFileState, GitSignature, HookOutput};
+
+use crate::{
+    details::{load_commit_details, parse_trailers},
+    resolve_repo_root, with_backend, with_backend_mut, GitBackend,
+};
+
+/// ステージ済みの変更をコミットし、作成したコミットの詳細を返す
+///
+/// メッセージはエディタで書いた場合と同じく "#" で始まる行と余分な空行を除いてから使い、
+/// commit.template のままなら拒否する。pre-commit と commit-msg フックはここで実行して
+/// 出力を返し、失敗すればコミットせずに HookFailed を返す。
+pub fn create_commit(
+    root_path: &str,
+    message: &str,
+    options: &CommitOptions,
+) -> Result<CommitResult, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+    if options
+        .author
+        .as_ref()
+        .is_some_and(|author| author.name.trim().is_empty() || author.email.trim().is_empty())
+    {
+        return Err(CoreError::invalid_argument(
+            "author の名前とメールアドレスが必要です",
+        ));
+    }
+
+    let plan = with_backend(|backend| prepare_commit(backend, root_path, options))?;
+
+    let mut message = cleanup_message(message);
+    if message.is_empty() {
+        return Err(CoreError::invalid_argument("コミットメッセージが空です"));
+    }
+    if plan
+        .template
+        .as_deref()
+        .is_some_and(|template| cleanup_message(template) == message)
+    {
+        return Err(CoreError::invalid_argument(
+            "コミットメッセージがテンプレートのままです",
+        ));
+    }
+    if let Some(signer) = &plan.signer {
+        message = append_sign_off(&message, &signer.name, &signer.email);
+    }
+
+    let mut hook_outputs = Vec::new();
+    hook_outputs.extend(run_hook(&plan, "pre-commit", &[])?);
+
+    // commit-msg フックはメッセージファイルを書き換えることがある
+    let message_file = plan.message_file.to_string_lossy().to_string();
+    fs::write(&plan.message_file, format!("{message}\n"))
+        .map_err(|e| CoreError::from_io(&message_file, &e))?;
+    if let Some(output) = run_hook(&plan, "commit-msg", &[plan.message_file.as_os_str()])? {
+        hook_outputs.push(output);
+        let edited = fs::read_to_string(&plan.message_file)
+            .map_err(|e| CoreError::from_io(&message_file, &e))?;
+        message = cleanup_message(&edited);
+        if message.is_empty() {
+            return Err(CoreError::invalid_argument("コミットメッセージが空です"));
+        }
+    }
+
+    // フックを再実行しないよう、コミットの作成と詳細の取得はそれぞれ単独でバックエンドに渡す
+    let sha = with_backend_mut(|backend| {
+        backend.create_commit(
+            &plan.repo_root,
+            &message,
+            options.amend,
+            options.author.as_ref(),
+        )
+    })?;
+    let details = with_backend(|backend| load_commit_details(backend, &plan.repo_root, &sha))?;
+    Ok(CommitResult {
+        details,
+        hook_outputs,
+    })
+}
+
+/// commit.template の内容（設定が無ければ None）。コミットメッセージ欄の初期値に使う
+pub fn commit_template(root_path: &str) -> Result<Option<String>, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+
+    with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        read_template(backend, &repo_root)
+    })
+}
+
+/// コミット前にバックエンドから集めておく情報
+struct CommitPlan {
+    repo_root: PathBuf,
+    template: Option<String>,
+    signer: Option<GitSignature>,
+    hooks_dir: PathBuf,
+    message_file: PathBuf,
+}
+
+fn prepare_commit(
+    backend: &dyn GitBackend,
+    root_path: &str,
+    options: &CommitOptions,
+) -> Result<CommitPlan, CoreError> {
+    let repo_root = resolve_repo_root(backend, root_path)?;
+    let status = backend.status(&repo_root)?;
+    if status.entries.iter().any(|entry| entry.conflict.is_some()) {
+        return Err(CoreError::invalid_argument(
+            "コンフリクトが解消されていないファイルがあります",
+        ));
+    }
+    if options.amend {
+        if status.branch.oid.is_none() {
+            return Err(CoreError::invalid_argument(
+                "amend するコミットがありません",
+            ));
+        }
+    } else if !status.entries.iter().any(|entry| {
+        !matches!(
+            entry.index,
+            GitFileState::Unmodified | GitFileState::Untracked
+        )
+    }) {
+        return Err(CoreError::NoChanges {
+            path: root_path.to_string(),
+        });
+    }
+
+    // -m でコミットする git と同じく、テンプレートが読めなくてもコミットは止めない
+    let template = read_template(backend, &repo_root).unwrap_or(None);
+    let signer = if options.sign_off {
+        Some(backend.committer(&repo_root)?)
+    } else {
+        None
+    };
+    Ok(CommitPlan {
+        hooks_dir: backend.git_path(&repo_root, "hooks")?,
+        message_file: backend.git_path(&repo_root, "COMMIT_EDITMSG")?,
+        repo_root,
+        template,
+        signer,
+    })
+}
+
+fn read_template(backend: &dyn GitBackend, repo_root: &Path) -> Result<Option<String>, CoreError> {
+    let Some(path) = backend.config_path(repo_root, "commit.template")? else {
+        return Ok(None);
+    };
+    fs::read_to_string(&path)
+        .map(Some)
+        .map_err(|e| CoreError::from_io(path.to_string_lossy(), &e))
+}
+
+/// フックが実行可能ファイルとしてあれば実行して出力を返す（無ければ None）
+fn run_hook(
+    plan: &CommitPlan,
+    hook: &str,
+    args: &[&OsStr],
+) -> Result<Option<HookOutput>, CoreError> {
+    let path = plan.hooks_dir.join(hook);
+    if !is_executable(&path) {
+        return Ok(None);
+    }
+
+    let output = Command::new(&path)
+        .args(args)
+        .current_dir(&plan.repo_root)
+        .env("GIT_EDITOR", ":")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| CoreError::from_io(path.to_string_lossy(), &e))?;
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    let text = text.trim_end().to_string();
+
+    if !output.status.success() {
+        return Err(CoreError::HookFailed {
+            hook: hook.to_string(),
+            output: text,
+            exit_code: output.status.code(),
+        });
+    }
+    Ok(Some(HookOutput {
+        hook: hook.to_string(),
+        output: text,
+    }))
+}
+
+/// git と同じく実行権限の無いフックは無視する
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// `git stripspace --strip-comments` 相当（"#" の行・行末の空白・連続する空行を除く）
+fn cleanup_message(message: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in message.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let line = line.trim_end();
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+/// "Signed-off-by:" トレーラーを付ける（最後のトレーラーが同じ署名なら付けない）
+fn append_sign_off(message: &str, name: &str, email: &str) -> String {
+    let value = format!("{name} <{email}>");
+    let trailers = parse_trailers(message);
+    if trailers
+        .last()
+        .is_some_and(|trailer| trailer.key == "Signed-off-by" && trailer.value == value)
+    {
+        return message.to_string();
+    }
+    // 既にトレーラーの段落があればそこに続ける
+    let separator = if trailers.is_empty() { "\n\n" } else { "\n" };
+    format!("{message}{separator}Signed-off-by: {value}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleanup_message_strips_comments_and_blank_lines() {
+        let message =
+            "\n\nFix parser  \n\n\n# Please enter the commit message\nBody line\t\n\n#comment\n\n";
+        assert_eq!(cleanup_message(message), "Fix parser\n\nBody line");
+        assert_eq!(cleanup_message("# only comments\n\n"), "");
+    }
+
+    #[test]
+    fn append_sign_off_joins_existing_trailers() {
+        assert_eq!(
+            append_sign_off("Fix parser", "Alice", "alice@example.com"),
+            "Fix parser\n\nSigned-off-by: Alice <alice@example.com>"
+        );
+        assert_eq!(
+            append_sign_off("Fix parser\n\nRefs: #12", "Alice", "alice@example.com"),
+            "Fix parser\n\nRefs: #12\nSigned-off-by: Alice <alice@example.com>"
+        );
+        let signed = "Fix parser\n\nSigned-off-by: Alice <alice@example.com>";
+        assert_eq!(
+            append_sign_off(signed, "Alice", "alice@example.com"),
+            signed
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use std::path::Path;
+
+use core_types::{
+    ChangedFile, CommitDetails, CommitRecord, CommitTrailer, CoreError, DiffFileStatus, GitFileDiff,
+};
+
+use crate::{
+    history::parse_commit_header, parse_unified_diff, resolve_repo_root, with_backend, GitBackend,
+};
+
+/// コミットの詳細（メッセージ全文・トレーラー・第一親との変更ファイル一覧）を返す
+pub fn commit_details(root_path: &str, commit: &str) -> Result<CommitDetails, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+    if commit.trim().is_empty() {
+        return Err(CoreError::invalid_argument("commit が空です"));
+    }
+
+    with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        load_commit_details(backend, &repo_root, commit)
+    })
+}
+
+pub(crate) fn load_commit_details(
+    backend: &dyn GitBackend,
+    repo_root: &Path,
+    commit: &str,
+) -> Result<CommitDetails, CoreError> {
+    let (record, message) = backend.commit_message(repo_root, commit)?;
+    let files = backend.changed_files(repo_root, commit)?;
+    let message = message.trim_end().to_string();
+    Ok(CommitDetails {
+        trailers: parse_trailers(&message),
+        commit: record,
+        message,
+        files,
+    })
+}
+
+/// コミット詳細の1ファイル分の差分を返す（リネームは old_path も渡すと1つの差分になる）
+pub fn commit_file_diff(
+    root_path: &str,
+    commit: &str,
+    path: &str,
+    old_path: Option<&str>,
+) -> Result<GitFileDiff, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+    if commit.trim().is_empty() {
+        return Err(CoreError::invalid_argument("commit が空です"));
+    }
+    if path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("path が空です"));
+    }
+
+    let mut paths = vec![path];
+    paths.extend(old_path);
+    with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        let diff_text = backend.commit_file_diff(&repo_root, commit, &paths)?;
+        if diff_text.trim().is_empty() {
+            return Err(CoreError::NoChanges {
+                path: path.to_string(),
+            });
+        }
+
+        Ok(GitFileDiff {
+            commit: commit.to_string(),
+            path: path.to_string(),
+            files: parse_unified_diff(&diff_text),
+            diff_text,
+        })
+    })
+}
+
+/// `--format=<LOG_FORMAT>%B` の出力をコミット情報とメッセージ全文に分ける
+pub(crate) fn parse_commit_message(output: &str) -> Result<(CommitRecord, String), CoreError> {
+    let record = output.trim_start_matches('\x1e');
+    // LOG_FORMAT の10個目の区切りまでがヘッダー
+    let Some((split, _)) = record.match_indices('\x1f').nth(9) else {
+        return Err(CoreError::internal(format!(
+            "git show の出力を解釈できません: {record}"
+        )));
+    };
+    let commit = parse_commit_header(&record[..split])?;
+    Ok((commit, record[split + 1..].to_string()))
+}
+
+/// メッセージ最後の段落がすべて "Key: value" 形式ならトレーラーとして取り出す
+/// （空白で始まる行は直前の値の続き）
+pub(crate) fn parse_trailers(message: &str) -> Vec<CommitTrailer> {
+    let paragraphs: Vec<&str> = message
+        .trim()
+        .split("\n\n")
+        .map(str::trim_end)
+        .filter(|p| !p.trim().is_empty())
+        .collect();
+    // 1段落だけのメッセージは件名なのでトレーラーは無い
+    let [_, .., last] = paragraphs.as_slice() else {
+        return Vec::new();
+    };
+
+    let mut trailers: Vec<CommitTrailer> = Vec::new();
+    for line in last.lines() {
+        if line.starts_with([' ', '\t']) {
+            match trailers.last_mut() {
+                Some(trailer) => {
+                    trailer.value.push(' ');
+                    trailer.value.push_str(line.trim());
+                    continue;
+                }
+                None => return Vec::new(),
+            }
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            return Vec::new();
+        };
+        let is_token =
+            !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !is_token {
+            return Vec::new();
+        }
+        trailers.push(CommitTrailer {
+            key: key.to_string(),
+            value: value.trim().to_string(),
+        });
+    }
+    trailers
+}
+
+/// `git diff-tree -r -M -z --raw --numstat` の出力をパースする
+pub(crate) fn parse_raw_numstat(output: &str) -> Result<Vec<ChangedFile>, CoreError> {
+    let mut tokens = output.split('\0').filter(|t| !t.is_empty()).peekable();
+    let mut files = Vec::new();
+
+    // 前半は raw 形式（":<mode> <mode> <sha> <sha> <status>" の後にパス）
+    while let Some(token) = tokens.next_if(|t| t.starts_with(':')) {
+        let status_field = token.split_whitespace().nth(4).unwrap_or_default();
+        let (code, score) = status_field.split_at(status_field.len().min(1));
+        let similarity = score.parse().ok();
+        let mut next_path = || {
+            tokens.next().map(str::to_string).ok_or_else(|| {
+                CoreError::internal(format!("diff-tree の出力が不完全です: {token}"))
+            })
+        };
+        let (status, path, old_path) = match code {
+            "A" => (DiffFileStatus::Added, next_path()?, None),
+            "D" => (DiffFileStatus::Deleted, next_path()?, None),
+            "R" | "C" => {
+                let old_path = next_path()?;
+                let status = if code == "R" {
+                    DiffFileStatus::Renamed
+                } else {
+                    DiffFileStatus::Copied
+                };
+                (status, next_path()?, Some(old_path))
+            }
+            _ => (DiffFileStatus::Modified, next_path()?, None),
+        };
+        files.push(ChangedFile {
+            path,
+            old_path,
+            status,
+            similarity: similarity.filter(|_| code == "R" || code == "C"),
+            insertions: 0,
+            deletions: 0,
+            is_binary: false,
+        });
+    }
+
+    // 後半は同じ順の numstat（リネームはパス欄が空で、続く2トークンが旧・新パス）
+    for file in &mut files {
+        let Some(token) = tokens.next() else {
+            break;
+        };
+        let mut fields = token.splitn(3, '\t');
+        let insertions = fields.next().unwrap_or_default();
+        let deletions = fields.next().unwrap_or_default();
+        if fields.next().unwrap_or_default().is_empty() {
+            tokens.next();
+            tokens.next();
+        }
+        match (insertions.parse(), deletions.parse()) {
+            (Ok(insertions), Ok(deletions)) => {
+                file.insertions = insertions;
+                file.deletions = deletions;
+            }
+            // バイナリは "-\t-"
+            _ => file.is_binary = true,
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_trailers_reads_last_paragraph() {
+        let message = "Fix crash\n\nLonger explanation.\n\nCo-authored-by: Bob <bob@example.com>\nSigned-off-by: Alice\n  <alice@example.com>\nReviewed-by: Carol";
+        let trailers = parse_trailers(message);
+        let pairs: Vec<(&str, &str)> = trailers
+            .iter()
+            .map(|t| (t.key.as_str(), t.value.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("Co-authored-by", "Bob <bob@example.com>"),
+                ("Signed-off-by", "Alice <alice@example.com>"),
+                ("Reviewed-by", "Carol"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_trailers_ignores_prose_and_subject_only_messages() {
+        assert!(parse_trailers("Subject: looks like a trailer").is_empty());
+        assert!(parse_trailers("Subject\n\nNote: this is prose\nand more prose").is_empty());
+    }
+
+    #[test]
+    fn parse_raw_numstat_pairs_status_and_counts() {
+        let output = concat!(
+            ":100644 100644 aaaaaaa bbbbbbb M\0src/lib.rs\0",
+            ":100644 100644 ccccccc ddddddd R087\0old.rs\0new.rs\0",
+            ":000000 100644 0000000 eeeeeee A\0logo.png\0",
+            "3\t1\tsrc/lib.rs\0",
+            "2\t2\t\0old.rs\0new.rs\0",
+            "-\t-\tlogo.png\0",
+        );
+        let files = parse_raw_numstat(output).unwrap();
+        assert_eq!(files.len(), 3);
+
+        assert_eq!(files[0].status, DiffFileStatus::Modified);
+        assert_eq!((files[0].insertions, files[0].deletions), (3, 1));
+
+        assert_eq!(files[1].status, DiffFileStatus::Renamed);
+        assert_eq!(files[1].path, "new.rs");
+        assert_eq!(files[1].old_path.as_deref(), Some("old.rs"));
+        assert_eq!(files[1].similarity, Some(87));
+        assert_eq!((files[1].insertions, files[1].deletions), (2, 2));
+
+        assert_eq!(files[2].status, DiffFileStatus::Added);
+        assert!(files[2].is_binary);
+    }
+}
@@ -0,0 +1,615 @@
+use core_types::{DiffFile, DiffFileStatus, DiffHunk, DiffLine, DiffLineOrigin};
+
+use crate::word_diff::annotate_word_changes;
+
+/// unified diff（git diff / git show の出力）をファイル・ハンク・行に分解する
+///
+/// 引用符付きパス（`"a/\303\251.txt"`）、リネーム・コピー・モード変更、バイナリ、
+/// 結合差分（diff --cc）に対応する。解釈できない行は読み飛ばす。
+/// 通常の差分では、対になる削除行・追加行に行内の変更範囲（word_changes）を付ける。
+pub fn parse_unified_diff(text: &str) -> Vec<DiffFile> {
+    let mut parser = Parser::default();
+    for raw_line in text.split('\n') {
+        parser.feed(raw_line);
+    }
+    parser.finish()
+}
+
+#[derive(Default)]
+struct Parser {
+    files: Vec<DiffFile>,
+    current: Option<DiffFile>,
+    hunk: Option<HunkState>,
+    /// GIT binary patch の本体を読み飛ばしている最中
+    in_binary_patch: bool,
+}
+
+struct HunkState {
+    hunk: DiffHunk,
+    /// 結合差分の親の数（通常の差分は 1）
+    parents: usize,
+    old_line: u32,
+    new_line: u32,
+    old_remaining: u32,
+    new_remaining: u32,
+}
+
+impl HunkState {
+    fn is_complete(&self) -> bool {
+        self.old_remaining == 0 && self.new_remaining == 0
+    }
+}
+
+impl Parser {
+    fn feed(&mut self, raw_line: &str) {
+        // 直前の行に付く "\ No newline at end of file"
+        if raw_line.starts_with('\\') {
+            if let Some(line) = self.last_line_mut() {
+                line.no_newline_at_eof = true;
+            }
+            return;
+        }
+
+        if let Some(state) = self.hunk.as_mut() {
+            if !state.is_complete() {
+                if let Some(line) = parse_hunk_line(state, raw_line) {
+                    state.hunk.lines.push(line);
+                    return;
+                }
+            }
+            self.finish_hunk();
+        }
+
+        let line = raw_line.strip_suffix('\r').unwrap_or(raw_line);
+
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            self.start_file(false);
+            if let Some((old, new)) = split_git_header_paths(rest) {
+                let file = self.current_file();
+                file.old_path = strip_path_prefix(&old, "a/");
+                file.new_path = strip_path_prefix(&new, "b/");
+            }
+            return;
+        }
+        if let Some(rest) = line
+            .strip_prefix("diff --cc ")
+            .or_else(|| line.strip_prefix("diff --combined "))
+        {
+            self.start_file(true);
+            let path = unquote_path(rest);
+            let file = self.current_file();
+            file.old_path = Some(path.clone());
+            file.new_path = Some(path);
+            return;
+        }
+
+        if self.in_binary_patch {
+            return;
+        }
+
+        if line.starts_with("@@") {
+            if let Some(state) = parse_hunk_header(line) {
+                self.current_file();
+                self.hunk = Some(state);
+            }
+            return;
+        }
+
+        self.parse_extended_header(line);
+    }
+
+    fn parse_extended_header(&mut self, line: &str) {
+        if let Some(rest) = line.strip_prefix("--- ") {
+            // ヘッダー無しの素の unified diff では --- が新しいファイルの開始
+            if self.current.as_ref().is_some_and(|f| !f.hunks.is_empty()) {
+                self.start_file(false);
+            }
+            let path = parse_marker_path(rest, "a/");
+            let file = self.current_file();
+            match path {
+                Some(path) => file.old_path = Some(path),
+                None => {
+                    file.old_path = None;
+                    file.status = DiffFileStatus::Added;
+                }
+            }
+            return;
+        }
+        if let Some(rest) = line.strip_prefix("+++ ") {
+            let path = parse_marker_path(rest, "b/");
+            let file = self.current_file();
+            match path {
+                Some(path) => file.new_path = Some(path),
+                None => {
+                    file.new_path = None;
+                    file.status = DiffFileStatus::Deleted;
+                }
+            }
+            return;
+        }
+
+        let Some(file) = self.current.as_mut() else {
+            return;
+        };
+        if let Some(mode) = line.strip_prefix("old mode ") {
+            file.old_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("new mode ") {
+            file.new_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("new file mode ") {
+            file.new_mode = Some(mode.to_string());
+            file.old_path = None;
+            file.status = DiffFileStatus::Added;
+        } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+            file.old_mode = Some(mode.to_string());
+            file.new_path = None;
+            file.status = DiffFileStatus::Deleted;
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            file.old_path = Some(unquote_path(path));
+            file.status = DiffFileStatus::Renamed;
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            file.new_path = Some(unquote_path(path));
+            file.status = DiffFileStatus::Renamed;
+        } else if let Some(path) = line.strip_prefix("copy from ") {
+            file.old_path = Some(unquote_path(path));
+            file.status = DiffFileStatus::Copied;
+        } else if let Some(path) = line.strip_prefix("copy to ") {
+            file.new_path = Some(unquote_path(path));
+            file.status = DiffFileStatus::Copied;
+        } else if let Some(value) = line
+            .strip_prefix("similarity index ")
+            .or_else(|| line.strip_prefix("dissimilarity index "))
+        {
+            file.similarity = value.trim_end_matches('%').parse().ok();
+        } else if let Some(rest) = line.strip_prefix("index ") {
+            // index <old>..<new> <mode>
+            if let Some((_, mode)) = rest.split_once(' ') {
+                if file.old_mode.is_none() && file.new_mode.is_none() {
+                    file.old_mode = Some(mode.to_string());
+                    file.new_mode = Some(mode.to_string());
+                }
+            }
+        } else if line.starts_with("Binary files ") {
+            file.is_binary = true;
+        } else if line == "GIT binary patch" {
+            file.is_binary = true;
+            self.in_binary_patch = true;
+        }
+    }
+
+    fn current_file(&mut self) -> &mut DiffFile {
+        self.current.get_or_insert_with(|| empty_file(false))
+    }
+
+    fn start_file(&mut self, is_combined: bool) {
+        self.finish_hunk();
+        if let Some(file) = self.current.take() {
+            self.files.push(file);
+        }
+        self.current = Some(empty_file(is_combined));
+        self.in_binary_patch = false;
+    }
+
+    fn finish_hunk(&mut self) {
+        if let Some(mut state) = self.hunk.take() {
+            if state.parents == 1 {
+                annotate_word_changes(&mut state.hunk);
+            }
+            self.current_file().hunks.push(state.hunk);
+        }
+    }
+
+    fn last_line_mut(&mut self) -> Option<&mut DiffLine> {
+        match self.hunk.as_mut() {
+            Some(state) => state.hunk.lines.last_mut(),
+            None => self
+                .current
+                .as_mut()
+                .and_then(|file| file.hunks.last_mut())
+                .and_then(|hunk| hunk.lines.last_mut()),
+        }
+    }
+
+    fn finish(mut self) -> Vec<DiffFile> {
+        self.finish_hunk();
+        if let Some(file) = self.current.take() {
+            self.files.push(file);
+        }
+        self.files
+    }
+}
+
+fn empty_file(is_combined: bool) -> DiffFile {
+    DiffFile {
+        old_path: None,
+        new_path: None,
+        status: DiffFileStatus::Modified,
+        old_mode: None,
+        new_mode: None,
+        similarity: None,
+        is_binary: false,
+        is_combined,
+        hunks: Vec::new(),
+    }
+}
+
+/// "@@ -1,3 +1,4 @@ ctx" または結合差分の "@@@ -1,3 -1,3 +1,4 @@@ ctx" をパースする
+fn parse_hunk_header(line: &str) -> Option<HunkState> {
+    let marker_len = line.chars().take_while(|&c| c == '@').count();
+    if marker_len < 2 {
+        return None;
+    }
+    let parents = marker_len - 1;
+    let marker = &line[..marker_len];
+    let body = &line[marker_len..];
+    let end = body.find(marker)?;
+    let ranges: Vec<&str> = body[..end].split_whitespace().collect();
+    if ranges.len() != parents + 1 {
+        return None;
+    }
+
+    let (old_start, old_lines) = parse_range(ranges[0].strip_prefix('-')?)?;
+    let (new_start, new_lines) = parse_range(ranges[parents].strip_prefix('+')?)?;
+    Some(HunkState {
+        hunk: DiffHunk {
+            header: line.to_string(),
+            old_start,
+            old_lines,
+            new_start,
+            new_lines,
+            lines: Vec::new(),
+        },
+        parents,
+        old_line: old_start.max(1),
+        new_line: new_start.max(1),
+        old_remaining: old_lines,
+        new_remaining: new_lines,
+    })
+}
+
+/// "12,3" / "12"（件数省略時は 1）
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// ハンク本体の1行をパースする（ハンクに属さない行なら None）
+fn parse_hunk_line(state: &mut HunkState, raw_line: &str) -> Option<DiffLine> {
+    // 末尾空白を削るツールを通った空のコンテキスト行
+    let (columns, content) = if raw_line.is_empty() {
+        (" ".repeat(state.parents), "")
+    } else {
+        if raw_line.len() < state.parents || !raw_line.is_char_boundary(state.parents) {
+            return None;
+        }
+        let (columns, content) = raw_line.split_at(state.parents);
+        if !columns.chars().all(|c| matches!(c, ' ' | '+' | '-')) {
+            return None;
+        }
+        (columns.to_string(), content)
+    };
+
+    // 第1親に存在する行か・結果に存在する行か
+    // 結合差分の削除行は '-' の列の親にだけ存在する
+    let in_new = !columns.contains('-');
+    let in_old = if in_new {
+        !columns.starts_with('+')
+    } else {
+        columns.starts_with('-')
+    };
+    let origin = if columns.contains('+') {
+        DiffLineOrigin::Added
+    } else if columns.contains('-') {
+        DiffLineOrigin::Removed
+    } else {
+        DiffLineOrigin::Context
+    };
+
+    let old_line = in_old.then(|| {
+        state.old_remaining = state.old_remaining.saturating_sub(1);
+        state.old_line += 1;
+        state.old_line - 1
+    });
+    let new_line = in_new.then(|| {
+        state.new_remaining = state.new_remaining.saturating_sub(1);
+        state.new_line += 1;
+        state.new_line - 1
+    });
+
+    Some(DiffLine {
+        origin,
+        old_line,
+        new_line,
+        content: content.to_string(),
+        no_newline_at_eof: false,
+        word_changes: Vec::new(),
+    })
+}
+
+/// "--- a/path" / "+++ b/path" のパス部分（/dev/null なら None）
+fn parse_marker_path(rest: &str, prefix: &str) -> Option<String> {
+    let path = if rest.starts_with('"') {
+        unquote_path(rest)
+    } else {
+        // git 以外の diff はパスの後ろにタブ区切りでタイムスタンプが付く
+        rest.split('\t').next().unwrap_or(rest).to_string()
+    };
+    if path == "/dev/null" {
+        return None;
+    }
+    strip_path_prefix(&path, prefix)
+}
+
+fn strip_path_prefix(path: &str, prefix: &str) -> Option<String> {
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// "diff --git a/x b/y" のパス部分を2つに分ける
+fn split_git_header_paths(rest: &str) -> Option<(String, String)> {
+    if rest.starts_with('"') {
+        let (old, remaining) = split_quoted(rest)?;
+        let remaining = remaining.trim_start();
+        return Some((old, unquote_path(remaining)));
+    }
+    if let Some(index) = rest.find(" \"") {
+        let new = &rest[index + 1..];
+        return Some((rest[..index].to_string(), unquote_path(new)));
+    }
+
+    // 空白を含むパスは曖昧なので、a/X b/X の形で左右が一致する分割点を優先する
+    let candidates: Vec<usize> = rest.match_indices(" b/").map(|(i, _)| i).collect();
+    let split = candidates
+        .iter()
+        .copied()
+        .find(|&i| rest[..i].strip_prefix("a/") == Some(&rest[i + 3..]))
+        .or_else(|| candidates.first().copied())?;
+    Some((rest[..split].to_string(), rest[split + 1..].to_string()))
+}
+
+/// 先頭の引用符付き文字列を取り出し、残りを返す
+fn split_quoted(text: &str) -> Option<(String, &str)> {
+    let bytes = text.as_bytes();
+    let mut index = 1;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'"' => return Some((unquote_path(&text[..=index]), &text[index + 1..])),
+            _ => index += 1,
+        }
+    }
+    None
+}
+
+/// git の C 形式の引用（`"\303\251\t"` など）を外す。引用されていなければそのまま返す
+fn unquote_path(text: &str) -> String {
+    let Some(inner) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) else {
+        return text.to_string();
+    };
+
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut chars = inner.bytes().peekable();
+    while let Some(byte) = chars.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        let Some(escaped) = chars.next() else {
+            break;
+        };
+        match escaped {
+            b'a' => bytes.push(0x07),
+            b'b' => bytes.push(0x08),
+            b't' => bytes.push(b'\t'),
+            b'n' => bytes.push(b'\n'),
+            b'v' => bytes.push(0x0b),
+            b'f' => bytes.push(0x0c),
+            b'r' => bytes.push(b'\r'),
+            b'0'..=b'7' => {
+                let mut value = u32::from(escaped - b'0');
+                for _ in 0..2 {
+                    match chars.peek() {
+                        Some(&digit @ b'0'..=b'7') => {
+                            value = value * 8 + u32::from(digit - b'0');
+                            chars.next();
+                        }
+                        _ => break,
+                    }
+                }
+                bytes.push(value as u8);
+            }
+            other => bytes.push(other),
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_modified_file_with_line_numbers() {
+        let input = "\
+diff --git a/src/main.rs b/src/main.rs
+index 1111111..2222222 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,3 @@ fn main() {
+ let a = 1;
+-let b = 2;
++let b = 20;
+ let c = 3;
+";
+        let files = parse_unified_diff(input);
+        assert_eq!(files.len(), 1);
+        let file = &files[0];
+        assert_eq!(file.old_path.as_deref(), Some("src/main.rs"));
+        assert_eq!(file.new_path.as_deref(), Some("src/main.rs"));
+        assert_eq!(file.status, DiffFileStatus::Modified);
+        assert_eq!(file.new_mode.as_deref(), Some("100644"));
+
+        let hunk = &file.hunks[0];
+        assert_eq!((hunk.old_start, hunk.old_lines), (1, 3));
+        assert_eq!(hunk.header, "@@ -1,3 +1,3 @@ fn main() {");
+        let summary: Vec<(DiffLineOrigin, Option<u32>, Option<u32>)> = hunk
+            .lines
+            .iter()
+            .map(|l| (l.origin, l.old_line, l.new_line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (DiffLineOrigin::Context, Some(1), Some(1)),
+                (DiffLineOrigin::Removed, Some(2), None),
+                (DiffLineOrigin::Added, None, Some(2)),
+                (DiffLineOrigin::Context, Some(3), Some(3)),
+            ]
+        );
+        assert_eq!(hunk.lines[2].content, "let b = 20;");
+        assert_eq!(
+            hunk.lines[2].word_changes,
+            vec![core_types::MatchRange {
+                start_col: 8,
+                end_col: 10
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_new_file_without_trailing_newline() {
+        let input = "\
+diff --git a/new.txt b/new.txt
+new file mode 100644
+index 0000000..3333333
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1,2 @@
++first
++second
+\\ No newline at end of file
+";
+        let files = parse_unified_diff(input);
+        let file = &files[0];
+        assert_eq!(file.status, DiffFileStatus::Added);
+        assert_eq!(file.old_path, None);
+        assert_eq!(file.new_path.as_deref(), Some("new.txt"));
+        let lines = &file.hunks[0].lines;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].new_line, Some(1));
+        assert!(!lines[0].no_newline_at_eof);
+        assert!(lines[1].no_newline_at_eof);
+    }
+
+    #[test]
+    fn parse_rename_mode_change_and_binary() {
+        let input = "\
+diff --git a/old name.txt b/new name.txt
+similarity index 95%
+rename from old name.txt
+rename to new name.txt
+diff --git a/script.sh b/script.sh
+old mode 100644
+new mode 100755
+diff --git a/image.png b/image.png
+deleted file mode 100644
+index 4444444..0000000
+Binary files a/image.png and /dev/null differ
+";
+        let files = parse_unified_diff(input);
+        assert_eq!(files.len(), 3);
+
+        assert_eq!(files[0].status, DiffFileStatus::Renamed);
+        assert_eq!(files[0].old_path.as_deref(), Some("old name.txt"));
+        assert_eq!(files[0].new_path.as_deref(), Some("new name.txt"));
+        assert_eq!(files[0].similarity, Some(95));
+
+        assert_eq!(files[1].old_mode.as_deref(), Some("100644"));
+        assert_eq!(files[1].new_mode.as_deref(), Some("100755"));
+        assert!(files[1].hunks.is_empty());
+
+        assert_eq!(files[2].status, DiffFileStatus::Deleted);
+        assert!(files[2].is_binary);
+        assert_eq!(files[2].new_path, None);
+    }
+
+    #[test]
+    fn parse_quoted_paths() {
+        let input = "\
+diff --git \"a/caf\\303\\251 \\\"x\\\".txt\" \"b/caf\\303\\251 \\\"x\\\".txt\"
+--- \"a/caf\\303\\251 \\\"x\\\".txt\"
++++ \"b/caf\\303\\251 \\\"x\\\".txt\"
+@@ -1 +1 @@
+-a
++b
+";
+        let files = parse_unified_diff(input);
+        assert_eq!(files[0].old_path.as_deref(), Some("café \"x\".txt"));
+        assert_eq!(files[0].new_path.as_deref(), Some("café \"x\".txt"));
+        assert_eq!(files[0].hunks[0].lines.len(), 2);
+    }
+
+    #[test]
+    fn parse_combined_diff() {
+        let input = "\
+diff --cc sample.swift
+index 1111111,2222222..3333333
+--- a/sample.swift
++++ b/sample.swift
+@@@ -1,2 -1,2 +1,2 @@@
+- let a = 100
+ -let a = 10
+++let a = 1000
+  let b = 2
+";
+        let files = parse_unified_diff(input);
+        let file = &files[0];
+        assert!(file.is_combined);
+        assert_eq!(file.new_path.as_deref(), Some("sample.swift"));
+
+        let lines = &file.hunks[0].lines;
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].origin, DiffLineOrigin::Removed);
+        assert_eq!(lines[0].old_line, Some(1));
+        // 第2親にだけあった行は第1親の行番号を持たない
+        assert_eq!(lines[1].origin, DiffLineOrigin::Removed);
+        assert_eq!(lines[1].old_line, None);
+        assert_eq!(lines[2].origin, DiffLineOrigin::Added);
+        assert_eq!(lines[2].new_line, Some(1));
+        assert_eq!(lines[2].content, "let a = 1000");
+        assert_eq!(lines[3].old_line, Some(2));
+        assert_eq!(lines[3].new_line, Some(2));
+    }
+
+    #[test]
+    fn parse_multiple_sections_and_dash_content() {
+        // 削除行の内容が "-- " で始まっても次ファイルのヘッダーと誤認しない
+        let input = "\
+diff --git a/a.sql b/a.sql
+--- a/a.sql
++++ b/a.sql
+@@ -1,2 +1,1 @@
+--- comment
+ select 1;
+
+diff --git a/a.sql b/a.sql
+--- a/a.sql
++++ b/a.sql
+@@ -1 +1,2 @@
+ select 1;
++select 2;
+";
+        let files = parse_unified_diff(input);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].hunks[0].lines[0].content, "-- comment");
+        assert_eq!(files[0].hunks[0].lines[0].origin, DiffLineOrigin::Removed);
+        assert_eq!(files[1].hunks[0].lines[1].new_line, Some(2));
+    }
+
+    #[test]
+    fn unquote_path_handles_escapes() {
+        assert_eq!(unquote_path("plain.txt"), "plain.txt");
+        assert_eq!(unquote_path("\"tab\\there\""), "tab\there");
+        assert_eq!(unquote_path("\"\\346\\227\\245.txt\""), "日.txt");
+    }
+}
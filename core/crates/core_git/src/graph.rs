@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use core_types::{CommitGraphRow, CommitRecord, CoreError, GraphEdge, RefDecoration, RefKind};
+
+use crate::{history::parse_commit_header, resolve_repo_root, with_backend};
+
+/// リポジトリ全体（HEAD・ブランチ・リモートブランチ・タグから辿れるコミット）のログを
+/// トポロジカル順に返す。`git log --graph` 相当のレーン割り当てと参照の表示名付き。
+///
+/// レーンは先頭からの履歴で決まるため、offset 件目までも内部では計算する。
+pub fn commit_log(
+    root_path: &str,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<CommitGraphRow>, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+    if limit == 0 {
+        return Err(CoreError::invalid_argument(
+            "limit は 1 以上を指定してください",
+        ));
+    }
+
+    let (commits, refs) = with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        let commits = backend.commits(&repo_root, offset as usize + limit as usize)?;
+        let refs = backend.refs(&repo_root)?;
+        Ok((commits, refs))
+    })?;
+
+    Ok(build_graph(commits, refs)
+        .into_iter()
+        .skip(offset as usize)
+        .collect())
+}
+
+/// 完全な参照名を表示用の短縮名と種別にする（対象外の参照は None）
+pub(crate) fn ref_decoration(refname: &str, head_ref: Option<&str>) -> Option<RefDecoration> {
+    let (kind, name) = if let Some(name) = refname.strip_prefix("refs/heads/") {
+        (RefKind::LocalBranch, name)
+    } else if let Some(name) = refname.strip_prefix("refs/remotes/") {
+        (RefKind::RemoteBranch, name)
+    } else if let Some(name) = refname.strip_prefix("refs/tags/") {
+        (RefKind::Tag, name)
+    } else {
+        return None;
+    };
+    Some(RefDecoration {
+        name: name.to_string(),
+        kind,
+        is_head: head_ref == Some(refname),
+    })
+}
+
+/// detached HEAD の表示
+pub(crate) fn detached_head() -> RefDecoration {
+    RefDecoration {
+        name: "HEAD".to_string(),
+        kind: RefKind::Head,
+        is_head: true,
+    }
+}
+
+/// `git log --format=LOG_FORMAT`（--name-status なし）の出力をパースする
+pub(crate) fn parse_commit_log(output: &str) -> Result<Vec<CommitRecord>, CoreError> {
+    output
+        .split('\x1e')
+        .filter(|record| !record.is_empty())
+        .map(|record| {
+            let header = record.trim_end_matches('\n');
+            parse_commit_header(header.strip_suffix('\x1f').unwrap_or(header))
+        })
+        .collect()
+}
+
+/// 各コミットにレーンと線を割り当てる
+///
+/// レーンごとに「次に現れるはずのコミット」を持ち、同じコミットを待つレーンは
+/// 最も左のものに寄せる。第一親はできるだけ同じ列を引き継ぐ。
+fn build_graph(
+    commits: Vec<CommitRecord>,
+    refs: Vec<(String, RefDecoration)>,
+) -> Vec<CommitGraphRow> {
+    let mut refs_by_sha: HashMap<String, Vec<RefDecoration>> = HashMap::new();
+    for (sha, decoration) in refs {
+        refs_by_sha.entry(sha).or_default().push(decoration);
+    }
+    for decorations in refs_by_sha.values_mut() {
+        decorations
+            .sort_by(|a, b| (!a.is_head, a.kind, &a.name).cmp(&(!b.is_head, b.kind, &b.name)));
+    }
+
+    let mut lanes: Vec<Option<String>> = Vec::new();
+    let mut child_counts: HashMap<String, u32> = HashMap::new();
+    let mut rows = Vec::with_capacity(commits.len());
+
+    for commit in commits {
+        let previous = lanes.clone();
+        let lane = match lanes
+            .iter()
+            .position(|l| l.as_deref() == Some(commit.sha.as_str()))
+        {
+            Some(lane) => lane,
+            None => free_lane(&mut lanes),
+        };
+        lanes[lane] = None;
+
+        for (index, parent) in commit.parents.iter().enumerate() {
+            *child_counts.entry(parent.clone()).or_default() += 1;
+            match lanes
+                .iter()
+                .position(|l| l.as_deref() == Some(parent.as_str()))
+            {
+                // 第一親が右の列で待たれていれば、こちらの列に寄せる
+                Some(existing) if index == 0 && lane < existing => {
+                    lanes[existing] = None;
+                    lanes[lane] = Some(parent.clone());
+                }
+                Some(_) => {}
+                None => {
+                    let slot = if index == 0 {
+                        lane
+                    } else {
+                        free_lane(&mut lanes)
+                    };
+                    lanes[slot] = Some(parent.clone());
+                }
+            }
+        }
+
+        let position = |sha: &str| lanes.iter().position(|l| l.as_deref() == Some(sha));
+        let mut edges: Vec<GraphEdge> = commit
+            .parents
+            .iter()
+            .filter_map(|parent| position(parent))
+            .map(|to| GraphEdge {
+                from_lane: lane as u32,
+                to_lane: to as u32,
+            })
+            .collect();
+        for (from, waiting) in previous.iter().enumerate() {
+            let Some(waiting) = waiting else { continue };
+            if from == lane || *waiting == commit.sha {
+                continue;
+            }
+            if let Some(to) = position(waiting) {
+                edges.push(GraphEdge {
+                    from_lane: from as u32,
+                    to_lane: to as u32,
+                });
+            }
+        }
+        edges.sort_by_key(|edge| (edge.from_lane, edge.to_lane));
+        edges.dedup();
+
+        let width = previous.len().max(lanes.len()).max(lane + 1) as u32;
+        while lanes.last().is_some_and(Option::is_none) {
+            lanes.pop();
+        }
+
+        rows.push(CommitGraphRow {
+            lane: lane as u32,
+            edges,
+            width,
+            is_merge: commit.parents.len() > 1,
+            is_branch_point: child_counts.get(&commit.sha).copied().unwrap_or(0) > 1,
+            refs: refs_by_sha.remove(&commit.sha).unwrap_or_default(),
+            commit,
+        });
+    }
+    rows
+}
+
+/// 空いている最も左の列（無ければ右端に追加）
+fn free_lane(lanes: &mut Vec<Option<String>>) -> usize {
+    match lanes.iter().position(Option::is_none) {
+        Some(lane) => lane,
+        None => {
+            lanes.push(None);
+            lanes.len() - 1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_types::GitSignature;
+
+    fn commit(sha: &str, parents: &[&str]) -> CommitRecord {
+        let signature = GitSignature {
+            name: "Alice".into(),
+            email: "alice@example.com".into(),
+            time: 1700000000,
+            offset_minutes: 0,
+        };
+        CommitRecord {
+            sha: sha.into(),
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+            author: signature.clone(),
+            committer: signature,
+            subject: sha.into(),
+            body: String::new(),
+        }
+    }
+
+    fn edges(row: &CommitGraphRow) -> Vec<(u32, u32)> {
+        row.edges.iter().map(|e| (e.from_lane, e.to_lane)).collect()
+    }
+
+    #[test]
+    fn build_graph_assigns_lanes_for_merge_and_branch_point() {
+        // M = merge(c3, fa), c3 と fa はどちらも c2 から分岐
+        let commits = vec![
+            commit("M", &["c3", "fa"]),
+            commit("fa", &["c2"]),
+            commit("c3", &["c2"]),
+            commit("c2", &["c1"]),
+            commit("c1", &[]),
+        ];
+        let refs = vec![
+            (
+                "M".to_string(),
+                ref_decoration("refs/tags/v1", None).unwrap(),
+            ),
+            (
+                "M".to_string(),
+                ref_decoration("refs/heads/main", Some("refs/heads/main")).unwrap(),
+            ),
+            (
+                "fa".to_string(),
+                ref_decoration("refs/heads/feat", None).unwrap(),
+            ),
+        ];
+        let rows = build_graph(commits, refs);
+
+        let lanes: Vec<u32> = rows.iter().map(|r| r.lane).collect();
+        assert_eq!(lanes, vec![0, 1, 0, 0, 0]);
+        assert_eq!(edges(&rows[0]), vec![(0, 0), (0, 1)]);
+        assert_eq!(edges(&rows[1]), vec![(0, 0), (1, 1)]);
+        // c3 の行で fa 側の線が左の列に合流する
+        assert_eq!(edges(&rows[2]), vec![(0, 0), (1, 0)]);
+        assert_eq!(edges(&rows[3]), vec![(0, 0)]);
+        assert!(edges(&rows[4]).is_empty());
+
+        let widths: Vec<u32> = rows.iter().map(|r| r.width).collect();
+        assert_eq!(widths, vec![2, 2, 2, 1, 1]);
+        assert!(rows[0].is_merge);
+        assert!(rows[3].is_branch_point);
+        assert!(!rows[2].is_branch_point);
+
+        let names: Vec<&str> = rows[0].refs.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["main", "v1"]);
+        assert_eq!(rows[1].refs[0].kind, RefKind::LocalBranch);
+    }
+
+    #[test]
+    fn build_graph_keeps_unrelated_tips_in_separate_lanes() {
+        let rows = build_graph(
+            vec![commit("a2", &["a1"]), commit("b1", &[]), commit("a1", &[])],
+            Vec::new(),
+        );
+        let lanes: Vec<u32> = rows.iter().map(|r| r.lane).collect();
+        assert_eq!(lanes, vec![0, 1, 0]);
+        assert_eq!(edges(&rows[1]), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn ref_decoration_shortens_names() {
+        let remote = ref_decoration("refs/remotes/origin/main", None).unwrap();
+        assert_eq!(remote.name, "origin/main");
+        assert_eq!(remote.kind, RefKind::RemoteBranch);
+        assert!(ref_decoration("refs/stash", None).is_none());
+    }
+}
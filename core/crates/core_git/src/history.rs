@@ -0,0 +1,173 @@
+use core_types::{CommitRecord, CoreError, FileHistoryEntry, GitSignature};
+
+use crate::{resolve_repo_context, with_backend};
+
+/// `git log` の --format（レコード区切り 0x1e、フィールド区切り 0x1f）
+pub(crate) const LOG_FORMAT: &str =
+    "--format=%x1e%H%x1f%P%x1f%an%x1f%ae%x1f%ad%x1f%cn%x1f%ce%x1f%cd%x1f%s%x1f%b%x1f";
+
+/// ファイルに触れたコミットを新しい順に返す（リネームを遡る `git log --follow` 相当）
+///
+/// offset 件読み飛ばしてから最大 limit 件返す。
+pub fn file_history(
+    file_path: &str,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<FileHistoryEntry>, CoreError> {
+    if file_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("file_path が空です"));
+    }
+    if limit == 0 {
+        return Err(CoreError::invalid_argument(
+            "limit は 1 以上を指定してください",
+        ));
+    }
+
+    with_backend(|backend| {
+        let (repo_root, relative_path) = resolve_repo_context(backend, file_path)?;
+        backend.file_history(&repo_root, &relative_path, limit as usize, offset as usize)
+    })
+}
+
+/// `--date=raw` の "<UNIX 時刻> <+hhmm>" を分解する
+pub(crate) fn parse_raw_date(raw: &str) -> Option<(i64, i32)> {
+    let (time, zone) = raw.trim().split_once(' ')?;
+    let time = time.parse().ok()?;
+    let (sign, digits) = match zone.as_bytes().first()? {
+        b'-' => (-1, &zone[1..]),
+        b'+' => (1, &zone[1..]),
+        _ => (1, zone),
+    };
+    if digits.len() != 4 {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    Some((time, sign * (hours * 60 + minutes)))
+}
+
+pub(crate) fn signature(
+    name: &str,
+    email: &str,
+    raw_date: &str,
+) -> Result<GitSignature, CoreError> {
+    let (time, offset_minutes) = parse_raw_date(raw_date).ok_or_else(|| {
+        CoreError::internal(format!("git log の日時を解釈できません: {raw_date}"))
+    })?;
+    Ok(GitSignature {
+        name: name.to_string(),
+        email: email.to_string(),
+        time,
+        offset_minutes,
+    })
+}
+
+/// LOG_FORMAT のヘッダー部分をコミット情報に変換する
+pub(crate) fn parse_commit_header(header: &str) -> Result<CommitRecord, CoreError> {
+    let fields: Vec<&str> = header.split('\x1f').collect();
+    if fields.len() < 10 {
+        return Err(CoreError::internal(format!(
+            "git log の出力を解釈できません: {header}"
+        )));
+    }
+    Ok(CommitRecord {
+        sha: fields[0].to_string(),
+        parents: fields[1].split_whitespace().map(str::to_string).collect(),
+        author: signature(fields[2], fields[3], fields[4])?,
+        committer: signature(fields[5], fields[6], fields[7])?,
+        subject: fields[8].to_string(),
+        body: fields[9].trim().to_string(),
+    })
+}
+
+/// `git log --follow --name-status -z` の出力をパースする
+///
+/// 各コミットの変更ファイル欄の最後のパスがそのコミット時点のパス。
+/// 差分が出ないマージコミットは直前のコミットで追っていたパスを引き継ぐ。
+pub(crate) fn parse_history_log(
+    output: &str,
+    relative_path: &str,
+) -> Result<Vec<FileHistoryEntry>, CoreError> {
+    let mut entries = Vec::new();
+    let mut current_path = relative_path.to_string();
+
+    for record in output.split('\x1e').filter(|r| !r.is_empty()) {
+        let Some((header, name_status)) = record.rsplit_once('\x1f') else {
+            return Err(CoreError::internal(format!(
+                "git log の出力を解釈できません: {record}"
+            )));
+        };
+        let commit = parse_commit_header(header)?;
+
+        let paths: Vec<&str> = name_status
+            .split('\0')
+            .map(|token| token.trim_start_matches('\n'))
+            .filter(|token| !token.is_empty())
+            .collect();
+        let path = match paths.as_slice() {
+            // R100 old new / C75 old new
+            [status, old_path, new_path, ..]
+                if status.starts_with('R') || status.starts_with('C') =>
+            {
+                let path = new_path.to_string();
+                current_path = old_path.to_string();
+                path
+            }
+            [_, path, ..] => {
+                current_path = path.to_string();
+                path.to_string()
+            }
+            _ => current_path.clone(),
+        };
+        entries.push(FileHistoryEntry { commit, path });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(sha: &str, subject: &str, body: &str, name_status: &str) -> String {
+        format!(
+            "\x1e{sha}\x1f{parent}\x1fAlice\x1falice@example.com\x1f1700000000 +0900\x1fBob\x1fbob@example.com\x1f1700000100 -0130\x1f{subject}\x1f{body}\x1f\0\n{name_status}",
+            parent = "p".repeat(40)
+        )
+    }
+
+    #[test]
+    fn parse_raw_date_reads_offset() {
+        assert_eq!(parse_raw_date("1700000000 +0900"), Some((1700000000, 540)));
+        assert_eq!(parse_raw_date("1700000000 -0130"), Some((1700000000, -90)));
+        assert_eq!(parse_raw_date("garbage"), None);
+    }
+
+    #[test]
+    fn parse_history_log_tracks_path_across_rename() {
+        let output = [
+            record(&"c".repeat(40), "edit b", "", "M\0b.txt\0"),
+            record(
+                &"b".repeat(40),
+                "rename",
+                "body line\n",
+                "R100\0a.txt\0b.txt\0",
+            ),
+            record(&"m".repeat(40), "merge", "", ""),
+            record(&"a".repeat(40), "add a", "", "A\0a.txt\0"),
+        ]
+        .concat();
+
+        let entries = parse_history_log(&output, "b.txt").unwrap();
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["b.txt", "b.txt", "a.txt", "a.txt"]);
+
+        let rename = &entries[1].commit;
+        assert_eq!(rename.subject, "rename");
+        assert_eq!(rename.body, "body line");
+        assert_eq!(rename.parents, vec!["p".repeat(40)]);
+        assert_eq!(rename.author.name, "Alice");
+        assert_eq!(rename.author.offset_minutes, 540);
+        assert_eq!(rename.committer.email, "bob@example.com");
+        assert_eq!(rename.committer.offset_minutes, -90);
+    }
+}
@@ -2,444 +2,519 @@ use std::{
     collections::HashMap,
     fs,
     path::{Path, PathBuf},
-    process::Command,
-    sync::{Mutex, OnceLock},
+    sync::{Mutex, OnceLock, RwLock},
 };
 
-use core_types::{BlameLine, GitFileDiff, GitStatus, GitStatusEntry};
+use core_types::{
+    BlameLine, BlameOptions, BranchInfo, ChangedFile, CommitAuthor, CommitRecord, CoreError,
+    FileHistoryEntry, GitBackendKind, GitDecoration, GitFileDiff, GitRepositoryStatus,
+    GitSignature, GitStatus, RefDecoration, StashApplyResult, StashEntry,
+};
+
+mod blame;
+mod branch;
+mod cli;
+mod commit;
+mod details;
+mod diff;
+mod graph;
+mod history;
+mod markers;
+mod native;
+mod split;
+mod staging;
+mod stash;
+mod status;
+mod word_diff;
+
+pub use blame::{blame_parent, blame_revision};
+pub use branch::{create_branch, delete_branch, list_branches, rename_branch, switch_branch};
+pub use cli::CliBackend;
+pub use commit::{commit_template, create_commit};
+pub use details::{commit_details, commit_file_diff};
+pub use diff::parse_unified_diff;
+pub use graph::commit_log;
+pub use history::file_history;
+pub use markers::line_change_markers;
+pub use native::NativeBackend;
+pub use split::split_diff;
+pub use staging::{
+    discard_hunk, discard_lines, stage_file, stage_hunk, stage_lines, undo_discard, unstage_file,
+    unstage_hunk, unstage_lines,
+};
+pub use stash::{apply_stash, create_stash, drop_stash, list_stashes, pop_stash, stash_diff};
+pub use status::repository_status;
 
 static DIFF_CACHE: OnceLock<Mutex<HashMap<String, GitFileDiff>>> = OnceLock::new();
+static BACKEND_KIND: RwLock<GitBackendKind> = RwLock::new(GitBackendKind::Auto);
 
 fn diff_cache() -> &'static Mutex<HashMap<String, GitFileDiff>> {
     DIFF_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
-#[cfg(target_os = "macos")]
-static GIT_BINARY_PATH: OnceLock<String> = OnceLock::new();
-
-#[cfg(target_os = "macos")]
-fn resolve_git_binary_path() -> String {
-    // App Sandbox では /usr/bin/git が xcrun 経由にフォールバックして失敗するケースがあるため、
-    // xcrun を介さない実体 git バイナリを優先する。
-    let candidates = [
-        "/Library/Developer/CommandLineTools/usr/bin/git",
-        "/Applications/Xcode.app/Contents/Developer/usr/bin/git",
-        "/usr/bin/git",
-    ];
-
-    candidates
-        .iter()
-        .find(|path| fs::metadata(path).map(|m| m.is_file()).unwrap_or(false))
-        .unwrap_or(&"/usr/bin/git")
-        .to_string()
+/// Git 操作の実装（libgit2 / git CLI）
+/// パスはすべて repo_root で解決済みのリポジトリ相対パス（区切りは `/`）
+/// 実装できない指定は UnsupportedByBackend を返す。書き換える操作では何も変更する前に返すこと
+pub trait GitBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// search_dir を含むリポジトリの作業ツリーのルート（正規化済み）
+    fn repo_root(&self, search_dir: &Path) -> Result<PathBuf, CoreError>;
+
+    /// revision 時点の内容に対する blame（None は作業ツリー。未コミット行は commit が 0000000）
+    fn blame(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+        revision: Option<&str>,
+        options: &BlameOptions,
+    ) -> Result<Vec<BlameLine>, CoreError>;
+
+    /// `git show --format= <commit> -- <path>` 相当の unified diff（変更がなければ空文字）
+    fn commit_diff(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+        commit: &str,
+    ) -> Result<String, CoreError>;
+
+    /// 作業ツリー差分・ステージ済み差分・未追跡ファイルの差分を連結したもの（なければ空文字）
+    fn working_diff(&self, repo_root: &Path, relative_path: &str) -> Result<String, CoreError>;
+
+    /// `git status --porcelain=v2 --branch --untracked-files=all` 相当
+    fn status(&self, repo_root: &Path) -> Result<GitRepositoryStatus, CoreError>;
+
+    /// 現在のブランチ名（detached HEAD の場合は detached@<short_sha>）
+    fn current_branch(&self, repo_root: &Path) -> Result<String, CoreError>;
+
+    /// ファイルに触れたコミットを新しい順に offset 件飛ばして最大 limit 件（リネームを遡る）
+    fn file_history(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<FileHistoryEntry>, CoreError>;
+
+    /// HEAD・ブランチ・リモートブランチ・タグから辿れるコミットをトポロジカル順に最大 max_count 件
+    fn commits(&self, repo_root: &Path, max_count: usize) -> Result<Vec<CommitRecord>, CoreError>;
+
+    /// ブランチ・リモートブランチ・タグ（と detached HEAD）と、それが指すコミット
+    fn refs(&self, repo_root: &Path) -> Result<Vec<(String, RefDecoration)>, CoreError>;
+
+    /// コミット情報とメッセージ全文
+    fn commit_message(
+        &self,
+        repo_root: &Path,
+        commit: &str,
+    ) -> Result<(CommitRecord, String), CoreError>;
+
+    /// 第一親（ルートコミットは空ツリー）からの変更ファイル一覧（リネーム検出あり）
+    fn changed_files(&self, repo_root: &Path, commit: &str) -> Result<Vec<ChangedFile>, CoreError>;
+
+    /// 第一親からの差分を paths に絞って unified diff 文字列で返す
+    fn commit_file_diff(
+        &self,
+        repo_root: &Path,
+        commit: &str,
+        paths: &[&str],
+    ) -> Result<String, CoreError>;
+
+    /// HEAD とインデックス（stage 0）にあるファイル内容（無ければ None）
+    fn base_versions(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+    ) -> Result<BaseVersions, CoreError>;
+
+    /// ローカルブランチとリモート追跡ブランチ（refs/heads, refs/remotes の参照名順）
+    fn branches(&self, repo_root: &Path) -> Result<Vec<BranchInfo>, CoreError>;
+
+    fn create_branch(
+        &self,
+        repo_root: &Path,
+        name: &str,
+        start_point: Option<&str>,
+    ) -> Result<(), CoreError>;
+
+    /// 作業ツリーの変更と食い違う場合は何も変更せず CheckoutConflict
+    fn switch_branch(&self, repo_root: &Path, name: &str) -> Result<(), CoreError>;
+
+    fn rename_branch(
+        &self,
+        repo_root: &Path,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<(), CoreError>;
+
+    fn delete_branch(&self, repo_root: &Path, name: &str, force: bool) -> Result<(), CoreError>;
+
+    /// インデックスと作業ツリー（cached なら HEAD とインデックス）の1ファイル分の差分
+    /// （リネーム検出なし・内容はバイト列のまま）
+    fn index_diff(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+        cached: bool,
+    ) -> Result<Vec<u8>, CoreError>;
+
+    /// unified diff をインデックス（to_index でなければ作業ツリー）に当てる
+    fn apply_patch(&self, repo_root: &Path, patch: &[u8], to_index: bool) -> Result<(), CoreError>;
+
+    /// 作業ツリーの状態をそのままインデックスに載せる（削除されていればインデックスからも消す）
+    fn stage_file(&self, repo_root: &Path, relative_path: &str) -> Result<(), CoreError>;
+
+    /// インデックスを HEAD の状態に戻す（HEAD に無いファイルはインデックスから外す）
+    fn unstage_file(&self, repo_root: &Path, relative_path: &str) -> Result<(), CoreError>;
+
+    /// 未追跡ファイルを内容なしでインデックスに載せる（`git add -N`。追跡済みなら何もしない）
+    fn intent_to_add(&self, repo_root: &Path, relative_path: &str) -> Result<(), CoreError>;
+
+    /// パスとして解釈した設定値（`git config --path`。未設定なら None）
+    fn config_path(&self, repo_root: &Path, key: &str) -> Result<Option<PathBuf>, CoreError>;
+
+    /// .git 配下のパス（`git rev-parse --git-path`。"hooks" は core.hooksPath に従う）
+    fn git_path(&self, repo_root: &Path, name: &str) -> Result<PathBuf, CoreError>;
+
+    /// コミッターとして使われる名前・メールアドレス
+    fn committer(&self, repo_root: &Path) -> Result<GitSignature, CoreError>;
+
+    /// インデックスの内容でコミットを作り、そのハッシュを返す（フックは実行しない）
+    /// amend で author が無ければ元のコミットの作成者を引き継ぐ
+    fn create_commit(
+        &self,
+        repo_root: &Path,
+        message: &str,
+        amend: bool,
+        author: Option<&CommitAuthor>,
+    ) -> Result<String, CoreError>;
+
+    /// stash の一覧（新しい順）
+    fn stashes(&self, repo_root: &Path) -> Result<Vec<StashEntry>, CoreError>;
+
+    /// stash する変更が無く、何も作らなかった場合は false
+    fn create_stash(
+        &self,
+        repo_root: &Path,
+        message: Option<&str>,
+        include_untracked: bool,
+        staged_only: bool,
+    ) -> Result<bool, CoreError>;
+
+    /// pop ならコンフリクトが無いときだけ stash を削除する
+    /// ローカルの変更が上書きされる場合は何も変更せず StashConflict
+    fn apply_stash(
+        &self,
+        repo_root: &Path,
+        index: usize,
+        pop: bool,
+    ) -> Result<StashApplyResult, CoreError>;
+
+    fn drop_stash(&self, repo_root: &Path, index: usize) -> Result<(), CoreError>;
+
+    /// stash したときの HEAD からの差分（未追跡ファイルは追加として続ける）
+    fn stash_diff(&self, repo_root: &Path, index: usize) -> Result<String, CoreError>;
 }
 
-fn git_command() -> Command {
-    #[cfg(target_os = "macos")]
-    {
-        let mut command = Command::new(
-            GIT_BINARY_PATH
-                .get_or_init(resolve_git_binary_path)
-                .as_str(),
-        );
-        // Xcode 実行環境の環境変数を引き継ぐと xcrun 解決に寄ることがあるため除去する。
-        command.env_remove("DEVELOPER_DIR");
-        command.env_remove("SDKROOT");
-        command
+/// 作業ツリーの比較元になる HEAD・インデックス上の内容
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BaseVersions {
+    pub head: Option<Vec<u8>>,
+    pub index: Option<Vec<u8>>,
+}
+
+/// 使用する Git 実装を切り替える（既定は Auto）
+pub fn set_git_backend(kind: GitBackendKind) {
+    match BACKEND_KIND.write() {
+        Ok(mut current) => *current = kind,
+        Err(poisoned) => *poisoned.into_inner() = kind,
     }
-    #[cfg(not(target_os = "macos"))]
-    {
-        Command::new("git")
+}
+
+pub fn git_backend() -> GitBackendKind {
+    match BACKEND_KIND.read() {
+        Ok(current) => *current,
+        Err(poisoned) => *poisoned.into_inner(),
     }
 }
 
-fn resolve_repo_context(file_path: &str) -> Result<(PathBuf, String), String> {
-    let path = Path::new(file_path);
-    let absolute_path = fs::canonicalize(path)
-        .map_err(|e| format!("対象ファイルの正規化に失敗しました: {file_path}: {e}"))?;
-    let search_dir = absolute_path
-        .parent()
-        .ok_or_else(|| format!("対象ファイルの親ディレクトリを取得できません: {file_path}"))?;
+/// 選択中のバックエンドで読み取り操作を実行する
+/// Auto ではネイティブ実装が失敗したときだけ CLI で再試行し、git が無ければ元のエラーを返す
+fn with_backend<T>(
+    operation: impl Fn(&dyn GitBackend) -> Result<T, CoreError>,
+) -> Result<T, CoreError> {
+    run_with_fallback(operation, should_fall_back)
+}
 
-    let repo_root_output = git_command()
-        .arg("-C")
-        .arg(search_dir)
-        .args(["rev-parse", "--show-toplevel"])
-        .output()
-        .map_err(|e| format!("git コマンドの実行に失敗しました: {e}"))?;
+/// 選択中のバックエンドで作業ツリー・index・ref を書き換える操作を実行する
+/// 途中まで書き換えた操作を CLI でやり直すと二重に適用されるので、Auto でも
+/// ネイティブ実装が書き込み前に UnsupportedByBackend を返したときだけ CLI で再試行する
+fn with_backend_mut<T>(
+    operation: impl Fn(&dyn GitBackend) -> Result<T, CoreError>,
+) -> Result<T, CoreError> {
+    run_with_fallback(operation, |error| {
+        matches!(error, CoreError::UnsupportedByBackend { .. })
+    })
+}
 
-    if !repo_root_output.status.success() {
-        let stderr = String::from_utf8_lossy(&repo_root_output.stderr);
-        return Err(format!("git rev-parse 失敗: {stderr}"));
+fn run_with_fallback<T>(
+    operation: impl Fn(&dyn GitBackend) -> Result<T, CoreError>,
+    fall_back: impl Fn(&CoreError) -> bool,
+) -> Result<T, CoreError> {
+    match git_backend() {
+        GitBackendKind::Native => operation(&NativeBackend),
+        GitBackendKind::Cli => operation(&CliBackend),
+        GitBackendKind::Auto => match operation(&NativeBackend) {
+            Err(error) if fall_back(&error) => match operation(&CliBackend) {
+                Err(CoreError::GitNotFound { .. }) => Err(error),
+                result => result,
+            },
+            result => result,
+        },
     }
+}
 
-    let repo_root_raw = String::from_utf8_lossy(&repo_root_output.stdout);
-    let repo_root = fs::canonicalize(repo_root_raw.trim())
-        .map_err(|e| format!("リポジトリルートの正規化に失敗しました: {e}"))?;
+/// 入力やリポジトリ状態に起因するエラーは CLI でも同じ結果になるので再試行しない
+fn should_fall_back(error: &CoreError) -> bool {
+    matches!(
+        error,
+        CoreError::GitFailed { .. }
+            | CoreError::UnsupportedByBackend { .. }
+            | CoreError::Internal { .. }
+    )
+}
+
+fn resolve_repo_context(
+    backend: &dyn GitBackend,
+    file_path: &str,
+) -> Result<(PathBuf, String), CoreError> {
+    let path = Path::new(file_path);
+    let absolute_path = fs::canonicalize(path).map_err(|e| CoreError::from_io(file_path, &e))?;
+    repo_context_for(backend, file_path, &absolute_path)
+}
+
+/// resolve_repo_context と同じだが、作業ツリーから削除されたファイルも受け付ける（ステージ操作用）
+fn resolve_repo_context_allowing_removed(
+    backend: &dyn GitBackend,
+    file_path: &str,
+) -> Result<(PathBuf, String), CoreError> {
+    let path = Path::new(file_path);
+    let absolute_path = match fs::canonicalize(path) {
+        Ok(absolute_path) => absolute_path,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let Some(file_name) = path.file_name() else {
+                return Err(CoreError::from_io(file_path, &e));
+            };
+            let parent = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            fs::canonicalize(parent)
+                .map_err(|_| CoreError::from_io(file_path, &e))?
+                .join(file_name)
+        }
+        Err(e) => return Err(CoreError::from_io(file_path, &e)),
+    };
+    repo_context_for(backend, file_path, &absolute_path)
+}
+
+fn repo_context_for(
+    backend: &dyn GitBackend,
+    file_path: &str,
+    absolute_path: &Path,
+) -> Result<(PathBuf, String), CoreError> {
+    let search_dir = absolute_path.parent().ok_or_else(|| {
+        CoreError::invalid_argument(format!(
+            "対象ファイルの親ディレクトリを取得できません: {file_path}"
+        ))
+    })?;
+
+    let repo_root = backend.repo_root(search_dir)?;
 
     let relative_path = absolute_path.strip_prefix(&repo_root).map_err(|_| {
-        format!(
+        CoreError::invalid_argument(format!(
             "対象ファイルがリポジトリ配下にありません: file={} repo={}",
             absolute_path.display(),
             repo_root.display()
-        )
+        ))
     })?;
 
     let relative_path = relative_path.to_string_lossy().replace('\\', "/");
     Ok((repo_root, relative_path))
 }
 
-fn resolve_repo_root(target_path: &str) -> Result<PathBuf, String> {
+fn resolve_repo_root(backend: &dyn GitBackend, target_path: &str) -> Result<PathBuf, CoreError> {
     let path = Path::new(target_path);
-    let absolute_path = fs::canonicalize(path)
-        .map_err(|e| format!("対象パスの正規化に失敗しました: {target_path}: {e}"))?;
+    let absolute_path = fs::canonicalize(path).map_err(|e| CoreError::from_io(target_path, &e))?;
     let search_dir = if absolute_path.is_dir() {
         absolute_path.clone()
     } else {
         absolute_path
             .parent()
-            .ok_or_else(|| format!("対象パスの親ディレクトリを取得できません: {target_path}"))?
+            .ok_or_else(|| {
+                CoreError::invalid_argument(format!(
+                    "対象パスの親ディレクトリを取得できません: {target_path}"
+                ))
+            })?
             .to_path_buf()
     };
 
-    let repo_root_output = git_command()
-        .arg("-C")
-        .arg(&search_dir)
-        .args(["rev-parse", "--show-toplevel"])
-        .output()
-        .map_err(|e| format!("git コマンドの実行に失敗しました: {e}"))?;
-
-    if !repo_root_output.status.success() {
-        let stderr = String::from_utf8_lossy(&repo_root_output.stderr);
-        return Err(format!("git rev-parse 失敗: {stderr}"));
-    }
-
-    let repo_root_raw = String::from_utf8_lossy(&repo_root_output.stdout);
-    fs::canonicalize(repo_root_raw.trim())
-        .map_err(|e| format!("リポジトリルートの正規化に失敗しました: {e}"))
+    backend.repo_root(&search_dir)
 }
 
-/// git blame --line-porcelain の出力をパースして BlameLine のリストを返す
-pub fn blame_file(file_path: &str) -> Result<Vec<BlameLine>, String> {
-    let (repo_root, relative_path) = resolve_repo_context(file_path)?;
-
-    let output = git_command()
-        .current_dir(&repo_root)
-        .args(["blame", "--line-porcelain", "--", &relative_path])
-        .output()
-        .map_err(|e| format!("git コマンドの実行に失敗しました: {e}"))?;
+/// リポジトリ直下の .git-blame-ignore-revs（blame.ignoreRevsFile の設定が無くても使う）
+pub(crate) fn ignore_revs_file(repo_root: &Path) -> Option<PathBuf> {
+    let path = repo_root.join(".git-blame-ignore-revs");
+    path.is_file().then_some(path)
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("git blame 失敗: {stderr}"));
+/// 解決できないコミット指定（どちらのバックエンドも git コマンドの失敗として返す）
+pub(crate) fn commit_not_found(command: &str, commit: &str) -> CoreError {
+    CoreError::GitFailed {
+        command: command.to_string(),
+        stderr: format!("コミットが見つかりません: {commit}"),
+        exit_code: None,
     }
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    parse_porcelain(&stdout)
+/// ファイルの各行の blame 情報を返す（未コミットの変更も含む）
+pub fn blame_file(file_path: &str, options: &BlameOptions) -> Result<Vec<BlameLine>, CoreError> {
+    with_backend(|backend| {
+        let (repo_root, relative_path) = resolve_repo_context(backend, file_path)?;
+        backend.blame(&repo_root, &relative_path, None, options)
+    })
 }
 
 /// 指定コミットの対象ファイル差分を unified diff 文字列で返す
-pub fn blame_commit_diff(file_path: &str, commit: &str) -> Result<GitFileDiff, String> {
+pub fn blame_commit_diff(file_path: &str, commit: &str) -> Result<GitFileDiff, CoreError> {
     if file_path.trim().is_empty() {
-        return Err("file_path が空です".to_string());
+        return Err(CoreError::invalid_argument("file_path が空です"));
     }
     if commit.trim().is_empty() {
-        return Err("commit が空です".to_string());
-    }
-
-    let (repo_root, relative_path) = resolve_repo_context(file_path)?;
-    let cache_key = format!("{commit}::{}::{relative_path}", repo_root.display());
-    if let Some(cached) = diff_cache()
-        .lock()
-        .map_err(|e| format!("diff cache lock 失敗: {e}"))?
-        .get(&cache_key)
-        .cloned()
-    {
-        return Ok(cached);
-    }
-
-    let output = git_command()
-        .current_dir(&repo_root)
-        .args([
-            "show",
-            "--no-color",
-            "--format=",
-            commit,
-            "--",
-            &relative_path,
-        ])
-        .output()
-        .map_err(|e| format!("git コマンドの実行に失敗しました: {e}"))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("git show 失敗: {stderr}"));
-    }
-
-    let diff_text = String::from_utf8_lossy(&output.stdout).to_string();
-    if diff_text.trim().is_empty() {
-        return Err("差分が見つかりませんでした".to_string());
-    }
-
-    let diff = GitFileDiff {
-        commit: commit.to_string(),
-        path: file_path.to_string(),
-        diff_text,
-    };
-
-    diff_cache()
-        .lock()
-        .map_err(|e| format!("diff cache lock 失敗: {e}"))?
-        .insert(cache_key, diff.clone());
-    Ok(diff)
-}
-
-/// 対象ファイルの現在差分（staged/unstaged/untracked）を unified diff 文字列で返す
-pub fn git_file_diff(file_path: &str) -> Result<GitFileDiff, String> {
-    if file_path.trim().is_empty() {
-        return Err("file_path が空です".to_string());
+        return Err(CoreError::invalid_argument("commit が空です"));
     }
 
-    let absolute_path = fs::canonicalize(file_path)
-        .map_err(|e| format!("対象ファイルの正規化に失敗しました: {file_path}: {e}"))?;
-    let (repo_root, relative_path) = resolve_repo_context(file_path)?;
-
-    let unstaged_output = git_command()
-        .current_dir(&repo_root)
-        .args(["diff", "--no-color", "--", &relative_path])
-        .output()
-        .map_err(|e| format!("git コマンドの実行に失敗しました: {e}"))?;
-
-    if !unstaged_output.status.success() {
-        let stderr = String::from_utf8_lossy(&unstaged_output.stderr);
-        return Err(format!("git diff 失敗: {stderr}"));
-    }
-
-    let staged_output = git_command()
-        .current_dir(&repo_root)
-        .args(["diff", "--no-color", "--cached", "--", &relative_path])
-        .output()
-        .map_err(|e| format!("git コマンドの実行に失敗しました: {e}"))?;
-
-    if !staged_output.status.success() {
-        let stderr = String::from_utf8_lossy(&staged_output.stderr);
-        return Err(format!("git diff --cached 失敗: {stderr}"));
-    }
-
-    let mut sections: Vec<String> = Vec::new();
-    let unstaged_diff = String::from_utf8_lossy(&unstaged_output.stdout).to_string();
-    if !unstaged_diff.trim().is_empty() {
-        sections.push(unstaged_diff);
-    }
-
-    let staged_diff = String::from_utf8_lossy(&staged_output.stdout).to_string();
-    if !staged_diff.trim().is_empty() {
-        sections.push(staged_diff);
-    }
-
-    if sections.is_empty() {
-        let untracked_output = git_command()
-            .current_dir(&repo_root)
-            .args([
-                "ls-files",
-                "--others",
-                "--exclude-standard",
-                "--",
-                &relative_path,
-            ])
-            .output()
-            .map_err(|e| format!("git コマンドの実行に失敗しました: {e}"))?;
+    with_backend(|backend| {
+        let (repo_root, relative_path) = resolve_repo_context(backend, file_path)?;
+        let cache_key = format!("{commit}::{}::{relative_path}", repo_root.display());
+        if let Some(cached) = diff_cache()
+            .lock()
+            .map_err(|e| CoreError::internal(format!("diff cache lock 失敗: {e}")))?
+            .get(&cache_key)
+            .cloned()
+        {
+            return Ok(cached);
+        }
 
-        if !untracked_output.status.success() {
-            let stderr = String::from_utf8_lossy(&untracked_output.stderr);
-            return Err(format!("git ls-files 失敗: {stderr}"));
+        let diff_text = backend.commit_diff(&repo_root, &relative_path, commit)?;
+        if diff_text.trim().is_empty() {
+            return Err(CoreError::NoChanges {
+                path: file_path.to_string(),
+            });
         }
 
-        if !String::from_utf8_lossy(&untracked_output.stdout)
-            .trim()
-            .is_empty()
-        {
-            let absolute_path_text = absolute_path.to_string_lossy().to_string();
-            let untracked_diff_output = git_command()
-                .current_dir(&repo_root)
-                .args([
-                    "diff",
-                    "--no-color",
-                    "--no-index",
-                    "--",
-                    "/dev/null",
-                    &absolute_path_text,
-                ])
-                .output()
-                .map_err(|e| format!("git コマンドの実行に失敗しました: {e}"))?;
+        let diff = GitFileDiff {
+            commit: commit.to_string(),
+            path: file_path.to_string(),
+            files: parse_unified_diff(&diff_text),
+            diff_text,
+        };
 
-            let status_code = untracked_diff_output.status.code();
-            if !(untracked_diff_output.status.success() || status_code == Some(1)) {
-                let stderr = String::from_utf8_lossy(&untracked_diff_output.stderr);
-                return Err(format!("git diff --no-index 失敗: {stderr}"));
-            }
+        diff_cache()
+            .lock()
+            .map_err(|e| CoreError::internal(format!("diff cache lock 失敗: {e}")))?
+            .insert(cache_key, diff.clone());
+        Ok(diff)
+    })
+}
 
-            let untracked_diff = String::from_utf8_lossy(&untracked_diff_output.stdout).to_string();
-            if !untracked_diff.trim().is_empty() {
-                sections.push(untracked_diff);
-            }
-        }
+/// 対象ファイルの現在差分（staged/unstaged/untracked）を unified diff 文字列で返す
+pub fn git_file_diff(file_path: &str) -> Result<GitFileDiff, CoreError> {
+    if file_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("file_path が空です"));
     }
 
-    if sections.is_empty() {
-        return Err("差分が見つかりませんでした".to_string());
-    }
+    with_backend(|backend| {
+        let (repo_root, relative_path) = resolve_repo_context(backend, file_path)?;
+        let diff_text = backend.working_diff(&repo_root, &relative_path)?;
+        if diff_text.trim().is_empty() {
+            return Err(CoreError::NoChanges {
+                path: file_path.to_string(),
+            });
+        }
 
-    let diff_text = sections.join("\n");
-    Ok(GitFileDiff {
-        commit: "working-tree".to_string(),
-        path: file_path.to_string(),
-        diff_text,
+        Ok(GitFileDiff {
+            commit: "working-tree".to_string(),
+            path: file_path.to_string(),
+            files: parse_unified_diff(&diff_text),
+            diff_text,
+        })
     })
 }
 
 /// リポジトリの変更状態（staged / unstaged / untracked）を返す
-pub fn git_status(root_path: &str) -> Result<GitStatus, String> {
+pub fn git_status(root_path: &str) -> Result<GitStatus, CoreError> {
     if root_path.trim().is_empty() {
-        return Err("root_path が空です".to_string());
-    }
-
-    let repo_root = resolve_repo_root(root_path)?;
-    let output = git_command()
-        .current_dir(&repo_root)
-        .args(["status", "--porcelain", "--untracked-files=all"])
-        .output()
-        .map_err(|e| format!("git コマンドの実行に失敗しました: {e}"))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("git status 失敗: {stderr}"));
+        return Err(CoreError::invalid_argument("root_path が空です"));
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    parse_status_porcelain(&stdout, &repo_root)
+    with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        backend
+            .status(&repo_root)
+            .map(|status| status::classify_status(&status))
+    })
 }
 
 /// 現在のブランチ名を返す（detached HEADの場合は detached@<short_sha>）
-pub fn git_current_branch(root_path: &str) -> Result<String, String> {
+pub fn git_current_branch(root_path: &str) -> Result<String, CoreError> {
     if root_path.trim().is_empty() {
-        return Err("root_path が空です".to_string());
-    }
-
-    let repo_root = resolve_repo_root(root_path)?;
-
-    let branch_output = git_command()
-        .current_dir(&repo_root)
-        .args(["branch", "--show-current"])
-        .output()
-        .map_err(|e| format!("git コマンドの実行に失敗しました: {e}"))?;
-
-    if !branch_output.status.success() {
-        let stderr = String::from_utf8_lossy(&branch_output.stderr);
-        return Err(format!("git branch --show-current 失敗: {stderr}"));
-    }
-
-    let branch_name = String::from_utf8_lossy(&branch_output.stdout)
-        .trim()
-        .to_string();
-    if !branch_name.is_empty() {
-        return Ok(branch_name);
-    }
-
-    let head_output = git_command()
-        .current_dir(&repo_root)
-        .args(["rev-parse", "--short", "HEAD"])
-        .output()
-        .map_err(|e| format!("git コマンドの実行に失敗しました: {e}"))?;
-
-    if !head_output.status.success() {
-        let stderr = String::from_utf8_lossy(&head_output.stderr);
-        return Err(format!("git rev-parse --short HEAD 失敗: {stderr}"));
+        return Err(CoreError::invalid_argument("root_path が空です"));
     }
 
-    let short_sha = String::from_utf8_lossy(&head_output.stdout)
-        .trim()
-        .to_string();
-    if short_sha.is_empty() {
-        return Err("ブランチ名を取得できませんでした".to_string());
-    }
-    Ok(format!("detached@{short_sha}"))
+    with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        backend.current_branch(&repo_root)
+    })
 }
 
-fn parse_status_porcelain(input: &str, repo_root: &Path) -> Result<GitStatus, String> {
-    let mut staged: Vec<GitStatusEntry> = Vec::new();
-    let mut unstaged: Vec<GitStatusEntry> = Vec::new();
-    let mut untracked: Vec<GitStatusEntry> = Vec::new();
-
-    for line in input.lines() {
-        if line.is_empty() {
-            continue;
-        }
-        if line.len() < 3 {
-            continue;
-        }
-
-        let bytes = line.as_bytes();
-        let x = bytes[0] as char;
-        let y = bytes[1] as char;
-        let raw_path = line[3..].trim();
-        if raw_path.is_empty() {
-            continue;
-        }
-
-        let relative_path = normalize_status_path(raw_path);
-        let absolute_path = repo_root
-            .join(relative_path)
-            .to_string_lossy()
-            .replace('\\', "/");
-        let status = format!("{x}{y}");
-
-        if x == '?' && y == '?' {
-            untracked.push(GitStatusEntry {
-                path: absolute_path,
-                status,
-            });
-            continue;
-        }
-
-        if x != ' ' {
-            staged.push(GitStatusEntry {
-                path: absolute_path.clone(),
-                status: status.clone(),
-            });
-        }
-        if y != ' ' {
-            unstaged.push(GitStatusEntry {
-                path: absolute_path,
-                status,
-            });
+/// porcelain の XY ステータスをツリー表示用の状態に変換する
+/// 作業ツリー側（Y）の変更をインデックス側（X）より優先する
+pub fn status_decoration(status: &str) -> Option<GitDecoration> {
+    let bytes = status.as_bytes();
+    if bytes.len() < 2 {
+        return None;
+    }
+    let (x, y) = (bytes[0], bytes[1]);
+    match (x, y) {
+        (b'?', b'?') => return Some(GitDecoration::Untracked),
+        (b'U', _) | (_, b'U') | (b'A', b'A') | (b'D', b'D') => {
+            return Some(GitDecoration::Conflicted)
         }
+        _ => {}
     }
 
-    Ok(GitStatus {
-        staged,
-        unstaged,
-        untracked,
-    })
+    let code = if y != b' ' { y } else { x };
+    match code {
+        b'M' | b'T' => Some(GitDecoration::Modified),
+        b'A' => Some(GitDecoration::Added),
+        b'D' => Some(GitDecoration::Deleted),
+        b'R' | b'C' => Some(GitDecoration::Renamed),
+        _ => None,
+    }
 }
 
-fn normalize_status_path(raw_path: &str) -> String {
-    let target = if let Some((_, new_path)) = raw_path.split_once(" -> ") {
-        new_path
-    } else {
-        raw_path
-    };
-
-    let trimmed = target.trim();
-    let without_quotes = trimmed
-        .strip_prefix('"')
-        .and_then(|s| s.strip_suffix('"'))
-        .unwrap_or(trimmed);
-    without_quotes.replace("\\\"", "\"")
+/// line-porcelain 形式の出力をパースする
+fn parse_porcelain(input: &str) -> Result<Vec<BlameLine>, CoreError> {
+    Ok(parse_porcelain_entries(input)?
+        .into_iter()
+        .map(|(_, line)| line)
+        .collect())
 }
 
-/// line-porcelain 形式の出力をパースする
-fn parse_porcelain(input: &str) -> Result<Vec<BlameLine>, String> {
+/// line-porcelain 形式の出力を、各行の完全なコミットハッシュと組にしてパースする
+/// （BlameLine.commit は短縮ハッシュなので、コミットを引き直す場合はこちらを使う）
+fn parse_porcelain_entries(input: &str) -> Result<Vec<(&str, BlameLine)>, CoreError> {
     let mut results = Vec::new();
     let mut lines = input.lines().peekable();
 
@@ -461,14 +536,18 @@ fn parse_porcelain(input: &str) -> Result<Vec<BlameLine>, String> {
             continue;
         }
 
+        let original_line: u32 = parts[1]
+            .parse()
+            .map_err(|_| CoreError::internal(format!("行番号のパースに失敗: {}", parts[1])))?;
         let final_line: u32 = parts[2]
             .parse()
-            .map_err(|_| format!("行番号のパースに失敗: {}", parts[2]))?;
+            .map_err(|_| CoreError::internal(format!("行番号のパースに失敗: {}", parts[2])))?;
 
         let commit = commit_hash[..7].to_string();
         let mut author = String::new();
         let mut author_time: i64 = 0;
         let mut summary = String::new();
+        let mut original_path = String::new();
 
         // メタデータ行を読む（TAB始まりの行まで）
         for line in lines.by_ref() {
@@ -483,16 +562,23 @@ fn parse_porcelain(input: &str) -> Result<Vec<BlameLine>, String> {
                 author_time = val.parse().unwrap_or(0);
             } else if let Some(val) = line.strip_prefix("summary ") {
                 summary = val.to_string();
+            } else if let Some(val) = line.strip_prefix("filename ") {
+                original_path = val.to_string();
             }
         }
 
-        results.push(BlameLine {
-            line: final_line,
-            author,
-            author_time,
-            summary,
-            commit,
-        });
+        results.push((
+            commit_hash,
+            BlameLine {
+                line: final_line,
+                author,
+                author_time,
+                summary,
+                commit,
+                original_path,
+                original_line,
+            },
+        ));
     }
 
     Ok(results)
@@ -501,6 +587,10 @@ fn parse_porcelain(input: &str) -> Result<Vec<BlameLine>, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use core_types::{
+        BlameMoveDetection, CommitAuthor, CommitOptions, DiffFileStatus, DiffLineRef,
+        GitConflictKind, GitFileState, LineChangeKind, RefKind, StashCreateOptions,
+    };
     use std::{fs, path::PathBuf};
 
     fn repository_file_path() -> PathBuf {
@@ -593,6 +683,29 @@ filename lib.rs
         assert_eq!(result[1].author_time, 1700100000);
     }
 
+    /// 短縮ハッシュが同じコミットも、完全なハッシュでは区別できる
+    #[test]
+    fn parse_porcelain_entries_keep_full_hashes() {
+        let input = "\
+abcdef1000000000000000000000000000000000 1 1 1
+author Alice
+summary first commit
+filename lib.rs
+\tline1
+abcdef1111111111111111111111111111111111 2 2 1
+author Bob
+summary second commit
+filename lib.rs
+\tline2
+";
+
+        let entries = parse_porcelain_entries(input).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, "abcdef1000000000000000000000000000000000");
+        assert_eq!(entries[1].0, "abcdef1111111111111111111111111111111111");
+        assert_eq!(entries[0].1.commit, entries[1].1.commit);
+    }
+
     /// 空入力の場合は空Vecを返す
     #[test]
     fn parse_porcelain_empty_input() {
@@ -612,7 +725,7 @@ filename lib.rs
     fn resolve_repo_context_for_repository_file() {
         let file_path = repository_file_path();
         let file_str = file_path.to_str().unwrap();
-        let (repo_root, relative_path) = resolve_repo_context(file_str).unwrap();
+        let (repo_root, relative_path) = resolve_repo_context(&NativeBackend, file_str).unwrap();
 
         assert!(repo_root.exists());
         assert!(!relative_path.is_empty());
@@ -633,9 +746,9 @@ filename lib.rs
         let file = tmp_dir.join("sample.txt");
         fs::write(&file, "hello").unwrap();
 
-        let result = resolve_repo_context(file.to_str().unwrap());
+        let result = resolve_repo_context(&CliBackend, file.to_str().unwrap());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("git rev-parse 失敗"));
+        assert!(matches!(result, Err(CoreError::NotAGitRepo { .. })));
         let _ = fs::remove_dir_all(tmp_dir);
     }
 
@@ -643,7 +756,7 @@ filename lib.rs
     #[test]
     fn blame_file_on_real_repo() {
         let file_path = repository_file_path();
-        let result = blame_file(file_path.to_str().unwrap());
+        let result = blame_file(file_path.to_str().unwrap(), &BlameOptions::default());
         // CI 環境や浅いクローンでは失敗する可能性があるのでエラーは許容
         if let Ok(lines) = result {
             assert!(!lines.is_empty());
@@ -658,15 +771,24 @@ filename lib.rs
     /// 存在しないファイルに対してはエラーを返す
     #[test]
     fn blame_file_nonexistent() {
-        let result = blame_file("/nonexistent/path/file.rs");
-        assert!(result.is_err());
+        let result = blame_file("/nonexistent/path/file.rs", &BlameOptions::default());
+        assert_eq!(
+            result.unwrap_err(),
+            CoreError::NotFound {
+                path: "/nonexistent/path/file.rs".into()
+            }
+        );
     }
 
     /// 無効コミットに対する差分取得はエラーを返す
     #[test]
     fn blame_commit_diff_invalid_commit_returns_err() {
-        let result = blame_commit_diff(file!(), "this-is-not-a-commit");
-        assert!(result.is_err());
+        let file_path = repository_file_path();
+        let result = blame_commit_diff(file_path.to_str().unwrap(), "this-is-not-a-commit");
+        assert!(matches!(
+            result,
+            Err(CoreError::GitFailed { ref command, .. }) if command == "git show"
+        ));
     }
 
     /// 空入力はバリデーションエラーにする
@@ -689,7 +811,7 @@ filename lib.rs
         fs::create_dir_all(&tmp_dir).unwrap();
 
         let run_git = |args: &[&str]| {
-            let output = git_command()
+            let output = cli::git_command()
                 .current_dir(&tmp_dir)
                 .args(args)
                 .output()
@@ -717,6 +839,9 @@ filename lib.rs
         assert!(diff.diff_text.contains("diff --git"));
         assert!(diff.diff_text.contains("-let value = 1"));
         assert!(diff.diff_text.contains("+let value = 2"));
+        assert_eq!(diff.files.len(), 1);
+        assert_eq!(diff.files[0].new_path.as_deref(), Some("sample.swift"));
+        assert_eq!(diff.files[0].hunks[0].lines.len(), 2);
 
         let _ = fs::remove_dir_all(tmp_dir);
     }
@@ -737,36 +862,22 @@ filename lib.rs
 
         let result = git_file_diff(file_path.to_str().unwrap());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("git rev-parse 失敗"));
+        assert!(matches!(result, Err(CoreError::NotAGitRepo { .. })));
 
         let _ = fs::remove_dir_all(tmp_dir);
     }
 
     #[test]
-    fn parse_status_porcelain_classifies_entries() {
-        let repo_root = PathBuf::from("/tmp/blink-repo");
-        let input = " M src/working.swift\nM  src/staged.swift\nMM src/both.swift\nR  src/old_name.swift -> src/new_name.swift\n?? src/new_file.swift\n";
-
-        let status = parse_status_porcelain(input, &repo_root).unwrap();
-
-        assert_eq!(status.staged.len(), 3);
-        assert_eq!(status.unstaged.len(), 2);
-        assert_eq!(status.untracked.len(), 1);
-
-        assert_eq!(status.staged[0].status, "M ");
-        assert!(status.staged[0].path.ends_with("/src/staged.swift"));
-        assert!(status.unstaged[0].path.ends_with("/src/working.swift"));
-        assert!(status.untracked[0].path.ends_with("/src/new_file.swift"));
-        assert!(status.staged[2].path.ends_with("/src/new_name.swift"));
-    }
-
-    #[test]
-    fn parse_status_porcelain_empty_returns_no_entries() {
-        let repo_root = PathBuf::from("/tmp/blink-repo");
-        let status = parse_status_porcelain("", &repo_root).unwrap();
-        assert!(status.staged.is_empty());
-        assert!(status.unstaged.is_empty());
-        assert!(status.untracked.is_empty());
+    fn status_decoration_maps_porcelain_codes() {
+        assert_eq!(status_decoration(" M"), Some(GitDecoration::Modified));
+        assert_eq!(status_decoration("A "), Some(GitDecoration::Added));
+        assert_eq!(status_decoration("AM"), Some(GitDecoration::Modified));
+        assert_eq!(status_decoration("R "), Some(GitDecoration::Renamed));
+        assert_eq!(status_decoration(" D"), Some(GitDecoration::Deleted));
+        assert_eq!(status_decoration("??"), Some(GitDecoration::Untracked));
+        assert_eq!(status_decoration("UU"), Some(GitDecoration::Conflicted));
+        assert_eq!(status_decoration("AA"), Some(GitDecoration::Conflicted));
+        assert_eq!(status_decoration("!!"), None);
     }
 
     #[test]
@@ -782,7 +893,7 @@ filename lib.rs
         fs::create_dir_all(&tmp_dir).unwrap();
         let result = git_status(tmp_dir.to_str().unwrap());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("git rev-parse 失敗"));
+        assert!(matches!(result, Err(CoreError::NotAGitRepo { .. })));
         let _ = fs::remove_dir_all(tmp_dir);
     }
 
@@ -799,7 +910,7 @@ filename lib.rs
         fs::create_dir_all(&tmp_dir).unwrap();
 
         let run_git = |args: &[&str]| {
-            let output = git_command()
+            let output = cli::git_command()
                 .current_dir(&tmp_dir)
                 .args(args)
                 .output()
@@ -839,8 +950,1315 @@ filename lib.rs
 
         let result = git_current_branch(tmp_dir.to_str().unwrap());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("git rev-parse 失敗"));
+        assert!(matches!(result, Err(CoreError::NotAGitRepo { .. })));
 
         let _ = fs::remove_dir_all(tmp_dir);
     }
+
+    fn run_git(dir: &Path, args: &[&str]) -> String {
+        let output = cli::git_command()
+            .current_dir(dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    /// main ブランチに1コミットある一時リポジトリを作る
+    fn init_test_repo(label: &str) -> PathBuf {
+        let tmp_dir = std::env::temp_dir().join(format!(
+            "blink-core-git-{label}-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&tmp_dir).unwrap();
+        let tmp_dir = fs::canonicalize(tmp_dir).unwrap();
+
+        run_git(&tmp_dir, &["init"]);
+        run_git(&tmp_dir, &["symbolic-ref", "HEAD", "refs/heads/main"]);
+        run_git(&tmp_dir, &["config", "user.name", "Blink Test"]);
+        run_git(&tmp_dir, &["config", "user.email", "blink@example.com"]);
+        fs::write(
+            tmp_dir.join("sample.swift"),
+            "let a = 1\nlet b = 2\nlet c = 3\n",
+        )
+        .unwrap();
+        run_git(&tmp_dir, &["add", "sample.swift"]);
+        run_git(&tmp_dir, &["commit", "-m", "initial"]);
+        tmp_dir
+    }
+
+    /// ヘッダーの index 行など実装差が出る行を除いた、比較用の差分行
+    fn diff_body(diff_text: &str) -> Vec<&str> {
+        diff_text
+            .lines()
+            .filter(|line| {
+                line.starts_with("@@")
+                    || line.starts_with('+')
+                    || line.starts_with('-')
+                    || line.starts_with(' ')
+            })
+            .collect()
+    }
+
+    #[test]
+    fn native_and_cli_backends_agree() {
+        let repo = init_test_repo("parity");
+        fs::write(
+            repo.join("sample.swift"),
+            "let a = 1\nlet b = 20\nlet c = 3\n",
+        )
+        .unwrap();
+        fs::create_dir_all(repo.join("src/nested")).unwrap();
+        fs::write(repo.join("src/nested/new.swift"), "let n = 1\n").unwrap();
+        fs::write(repo.join("staged.swift"), "let s = 1\n").unwrap();
+        run_git(&repo, &["add", "staged.swift"]);
+
+        let native = NativeBackend;
+        let cli = CliBackend;
+
+        assert_eq!(
+            native.repo_root(&repo.join("src")).unwrap(),
+            cli.repo_root(&repo.join("src")).unwrap()
+        );
+        assert_eq!(native.status(&repo).unwrap(), cli.status(&repo).unwrap());
+        assert_eq!(
+            native.current_branch(&repo).unwrap(),
+            cli.current_branch(&repo).unwrap()
+        );
+
+        let options = BlameOptions::default();
+        let native_blame = native.blame(&repo, "sample.swift", None, &options).unwrap();
+        let cli_blame = cli.blame(&repo, "sample.swift", None, &options).unwrap();
+        assert_eq!(native_blame.len(), 3);
+        for (n, c) in native_blame.iter().zip(&cli_blame) {
+            assert_eq!(
+                (n.line, &n.commit, &n.author),
+                (c.line, &c.commit, &c.author)
+            );
+            assert_eq!(n.summary, c.summary);
+        }
+        assert_eq!(native_blame[1].commit, "0000000");
+
+        for path in ["sample.swift", "staged.swift", "src/nested/new.swift"] {
+            let native_diff = native.working_diff(&repo, path).unwrap();
+            let cli_diff = cli.working_diff(&repo, path).unwrap();
+            assert!(!native_diff.is_empty(), "{path}");
+            assert_eq!(diff_body(&native_diff), diff_body(&cli_diff), "{path}");
+        }
+
+        let head = run_git(&repo, &["rev-parse", "HEAD"]);
+        let native_diff = native
+            .commit_diff(&repo, "sample.swift", head.trim())
+            .unwrap();
+        let cli_diff = cli.commit_diff(&repo, "sample.swift", head.trim()).unwrap();
+        assert!(native_diff.contains("+let b = 2"));
+        assert_eq!(diff_body(&native_diff), diff_body(&cli_diff));
+
+        for path in ["sample.swift", "staged.swift", "src/nested/new.swift"] {
+            assert_eq!(
+                native.base_versions(&repo, path).unwrap(),
+                cli.base_versions(&repo, path).unwrap(),
+                "{path}"
+            );
+        }
+        let staged = native.base_versions(&repo, "staged.swift").unwrap();
+        assert_eq!(staged.head, None);
+        assert_eq!(staged.index.as_deref(), Some(&b"let s = 1\n"[..]));
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn file_history_follows_renames_and_paginates() {
+        let repo = init_test_repo("history");
+        fs::write(repo.join("other.txt"), "unrelated\n").unwrap();
+        run_git(&repo, &["add", "other.txt"]);
+        run_git(&repo, &["commit", "-m", "unrelated"]);
+        fs::create_dir_all(repo.join("src")).unwrap();
+        run_git(&repo, &["mv", "sample.swift", "src/renamed.swift"]);
+        run_git(&repo, &["commit", "-m", "move sample", "-m", "into src"]);
+        fs::write(
+            repo.join("src/renamed.swift"),
+            "let a = 1\nlet b = 2\nlet c = 3\nlet d = 4\n",
+        )
+        .unwrap();
+        run_git(&repo, &["commit", "-am", "add d"]);
+
+        let native = NativeBackend
+            .file_history(&repo, "src/renamed.swift", 10, 0)
+            .unwrap();
+        let cli = CliBackend
+            .file_history(&repo, "src/renamed.swift", 10, 0)
+            .unwrap();
+        assert_eq!(native, cli);
+
+        let summary: Vec<(&str, &str)> = native
+            .iter()
+            .map(|e| (e.commit.subject.as_str(), e.path.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("add d", "src/renamed.swift"),
+                ("move sample", "src/renamed.swift"),
+                ("initial", "sample.swift"),
+            ]
+        );
+        assert_eq!(native[1].commit.body, "into src");
+        assert_eq!(native[2].commit.parents, Vec::<String>::new());
+
+        let file = repo.join("src/renamed.swift");
+        let page = file_history(file.to_str().unwrap(), 1, 1).unwrap();
+        assert_eq!(page, vec![native[1].clone()]);
+        assert!(matches!(
+            file_history(file.to_str().unwrap(), 0, 0),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn commit_log_builds_graph_with_refs() {
+        let repo = init_test_repo("log");
+        run_git(&repo, &["checkout", "-q", "-b", "feature"]);
+        fs::write(repo.join("feature.swift"), "let f = 1\n").unwrap();
+        run_git(&repo, &["add", "feature.swift"]);
+        run_git(&repo, &["commit", "-m", "feature work"]);
+        run_git(&repo, &["checkout", "-q", "main"]);
+        fs::write(repo.join("main.swift"), "let m = 1\n").unwrap();
+        run_git(&repo, &["add", "main.swift"]);
+        run_git(&repo, &["commit", "-m", "main work"]);
+        run_git(
+            &repo,
+            &["merge", "--no-ff", "-m", "merge feature", "feature"],
+        );
+        run_git(&repo, &["tag", "-a", "v1", "-m", "release"]);
+
+        for backend in [&NativeBackend as &dyn GitBackend, &CliBackend] {
+            let commits = backend.commits(&repo, 10).unwrap();
+            assert_eq!(commits.len(), 4, "{}", backend.name());
+            assert_eq!(commits[0].subject, "merge feature");
+            assert_eq!(commits.last().unwrap().subject, "initial");
+        }
+        let mut native_refs = NativeBackend.refs(&repo).unwrap();
+        let mut cli_refs = CliBackend.refs(&repo).unwrap();
+        native_refs.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+        cli_refs.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+        assert_eq!(native_refs, cli_refs);
+
+        let rows = commit_log(repo.to_str().unwrap(), 10, 0).unwrap();
+        assert!(rows[0].is_merge);
+        let names: Vec<&str> = rows[0].refs.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["main", "v1"]);
+        assert!(rows[0].refs[0].is_head);
+        assert!(rows.last().unwrap().is_branch_point);
+
+        let page = commit_log(repo.to_str().unwrap(), 2, 2).unwrap();
+        assert_eq!(page, rows[2..].to_vec());
+
+        run_git(&repo, &["checkout", "-q", "--detach", "HEAD~1"]);
+        let refs = NativeBackend.refs(&repo).unwrap();
+        assert!(refs.iter().any(|(_, r)| r.name == "HEAD" && r.is_head));
+        assert_eq!(refs, CliBackend.refs(&repo).unwrap());
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn commit_details_lists_changed_files_and_trailers() {
+        let repo = init_test_repo("details");
+        fs::write(repo.join("notes.txt"), "one\ntwo\nthree\nfour\nfive\nsix\n").unwrap();
+        run_git(&repo, &["add", "notes.txt"]);
+        run_git(&repo, &["commit", "-m", "add notes"]);
+
+        run_git(&repo, &["mv", "notes.txt", "renamed.txt"]);
+        fs::write(
+            repo.join("renamed.txt"),
+            "one\ntwo\nthree\nfour\nfive\nsix\nseven\n",
+        )
+        .unwrap();
+        fs::write(
+            repo.join("sample.swift"),
+            "let a = 1\nlet b = 20\nlet c = 3\n",
+        )
+        .unwrap();
+        fs::write(repo.join("logo.bin"), [0u8, 159, 146, 150, 0, 1]).unwrap();
+        run_git(&repo, &["add", "-A"]);
+        run_git(
+            &repo,
+            &[
+                "commit",
+                "-m",
+                "Rework notes",
+                "-m",
+                "Explain why.",
+                "-m",
+                "Co-authored-by: Bob <bob@example.com>\nSigned-off-by: Blink Test <blink@example.com>",
+            ],
+        );
+
+        for backend in [&NativeBackend as &dyn GitBackend, &CliBackend] {
+            let (record, message) = backend.commit_message(&repo, "HEAD").unwrap();
+            assert_eq!(record.subject, "Rework notes", "{}", backend.name());
+            assert!(message.starts_with("Rework notes\n\nExplain why."));
+        }
+        let mut native_files = NativeBackend.changed_files(&repo, "HEAD").unwrap();
+        let mut cli_files = CliBackend.changed_files(&repo, "HEAD").unwrap();
+        native_files.sort_by(|a, b| a.path.cmp(&b.path));
+        cli_files.sort_by(|a, b| a.path.cmp(&b.path));
+        // 類似度は libgit2 と git で算出方法が違うので、有無だけ揃っていればよい
+        for file in native_files.iter_mut().chain(cli_files.iter_mut()) {
+            file.similarity = file.similarity.map(|_| 0);
+        }
+        assert_eq!(native_files, cli_files);
+
+        let details = commit_details(repo.to_str().unwrap(), "HEAD").unwrap();
+        let keys: Vec<&str> = details.trailers.iter().map(|t| t.key.as_str()).collect();
+        assert_eq!(keys, vec!["Co-authored-by", "Signed-off-by"]);
+        let renamed = details
+            .files
+            .iter()
+            .find(|f| f.path == "renamed.txt")
+            .unwrap();
+        assert_eq!(renamed.status, DiffFileStatus::Renamed);
+        assert_eq!(renamed.old_path.as_deref(), Some("notes.txt"));
+        assert!(renamed.similarity.is_some_and(|s| s >= 50));
+        assert_eq!((renamed.insertions, renamed.deletions), (1, 0));
+        let binary = details.files.iter().find(|f| f.path == "logo.bin").unwrap();
+        assert!(binary.is_binary);
+        assert_eq!(binary.status, DiffFileStatus::Added);
+
+        let native_diff = NativeBackend
+            .commit_file_diff(&repo, "HEAD", &["renamed.txt", "notes.txt"])
+            .unwrap();
+        let cli_diff = CliBackend
+            .commit_file_diff(&repo, "HEAD", &["renamed.txt", "notes.txt"])
+            .unwrap();
+        assert_eq!(diff_body(&native_diff), diff_body(&cli_diff));
+        let diff = commit_file_diff(
+            repo.to_str().unwrap(),
+            "HEAD",
+            "renamed.txt",
+            Some("notes.txt"),
+        )
+        .unwrap();
+        assert_eq!(diff.files.len(), 1);
+        assert_eq!(diff.files[0].status, DiffFileStatus::Renamed);
+
+        // ルートコミットは全ファイルが追加扱い
+        let root = run_git(&repo, &["rev-list", "--max-parents=0", "HEAD"]);
+        let root_files = commit_details(repo.to_str().unwrap(), root.trim())
+            .unwrap()
+            .files;
+        assert_eq!(root_files.len(), 1);
+        assert_eq!(root_files[0].status, DiffFileStatus::Added);
+        assert!(matches!(
+            NativeBackend.changed_files(&repo, "no-such-commit"),
+            Err(CoreError::GitFailed { .. })
+        ));
+        assert!(matches!(
+            CliBackend.changed_files(&repo, "no-such-commit"),
+            Err(CoreError::GitFailed { .. })
+        ));
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn missing_commit_is_git_failed_on_both_backends() {
+        let repo = init_test_repo("missing-commit");
+
+        for backend in [&NativeBackend as &dyn GitBackend, &CliBackend] {
+            let name = backend.name();
+            assert!(
+                matches!(
+                    backend.blame(
+                        &repo,
+                        "sample.swift",
+                        Some("no-such-commit"),
+                        &BlameOptions::default()
+                    ),
+                    Err(CoreError::GitFailed { .. })
+                ),
+                "{name}"
+            );
+            assert!(
+                matches!(
+                    backend.commit_diff(&repo, "sample.swift", "no-such-commit"),
+                    Err(CoreError::GitFailed { .. })
+                ),
+                "{name}"
+            );
+            assert!(
+                matches!(
+                    backend.commit_message(&repo, "no-such-commit"),
+                    Err(CoreError::GitFailed { .. })
+                ),
+                "{name}"
+            );
+            assert!(
+                matches!(
+                    backend.commit_file_diff(&repo, "no-such-commit", &["sample.swift"]),
+                    Err(CoreError::GitFailed { .. })
+                ),
+                "{name}"
+            );
+        }
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn blame_parent_steps_past_reformat_commit() {
+        let repo = init_test_repo("blame-parent");
+        run_git(&repo, &["mv", "sample.swift", "renamed.swift"]);
+        run_git(&repo, &["commit", "-m", "rename"]);
+        fs::write(
+            repo.join("renamed.swift"),
+            "let a = 1\nlet b  =  2\nlet c = 3\n",
+        )
+        .unwrap();
+        run_git(&repo, &["commit", "-am", "reformat"]);
+
+        let head = run_git(&repo, &["rev-parse", "HEAD"]);
+        for backend in [&NativeBackend as &dyn GitBackend, &CliBackend] {
+            let lines = backend
+                .blame(
+                    &repo,
+                    "renamed.swift",
+                    Some(head.trim()),
+                    &BlameOptions::default(),
+                )
+                .unwrap();
+            assert_eq!(lines.len(), 3, "{}", backend.name());
+            assert_eq!(lines[1].summary, "reformat");
+            assert_eq!(lines[1].original_line, 2);
+            assert_eq!(lines[0].summary, "initial");
+            assert_eq!(lines[0].original_path, "sample.swift");
+        }
+
+        let root = repo.to_str().unwrap();
+        let lines = blame_revision(root, "HEAD", "renamed.swift").unwrap();
+        assert!(matches!(
+            blame_revision(root, "--output=blame.txt", "renamed.swift"),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+        assert!(!repo.join("blame.txt").exists());
+        let navigation = blame_parent(root, &lines[1]).unwrap();
+        assert_eq!(navigation.path, "renamed.swift");
+        assert_eq!(navigation.line, 2);
+        assert_eq!(navigation.lines[1].summary, "initial");
+        assert_eq!(
+            navigation.revision,
+            run_git(&repo, &["rev-parse", "HEAD~1"]).trim()
+        );
+
+        // ファイルを追加したコミットより前には遡れない
+        let initial = &navigation.lines[1];
+        assert!(matches!(
+            blame_parent(root, initial),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn blame_options_apply_whitespace_and_mailmap() {
+        let repo = init_test_repo("blame-options");
+        fs::write(
+            repo.join("sample.swift"),
+            "let a = 1\nlet b  =  2\nlet c = 3\n",
+        )
+        .unwrap();
+        run_git(&repo, &["commit", "-am", "reformat"]);
+        fs::write(
+            repo.join(".mailmap"),
+            "Canonical Name <blink@example.com> Blink Test <blink@example.com>\n",
+        )
+        .unwrap();
+
+        for backend in [&NativeBackend as &dyn GitBackend, &CliBackend] {
+            let defaults = BlameOptions::default();
+            let lines = backend
+                .blame(&repo, "sample.swift", None, &defaults)
+                .unwrap();
+            assert_eq!(lines[1].summary, "reformat", "{}", backend.name());
+            assert_eq!(lines[0].author, "Canonical Name", "{}", backend.name());
+
+            let options = BlameOptions {
+                ignore_whitespace: true,
+                use_mailmap: false,
+                ..BlameOptions::default()
+            };
+            let lines = backend
+                .blame(&repo, "sample.swift", None, &options)
+                .unwrap();
+            assert_eq!(lines[1].summary, "initial", "{}", backend.name());
+            assert_eq!(lines[0].author, "Blink Test", "{}", backend.name());
+        }
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn blame_options_ignore_revs_and_move_detection_use_cli() {
+        let repo = init_test_repo("blame-ignore-revs");
+        fs::write(
+            repo.join("sample.swift"),
+            "let a = 1\nlet b = 2 // reformatted\nlet c = 3\n",
+        )
+        .unwrap();
+        run_git(&repo, &["commit", "-am", "reformat"]);
+        let reformat = run_git(&repo, &["rev-parse", "HEAD"]);
+        fs::write(repo.join(".git-blame-ignore-revs"), &reformat).unwrap();
+
+        let file_path = repo.join("sample.swift");
+        let file_path = file_path.to_str().unwrap();
+        let lines = blame_file(file_path, &BlameOptions::default()).unwrap();
+        assert_eq!(lines[1].summary, "initial");
+        let options = BlameOptions {
+            use_ignore_revs: false,
+            ..BlameOptions::default()
+        };
+        let lines = blame_file(file_path, &options).unwrap();
+        assert_eq!(lines[1].summary, "reformat");
+        // libgit2 で扱えない設定は UnsupportedByBackend を返して CLI に任せる
+        assert!(matches!(
+            NativeBackend.blame(&repo, "sample.swift", None, &BlameOptions::default()),
+            Err(CoreError::UnsupportedByBackend { .. })
+        ));
+
+        // ファイル内で移動したブロックは元のコミットのまま
+        fs::write(
+            repo.join("moved.swift"),
+            "let first = \"alpha bravo charlie delta\"\nlet second = 2\n",
+        )
+        .unwrap();
+        run_git(&repo, &["add", "moved.swift"]);
+        run_git(&repo, &["commit", "-m", "add moved"]);
+        fs::write(
+            repo.join("moved.swift"),
+            "let second = 2\nlet first = \"alpha bravo charlie delta\"\n",
+        )
+        .unwrap();
+        run_git(&repo, &["commit", "-am", "move"]);
+        let options = BlameOptions {
+            move_detection: Some(BlameMoveDetection::WithinFile),
+            ..BlameOptions::default()
+        };
+        let path = repo.join("moved.swift");
+        let lines = blame_file(path.to_str().unwrap(), &options).unwrap();
+        assert_eq!(lines[1].summary, "add moved");
+        assert_eq!(lines[1].original_line, 1);
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn repository_status_reports_branch_renames_and_submodules() {
+        let upstream = init_test_repo("status-upstream");
+        let repo = init_test_repo("status-v2");
+        let upstream_path = upstream.to_str().unwrap();
+        run_git(&repo, &["remote", "add", "origin", upstream_path]);
+        run_git(&repo, &["fetch", "origin"]);
+        run_git(&repo, &["reset", "--hard", "origin/main"]);
+        run_git(&repo, &["branch", "--set-upstream-to", "origin/main"]);
+        fs::write(repo.join("extra.swift"), "let x = 1\n").unwrap();
+        run_git(&repo, &["add", "extra.swift"]);
+        run_git(&repo, &["commit", "-m", "extra"]);
+
+        run_git(
+            &repo,
+            &[
+                "-c",
+                "protocol.file.allow=always",
+                "submodule",
+                "add",
+                upstream_path,
+                "vendor/lib",
+            ],
+        );
+        run_git(&repo, &["commit", "-m", "add submodule"]);
+        fs::write(repo.join("vendor/lib/scratch.txt"), "scratch\n").unwrap();
+
+        run_git(&repo, &["mv", "extra.swift", "renamed extra.swift"]);
+        fs::write(repo.join("sample.swift"), "let a = 10\n").unwrap();
+        fs::write(repo.join("new file.swift"), "let n = 1\n").unwrap();
+
+        let native = NativeBackend.status(&repo).unwrap();
+        assert_eq!(native, CliBackend.status(&repo).unwrap());
+
+        assert_eq!(native.branch.head.as_deref(), Some("main"));
+        assert_eq!(native.branch.upstream.as_deref(), Some("origin/main"));
+        assert_eq!(
+            (native.branch.ahead, native.branch.behind),
+            (Some(2), Some(0))
+        );
+
+        let root = repo.to_string_lossy().replace('\\', "/");
+        let paths: Vec<&str> = native
+            .entries
+            .iter()
+            .map(|e| e.path.strip_prefix(&root).unwrap())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/renamed extra.swift",
+                "/sample.swift",
+                "/vendor/lib",
+                "/new file.swift"
+            ]
+        );
+        let renamed = &native.entries[0];
+        assert_eq!(renamed.index, GitFileState::Renamed);
+        assert_eq!(
+            renamed.original_path.as_deref(),
+            Some(format!("{root}/extra.swift").as_str())
+        );
+        let submodule = native.entries[2].submodule.as_ref().unwrap();
+        assert!(submodule.has_untracked_changes);
+        assert!(!submodule.commit_changed);
+
+        let status = git_status(repo.to_str().unwrap()).unwrap();
+        assert_eq!(status.staged[0].status, "R ");
+        assert_eq!(status.untracked.len(), 1);
+
+        let _ = fs::remove_dir_all(repo);
+        let _ = fs::remove_dir_all(upstream);
+    }
+
+    #[test]
+    fn branches_list_with_tracking_on_both_backends() {
+        let upstream = init_test_repo("branches-upstream");
+        let repo = init_test_repo("branches");
+        run_git(
+            &repo,
+            &["remote", "add", "origin", upstream.to_str().unwrap()],
+        );
+        run_git(&repo, &["fetch", "origin"]);
+        run_git(&repo, &["reset", "--hard", "origin/main"]);
+        run_git(&repo, &["branch", "--set-upstream-to", "origin/main"]);
+        fs::write(repo.join("sample.swift"), "let a = 10\n").unwrap();
+        run_git(&repo, &["commit", "-am", "local work"]);
+        run_git(&repo, &["remote", "set-head", "origin", "main"]);
+
+        for backend in [&NativeBackend as &dyn GitBackend, &CliBackend] {
+            backend
+                .create_branch(&repo, "topic", Some("origin/main"))
+                .unwrap();
+            let branches = backend.branches(&repo).unwrap();
+            let names: Vec<&str> = branches.iter().map(|b| b.name.as_str()).collect();
+            assert_eq!(
+                names,
+                vec!["main", "topic", "origin/main"],
+                "{}",
+                backend.name()
+            );
+
+            let main = &branches[0];
+            assert!(main.is_head);
+            assert_eq!(main.upstream.as_deref(), Some("origin/main"));
+            assert_eq!((main.ahead, main.behind), (Some(1), Some(0)));
+            assert_eq!(main.last_commit.subject, "local work");
+            // リモート追跡ブランチから作ると上流が設定される
+            assert_eq!(branches[1].upstream.as_deref(), Some("origin/main"));
+            assert_eq!(branches[2].kind, RefKind::RemoteBranch);
+
+            assert!(matches!(
+                backend.create_branch(&repo, "topic", None),
+                Err(CoreError::BranchExists { .. })
+            ));
+            run_git(&repo, &["branch", "-D", "topic"]);
+        }
+        assert_eq!(
+            NativeBackend.branches(&repo).unwrap(),
+            CliBackend.branches(&repo).unwrap()
+        );
+
+        let _ = fs::remove_dir_all(repo);
+        let _ = fs::remove_dir_all(upstream);
+    }
+
+    #[test]
+    fn branch_switch_rename_and_delete_report_typed_errors() {
+        for backend in [&NativeBackend as &dyn GitBackend, &CliBackend] {
+            let repo = init_test_repo(&format!("branch-ops-{}", backend.name()));
+            backend.create_branch(&repo, "topic", None).unwrap();
+            backend.switch_branch(&repo, "topic").unwrap();
+            fs::write(repo.join("sample.swift"), "let a = 2\n").unwrap();
+            run_git(&repo, &["commit", "-am", "topic change"]);
+
+            // 食い違わない変更は持ち越して切り替える
+            fs::write(repo.join("notes.txt"), "memo\n").unwrap();
+            backend.switch_branch(&repo, "main").unwrap();
+            assert_eq!(run_git(&repo, &["branch", "--show-current"]).trim(), "main");
+            assert!(repo.join("notes.txt").exists());
+
+            // 切り替え先で内容が変わるファイルの変更は上書きせず拒否する
+            fs::write(repo.join("sample.swift"), "let local = 1\n").unwrap();
+            match backend.switch_branch(&repo, "topic") {
+                Err(CoreError::CheckoutConflict { branch, paths }) => {
+                    assert_eq!(branch, "topic");
+                    assert_eq!(paths, vec!["sample.swift"], "{}", backend.name());
+                }
+                other => panic!("{}: {other:?}", backend.name()),
+            }
+            assert_eq!(
+                fs::read_to_string(repo.join("sample.swift")).unwrap(),
+                "let local = 1\n"
+            );
+            assert!(matches!(
+                backend.switch_branch(&repo, "missing"),
+                Err(CoreError::BranchNotFound { .. })
+            ));
+
+            assert!(matches!(
+                backend.delete_branch(&repo, "topic", false),
+                Err(CoreError::BranchNotMerged { .. })
+            ));
+            assert!(matches!(
+                backend.delete_branch(&repo, "main", true),
+                Err(CoreError::InvalidArgument { .. })
+            ));
+            backend.rename_branch(&repo, "topic", "feature").unwrap();
+            assert!(matches!(
+                backend.rename_branch(&repo, "feature", "main"),
+                Err(CoreError::BranchExists { .. })
+            ));
+            assert!(matches!(
+                backend.rename_branch(&repo, "feature", "bad..name"),
+                Err(CoreError::InvalidArgument { .. })
+            ));
+            // チェックアウト中のブランチを改名すると HEAD も追従する
+            backend.rename_branch(&repo, "main", "trunk").unwrap();
+            assert_eq!(
+                run_git(&repo, &["branch", "--show-current"]).trim(),
+                "trunk"
+            );
+
+            backend.delete_branch(&repo, "feature", true).unwrap();
+            let names: Vec<String> = backend
+                .branches(&repo)
+                .unwrap()
+                .into_iter()
+                .map(|b| b.name)
+                .collect();
+            assert_eq!(names, vec!["trunk"]);
+
+            let _ = fs::remove_dir_all(repo);
+        }
+    }
+
+    #[test]
+    fn staging_index_operations_agree_on_both_backends() {
+        for backend in [&NativeBackend as &dyn GitBackend, &CliBackend] {
+            let repo = init_test_repo(&format!("staging-backend-{}", backend.name()));
+            fs::write(repo.join("sample.swift"), "let a = 1\nlet b = 20\n").unwrap();
+
+            // 作業ツリー側の差分をそのままインデックスに当てるとファイル全体のステージと同じになる
+            let diff = backend.index_diff(&repo, "sample.swift", false).unwrap();
+            assert!(String::from_utf8_lossy(&diff).contains("+let b = 20"));
+            backend.apply_patch(&repo, &diff, true).unwrap();
+            assert_eq!(
+                run_git(&repo, &["diff", "--cached", "--name-only"]).trim(),
+                "sample.swift"
+            );
+            assert!(backend
+                .index_diff(&repo, "sample.swift", false)
+                .unwrap()
+                .is_empty());
+            let cached = backend.index_diff(&repo, "sample.swift", true).unwrap();
+            assert!(String::from_utf8_lossy(&cached).contains("-let c = 3"));
+
+            backend.unstage_file(&repo, "sample.swift").unwrap();
+            assert!(run_git(&repo, &["diff", "--cached"]).is_empty());
+
+            // 削除も未追跡ファイルもステージできる
+            fs::remove_file(repo.join("sample.swift")).unwrap();
+            fs::write(repo.join("new.swift"), "let n = 1\n").unwrap();
+            backend.stage_file(&repo, "sample.swift").unwrap();
+            backend.stage_file(&repo, "new.swift").unwrap();
+            assert_eq!(
+                run_git(&repo, &["diff", "--cached", "--name-status"]),
+                "A\tnew.swift\nD\tsample.swift\n"
+            );
+            backend.unstage_file(&repo, "new.swift").unwrap();
+            assert_eq!(
+                run_git(&repo, &["diff", "--cached", "--name-status"]),
+                "D\tsample.swift\n"
+            );
+
+            let _ = fs::remove_dir_all(repo);
+        }
+    }
+
+    #[test]
+    fn stage_hunks_and_lines_then_undo_discards() {
+        let repo = init_test_repo("staging-hunks");
+        let original: String = (1..=12).map(|n| format!("line {n}\n")).collect();
+        fs::write(repo.join("long.txt"), &original).unwrap();
+        run_git(&repo, &["add", "long.txt"]);
+        run_git(&repo, &["commit", "-m", "add long"]);
+        let modified = original
+            .replace("line 2\n", "LINE 2\n")
+            .replace("line 11\n", "LINE 11\n");
+        fs::write(repo.join("long.txt"), &modified).unwrap();
+        let file_path = repo.join("long.txt").to_string_lossy().to_string();
+        let cached_diff = || run_git(&repo, &["diff", "--cached"]);
+
+        stage_hunk(&file_path, 1).unwrap();
+        assert!(cached_diff().contains("+LINE 11"));
+        assert!(!cached_diff().contains("+LINE 2"));
+        // ステージ後は2つ目のハンクが未ステージ側から消えている
+        assert!(matches!(
+            stage_hunk(&file_path, 1),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+        unstage_hunk(&file_path, 0).unwrap();
+        assert!(cached_diff().is_empty());
+
+        // 追加行だけを選ぶと削除行は文脈として残る
+        stage_lines(
+            &file_path,
+            &[DiffLineRef {
+                old_line: None,
+                new_line: Some(2),
+            }],
+        )
+        .unwrap();
+        assert!(cached_diff().contains(" line 2\n+LINE 2\n"));
+        unstage_file(&file_path).unwrap();
+        assert!(cached_diff().is_empty());
+
+        discard_hunk(&file_path, 1).unwrap();
+        discard_lines(
+            &file_path,
+            &[
+                DiffLineRef {
+                    old_line: Some(2),
+                    new_line: None,
+                },
+                DiffLineRef {
+                    old_line: None,
+                    new_line: Some(2),
+                },
+            ],
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(repo.join("long.txt")).unwrap(), original);
+        assert!(matches!(
+            stage_hunk(&file_path, 0),
+            Err(CoreError::NoChanges { .. })
+        ));
+
+        // 新しい破棄から順に戻す
+        let root = repo.to_string_lossy().to_string();
+        assert_eq!(undo_discard(&root).unwrap(), Some(file_path.clone()));
+        assert!(fs::read_to_string(repo.join("long.txt"))
+            .unwrap()
+            .contains("LINE 2\n"));
+        assert_eq!(undo_discard(&root).unwrap(), Some(file_path.clone()));
+        assert_eq!(fs::read_to_string(repo.join("long.txt")).unwrap(), modified);
+        assert_eq!(undo_discard(&root).unwrap(), None);
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn stage_lines_of_added_and_deleted_files() {
+        let repo = init_test_repo("staging-added-deleted");
+        fs::write(repo.join("new.txt"), "one\ntwo\nthree\n").unwrap();
+        let new_path = repo.join("new.txt").to_string_lossy().to_string();
+        let name_status = || run_git(&repo, &["diff", "--cached", "--name-status"]);
+
+        // 未追跡ファイルも一部の行だけステージできる
+        stage_lines(
+            &new_path,
+            &[DiffLineRef {
+                old_line: None,
+                new_line: Some(1),
+            }],
+        )
+        .unwrap();
+        assert_eq!(name_status(), "A\tnew.txt\n");
+        assert_eq!(run_git(&repo, &["show", ":new.txt"]), "one\n");
+
+        // すべての行のステージを外すと、空のファイルを残さず未追跡に戻る
+        unstage_lines(
+            &new_path,
+            &[DiffLineRef {
+                old_line: None,
+                new_line: Some(1),
+            }],
+        )
+        .unwrap();
+        assert!(name_status().is_empty());
+        assert_eq!(
+            run_git(&repo, &["status", "--porcelain", "--", "new.txt"]),
+            "?? new.txt\n"
+        );
+
+        stage_hunk(&new_path, 0).unwrap();
+        assert_eq!(run_git(&repo, &["show", ":new.txt"]), "one\ntwo\nthree\n");
+        unstage_hunk(&new_path, 0).unwrap();
+        assert!(name_status().is_empty());
+
+        // 削除されたファイルは全行を選ぶと削除としてステージされる
+        fs::remove_file(repo.join("sample.swift")).unwrap();
+        let deleted_path = repo.join("sample.swift").to_string_lossy().to_string();
+        stage_hunk(&deleted_path, 0).unwrap();
+        assert_eq!(name_status(), "D\tsample.swift\n");
+        unstage_hunk(&deleted_path, 0).unwrap();
+        assert!(name_status().is_empty());
+
+        // 削除の破棄はファイルを作り直し、元に戻すと再び削除される
+        discard_hunk(&deleted_path, 0).unwrap();
+        assert_eq!(
+            fs::read_to_string(repo.join("sample.swift")).unwrap(),
+            "let a = 1\nlet b = 2\nlet c = 3\n"
+        );
+        let root = repo.to_string_lossy().to_string();
+        assert_eq!(undo_discard(&root).unwrap(), Some(deleted_path));
+        assert!(!repo.join("sample.swift").exists());
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn intent_to_add_shows_untracked_file_as_added_on_both_backends() {
+        for backend in [&NativeBackend as &dyn GitBackend, &CliBackend] {
+            let repo = init_test_repo(&format!("intent-to-add-{}", backend.name()));
+            fs::write(repo.join("new.txt"), "one\n").unwrap();
+            assert!(backend
+                .index_diff(&repo, "new.txt", false)
+                .unwrap()
+                .is_empty());
+
+            backend.intent_to_add(&repo, "new.txt").unwrap();
+            let diff = backend.index_diff(&repo, "new.txt", false).unwrap();
+            assert!(
+                String::from_utf8_lossy(&diff).contains("+one"),
+                "{}",
+                backend.name()
+            );
+            assert!(run_git(&repo, &["diff", "--", "new.txt"]).contains("new file mode"));
+            // 追跡済みのファイルには何もしない
+            fs::write(repo.join("sample.swift"), "let a = 2\n").unwrap();
+            backend.intent_to_add(&repo, "sample.swift").unwrap();
+            assert!(run_git(&repo, &["diff", "--cached"]).is_empty());
+
+            let _ = fs::remove_dir_all(repo);
+        }
+    }
+
+    #[test]
+    fn commit_backends_amend_and_override_author() {
+        for backend in [&NativeBackend as &dyn GitBackend, &CliBackend] {
+            let repo = init_test_repo(&format!("commit-backend-{}", backend.name()));
+            assert_eq!(backend.committer(&repo).unwrap().name, "Blink Test");
+            assert_eq!(
+                backend.git_path(&repo, "hooks").unwrap(),
+                repo.join(".git/hooks")
+            );
+            assert_eq!(backend.config_path(&repo, "commit.template").unwrap(), None);
+            run_git(&repo, &["config", "commit.template", "template.txt"]);
+            assert_eq!(
+                backend.config_path(&repo, "commit.template").unwrap(),
+                Some(repo.join("template.txt"))
+            );
+
+            fs::write(repo.join("sample.swift"), "let a = 2\n").unwrap();
+            run_git(&repo, &["add", "sample.swift"]);
+            let author = CommitAuthor {
+                name: "Alice".to_string(),
+                email: "alice@example.com".to_string(),
+            };
+            let sha = backend
+                .create_commit(&repo, "Second\n\nBody", false, Some(&author))
+                .unwrap();
+            assert_eq!(run_git(&repo, &["rev-parse", "HEAD"]).trim(), sha);
+            assert_eq!(
+                run_git(&repo, &["log", "-1", "--format=%an|%cn|%s|%b"]).trim(),
+                "Alice|Blink Test|Second|Body"
+            );
+
+            // amend は作成者を引き継ぎ、履歴を増やさない
+            let amended = backend
+                .create_commit(&repo, "Second amended", true, None)
+                .unwrap();
+            assert_ne!(amended, sha);
+            assert_eq!(
+                run_git(&repo, &["log", "-1", "--format=%an|%s"]).trim(),
+                "Alice|Second amended"
+            );
+            assert_eq!(run_git(&repo, &["rev-list", "--count", "HEAD"]).trim(), "2");
+
+            let _ = fs::remove_dir_all(repo);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn create_commit_runs_hooks_with_template_and_sign_off() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let repo = init_test_repo("commit-hooks");
+        let root = repo.to_string_lossy().to_string();
+        let write_hook = |name: &str, script: &str| {
+            let path = repo.join(".git/hooks").join(name);
+            fs::write(&path, script).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        };
+
+        fs::write(repo.join("template.txt"), "Summary\n\n# Explain why\n").unwrap();
+        run_git(&repo, &["config", "commit.template", "template.txt"]);
+        assert_eq!(
+            commit_template(&root).unwrap().as_deref(),
+            Some("Summary\n\n# Explain why\n")
+        );
+        assert!(matches!(
+            create_commit(&root, "Add feature", &CommitOptions::default()),
+            Err(CoreError::NoChanges { .. })
+        ));
+
+        fs::write(repo.join("sample.swift"), "let a = 2\n").unwrap();
+        run_git(&repo, &["add", "sample.swift"]);
+        assert!(matches!(
+            create_commit(
+                &root,
+                "Summary\n\n# Explain why\n",
+                &CommitOptions::default()
+            ),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+
+        write_hook("pre-commit", "#!/bin/sh\necho lint failed\nexit 3\n");
+        match create_commit(&root, "Add feature", &CommitOptions::default()) {
+            Err(CoreError::HookFailed {
+                hook,
+                output,
+                exit_code,
+            }) => {
+                assert_eq!(hook, "pre-commit");
+                assert_eq!(output, "lint failed");
+                assert_eq!(exit_code, Some(3));
+            }
+            other => panic!("{other:?}"),
+        }
+        assert_eq!(run_git(&repo, &["rev-list", "--count", "HEAD"]).trim(), "1");
+
+        // commit-msg フックが追記したトレーラーもコミットに残る
+        write_hook("pre-commit", "#!/bin/sh\necho checked\n");
+        write_hook(
+            "commit-msg",
+            "#!/bin/sh\necho 'Reviewed-by: Bob <bob@example.com>' >> \"$1\"\n",
+        );
+        let result = create_commit(
+            &root,
+            "Add feature\n# comment\n",
+            &CommitOptions {
+                sign_off: true,
+                ..CommitOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            result.details.message,
+            "Add feature\n\nSigned-off-by: Blink Test <blink@example.com>\nReviewed-by: Bob <bob@example.com>"
+        );
+        assert_eq!(result.details.trailers.len(), 2);
+        assert_eq!(result.details.files.len(), 1);
+        let hooks: Vec<(&str, &str)> = result
+            .hook_outputs
+            .iter()
+            .map(|h| (h.hook.as_str(), h.output.as_str()))
+            .collect();
+        assert_eq!(hooks, vec![("pre-commit", "checked"), ("commit-msg", "")]);
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn stash_create_list_show_and_pop_on_both_backends() {
+        for backend in [&NativeBackend as &dyn GitBackend, &CliBackend] {
+            let repo = init_test_repo(&format!("stash-{}", backend.name()));
+            assert!(backend.stashes(&repo).unwrap().is_empty());
+            assert!(!backend.create_stash(&repo, None, false, false).unwrap());
+
+            fs::write(
+                repo.join("sample.swift"),
+                "let a = 10\nlet b = 2\nlet c = 3\n",
+            )
+            .unwrap();
+            assert!(backend.create_stash(&repo, None, false, false).unwrap());
+            fs::write(
+                repo.join("sample.swift"),
+                "let a = 1\nlet b = 2\nlet c = 30\n",
+            )
+            .unwrap();
+            fs::write(repo.join("new.swift"), "let n = 1\n").unwrap();
+            assert!(backend
+                .create_stash(&repo, Some("with new file"), true, false)
+                .unwrap());
+            assert!(!repo.join("new.swift").exists());
+
+            let stashes = backend.stashes(&repo).unwrap();
+            assert_eq!(stashes.len(), 2, "{}", backend.name());
+            assert_eq!(stashes[0].message, "with new file");
+            assert_eq!(stashes[0].branch.as_deref(), Some("main"));
+            assert!(
+                stashes[1].message.ends_with(" initial"),
+                "{}",
+                backend.name()
+            );
+            assert_eq!(stashes[1].committer.name, "Blink Test");
+
+            // 未追跡ファイルは追加として差分に含まれる
+            let mut paths: Vec<String> = parse_unified_diff(&backend.stash_diff(&repo, 0).unwrap())
+                .into_iter()
+                .filter_map(|file| file.new_path)
+                .collect();
+            paths.sort();
+            assert_eq!(
+                paths,
+                vec!["new.swift", "sample.swift"],
+                "{}",
+                backend.name()
+            );
+
+            let result = backend.apply_stash(&repo, 1, true).unwrap();
+            assert!(result.conflicts.is_empty());
+            assert!(result.dropped);
+            assert_eq!(
+                fs::read_to_string(repo.join("sample.swift")).unwrap(),
+                "let a = 10\nlet b = 2\nlet c = 3\n"
+            );
+            backend.drop_stash(&repo, 0).unwrap();
+            assert!(backend.stashes(&repo).unwrap().is_empty());
+
+            let _ = fs::remove_dir_all(repo);
+        }
+    }
+
+    #[test]
+    fn stash_apply_reports_conflicts_and_local_changes() {
+        let repo = init_test_repo("stash-conflicts");
+        let root = repo.to_string_lossy().to_string();
+        fs::write(
+            repo.join("sample.swift"),
+            "let a = 10\nlet b = 2\nlet c = 3\n",
+        )
+        .unwrap();
+        fs::write(repo.join("staged.swift"), "let s = 1\n").unwrap();
+        run_git(&repo, &["add", "staged.swift"]);
+        let created = create_stash(
+            &root,
+            &StashCreateOptions {
+                staged_only: true,
+                ..StashCreateOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(created.index, 0);
+        assert!(!repo.join("staged.swift").exists());
+        // libgit2 で作れない stash は何もせずに UnsupportedByBackend を返す
+        assert!(matches!(
+            NativeBackend.create_stash(&repo, None, false, true),
+            Err(CoreError::UnsupportedByBackend { .. })
+        ));
+        assert!(matches!(
+            create_stash(
+                &root,
+                &StashCreateOptions {
+                    staged_only: true,
+                    include_untracked: true,
+                    message: None,
+                }
+            ),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+
+        let entry = create_stash(&root, &StashCreateOptions::default()).unwrap();
+        assert_eq!(list_stashes(&root).unwrap()[0].sha, entry.sha);
+        assert_eq!(list_stashes(&root).unwrap().len(), 2);
+
+        // ローカルの変更と食い違う stash は適用しない
+        fs::write(repo.join("sample.swift"), "let local = 1\n").unwrap();
+        for backend in [&NativeBackend as &dyn GitBackend, &CliBackend] {
+            match backend.apply_stash(&repo, 0, false) {
+                Err(CoreError::StashConflict { stash, paths }) => {
+                    assert_eq!(stash, "stash@{0}", "{}", backend.name());
+                    assert_eq!(paths, vec!["sample.swift"], "{}", backend.name());
+                }
+                other => panic!("{}: {other:?}", backend.name()),
+            }
+        }
+        assert!(matches!(
+            apply_stash(&root, 0),
+            Err(CoreError::StashConflict { .. })
+        ));
+        assert_eq!(
+            fs::read_to_string(repo.join("sample.swift")).unwrap(),
+            "let local = 1\n"
+        );
+
+        // コミット済みの変更とぶつかる stash はマーカー付きで適用し、pop でも残す
+        run_git(&repo, &["commit", "-am", "conflicting change"]);
+        let result = pop_stash(&root, 0).unwrap();
+        assert_eq!(
+            result.conflicts,
+            vec![repo.join("sample.swift").to_string_lossy().to_string()]
+        );
+        assert!(!result.dropped);
+        assert!(fs::read_to_string(repo.join("sample.swift"))
+            .unwrap()
+            .contains("<<<<<<<"));
+        assert_eq!(list_stashes(&root).unwrap().len(), 2);
+        assert!(matches!(
+            apply_stash(&root, 1),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+
+        let files = stash_diff(&root, 1).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].new_path.as_deref(), Some("staged.swift"));
+        assert!(matches!(
+            drop_stash(&root, 5),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn line_change_markers_compare_buffer_with_index_and_head() {
+        let repo = init_test_repo("markers");
+        let file = repo.join("sample.swift");
+        fs::write(&file, "let a = 1\nlet b = 20\nlet c = 3\n").unwrap();
+        run_git(&repo, &["add", "sample.swift"]);
+        fs::write(&file, "let z = 0\nlet a = 1\nlet b = 20\nlet c = 3\n").unwrap();
+
+        let markers = line_change_markers(file.to_str().unwrap(), None).unwrap();
+        let summary: Vec<_> = markers.iter().map(|m| (m.line, m.kind, m.staged)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, LineChangeKind::Added, false),
+                (3, LineChangeKind::Modified, true),
+            ]
+        );
+
+        // 未保存のバッファ内容はディスク上のファイルより優先される
+        let markers =
+            line_change_markers(file.to_str().unwrap(), Some("let a = 1\nlet b = 20\n")).unwrap();
+        let summary: Vec<_> = markers.iter().map(|m| (m.line, m.kind, m.staged)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, LineChangeKind::Modified, true),
+                (3, LineChangeKind::DeletedAbove, false),
+            ]
+        );
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn native_current_branch_handles_detached_and_unborn_head() {
+        let repo = init_test_repo("native-branch");
+        let short_sha = run_git(&repo, &["rev-parse", "--short", "HEAD"]);
+        run_git(&repo, &["checkout", "--detach"]);
+        assert_eq!(
+            NativeBackend.current_branch(&repo).unwrap(),
+            format!("detached@{}", short_sha.trim())
+        );
+
+        run_git(&repo, &["checkout", "--orphan", "fresh"]);
+        assert_eq!(NativeBackend.current_branch(&repo).unwrap(), "fresh");
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    #[test]
+    fn native_status_reports_conflicts() {
+        let repo = init_test_repo("native-conflict");
+        run_git(&repo, &["checkout", "-b", "topic"]);
+        fs::write(repo.join("sample.swift"), "let a = 10\n").unwrap();
+        run_git(&repo, &["commit", "-am", "topic"]);
+        run_git(&repo, &["checkout", "main"]);
+        fs::write(repo.join("sample.swift"), "let a = 100\n").unwrap();
+        run_git(&repo, &["commit", "-am", "main"]);
+        let _ = cli::git_command()
+            .current_dir(&repo)
+            .args(["merge", "topic"])
+            .output()
+            .unwrap();
+
+        let native = NativeBackend.status(&repo).unwrap();
+        assert_eq!(native, CliBackend.status(&repo).unwrap());
+        assert_eq!(
+            native.entries[0].conflict,
+            Some(GitConflictKind::BothModified)
+        );
+        let status = git_status(repo.to_str().unwrap()).unwrap();
+        assert_eq!(status.unstaged[0].status, "UU");
+
+        let _ = fs::remove_dir_all(repo);
+    }
+
+    /// マージコミットの結合差分はネイティブでは扱わず、Auto では CLI にフォールバックする
+    #[test]
+    fn merge_commit_diff_falls_back_to_cli() {
+        let repo = init_test_repo("merge-fallback");
+        run_git(&repo, &["checkout", "-b", "topic"]);
+        fs::write(
+            repo.join("sample.swift"),
+            "let a = 10\nlet b = 2\nlet c = 3\n",
+        )
+        .unwrap();
+        run_git(&repo, &["commit", "-am", "topic"]);
+        run_git(&repo, &["checkout", "main"]);
+        fs::write(
+            repo.join("sample.swift"),
+            "let a = 100\nlet b = 2\nlet c = 3\n",
+        )
+        .unwrap();
+        run_git(&repo, &["commit", "-am", "main"]);
+        let _ = cli::git_command()
+            .current_dir(&repo)
+            .args(["merge", "topic"])
+            .output()
+            .unwrap();
+        fs::write(
+            repo.join("sample.swift"),
+            "let a = 1000\nlet b = 2\nlet c = 3\n",
+        )
+        .unwrap();
+        run_git(&repo, &["commit", "-am", "merge"]);
+        let merge = run_git(&repo, &["rev-parse", "HEAD"]);
+
+        assert!(matches!(
+            NativeBackend.commit_diff(&repo, "sample.swift", merge.trim()),
+            Err(CoreError::UnsupportedByBackend { .. })
+        ));
+        let file_path = repo.join("sample.swift");
+        let diff = blame_commit_diff(file_path.to_str().unwrap(), merge.trim()).unwrap();
+        assert!(diff.diff_text.contains("diff --cc"));
+        assert!(diff.files[0].is_combined);
+
+        let _ = fs::remove_dir_all(repo);
+    }
 }
@@ -0,0 +1,208 @@
+use std::{collections::BTreeMap, fs};
+
+use core_types::{CoreError, LineChangeKind, LineChangeMarker};
+use git2::{DiffOptions, Patch};
+
+use crate::{resolve_repo_context, with_backend, BaseVersions};
+
+/// 行番号の範囲だけを持つハンク（件数 0 の範囲は「直前の行の後ろ」を指す）
+#[derive(Debug, Clone, Copy)]
+struct LineRange {
+    old_start: u32,
+    old_lines: u32,
+    new_start: u32,
+    new_lines: u32,
+}
+
+impl LineRange {
+    fn first_old(&self) -> u32 {
+        if self.old_lines == 0 {
+            self.old_start + 1
+        } else {
+            self.old_start
+        }
+    }
+}
+
+/// 開いているファイルのガター用変更マーカーを返す
+///
+/// `text` に未保存のバッファ内容を渡すと、ディスク上のファイルの代わりに比較する。
+/// 未ステージの変更はインデックスと、ステージ済みの変更は HEAD とインデックスの差から求め、
+/// バッファの行番号に合わせて返す。
+pub fn line_change_markers(
+    file_path: &str,
+    text: Option<&str>,
+) -> Result<Vec<LineChangeMarker>, CoreError> {
+    if file_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("file_path が空です"));
+    }
+
+    let base = with_backend(|backend| {
+        let (repo_root, relative_path) = resolve_repo_context(backend, file_path)?;
+        backend.base_versions(&repo_root, &relative_path)
+    })?;
+
+    let buffer = match text {
+        Some(text) => text.as_bytes().to_vec(),
+        None => fs::read(file_path).map_err(|e| CoreError::from_io(file_path, &e))?,
+    };
+    compute_markers(&base, &buffer)
+}
+
+fn compute_markers(base: &BaseVersions, buffer: &[u8]) -> Result<Vec<LineChangeMarker>, CoreError> {
+    let index = base.index.as_deref().unwrap_or_default();
+    let head = base.head.as_deref().unwrap_or_default();
+
+    let unstaged = line_ranges(index, buffer)?;
+    let staged = line_ranges(head, index)?;
+
+    let mut markers: BTreeMap<u32, LineChangeMarker> = BTreeMap::new();
+    for range in &unstaged {
+        push_markers(&mut markers, range, false, Some);
+    }
+    // ステージ済みの変更はインデックスの行番号なので、未ステージの変更分ずらしてバッファに合わせる
+    for range in &staged {
+        push_markers(&mut markers, range, true, |line| {
+            map_index_line(line, &unstaged)
+        });
+    }
+    Ok(markers.into_values().collect())
+}
+
+/// コンテキスト行なしで比較したハンクの行範囲（バイナリは空）
+fn line_ranges(old: &[u8], new: &[u8]) -> Result<Vec<LineRange>, CoreError> {
+    let mut options = DiffOptions::new();
+    options.context_lines(0).interhunk_lines(0);
+    let patch = Patch::from_buffers(old, None, new, None, Some(&mut options))
+        .map_err(|e| CoreError::internal(format!("行差分の計算に失敗しました: {e}")))?;
+
+    let mut ranges = Vec::with_capacity(patch.num_hunks());
+    for index in 0..patch.num_hunks() {
+        let (hunk, _) = patch
+            .hunk(index)
+            .map_err(|e| CoreError::internal(format!("行差分の計算に失敗しました: {e}")))?;
+        ranges.push(LineRange {
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+        });
+    }
+    Ok(ranges)
+}
+
+/// 既に同じ行にマーカーがあれば追加しない（未ステージの変更を優先する）
+fn push_markers(
+    markers: &mut BTreeMap<u32, LineChangeMarker>,
+    range: &LineRange,
+    staged: bool,
+    map_line: impl Fn(u32) -> Option<u32>,
+) {
+    let (kind, lines) = if range.new_lines == 0 {
+        (
+            LineChangeKind::DeletedAbove,
+            range.new_start + 1..range.new_start + 2,
+        )
+    } else if range.old_lines == 0 {
+        (
+            LineChangeKind::Added,
+            range.new_start..range.new_start + range.new_lines,
+        )
+    } else {
+        (
+            LineChangeKind::Modified,
+            range.new_start..range.new_start + range.new_lines,
+        )
+    };
+
+    for line in lines.filter_map(map_line) {
+        markers
+            .entry(line)
+            .or_insert(LineChangeMarker { line, kind, staged });
+    }
+}
+
+/// インデックスの行番号をバッファの行番号に変換する（未ステージの変更で置き換えられた行は None）
+fn map_index_line(line: u32, unstaged: &[LineRange]) -> Option<u32> {
+    let mut shift: i64 = 0;
+    for range in unstaged {
+        let first_old = range.first_old();
+        if line < first_old {
+            break;
+        }
+        if line < first_old + range.old_lines {
+            return None;
+        }
+        shift += i64::from(range.new_lines) - i64::from(range.old_lines);
+    }
+    u32::try_from(i64::from(line) + shift).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base(head: Option<&str>, index: Option<&str>) -> BaseVersions {
+        BaseVersions {
+            head: head.map(|t| t.as_bytes().to_vec()),
+            index: index.map(|t| t.as_bytes().to_vec()),
+        }
+    }
+
+    fn marker(line: u32, kind: LineChangeKind, staged: bool) -> LineChangeMarker {
+        LineChangeMarker { line, kind, staged }
+    }
+
+    #[test]
+    fn markers_for_unstaged_changes() {
+        let original = "a\nb\nc\nd\n";
+        let base = base(Some(original), Some(original));
+
+        let markers = compute_markers(&base, b"a\nB\nc\nnew\nd\n").unwrap();
+        assert_eq!(
+            markers,
+            vec![
+                marker(2, LineChangeKind::Modified, false),
+                marker(4, LineChangeKind::Added, false),
+            ]
+        );
+
+        let markers = compute_markers(&base, b"a\nd\n").unwrap();
+        assert_eq!(
+            markers,
+            vec![marker(2, LineChangeKind::DeletedAbove, false)]
+        );
+    }
+
+    #[test]
+    fn markers_map_staged_changes_through_unstaged_edits() {
+        let base = base(Some("a\nb\nc\nd\n"), Some("a\nb\nc\nD\n"));
+
+        // 先頭に2行追加したぶん、ステージ済みの 4 行目はバッファの 6 行目になる
+        let markers = compute_markers(&base, b"x\ny\na\nb\nc\nD\n").unwrap();
+        assert_eq!(
+            markers,
+            vec![
+                marker(1, LineChangeKind::Added, false),
+                marker(2, LineChangeKind::Added, false),
+                marker(6, LineChangeKind::Modified, true),
+            ]
+        );
+
+        // ステージ済みの行をさらに編集した場合は未ステージ扱い
+        let markers = compute_markers(&base, b"a\nb\nc\nDD\n").unwrap();
+        assert_eq!(markers, vec![marker(4, LineChangeKind::Modified, false)]);
+    }
+
+    #[test]
+    fn markers_for_untracked_file_mark_every_line_added() {
+        let markers = compute_markers(&base(None, None), b"one\ntwo\n").unwrap();
+        assert_eq!(
+            markers,
+            vec![
+                marker(1, LineChangeKind::Added, false),
+                marker(2, LineChangeKind::Added, false),
+            ]
+        );
+    }
+}
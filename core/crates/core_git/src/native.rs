@@ -0,0 +1,1420 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use core_types::{
+    BlameLine, BlameMoveDetection, BlameOptions, BranchInfo, ChangedFile, CommitAuthor,
+    CommitRecord, CoreError, DiffFileStatus, FileHistoryEntry, GitBranchStatus, GitConflictKind,
+    GitFileState, GitRepositoryStatus, GitSignature, GitStatusItem, GitSubmoduleState,
+    RefDecoration, RefKind, StashApplyResult, StashEntry,
+};
+use git2::{
+    build::CheckoutBuilder, ApplyLocation, Branch, BranchType, CheckoutNotificationType, Commit,
+    Delta, Diff, DiffFindOptions, DiffFormat, DiffOptions, ErrorCode, IndexEntryExtendedFlag, Oid,
+    Patch, ReferenceType, Repository, RepositoryState, Signature, Sort, StashApplyOptions,
+    StashFlags, Status, StatusOptions, SubmoduleIgnore, SubmoduleStatus, Tree,
+};
+
+use crate::{
+    commit_not_found,
+    graph::{detached_head, ref_decoration},
+    ignore_revs_file,
+    stash::parse_stash_subject,
+    status::{absolute_status_path, conflict_states},
+    BaseVersions, GitBackend,
+};
+
+/// libgit2 でリポジトリを直接読むバックエンド（git バイナリ不要・プロセス起動なし）
+pub struct NativeBackend;
+
+/// libgit2 のエラーを、同等の git コマンドが失敗した扱いで GitFailed に変換する
+fn native_error(command: &str, error: git2::Error) -> CoreError {
+    CoreError::GitFailed {
+        command: command.to_string(),
+        stderr: error.message().to_string(),
+        exit_code: None,
+    }
+}
+
+/// libgit2 では扱えない指定。CLI で再試行できるよう、書き換える操作では何も変更する前に返す
+fn unsupported(reason: &str) -> CoreError {
+    CoreError::UnsupportedByBackend {
+        backend: "native".to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn open_repo(repo_root: &Path) -> Result<Repository, CoreError> {
+    Repository::open(repo_root).map_err(|e| match e.code() {
+        ErrorCode::NotFound => CoreError::NotAGitRepo {
+            path: repo_root.to_string_lossy().to_string(),
+        },
+        _ => native_error("git rev-parse", e),
+    })
+}
+
+/// 差分を `git diff` と同じ unified diff 形式のバイト列にする
+fn diff_to_bytes(command: &str, diff: &Diff<'_>) -> Result<Vec<u8>, CoreError> {
+    let mut bytes = Vec::new();
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            bytes.push(line.origin() as u8);
+        }
+        bytes.extend_from_slice(line.content());
+        true
+    })
+    .map_err(|e| native_error(command, e))?;
+    Ok(bytes)
+}
+
+/// 差分を `git diff` と同じ unified diff 形式の文字列にする
+fn diff_to_text(command: &str, diff: &Diff<'_>) -> Result<String, CoreError> {
+    Ok(String::from_utf8_lossy(&diff_to_bytes(command, diff)?).to_string())
+}
+
+/// HEAD のツリー（コミットが1つもなければ None）
+fn head_tree<'r>(repo: &'r Repository, command: &str) -> Result<Option<Tree<'r>>, CoreError> {
+    match repo.head() {
+        Ok(head) => Ok(Some(
+            head.peel_to_tree().map_err(|e| native_error(command, e))?,
+        )),
+        Err(e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => {
+            Ok(None)
+        }
+        Err(e) => Err(native_error(command, e)),
+    }
+}
+
+fn path_diff_options(relative_path: &str) -> DiffOptions {
+    let mut options = DiffOptions::new();
+    options.pathspec(relative_path).disable_pathspec_match(true);
+    options
+}
+
+/// インデックス側（X）の状態
+fn index_state(status: Status) -> GitFileState {
+    if status.contains(Status::INDEX_NEW) {
+        GitFileState::Added
+    } else if status.contains(Status::INDEX_MODIFIED) {
+        GitFileState::Modified
+    } else if status.contains(Status::INDEX_DELETED) {
+        GitFileState::Deleted
+    } else if status.contains(Status::INDEX_RENAMED) {
+        GitFileState::Renamed
+    } else if status.contains(Status::INDEX_TYPECHANGE) {
+        GitFileState::TypeChanged
+    } else {
+        GitFileState::Unmodified
+    }
+}
+
+/// 作業ツリー側（Y）の状態
+fn worktree_state(status: Status) -> GitFileState {
+    if status.contains(Status::WT_MODIFIED) {
+        GitFileState::Modified
+    } else if status.contains(Status::WT_DELETED) {
+        GitFileState::Deleted
+    } else if status.contains(Status::WT_RENAMED) {
+        GitFileState::Renamed
+    } else if status.contains(Status::WT_TYPECHANGE) {
+        GitFileState::TypeChanged
+    } else {
+        GitFileState::Unmodified
+    }
+}
+
+/// コンフリクト中のパスごとにコンフリクトの種類（UU, AA, DU など）を求める
+fn conflict_kinds(repo: &Repository) -> Result<HashMap<String, GitConflictKind>, CoreError> {
+    let index = repo.index().map_err(|e| native_error("git status", e))?;
+    let mut kinds = HashMap::new();
+    if !index.has_conflicts() {
+        return Ok(kinds);
+    }
+
+    let conflicts = index
+        .conflicts()
+        .map_err(|e| native_error("git status", e))?;
+    for conflict in conflicts {
+        let conflict = conflict.map_err(|e| native_error("git status", e))?;
+        let Some(entry) = conflict
+            .our
+            .as_ref()
+            .or(conflict.their.as_ref())
+            .or(conflict.ancestor.as_ref())
+        else {
+            continue;
+        };
+        let path = String::from_utf8_lossy(&entry.path).to_string();
+        let kind = match (
+            conflict.ancestor.is_some(),
+            conflict.our.is_some(),
+            conflict.their.is_some(),
+        ) {
+            (false, true, true) => GitConflictKind::BothAdded,
+            (true, false, false) => GitConflictKind::BothDeleted,
+            (false, true, false) => GitConflictKind::AddedByUs,
+            (true, true, false) => GitConflictKind::DeletedByThem,
+            (false, false, true) => GitConflictKind::AddedByThem,
+            (true, false, true) => GitConflictKind::DeletedByUs,
+            _ => GitConflictKind::BothModified,
+        };
+        kinds.insert(path, kind);
+    }
+    Ok(kinds)
+}
+
+/// サブモジュールの状態（porcelain v2 の S<c><m><u> と同じ判定）
+fn submodule_state(repo: &Repository, name: &str) -> Result<GitSubmoduleState, CoreError> {
+    let status = repo
+        .submodule_status(name, SubmoduleIgnore::None)
+        .map_err(|e| native_error("git status", e))?;
+    Ok(GitSubmoduleState {
+        commit_changed: status.contains(SubmoduleStatus::WD_MODIFIED),
+        has_tracked_changes: status
+            .intersects(SubmoduleStatus::WD_INDEX_MODIFIED | SubmoduleStatus::WD_WD_MODIFIED),
+        has_untracked_changes: status.contains(SubmoduleStatus::WD_UNTRACKED),
+    })
+}
+
+/// `git status --branch` のヘッダー相当（ブランチ・上流・ahead/behind）
+fn branch_status(repo: &Repository) -> Result<GitBranchStatus, CoreError> {
+    let head_ref = repo
+        .find_reference("HEAD")
+        .map_err(|e| native_error("git status", e))?;
+    let head = head_ref
+        .symbolic_target()
+        .and_then(|target| target.strip_prefix("refs/heads/"))
+        .map(str::to_string);
+    // コミット前のブランチは HEAD を解決できない
+    let oid = repo.head().ok().and_then(|head| head.target());
+    let (upstream, ahead, behind) = match &head {
+        Some(branch) => upstream_tracking(repo, &format!("refs/heads/{branch}"), oid)?,
+        None => (None, None, None),
+    };
+    Ok(GitBranchStatus {
+        head,
+        oid: oid.map(|oid| oid.to_string()),
+        upstream,
+        ahead,
+        behind,
+    })
+}
+
+/// 上流の短縮名と ahead/behind
+type UpstreamTracking = (Option<String>, Option<u32>, Option<u32>);
+
+/// ローカルブランチの上流の短縮名と ahead/behind
+/// （上流の参照が消えている場合は ahead/behind を出さない）
+fn upstream_tracking(
+    repo: &Repository,
+    refname: &str,
+    local: Option<Oid>,
+) -> Result<UpstreamTracking, CoreError> {
+    let Ok(upstream) = repo.branch_upstream_name(refname) else {
+        return Ok((None, None, None));
+    };
+    let Some(upstream) = upstream.as_str() else {
+        return Ok((None, None, None));
+    };
+    let short_name = upstream
+        .strip_prefix("refs/remotes/")
+        .or_else(|| upstream.strip_prefix("refs/heads/"))
+        .unwrap_or(upstream)
+        .to_string();
+
+    match (local, repo.refname_to_id(upstream)) {
+        (Some(local), Ok(remote)) => {
+            let (ahead, behind) = repo
+                .graph_ahead_behind(local, remote)
+                .map_err(|e| native_error("git rev-list --count", e))?;
+            Ok((Some(short_name), Some(ahead as u32), Some(behind as u32)))
+        }
+        _ => Ok((Some(short_name), None, None)),
+    }
+}
+
+fn signature_record(signature: &Signature<'_>) -> GitSignature {
+    GitSignature {
+        name: String::from_utf8_lossy(signature.name_bytes()).to_string(),
+        email: String::from_utf8_lossy(signature.email_bytes()).to_string(),
+        time: signature.when().seconds(),
+        offset_minutes: signature.when().offset_minutes(),
+    }
+}
+
+pub(crate) fn commit_record(commit: &Commit<'_>) -> CommitRecord {
+    CommitRecord {
+        sha: commit.id().to_string(),
+        parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+        author: signature_record(&commit.author()),
+        committer: signature_record(&commit.committer()),
+        subject: String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default()).to_string(),
+        body: String::from_utf8_lossy(commit.body_bytes().unwrap_or_default())
+            .trim()
+            .to_string(),
+    }
+}
+
+/// ツリー上で path が指すオブジェクト（無ければ None）
+fn path_entry_id(tree: &Tree<'_>, path: &str) -> Option<Oid> {
+    tree.get_path(Path::new(path)).ok().map(|entry| entry.id())
+}
+
+/// path が新しく現れたコミットで、リネーム元のパスを探す（`git log --follow` と同じ類似度判定）
+fn rename_source(
+    repo: &Repository,
+    parent_tree: &Tree<'_>,
+    tree: &Tree<'_>,
+    path: &str,
+) -> Result<Option<String>, CoreError> {
+    let mut diff = repo
+        .diff_tree_to_tree(Some(parent_tree), Some(tree), None)
+        .map_err(|e| native_error("git log", e))?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))
+        .map_err(|e| native_error("git log", e))?;
+
+    Ok(diff
+        .deltas()
+        .find(|delta| {
+            delta.status() == Delta::Renamed && delta.new_file().path() == Some(Path::new(path))
+        })
+        .and_then(|delta| delta.old_file().path())
+        .map(|old_path| old_path.to_string_lossy().replace('\\', "/")))
+}
+
+/// コミットを解決する（タグなどはコミットまで辿る。見つからなければ CLI と同じく GitFailed）
+fn find_commit<'r>(
+    repo: &'r Repository,
+    commit: &str,
+    command: &str,
+) -> Result<Commit<'r>, CoreError> {
+    repo.revparse_single(commit)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| match e.code() {
+            ErrorCode::NotFound | ErrorCode::InvalidSpec | ErrorCode::Ambiguous => {
+                commit_not_found(command, commit)
+            }
+            _ => native_error(command, e),
+        })
+}
+
+/// 第一親（ルートコミットは空ツリー）からの差分（リネーム検出あり）
+fn first_parent_diff<'r>(
+    repo: &'r Repository,
+    commit: &Commit<'_>,
+    options: Option<&mut DiffOptions>,
+    command: &str,
+) -> Result<Diff<'r>, CoreError> {
+    let tree = commit.tree().map_err(|e| native_error(command, e))?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree().map_err(|e| native_error(command, e))?),
+        Err(_) => None,
+    };
+    let mut diff = repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), options)
+        .map_err(|e| native_error(command, e))?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))
+        .map_err(|e| native_error(command, e))?;
+    Ok(diff)
+}
+
+/// raw 形式の各行から、リネーム・コピーの類似度を差分の順に取り出す
+fn delta_similarities(diff: &Diff<'_>) -> Result<Vec<Option<u32>>, CoreError> {
+    let mut similarities = Vec::new();
+    diff.print(DiffFormat::Raw, |delta, _, line| {
+        let raw = String::from_utf8_lossy(line.content());
+        let status = raw
+            .split('\t')
+            .next()
+            .and_then(|header| header.split_whitespace().nth(4))
+            .unwrap_or_default();
+        let similarity = match delta.status() {
+            Delta::Renamed | Delta::Copied => status.get(1..).and_then(|s| s.parse().ok()),
+            _ => None,
+        };
+        similarities.push(similarity);
+        true
+    })
+    .map_err(|e| native_error("git diff-tree", e))?;
+    Ok(similarities)
+}
+
+impl GitBackend for NativeBackend {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn repo_root(&self, search_dir: &Path) -> Result<PathBuf, CoreError> {
+        let not_a_repo = || CoreError::NotAGitRepo {
+            path: search_dir.to_string_lossy().to_string(),
+        };
+        let repo = Repository::discover(search_dir).map_err(|e| match e.code() {
+            ErrorCode::NotFound => not_a_repo(),
+            _ => native_error("git rev-parse", e),
+        })?;
+        let workdir = repo.workdir().ok_or_else(not_a_repo)?;
+        fs::canonicalize(workdir)
+            .map_err(|e| CoreError::from_io(workdir.to_string_lossy().to_string(), &e))
+    }
+
+    fn blame(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+        revision: Option<&str>,
+        options: &BlameOptions,
+    ) -> Result<Vec<BlameLine>, CoreError> {
+        let repo = open_repo(repo_root)?;
+
+        // libgit2 は ignore-revs と行の移動・コピー検出に対応していないため CLI に任せる
+        if options
+            .move_detection
+            .is_some_and(|detection| detection != BlameMoveDetection::Off)
+        {
+            return Err(unsupported("行の移動・コピー検出"));
+        }
+        let configured_ignore_revs = repo
+            .config()
+            .and_then(|config| config.get_string("blame.ignoreRevsFile"))
+            .is_ok_and(|path| !path.is_empty());
+        if options.use_ignore_revs
+            && (configured_ignore_revs || ignore_revs_file(repo_root).is_some())
+        {
+            return Err(unsupported("ignore-revs"));
+        }
+
+        let mut blame_options = git2::BlameOptions::new();
+        blame_options.ignore_whitespace(options.ignore_whitespace);
+        if let Some(revision) = revision {
+            blame_options.newest_commit(find_commit(&repo, revision, "git blame")?.id());
+        }
+        let committed = repo
+            .blame_file(Path::new(relative_path), Some(&mut blame_options))
+            .map_err(|e| native_error("git blame", e))?;
+
+        // git blame と同じく、リビジョン指定が無ければ未コミットの変更を含む作業ツリーの内容で blame する
+        let blame = match revision {
+            Some(_) => committed,
+            None => {
+                let contents = fs::read(repo_root.join(relative_path))
+                    .map_err(|e| CoreError::from_io(relative_path, &e))?;
+                committed
+                    .blame_buffer(&contents)
+                    .map_err(|e| native_error("git blame", e))?
+            }
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        // blame_buffer が返すハンクは署名を持たないことがあるため、作者はコミットから引く
+        let mailmap = options.use_mailmap.then(|| repo.mailmap().ok()).flatten();
+        let mut commits: HashMap<Oid, (String, i64, String)> = HashMap::new();
+        let mut results = Vec::new();
+
+        for hunk in blame.iter() {
+            let commit_id = hunk.final_commit_id();
+            let (author, author_time, summary) = if commit_id.is_zero() {
+                (
+                    "Not Committed Yet".to_string(),
+                    now,
+                    format!("Version of {relative_path} from {relative_path}"),
+                )
+            } else if let Some(info) = commits.get(&commit_id) {
+                info.clone()
+            } else {
+                let commit = repo
+                    .find_commit(commit_id)
+                    .map_err(|e| native_error("git blame", e))?;
+                let author = match &mailmap {
+                    Some(mailmap) => commit.author_with_mailmap(mailmap),
+                    None => Ok(commit.author()),
+                }
+                .map_err(|e| native_error("git blame", e))?;
+                let info = (
+                    author.name().unwrap_or_default().to_string(),
+                    author.when().seconds(),
+                    commit.summary().unwrap_or_default().to_string(),
+                );
+                commits.insert(commit_id, info.clone());
+                info
+            };
+
+            let commit = commit_id.to_string()[..7].to_string();
+            let start = hunk.final_start_line() as u32;
+            let original_start = hunk.orig_start_line() as u32;
+            let original_path = hunk
+                .path()
+                .map(|path| path.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_else(|| relative_path.to_string());
+            for offset in 0..hunk.lines_in_hunk() as u32 {
+                results.push(BlameLine {
+                    line: start + offset,
+                    author: author.clone(),
+                    author_time,
+                    summary: summary.clone(),
+                    commit: commit.clone(),
+                    original_path: original_path.clone(),
+                    original_line: original_start + offset,
+                });
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn commit_diff(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+        commit: &str,
+    ) -> Result<String, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let commit = find_commit(&repo, commit, "git show")?;
+
+        // マージコミットの結合差分（--cc）は libgit2 では作れないため CLI に任せる
+        if commit.parent_count() > 1 {
+            return Err(unsupported("マージコミットの結合差分"));
+        }
+
+        let tree = commit.tree().map_err(|e| native_error("git show", e))?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree().map_err(|e| native_error("git show", e))?),
+            Err(_) => None,
+        };
+        let mut options = path_diff_options(relative_path);
+        let diff = repo
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut options))
+            .map_err(|e| native_error("git show", e))?;
+        diff_to_text("git show", &diff)
+    }
+
+    fn working_diff(&self, repo_root: &Path, relative_path: &str) -> Result<String, CoreError> {
+        let repo = open_repo(repo_root)?;
+
+        // 未追跡ファイルは /dev/null との差分として作業ツリー側に含める
+        let mut unstaged_options = path_diff_options(relative_path);
+        unstaged_options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+        let unstaged = repo
+            .diff_index_to_workdir(None, Some(&mut unstaged_options))
+            .map_err(|e| native_error("git diff", e))?;
+
+        // コミットが1つもないリポジトリでは空ツリーと比較する
+        let head_tree = head_tree(&repo, "git diff --cached")?;
+        let mut staged_options = path_diff_options(relative_path);
+        let staged = repo
+            .diff_tree_to_index(head_tree.as_ref(), None, Some(&mut staged_options))
+            .map_err(|e| native_error("git diff --cached", e))?;
+
+        let sections: Vec<String> = [
+            diff_to_text("git diff", &unstaged)?,
+            diff_to_text("git diff --cached", &staged)?,
+        ]
+        .into_iter()
+        .filter(|section| !section.trim().is_empty())
+        .collect();
+        Ok(sections.join("\n"))
+    }
+
+    fn status(&self, repo_root: &Path) -> Result<GitRepositoryStatus, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false)
+            .renames_head_to_index(true);
+        let statuses = repo
+            .statuses(Some(&mut options))
+            .map_err(|e| native_error("git status", e))?;
+        let conflicts = conflict_kinds(&repo)?;
+        let submodules: HashMap<String, String> = repo
+            .submodules()
+            .map_err(|e| native_error("git status", e))?
+            .iter()
+            .filter_map(|submodule| {
+                let path = submodule.path().to_string_lossy().replace('\\', "/");
+                Some((path, submodule.name()?.to_string()))
+            })
+            .collect();
+
+        let mut entries = Vec::new();
+        let mut untracked = Vec::new();
+        for entry in statuses.iter() {
+            let flags = entry.status();
+            // リネームはリネーム先のパスで報告し、元のパスを添える
+            let renamed = entry
+                .head_to_index()
+                .filter(|delta| delta.status() == Delta::Renamed);
+            let relative_path = renamed
+                .as_ref()
+                .and_then(|delta| delta.new_file().path_bytes())
+                .unwrap_or(entry.path_bytes());
+            let path = absolute_status_path(repo_root, relative_path);
+
+            if flags.contains(Status::WT_NEW) {
+                untracked.push(GitStatusItem {
+                    path,
+                    original_path: None,
+                    index: GitFileState::Untracked,
+                    worktree: GitFileState::Untracked,
+                    conflict: None,
+                    submodule: None,
+                });
+                continue;
+            }
+
+            let key = String::from_utf8_lossy(relative_path).to_string();
+            let conflict = flags.contains(Status::CONFLICTED).then(|| {
+                conflicts
+                    .get(&key)
+                    .copied()
+                    .unwrap_or(GitConflictKind::BothModified)
+            });
+            let (index, worktree) = match conflict {
+                Some(kind) => conflict_states(kind),
+                None => (index_state(flags), worktree_state(flags)),
+            };
+            let submodule = submodules
+                .get(&key)
+                .map(|name| submodule_state(&repo, name))
+                .transpose()?;
+            entries.push(GitStatusItem {
+                path,
+                original_path: renamed
+                    .and_then(|delta| delta.old_file().path_bytes())
+                    .map(|old_path| absolute_status_path(repo_root, old_path)),
+                index,
+                worktree,
+                conflict,
+                submodule,
+            });
+        }
+        // git と同じく追跡ファイルの変更をパス順に並べた後に未追跡ファイル
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        untracked.sort_by(|a, b| a.path.cmp(&b.path));
+        entries.extend(untracked);
+
+        Ok(GitRepositoryStatus {
+            branch: branch_status(&repo)?,
+            entries,
+        })
+    }
+
+    fn current_branch(&self, repo_root: &Path) -> Result<String, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let head = match repo.head() {
+            Ok(head) => head,
+            // コミット前のブランチは HEAD の参照先から名前だけ取る
+            Err(e) if e.code() == ErrorCode::UnbornBranch => {
+                let head = repo
+                    .find_reference("HEAD")
+                    .map_err(|e| native_error("git branch --show-current", e))?;
+                let target = head.symbolic_target().unwrap_or_default();
+                return Ok(target
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(target)
+                    .to_string());
+            }
+            Err(e) => return Err(native_error("git branch --show-current", e)),
+        };
+
+        if head.is_branch() {
+            if let Some(name) = head.shorthand() {
+                return Ok(name.to_string());
+            }
+        }
+
+        let short_sha = head
+            .peel_to_commit()
+            .and_then(|commit| commit.as_object().short_id())
+            .map_err(|e| native_error("git rev-parse --short HEAD", e))?;
+        let short_sha = short_sha.as_str().unwrap_or_default().to_string();
+        if short_sha.is_empty() {
+            return Err(CoreError::internal("ブランチ名を取得できませんでした"));
+        }
+        Ok(format!("detached@{short_sha}"))
+    }
+
+    fn file_history(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<FileHistoryEntry>, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let mut walk = repo.revwalk().map_err(|e| native_error("git log", e))?;
+        match walk.push_head() {
+            Ok(()) => {}
+            Err(e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => {
+                return Ok(Vec::new());
+            }
+            Err(e) => return Err(native_error("git log", e)),
+        }
+        // 日時が同じでも子コミットを親より先に返す（リネーム元の追跡順が崩れないように）
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+            .map_err(|e| native_error("git log", e))?;
+
+        let mut entries = Vec::new();
+        let mut matched = 0;
+        let mut path = relative_path.to_string();
+        for oid in walk {
+            let commit = oid
+                .and_then(|oid| repo.find_commit(oid))
+                .map_err(|e| native_error("git log", e))?;
+            let tree = commit.tree().map_err(|e| native_error("git log", e))?;
+            let Some(id) = path_entry_id(&tree, &path) else {
+                continue;
+            };
+            let parent_trees = commit
+                .parents()
+                .map(|parent| parent.tree())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| native_error("git log", e))?;
+            let parent_ids: Vec<Option<Oid>> = parent_trees
+                .iter()
+                .map(|parent_tree| path_entry_id(parent_tree, &path))
+                .collect();
+            // いずれかの親と同じ内容なら変更なし（マージは git log の履歴簡略化と同じ扱い）
+            if parent_ids.contains(&Some(id)) {
+                continue;
+            }
+
+            let commit_path = path.clone();
+            if let ([parent_tree], [None]) = (parent_trees.as_slice(), parent_ids.as_slice()) {
+                if let Some(old_path) = rename_source(&repo, parent_tree, &tree, &path)? {
+                    path = old_path;
+                }
+            }
+
+            matched += 1;
+            if matched > offset {
+                entries.push(FileHistoryEntry {
+                    commit: commit_record(&commit),
+                    path: commit_path,
+                });
+                if entries.len() >= limit {
+                    break;
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    fn commits(&self, repo_root: &Path, max_count: usize) -> Result<Vec<CommitRecord>, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let mut walk = repo.revwalk().map_err(|e| native_error("git log", e))?;
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+            .map_err(|e| native_error("git log", e))?;
+
+        if let Ok(head) = repo.head().and_then(|head| head.peel_to_commit()) {
+            walk.push(head.id())
+                .map_err(|e| native_error("git log", e))?;
+        }
+        let references = repo.references().map_err(|e| native_error("git log", e))?;
+        for reference in references {
+            let reference = reference.map_err(|e| native_error("git log", e))?;
+            if ref_decoration(reference.name().unwrap_or_default(), None).is_none() {
+                continue;
+            }
+            // コミット以外を指すタグは起点にしない
+            if let Ok(commit) = reference.peel_to_commit() {
+                walk.push(commit.id())
+                    .map_err(|e| native_error("git log", e))?;
+            }
+        }
+
+        walk.take(max_count)
+            .map(|oid| {
+                let commit = oid
+                    .and_then(|oid| repo.find_commit(oid))
+                    .map_err(|e| native_error("git log", e))?;
+                Ok(commit_record(&commit))
+            })
+            .collect()
+    }
+
+    fn refs(&self, repo_root: &Path) -> Result<Vec<(String, RefDecoration)>, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let head = repo.find_reference("HEAD").ok();
+        let head_ref = head
+            .as_ref()
+            .and_then(|head| head.symbolic_target())
+            .map(str::to_string);
+
+        let mut refs = Vec::new();
+        let references = repo
+            .references()
+            .map_err(|e| native_error("git for-each-ref", e))?;
+        for reference in references {
+            let reference = reference.map_err(|e| native_error("git for-each-ref", e))?;
+            let Some(decoration) =
+                ref_decoration(reference.name().unwrap_or_default(), head_ref.as_deref())
+            else {
+                continue;
+            };
+            // 注釈付きタグはタグオブジェクトではなく指しているコミットに付ける
+            let target = reference
+                .peel_to_commit()
+                .map(|commit| Some(commit.id()))
+                .or_else(|_| reference.resolve().map(|r| r.target()))
+                .ok()
+                .flatten();
+            if let Some(target) = target {
+                refs.push((target.to_string(), decoration));
+            }
+        }
+
+        if head_ref.is_none() {
+            if let Some(target) = head.and_then(|head| head.target()) {
+                refs.push((target.to_string(), detached_head()));
+            }
+        }
+        Ok(refs)
+    }
+
+    fn commit_message(
+        &self,
+        repo_root: &Path,
+        commit: &str,
+    ) -> Result<(CommitRecord, String), CoreError> {
+        let repo = open_repo(repo_root)?;
+        let commit = find_commit(&repo, commit, "git show")?;
+        let message = String::from_utf8_lossy(commit.message_raw_bytes()).to_string();
+        Ok((commit_record(&commit), message))
+    }
+
+    fn changed_files(&self, repo_root: &Path, commit: &str) -> Result<Vec<ChangedFile>, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let commit = find_commit(&repo, commit, "git diff-tree")?;
+        let diff = first_parent_diff(&repo, &commit, None, "git diff-tree")?;
+        let similarities = delta_similarities(&diff)?;
+
+        let mut files = Vec::with_capacity(diff.deltas().len());
+        for (index, delta) in diff.deltas().enumerate() {
+            let patch =
+                Patch::from_diff(&diff, index).map_err(|e| native_error("git diff-tree", e))?;
+            let (insertions, deletions) = match &patch {
+                Some(patch) => {
+                    let (_, insertions, deletions) = patch
+                        .line_stats()
+                        .map_err(|e| native_error("git diff-tree", e))?;
+                    (insertions as u32, deletions as u32)
+                }
+                None => (0, 0),
+            };
+            let is_binary = patch.is_none() || delta.flags().is_binary();
+
+            let path_of = |file: git2::DiffFile<'_>| {
+                file.path()
+                    .map(|path| path.to_string_lossy().replace('\\', "/"))
+                    .unwrap_or_default()
+            };
+            let status = match delta.status() {
+                Delta::Added => DiffFileStatus::Added,
+                Delta::Deleted => DiffFileStatus::Deleted,
+                Delta::Renamed => DiffFileStatus::Renamed,
+                Delta::Copied => DiffFileStatus::Copied,
+                _ => DiffFileStatus::Modified,
+            };
+            let (path, old_path) = match status {
+                DiffFileStatus::Deleted => (path_of(delta.old_file()), None),
+                DiffFileStatus::Renamed | DiffFileStatus::Copied => {
+                    (path_of(delta.new_file()), Some(path_of(delta.old_file())))
+                }
+                _ => (path_of(delta.new_file()), None),
+            };
+
+            files.push(ChangedFile {
+                path,
+                old_path,
+                status,
+                similarity: similarities.get(index).copied().flatten(),
+                insertions: if is_binary { 0 } else { insertions },
+                deletions: if is_binary { 0 } else { deletions },
+                is_binary,
+            });
+        }
+        Ok(files)
+    }
+
+    fn commit_file_diff(
+        &self,
+        repo_root: &Path,
+        commit: &str,
+        paths: &[&str],
+    ) -> Result<String, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let commit = find_commit(&repo, commit, "git diff-tree")?;
+        let mut options = DiffOptions::new();
+        options.disable_pathspec_match(true);
+        for path in paths {
+            options.pathspec(path);
+        }
+        let diff = first_parent_diff(&repo, &commit, Some(&mut options), "git diff-tree")?;
+        diff_to_text("git diff-tree", &diff)
+    }
+
+    fn base_versions(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+    ) -> Result<BaseVersions, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let head = match repo.head().and_then(|head| head.peel_to_tree()) {
+            Ok(tree) => match tree.get_path(Path::new(relative_path)) {
+                Ok(entry) => {
+                    let blob = repo
+                        .find_blob(entry.id())
+                        .map_err(|e| native_error("git cat-file", e))?;
+                    Some(blob.content().to_vec())
+                }
+                Err(e) if e.code() == ErrorCode::NotFound => None,
+                Err(e) => return Err(native_error("git cat-file", e)),
+            },
+            Err(e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => {
+                None
+            }
+            Err(e) => return Err(native_error("git cat-file", e)),
+        };
+
+        let index = repo.index().map_err(|e| native_error("git cat-file", e))?;
+        let index = match index.get_path(Path::new(relative_path), 0) {
+            Some(entry) => {
+                let blob = repo
+                    .find_blob(entry.id)
+                    .map_err(|e| native_error("git cat-file", e))?;
+                Some(blob.content().to_vec())
+            }
+            None => None,
+        };
+
+        Ok(BaseVersions { head, index })
+    }
+
+    fn branches(&self, repo_root: &Path) -> Result<Vec<BranchInfo>, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let iter = repo
+            .branches(None)
+            .map_err(|e| native_error("git for-each-ref", e))?;
+
+        let mut branches = Vec::new();
+        for item in iter {
+            let (branch, branch_type) = item.map_err(|e| native_error("git for-each-ref", e))?;
+            let reference = branch.get();
+            // origin/HEAD などのシンボリック参照は除く
+            if reference.kind() == Some(ReferenceType::Symbolic) {
+                continue;
+            }
+            let (Some(refname), Ok(Some(name))) = (reference.name(), branch.name()) else {
+                continue;
+            };
+            let commit = reference
+                .peel_to_commit()
+                .map_err(|e| native_error("git for-each-ref", e))?;
+
+            let (kind, (upstream, ahead, behind)) = match branch_type {
+                BranchType::Local => (
+                    RefKind::LocalBranch,
+                    upstream_tracking(&repo, refname, Some(commit.id()))?,
+                ),
+                BranchType::Remote => (RefKind::RemoteBranch, (None, None, None)),
+            };
+            branches.push(BranchInfo {
+                name: name.to_string(),
+                kind,
+                is_head: branch.is_head(),
+                upstream,
+                ahead,
+                behind,
+                last_commit: commit_record(&commit),
+            });
+        }
+        // for-each-ref と同じ参照名順（refs/heads の後に refs/remotes）
+        branches.sort_by(|a, b| (a.kind, &a.name).cmp(&(b.kind, &b.name)));
+        Ok(branches)
+    }
+
+    fn create_branch(
+        &self,
+        repo_root: &Path,
+        name: &str,
+        start_point: Option<&str>,
+    ) -> Result<(), CoreError> {
+        let repo = open_repo(repo_root)?;
+        check_branch_name(name)?;
+        let commit = find_commit(&repo, start_point.unwrap_or("HEAD"), "git branch")?;
+        let mut branch = repo
+            .branch(name, &commit, false)
+            .map_err(|e| match e.code() {
+                ErrorCode::Exists => CoreError::BranchExists {
+                    name: name.to_string(),
+                },
+                _ => native_error("git branch", e),
+            })?;
+
+        // git branch の既定（branch.autoSetupMerge）と同じくリモート追跡ブランチは上流にする
+        if let Some(start_point) = start_point {
+            if repo.find_branch(start_point, BranchType::Remote).is_ok() {
+                if let Err(e) = branch.set_upstream(Some(start_point)) {
+                    // 上流を設定できなければ作ったブランチを消し、何もしなかった状態で失敗する
+                    let _ = branch.delete();
+                    return Err(native_error("git branch", e));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn switch_branch(&self, repo_root: &Path, name: &str) -> Result<(), CoreError> {
+        let repo = open_repo(repo_root)?;
+        let branch = find_local_branch(&repo, name)?;
+        let Some(refname) = branch.get().name().map(str::to_string) else {
+            return Err(CoreError::BranchNotFound {
+                name: name.to_string(),
+            });
+        };
+        let commit = branch
+            .get()
+            .peel_to_commit()
+            .map_err(|e| native_error("git switch", e))?;
+
+        // safe: 作業ツリーの変更と食い違うファイルがあれば何も書き換えずに失敗する
+        let mut conflicts = Vec::new();
+        let result = {
+            let mut checkout = CheckoutBuilder::new();
+            checkout
+                .safe()
+                .notify_on(CheckoutNotificationType::CONFLICT)
+                .notify(|_, path, _, _, _| {
+                    if let Some(path) = path {
+                        conflicts.push(path.to_string_lossy().replace('\\', "/"));
+                    }
+                    true
+                });
+            repo.checkout_tree(commit.as_object(), Some(&mut checkout))
+        };
+        match result {
+            Err(e) if e.code() == ErrorCode::Conflict || !conflicts.is_empty() => {
+                conflicts.sort();
+                return Err(CoreError::CheckoutConflict {
+                    branch: name.to_string(),
+                    paths: conflicts,
+                });
+            }
+            Err(e) => return Err(native_error("git switch", e)),
+            Ok(()) => {}
+        }
+
+        repo.set_head(&refname)
+            .map_err(|e| native_error("git switch", e))
+    }
+
+    fn rename_branch(
+        &self,
+        repo_root: &Path,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<(), CoreError> {
+        let repo = open_repo(repo_root)?;
+        let mut branch = find_local_branch(&repo, old_name)?;
+        check_branch_name(new_name)?;
+        branch.rename(new_name, false).map_err(|e| match e.code() {
+            ErrorCode::Exists => CoreError::BranchExists {
+                name: new_name.to_string(),
+            },
+            _ => native_error("git branch -m", e),
+        })?;
+        Ok(())
+    }
+
+    fn delete_branch(&self, repo_root: &Path, name: &str, force: bool) -> Result<(), CoreError> {
+        let repo = open_repo(repo_root)?;
+        let mut branch = find_local_branch(&repo, name)?;
+        if branch.is_head() {
+            return Err(CoreError::invalid_argument(format!(
+                "チェックアウト中のブランチは削除できません: {name}"
+            )));
+        }
+
+        if !force {
+            // `git branch -d` と同じく、上流があれば上流、無ければ HEAD に含まれているか
+            let base = match branch.upstream() {
+                Ok(upstream) => upstream.get().target(),
+                Err(_) => repo.head().ok().and_then(|head| head.target()),
+            };
+            let merged = match (branch.get().target(), base) {
+                (Some(tip), Some(base)) => {
+                    tip == base
+                        || repo
+                            .graph_descendant_of(base, tip)
+                            .map_err(|e| native_error("git merge-base", e))?
+                }
+                _ => false,
+            };
+            if !merged {
+                return Err(CoreError::BranchNotMerged {
+                    name: name.to_string(),
+                });
+            }
+        }
+
+        branch
+            .delete()
+            .map_err(|e| native_error("git branch -D", e))
+    }
+
+    fn index_diff(
+        &self,
+        repo_root: &Path,
+        relative_path: &str,
+        cached: bool,
+    ) -> Result<Vec<u8>, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let mut options = path_diff_options(relative_path);
+        let diff = if cached {
+            let head_tree = head_tree(&repo, "git diff --cached")?;
+            repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut options))
+                .map_err(|e| native_error("git diff --cached", e))?
+        } else {
+            repo.diff_index_to_workdir(None, Some(&mut options))
+                .map_err(|e| native_error("git diff", e))?
+        };
+        diff_to_bytes("git diff", &diff)
+    }
+
+    fn apply_patch(&self, repo_root: &Path, patch: &[u8], to_index: bool) -> Result<(), CoreError> {
+        let repo = open_repo(repo_root)?;
+        let diff = Diff::from_buffer(patch).map_err(|e| native_error("git apply", e))?;
+        let location = if to_index {
+            ApplyLocation::Index
+        } else {
+            ApplyLocation::WorkDir
+        };
+        repo.apply(&diff, location, None)
+            .map_err(|e| native_error("git apply", e))
+    }
+
+    fn stage_file(&self, repo_root: &Path, relative_path: &str) -> Result<(), CoreError> {
+        let repo = open_repo(repo_root)?;
+        let mut index = repo.index().map_err(|e| native_error("git add", e))?;
+        let path = Path::new(relative_path);
+        if fs::symlink_metadata(repo_root.join(path)).is_ok() {
+            index.add_path(path)
+        } else {
+            index.remove_path(path)
+        }
+        .and_then(|_| index.write())
+        .map_err(|e| native_error("git add", e))
+    }
+
+    fn unstage_file(&self, repo_root: &Path, relative_path: &str) -> Result<(), CoreError> {
+        let repo = open_repo(repo_root)?;
+        let head = repo.head().and_then(|head| head.peel_to_commit());
+        match head {
+            Ok(commit) => repo
+                .reset_default(Some(commit.as_object()), [relative_path])
+                .map_err(|e| native_error("git reset", e)),
+            Err(e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => {
+                let mut index = repo.index().map_err(|e| native_error("git rm", e))?;
+                index
+                    .remove_path(Path::new(relative_path))
+                    .and_then(|_| index.write())
+                    .map_err(|e| native_error("git rm", e))
+            }
+            Err(e) => Err(native_error("git reset", e)),
+        }
+    }
+
+    fn intent_to_add(&self, repo_root: &Path, relative_path: &str) -> Result<(), CoreError> {
+        let repo = open_repo(repo_root)?;
+        let mut index = repo.index().map_err(|e| native_error("git add -N", e))?;
+        let path = Path::new(relative_path);
+        if index.get_path(path, 0).is_some() {
+            return Ok(());
+        }
+
+        // モードを作業ツリーから取るため一度追加し、内容を空の blob に差し替える
+        index
+            .add_path(path)
+            .map_err(|e| native_error("git add -N", e))?;
+        let mut entry = index.get_path(path, 0).ok_or_else(|| {
+            CoreError::internal(format!("インデックスに追加できません: {relative_path}"))
+        })?;
+        entry.id = repo.blob(&[]).map_err(|e| native_error("git add -N", e))?;
+        entry.file_size = 0;
+        entry.flags_extended |= IndexEntryExtendedFlag::INTENT_TO_ADD.bits();
+        index
+            .add(&entry)
+            .and_then(|_| index.write())
+            .map_err(|e| native_error("git add -N", e))
+    }
+
+    fn config_path(&self, repo_root: &Path, key: &str) -> Result<Option<PathBuf>, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let config = repo.config().map_err(|e| native_error("git config", e))?;
+        match config.get_path(key) {
+            Ok(path) => Ok(Some(repo_root.join(path))),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(native_error("git config", e)),
+        }
+    }
+
+    fn git_path(&self, repo_root: &Path, name: &str) -> Result<PathBuf, CoreError> {
+        if name == "hooks" {
+            if let Some(hooks_path) = self.config_path(repo_root, "core.hooksPath")? {
+                return Ok(hooks_path);
+            }
+        }
+        let repo = open_repo(repo_root)?;
+        // フックは worktree ごとではなく共通の .git 配下にある
+        let base = if name == "hooks" {
+            repo.commondir()
+        } else {
+            repo.path()
+        };
+        Ok(base.join(name))
+    }
+
+    fn committer(&self, repo_root: &Path) -> Result<GitSignature, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let signature = repo.signature().map_err(|e| native_error("git var", e))?;
+        Ok(signature_record(&signature))
+    }
+
+    fn create_commit(
+        &self,
+        repo_root: &Path,
+        message: &str,
+        amend: bool,
+        author: Option<&CommitAuthor>,
+    ) -> Result<String, CoreError> {
+        let repo = open_repo(repo_root)?;
+        // マージ・cherry-pick 中の親の扱いや署名は git に任せる
+        if repo.state() != RepositoryState::Clean {
+            return Err(unsupported("進行中の操作があるリポジトリでのコミット"));
+        }
+        let config = repo.config().map_err(|e| native_error("git commit", e))?;
+        if config.get_bool("commit.gpgSign").unwrap_or(false) {
+            return Err(unsupported("署名付きコミット"));
+        }
+
+        let mut index = repo.index().map_err(|e| native_error("git commit", e))?;
+        let tree = index
+            .write_tree()
+            .and_then(|tree_id| repo.find_tree(tree_id))
+            .map_err(|e| native_error("git commit", e))?;
+        let committer = repo
+            .signature()
+            .map_err(|e| native_error("git commit", e))?;
+        let author = author
+            .map(|author| Signature::now(&author.name, &author.email))
+            .transpose()
+            .map_err(|e| native_error("git commit", e))?;
+        let message = format!("{message}\n");
+
+        let head = match repo.head().and_then(|head| head.peel_to_commit()) {
+            Ok(commit) => Some(commit),
+            Err(e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => {
+                None
+            }
+            Err(e) => return Err(native_error("git commit", e)),
+        };
+        let oid = match (amend, head) {
+            (true, Some(head)) => head.amend(
+                Some("HEAD"),
+                author.as_ref(),
+                Some(&committer),
+                None,
+                Some(&message),
+                Some(&tree),
+            ),
+            (true, None) => {
+                return Err(CoreError::invalid_argument(
+                    "amend するコミットがありません",
+                ))
+            }
+            (false, head) => {
+                let parents: Vec<&Commit<'_>> = head.iter().collect();
+                repo.commit(
+                    Some("HEAD"),
+                    author.as_ref().unwrap_or(&committer),
+                    &committer,
+                    &message,
+                    &tree,
+                    &parents,
+                )
+            }
+        }
+        .map_err(|e| native_error("git commit", e))?;
+        Ok(oid.to_string())
+    }
+
+    fn stashes(&self, repo_root: &Path) -> Result<Vec<StashEntry>, CoreError> {
+        let repo = open_repo(repo_root)?;
+        // refs/stash の reflog が stash の一覧（先頭が stash@{0}）
+        let reflog = repo
+            .reflog("refs/stash")
+            .map_err(|e| native_error("git stash list", e))?;
+        let mut stashes = Vec::new();
+        for (index, entry) in reflog.iter().enumerate() {
+            let commit = repo
+                .find_commit(entry.id_new())
+                .map_err(|e| native_error("git stash list", e))?;
+            let (branch, message) = parse_stash_subject(commit.summary().unwrap_or_default());
+            stashes.push(StashEntry {
+                index: index as u32,
+                sha: commit.id().to_string(),
+                message,
+                branch,
+                committer: signature_record(&commit.committer()),
+            });
+        }
+        Ok(stashes)
+    }
+
+    fn create_stash(
+        &self,
+        repo_root: &Path,
+        message: Option<&str>,
+        include_untracked: bool,
+        staged_only: bool,
+    ) -> Result<bool, CoreError> {
+        // libgit2 にはステージ済みの変更だけを stash するモードが無い
+        if staged_only {
+            return Err(unsupported("ステージ済みの変更だけの stash"));
+        }
+
+        let mut repo = open_repo(repo_root)?;
+        let stasher = repo
+            .signature()
+            .map_err(|e| native_error("git stash push", e))?;
+        let mut flags = StashFlags::DEFAULT;
+        if include_untracked {
+            flags |= StashFlags::INCLUDE_UNTRACKED;
+        }
+        match repo.stash_save2(&stasher, message, Some(flags)) {
+            Ok(_) => Ok(true),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(false),
+            Err(e) => Err(native_error("git stash push", e)),
+        }
+    }
+
+    fn apply_stash(
+        &self,
+        repo_root: &Path,
+        index: usize,
+        pop: bool,
+    ) -> Result<StashApplyResult, CoreError> {
+        let mut repo = open_repo(repo_root)?;
+
+        // safe: ローカルの変更と食い違うファイルがあれば何も書き換えずに失敗する。
+        // libgit2 の pop はコンフリクトしても stash を消すので、適用と削除を分ける
+        let mut overwritten = Vec::new();
+        let result = {
+            let mut checkout = CheckoutBuilder::new();
+            checkout
+                .safe()
+                .notify_on(CheckoutNotificationType::CONFLICT)
+                .notify(|_, path, _, _, _| {
+                    if let Some(path) = path {
+                        overwritten.push(path.to_string_lossy().replace('\\', "/"));
+                    }
+                    true
+                });
+            let mut options = StashApplyOptions::new();
+            options.checkout_options(checkout);
+            repo.stash_apply(index, Some(&mut options))
+        };
+        match result {
+            Err(e) if e.code() == ErrorCode::Conflict || !overwritten.is_empty() => {
+                overwritten.sort();
+                return Err(CoreError::StashConflict {
+                    stash: format!("stash@{{{index}}}"),
+                    paths: overwritten,
+                });
+            }
+            Err(e) => return Err(native_error("git stash apply", e)),
+            Ok(()) => {}
+        }
+
+        let mut conflicts: Vec<String> = conflict_kinds(&repo)?
+            .into_keys()
+            .map(|path| absolute_status_path(repo_root, path.as_bytes()))
+            .collect();
+        conflicts.sort();
+        let dropped = pop && conflicts.is_empty();
+        if dropped {
+            repo.stash_drop(index)
+                .map_err(|e| native_error("git stash drop", e))?;
+        }
+        Ok(StashApplyResult { conflicts, dropped })
+    }
+
+    fn drop_stash(&self, repo_root: &Path, index: usize) -> Result<(), CoreError> {
+        let mut repo = open_repo(repo_root)?;
+        repo.stash_drop(index)
+            .map_err(|e| native_error("git stash drop", e))
+    }
+
+    fn stash_diff(&self, repo_root: &Path, index: usize) -> Result<String, CoreError> {
+        let repo = open_repo(repo_root)?;
+        let command = "git stash show";
+        let stash = repo
+            .revparse_single(&format!("stash@{{{index}}}"))
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| native_error(command, e))?;
+
+        // 第一親が stash したときの HEAD、第三親（-u のときだけ）が未追跡ファイル
+        let base = stash
+            .parent(0)
+            .and_then(|parent| parent.tree())
+            .map_err(|e| native_error(command, e))?;
+        let tree = stash.tree().map_err(|e| native_error(command, e))?;
+        let mut diff = repo
+            .diff_tree_to_tree(Some(&base), Some(&tree), None)
+            .map_err(|e| native_error(command, e))?;
+        diff.find_similar(None)
+            .map_err(|e| native_error(command, e))?;
+        let mut text = diff_to_text(command, &diff)?;
+
+        if stash.parent_count() > 2 {
+            let untracked = stash
+                .parent(2)
+                .and_then(|parent| parent.tree())
+                .map_err(|e| native_error(command, e))?;
+            let diff = repo
+                .diff_tree_to_tree(None, Some(&untracked), None)
+                .map_err(|e| native_error(command, e))?;
+            text.push_str(&diff_to_text(command, &diff)?);
+        }
+        Ok(text)
+    }
+}
+
+fn find_local_branch<'r>(repo: &'r Repository, name: &str) -> Result<Branch<'r>, CoreError> {
+    repo.find_branch(name, BranchType::Local)
+        .map_err(|e| match e.code() {
+            ErrorCode::NotFound | ErrorCode::InvalidSpec => CoreError::BranchNotFound {
+                name: name.to_string(),
+            },
+            _ => native_error("git rev-parse", e),
+        })
+}
+
+fn check_branch_name(name: &str) -> Result<(), CoreError> {
+    if !Branch::name_is_valid(name).unwrap_or(false) {
+        return Err(CoreError::invalid_argument(format!(
+            "ブランチ名が不正です: {name}"
+        )));
+    }
+    Ok(())
+}
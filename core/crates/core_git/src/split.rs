@@ -0,0 +1,350 @@
+use std::collections::HashSet;
+
+use core_types::{
+    DiffFile, DiffHunk, DiffLine, DiffLineOrigin, SplitDiffCell, SplitDiffOptions, SplitDiffRow,
+};
+
+/// 差分1ファイル分を左右分割表示の行に並べる
+///
+/// 削除行と追加行は先頭から順に同じ行へ揃え、足りない側は埋め草（None）にする。
+/// ハンクの前には区切り行を、ハンク間と長い変更なし区間には折りたたみ行を置く。
+pub fn split_diff(file: &DiffFile, options: &SplitDiffOptions) -> Vec<SplitDiffRow> {
+    let new_lines: Option<Vec<&str>> = options.new_text.as_deref().map(|t| t.lines().collect());
+    let expanded: HashSet<u32> = options.expanded.iter().copied().collect();
+    let mut rows = Vec::new();
+    let (mut next_old, mut next_new) = (1u32, 1u32);
+
+    for hunk in &file.hunks {
+        // 件数 0 の範囲は「直前の行の後ろ」を指す
+        let first_old = if hunk.old_lines == 0 {
+            hunk.old_start + 1
+        } else {
+            hunk.old_start
+        };
+        let first_new = if hunk.new_lines == 0 {
+            hunk.new_start + 1
+        } else {
+            hunk.new_start
+        };
+
+        let gap = first_new.saturating_sub(next_new);
+        push_gap(
+            &mut rows,
+            next_old,
+            next_new,
+            gap,
+            new_lines.as_deref(),
+            &expanded,
+        );
+        rows.push(SplitDiffRow::HunkHeader {
+            header: hunk.header.clone(),
+        });
+        push_hunk(&mut rows, hunk, options.context_lines, &expanded);
+
+        next_old = first_old + hunk.old_lines;
+        next_new = first_new + hunk.new_lines;
+    }
+
+    if let Some(lines) = new_lines.as_deref() {
+        if !file.hunks.is_empty() {
+            let trailing = (lines.len() as u32).saturating_sub(next_new - 1);
+            push_gap(
+                &mut rows,
+                next_old,
+                next_new,
+                trailing,
+                Some(lines),
+                &expanded,
+            );
+        }
+    }
+    rows
+}
+
+/// ハンク外の変更なし区間（ファイル全体が無ければ展開できない）
+fn push_gap(
+    rows: &mut Vec<SplitDiffRow>,
+    old_start: u32,
+    new_start: u32,
+    line_count: u32,
+    new_lines: Option<&[&str]>,
+    expanded: &HashSet<u32>,
+) {
+    if line_count == 0 {
+        return;
+    }
+    let end = (new_start + line_count - 1) as usize;
+    match new_lines {
+        Some(lines) if expanded.contains(&new_start) && end <= lines.len() => {
+            for offset in 0..line_count {
+                let content = lines[(new_start + offset - 1) as usize];
+                rows.push(SplitDiffRow::Line {
+                    left: Some(context_cell(old_start + offset, content)),
+                    right: Some(context_cell(new_start + offset, content)),
+                });
+            }
+        }
+        _ => rows.push(SplitDiffRow::Collapsed {
+            old_start,
+            new_start,
+            line_count,
+        }),
+    }
+}
+
+fn push_hunk(
+    rows: &mut Vec<SplitDiffRow>,
+    hunk: &DiffHunk,
+    context_lines: u32,
+    expanded: &HashSet<u32>,
+) {
+    let aligned = align_lines(&hunk.lines);
+    let is_context = |row: &SplitDiffRow| {
+        matches!(
+            row,
+            SplitDiffRow::Line {
+                left: Some(SplitDiffCell {
+                    origin: DiffLineOrigin::Context,
+                    ..
+                }),
+                ..
+            }
+        )
+    };
+
+    let context_lines = context_lines as usize;
+    let mut index = 0;
+    while index < aligned.len() {
+        if !is_context(&aligned[index]) {
+            rows.push(aligned[index].clone());
+            index += 1;
+            continue;
+        }
+
+        let run_start = index;
+        while index < aligned.len() && is_context(&aligned[index]) {
+            index += 1;
+        }
+        let run = &aligned[run_start..index];
+        // ハンク先頭・末尾の区間は変更に接する側だけコンテキストを残す
+        let keep_head = if run_start == 0 { 0 } else { context_lines };
+        let keep_tail = if index == aligned.len() {
+            0
+        } else {
+            context_lines
+        };
+
+        if run.len() <= keep_head + keep_tail {
+            rows.extend_from_slice(run);
+            continue;
+        }
+        let hidden = &run[keep_head..run.len() - keep_tail];
+        let (old_start, new_start) = match &hidden[0] {
+            SplitDiffRow::Line {
+                left: Some(left),
+                right: Some(right),
+            } => (left.line, right.line),
+            _ => (0, 0),
+        };
+
+        rows.extend_from_slice(&run[..keep_head]);
+        if expanded.contains(&new_start) {
+            rows.extend_from_slice(hidden);
+        } else {
+            rows.push(SplitDiffRow::Collapsed {
+                old_start,
+                new_start,
+                line_count: hidden.len() as u32,
+            });
+        }
+        rows.extend_from_slice(&run[run.len() - keep_tail..]);
+    }
+}
+
+/// ハンク内の行を左右に揃える（連続する削除行と追加行を先頭から対にする）
+fn align_lines(lines: &[DiffLine]) -> Vec<SplitDiffRow> {
+    let mut rows = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = &lines[index];
+        if line.origin == DiffLineOrigin::Context {
+            rows.push(SplitDiffRow::Line {
+                left: line.old_line.map(|n| cell(line, n)),
+                right: line.new_line.map(|n| cell(line, n)),
+            });
+            index += 1;
+            continue;
+        }
+
+        let removed_start = index;
+        while index < lines.len() && lines[index].origin == DiffLineOrigin::Removed {
+            index += 1;
+        }
+        let added_start = index;
+        while index < lines.len() && lines[index].origin == DiffLineOrigin::Added {
+            index += 1;
+        }
+        let removed = &lines[removed_start..added_start];
+        let added = &lines[added_start..index];
+
+        for offset in 0..removed.len().max(added.len()) {
+            rows.push(SplitDiffRow::Line {
+                left: removed
+                    .get(offset)
+                    .and_then(|l| l.old_line.map(|n| cell(l, n))),
+                right: added
+                    .get(offset)
+                    .and_then(|l| l.new_line.map(|n| cell(l, n))),
+            });
+        }
+    }
+    rows
+}
+
+fn cell(line: &DiffLine, number: u32) -> SplitDiffCell {
+    SplitDiffCell {
+        line: number,
+        origin: line.origin,
+        content: line.content.clone(),
+        word_changes: line.word_changes.clone(),
+        no_newline_at_eof: line.no_newline_at_eof,
+    }
+}
+
+fn context_cell(number: u32, content: &str) -> SplitDiffCell {
+    SplitDiffCell {
+        line: number,
+        origin: DiffLineOrigin::Context,
+        content: content.to_string(),
+        word_changes: Vec::new(),
+        no_newline_at_eof: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_unified_diff;
+
+    const TWO_HUNKS: &str = "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -2,3 +2,3 @@
+ two
+-three
++THREE
+ four
+@@ -8,3 +8,4 @@
+ eight
+-nine
++NINE
++nine-and-a-half
+ ten
+";
+
+    fn options(context_lines: u32, new_text: Option<&str>, expanded: Vec<u32>) -> SplitDiffOptions {
+        SplitDiffOptions {
+            context_lines,
+            new_text: new_text.map(str::to_string),
+            expanded,
+        }
+    }
+
+    fn line_numbers(row: &SplitDiffRow) -> (Option<u32>, Option<u32>) {
+        match row {
+            SplitDiffRow::Line { left, right } => (
+                left.as_ref().map(|c| c.line),
+                right.as_ref().map(|c| c.line),
+            ),
+            other => panic!("not a line row: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn split_aligns_changes_with_fillers_and_gaps() {
+        let file = &parse_unified_diff(TWO_HUNKS)[0];
+        let rows = split_diff(file, &options(3, None, vec![]));
+
+        assert_eq!(
+            rows[0],
+            SplitDiffRow::Collapsed {
+                old_start: 1,
+                new_start: 1,
+                line_count: 1
+            }
+        );
+        assert!(matches!(rows[1], SplitDiffRow::HunkHeader { .. }));
+        assert_eq!(line_numbers(&rows[2]), (Some(2), Some(2)));
+        assert_eq!(line_numbers(&rows[3]), (Some(3), Some(3)));
+        assert_eq!(line_numbers(&rows[4]), (Some(4), Some(4)));
+        assert_eq!(
+            rows[5],
+            SplitDiffRow::Collapsed {
+                old_start: 5,
+                new_start: 5,
+                line_count: 3
+            }
+        );
+        assert!(matches!(rows[6], SplitDiffRow::HunkHeader { .. }));
+        // 追加が1行多いぶん左側は埋め草になる
+        assert_eq!(line_numbers(&rows[8]), (Some(9), Some(9)));
+        assert_eq!(line_numbers(&rows[9]), (None, Some(10)));
+        assert_eq!(line_numbers(&rows[10]), (Some(10), Some(11)));
+        assert_eq!(rows.len(), 11);
+    }
+
+    #[test]
+    fn split_expands_gaps_with_new_text() {
+        let file = &parse_unified_diff(TWO_HUNKS)[0];
+        let new_text = "one\ntwo\nTHREE\nfour\nfive\nsix\nseven\neight\nNINE\nnine-and-a-half\nten\neleven\ntwelve\n";
+
+        let rows = split_diff(file, &options(3, Some(new_text), vec![5]));
+        assert_eq!(line_numbers(&rows[5]), (Some(5), Some(5)));
+        assert_eq!(line_numbers(&rows[7]), (Some(7), Some(7)));
+        // 末尾の区間はファイル全体を渡したときだけ現れる
+        assert_eq!(
+            rows.last().unwrap(),
+            &SplitDiffRow::Collapsed {
+                old_start: 11,
+                new_start: 12,
+                line_count: 2
+            }
+        );
+    }
+
+    #[test]
+    fn split_collapses_long_context_inside_hunk() {
+        let input = "\
+@@ -1,8 +1,8 @@
+-a
++A
+ b
+ c
+ d
+ e
+ f
+-g
++G
+ h
+";
+        let file = &parse_unified_diff(input)[0];
+        let rows = split_diff(file, &options(1, None, vec![]));
+        assert_eq!(line_numbers(&rows[2]), (Some(2), Some(2)));
+        assert_eq!(
+            rows[3],
+            SplitDiffRow::Collapsed {
+                old_start: 3,
+                new_start: 3,
+                line_count: 3
+            }
+        );
+        assert_eq!(line_numbers(&rows[4]), (Some(6), Some(6)));
+        // 末尾のコンテキストは変更側に1行だけ残す
+        assert_eq!(line_numbers(&rows[6]), (Some(8), Some(8)));
+        assert_eq!(rows.len(), 7);
+
+        let rows = split_diff(file, &options(1, None, vec![3]));
+        assert_eq!(rows.len(), 9);
+    }
+}
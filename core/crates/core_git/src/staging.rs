@@ -0,0 +1,716 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock, PoisonError},
+};
+
+use core_types::{CoreError, DiffLineRef};
+
+use crate::{
+    resolve_repo_context_allowing_removed, resolve_repo_root, with_backend, with_backend_mut,
+};
+
+/// 元に戻せる破棄の件数（古いものから捨てる）
+const UNDO_LIMIT: usize = 50;
+
+static DISCARD_UNDO: OnceLock<Mutex<Vec<DiscardedChange>>> = OnceLock::new();
+
+/// 破棄した変更（patch は作業ツリーに当てると破棄前に戻る）
+struct DiscardedChange {
+    repo_root: PathBuf,
+    relative_path: String,
+    patch: Vec<u8>,
+}
+
+fn discard_undo() -> &'static Mutex<Vec<DiscardedChange>> {
+    DISCARD_UNDO.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// ファイル全体をステージする（削除・未追跡ファイルも含む `git add -A -- <path>` 相当）
+pub fn stage_file(file_path: &str) -> Result<(), CoreError> {
+    if file_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("file_path が空です"));
+    }
+
+    let (repo_root, relative_path) =
+        with_backend(|backend| resolve_repo_context_allowing_removed(backend, file_path))?;
+    with_backend_mut(|backend| backend.stage_file(&repo_root, &relative_path))
+}
+
+/// ファイル全体のステージを解除する（作業ツリーはそのまま）
+pub fn unstage_file(file_path: &str) -> Result<(), CoreError> {
+    if file_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("file_path が空です"));
+    }
+
+    let (repo_root, relative_path) =
+        with_backend(|backend| resolve_repo_context_allowing_removed(backend, file_path))?;
+    with_backend_mut(|backend| backend.unstage_file(&repo_root, &relative_path))
+}
+
+/// 未ステージの差分（git_file_diff の作業ツリー側）の hunk_index 番目のハンクをステージする
+pub fn stage_hunk(file_path: &str, hunk_index: u32) -> Result<(), CoreError> {
+    apply_selection(file_path, PatchTarget::Stage, Selection::Hunk(hunk_index))
+}
+
+/// ステージ済みの差分（git_file_diff のインデックス側）の hunk_index 番目のハンクを外す
+pub fn unstage_hunk(file_path: &str, hunk_index: u32) -> Result<(), CoreError> {
+    apply_selection(file_path, PatchTarget::Unstage, Selection::Hunk(hunk_index))
+}
+
+/// 未ステージの差分の hunk_index 番目のハンクを作業ツリーから破棄する（undo_discard で戻せる）
+pub fn discard_hunk(file_path: &str, hunk_index: u32) -> Result<(), CoreError> {
+    apply_selection(file_path, PatchTarget::Discard, Selection::Hunk(hunk_index))
+}
+
+/// 未ステージの差分のうち選んだ追加行・削除行だけをステージする
+pub fn stage_lines(file_path: &str, lines: &[DiffLineRef]) -> Result<(), CoreError> {
+    apply_selection(file_path, PatchTarget::Stage, Selection::Lines(lines))
+}
+
+/// ステージ済みの差分のうち選んだ追加行・削除行だけステージを解除する
+pub fn unstage_lines(file_path: &str, lines: &[DiffLineRef]) -> Result<(), CoreError> {
+    apply_selection(file_path, PatchTarget::Unstage, Selection::Lines(lines))
+}
+
+/// 未ステージの差分のうち選んだ追加行・削除行だけを作業ツリーから破棄する
+pub fn discard_lines(file_path: &str, lines: &[DiffLineRef]) -> Result<(), CoreError> {
+    apply_selection(file_path, PatchTarget::Discard, Selection::Lines(lines))
+}
+
+/// リポジトリで最後に破棄した変更を作業ツリーに戻し、そのファイルの絶対パスを返す
+/// （戻せる破棄が無ければ None）
+pub fn undo_discard(root_path: &str) -> Result<Option<String>, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+
+    let repo_root = with_backend(|backend| resolve_repo_root(backend, root_path))?;
+    // バッファは書き換えの途中で panic しないので、poison されていても中身はそのまま使える
+    let mut changes = discard_undo()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let Some(position) = changes.iter().rposition(|c| c.repo_root == repo_root) else {
+        return Ok(None);
+    };
+
+    // 破棄後にファイルが変わっていて当たらなければ、バッファに残したままエラーにする
+    with_backend_mut(|backend| backend.apply_patch(&repo_root, &changes[position].patch, false))?;
+    let change = changes.remove(position);
+    Ok(Some(
+        repo_root
+            .join(&change.relative_path)
+            .to_string_lossy()
+            .replace('\\', "/"),
+    ))
+}
+
+/// 部分パッチを当てる先
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PatchTarget {
+    /// 未ステージの差分をインデックスに当てる
+    Stage,
+    /// ステージ済みの差分を逆向きにインデックスに当てる
+    Unstage,
+    /// 未ステージの差分を逆向きに作業ツリーに当てる
+    Discard,
+}
+
+enum Selection<'a> {
+    Hunk(u32),
+    Lines(&'a [DiffLineRef]),
+}
+
+fn apply_selection(
+    file_path: &str,
+    target: PatchTarget,
+    selection: Selection<'_>,
+) -> Result<(), CoreError> {
+    if file_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("file_path が空です"));
+    }
+    if matches!(selection, Selection::Lines(lines) if lines.is_empty()) {
+        return Err(CoreError::invalid_argument("lines が空です"));
+    }
+
+    let (repo_root, relative_path) =
+        with_backend(|backend| resolve_repo_context_allowing_removed(backend, file_path))?;
+    let cached = target == PatchTarget::Unstage;
+    let mut diff = with_backend(|backend| backend.index_diff(&repo_root, &relative_path, cached))?;
+    // 未追跡ファイルは差分に出ないので、内容なしでインデックスに載せてから差分を取り直す
+    let mut intent_added = false;
+    if target == PatchTarget::Stage
+        && diff.is_empty()
+        && repo_root.join(&relative_path).is_file()
+        && with_backend(|backend| backend.base_versions(&repo_root, &relative_path))?
+            .index
+            .is_none()
+    {
+        with_backend_mut(|backend| backend.intent_to_add(&repo_root, &relative_path))?;
+        intent_added = true;
+        diff = with_backend(|backend| backend.index_diff(&repo_root, &relative_path, false))?;
+    }
+
+    let result = apply_diff_selection(
+        file_path,
+        &repo_root,
+        &relative_path,
+        &diff,
+        target,
+        selection,
+    );
+    // 部分ステージに失敗したら、載せただけの未追跡ファイルをインデックスから外しておく
+    if result.is_err() && intent_added {
+        let _ = with_backend_mut(|backend| backend.unstage_file(&repo_root, &relative_path));
+    }
+    result
+}
+
+/// 1ファイル分の差分から選んだ範囲の部分パッチを作って当てる
+fn apply_diff_selection(
+    file_path: &str,
+    repo_root: &Path,
+    relative_path: &str,
+    diff: &[u8],
+    target: PatchTarget,
+    selection: Selection<'_>,
+) -> Result<(), CoreError> {
+    let header = parse_file_header(diff);
+    let hunks = parse_raw_hunks(diff)?;
+    if hunks.is_empty() {
+        return Err(CoreError::NoChanges {
+            path: file_path.to_string(),
+        });
+    }
+    if let Selection::Hunk(index) = selection {
+        if index as usize >= hunks.len() {
+            return Err(CoreError::invalid_argument(format!(
+                "hunk_index が範囲外です: {index}（ハンク数 {}）",
+                hunks.len()
+            )));
+        }
+    }
+
+    let reverse = target != PatchTarget::Stage;
+    let patch = build_patch(
+        relative_path,
+        &header,
+        &hunks,
+        reverse,
+        |index, line| match selection {
+            Selection::Hunk(selected) => index == selected as usize,
+            Selection::Lines(lines) => lines.contains(line),
+        },
+    )
+    .ok_or_else(|| CoreError::invalid_argument("選択範囲に変更行がありません"))?;
+
+    with_backend_mut(|backend| {
+        backend.apply_patch(repo_root, &patch, target != PatchTarget::Discard)
+    })?;
+    if target == PatchTarget::Discard {
+        let mut changes = discard_undo()
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if changes.len() >= UNDO_LIMIT {
+            changes.remove(0);
+        }
+        changes.push(DiscardedChange {
+            repo_root: repo_root.to_path_buf(),
+            relative_path: relative_path.to_string(),
+            patch: invert_patch(&patch),
+        });
+    }
+    Ok(())
+}
+
+/// unified diff の1ハンク（行は先頭の記号を含む生のバイト列）
+#[derive(Debug)]
+struct RawHunk<'a> {
+    old_start: u32,
+    new_start: u32,
+    lines: Vec<&'a [u8]>,
+}
+
+/// 1ファイル分の diff をハンクに分ける（内容はバイト列のまま扱い、改行コードも保つ）
+fn parse_raw_hunks(diff: &[u8]) -> Result<Vec<RawHunk<'_>>, CoreError> {
+    let mut hunks: Vec<RawHunk<'_>> = Vec::new();
+    for line in diff.split(|b| *b == b'\n') {
+        if line.starts_with(b"@@ ") {
+            let header = String::from_utf8_lossy(line);
+            let (old_start, new_start) = parse_hunk_starts(&header).ok_or_else(|| {
+                CoreError::internal(format!("ハンクヘッダーを解釈できません: {header}"))
+            })?;
+            hunks.push(RawHunk {
+                old_start,
+                new_start,
+                lines: Vec::new(),
+            });
+            continue;
+        }
+        if line.starts_with(b"Binary files ") || line.starts_with(b"GIT binary patch") {
+            return Err(CoreError::invalid_argument(
+                "バイナリファイルはハンク・行単位で扱えません",
+            ));
+        }
+        if let Some(hunk) = hunks.last_mut() {
+            if matches!(line.first(), Some(b' ' | b'+' | b'-' | b'\\')) {
+                hunk.lines.push(line);
+            }
+        }
+    }
+    Ok(hunks)
+}
+
+/// diff のファイルヘッダーにある、追加・削除されたファイルのモード
+#[derive(Debug, Default)]
+struct FileHeader {
+    /// 旧側が無い（追加された）ファイルのモード
+    new_file_mode: Option<String>,
+    /// 新側が無い（削除された）ファイルのモード
+    deleted_file_mode: Option<String>,
+}
+
+/// 最初のハンクより前の行からファイルの追加・削除を読み取る
+fn parse_file_header(diff: &[u8]) -> FileHeader {
+    let mut header = FileHeader::default();
+    for line in diff.split(|b| *b == b'\n') {
+        if line.starts_with(b"@@ ") {
+            break;
+        }
+        let line = String::from_utf8_lossy(line);
+        if let Some(mode) = line.strip_prefix("new file mode ") {
+            header.new_file_mode = Some(mode.trim_end().to_string());
+        } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+            header.deleted_file_mode = Some(mode.trim_end().to_string());
+        }
+    }
+    header
+}
+
+/// "@@ -a,b +c,d @@ ..." の a と c
+fn parse_hunk_starts(header: &str) -> Option<(u32, u32)> {
+    let rest = header.strip_prefix("@@ -")?;
+    let (old_range, rest) = rest.split_once(" +")?;
+    let (new_range, _) = rest.split_once(" @@")?;
+    let start = |range: &str| range.split(',').next()?.parse().ok();
+    Some((start(old_range)?, start(new_range)?))
+}
+
+/// ハンク内の1行（"\ No newline at end of file" は直前の行に付ける）
+struct HunkLine<'a> {
+    prefix: u8,
+    content: &'a [u8],
+    no_newline_marker: Option<&'a [u8]>,
+    line_ref: DiffLineRef,
+}
+
+/// 部分パッチの1ハンク分の出力
+#[derive(Default)]
+struct PartialHunk {
+    lines: Vec<u8>,
+    target_count: u32,
+    result_count: u32,
+    changed: bool,
+}
+
+impl PartialHunk {
+    /// prefix は出力側の記号（None は捨てる行）
+    fn push(&mut self, prefix: Option<u8>, line: &HunkLine<'_>) {
+        let Some(prefix) = prefix else {
+            return;
+        };
+        self.changed |= prefix != b' ';
+        if prefix != b'+' {
+            self.target_count += 1;
+        }
+        if prefix != b'-' {
+            self.result_count += 1;
+        }
+        self.lines.push(prefix);
+        self.lines.extend_from_slice(line.content);
+        self.lines.push(b'\n');
+        if let Some(marker) = line.no_newline_marker {
+            self.lines.extend_from_slice(marker);
+            self.lines.push(b'\n');
+        }
+    }
+}
+
+fn hunk_lines<'a>(hunk: &RawHunk<'a>) -> Vec<HunkLine<'a>> {
+    let (mut old_line, mut new_line) = (hunk.old_start, hunk.new_start);
+    let mut lines: Vec<HunkLine<'a>> = Vec::new();
+    for raw in &hunk.lines {
+        let Some((&prefix, content)) = raw.split_first() else {
+            continue;
+        };
+        let line_ref = match prefix {
+            b' ' => {
+                old_line += 1;
+                new_line += 1;
+                DiffLineRef {
+                    old_line: Some(old_line - 1),
+                    new_line: Some(new_line - 1),
+                }
+            }
+            b'-' => {
+                old_line += 1;
+                DiffLineRef {
+                    old_line: Some(old_line - 1),
+                    new_line: None,
+                }
+            }
+            b'+' => {
+                new_line += 1;
+                DiffLineRef {
+                    old_line: None,
+                    new_line: Some(new_line - 1),
+                }
+            }
+            _ => {
+                if let Some(previous) = lines.last_mut() {
+                    previous.no_newline_marker = Some(raw);
+                }
+                continue;
+            }
+        };
+        lines.push(HunkLine {
+            prefix,
+            content,
+            no_newline_marker: None,
+            line_ref,
+        });
+    }
+    lines
+}
+
+/// 選んだ行だけを変更として残した部分パッチを作る（変更行が残らなければ None）
+///
+/// 順向き（reverse = false）は差分の旧側に当てるパッチで、選ばなかった削除行は文脈に、
+/// 追加行は捨てる。逆向きは新側に当てて変更を取り消すパッチで、選ばなかった追加行は文脈に、
+/// 削除行は捨てる。どちらも当てる先の行は変えないので、ハンクの開始位置はそのまま使える。
+/// 削除行と追加行が続く変更ブロックでは i 番目同士を対にして並べ、一部だけ選んでも
+/// 置き換えた位置に入るようにする。
+/// 当てる先にファイルが無ければ作成、結果が空になるなら削除のパッチにする（/dev/null 側を使う）。
+fn build_patch(
+    path: &str,
+    header: &FileHeader,
+    hunks: &[RawHunk<'_>],
+    reverse: bool,
+    selected: impl Fn(usize, &DiffLineRef) -> bool,
+) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut shift: i64 = 0;
+    let mut result_lines: u32 = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let lines = hunk_lines(hunk);
+        let removed_prefix = |line: &HunkLine<'_>| match (reverse, selected(index, &line.line_ref))
+        {
+            (false, true) => Some(b'-'),
+            (false, false) => Some(b' '),
+            (true, true) => Some(b'+'),
+            (true, false) => None,
+        };
+        let added_prefix = |line: &HunkLine<'_>| match (reverse, selected(index, &line.line_ref)) {
+            (false, true) => Some(b'+'),
+            (false, false) => None,
+            (true, true) => Some(b'-'),
+            (true, false) => Some(b' '),
+        };
+
+        let mut partial = PartialHunk::default();
+        let mut position = 0;
+        while position < lines.len() {
+            if lines[position].prefix == b' ' {
+                partial.push(Some(b' '), &lines[position]);
+                position += 1;
+                continue;
+            }
+            let removed_start = position;
+            while position < lines.len() && lines[position].prefix == b'-' {
+                position += 1;
+            }
+            let added_start = position;
+            while position < lines.len() && lines[position].prefix == b'+' {
+                position += 1;
+            }
+            let removed = &lines[removed_start..added_start];
+            let added = &lines[added_start..position];
+
+            // 当てる先にある行を先に置く（順向きは削除行、逆向きは追加行）
+            for pair in 0..removed.len().max(added.len()) {
+                let removed = removed.get(pair).map(|line| (removed_prefix(line), line));
+                let added = added.get(pair).map(|line| (added_prefix(line), line));
+                let (first, second) = if reverse {
+                    (added, removed)
+                } else {
+                    (removed, added)
+                };
+                for (prefix, line) in first.into_iter().chain(second) {
+                    partial.push(prefix, line);
+                }
+            }
+        }
+        if !partial.changed {
+            continue;
+        }
+
+        // 件数 0 の範囲の開始位置は「直前の行」を指す
+        let target_start = if reverse {
+            hunk.new_start
+        } else {
+            hunk.old_start
+        };
+        let result_start = match (partial.target_count, partial.result_count) {
+            (0, _) => i64::from(target_start) + 1 + shift,
+            (_, 0) => i64::from(target_start) - 1 + shift,
+            _ => i64::from(target_start) + shift,
+        };
+        shift += i64::from(partial.result_count) - i64::from(partial.target_count);
+        result_lines += partial.result_count;
+
+        body.extend_from_slice(
+            format!(
+                "@@ -{target_start},{} +{},{} @@\n",
+                partial.target_count,
+                result_start.max(0),
+                partial.result_count
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(&partial.lines);
+    }
+
+    if body.is_empty() {
+        return None;
+    }
+    // 順向きは旧側、逆向きは新側に当てる。全体を追加・削除する差分はハンクが1つだけになる
+    let (target_missing, result_missing) = if reverse {
+        (&header.deleted_file_mode, &header.new_file_mode)
+    } else {
+        (&header.new_file_mode, &header.deleted_file_mode)
+    };
+    let file_header = match (target_missing, result_missing) {
+        (Some(mode), _) => format!("new file mode {mode}\n--- /dev/null\n+++ b/{path}\n"),
+        (_, Some(mode)) if result_lines == 0 => {
+            format!("deleted file mode {mode}\n--- a/{path}\n+++ /dev/null\n")
+        }
+        _ => format!("--- a/{path}\n+++ b/{path}\n"),
+    };
+    let mut patch = format!("diff --git a/{path} b/{path}\n{file_header}").into_bytes();
+    patch.extend_from_slice(&body);
+    Some(patch)
+}
+
+/// build_patch で作ったパッチを逆向きにする（当てた変更を取り消すパッチ）
+fn invert_patch(patch: &[u8]) -> Vec<u8> {
+    let mut inverted = Vec::with_capacity(patch.len());
+    let mut in_hunk = false;
+    let mut old_label: &[u8] = b"";
+    for line in patch.split(|b| *b == b'\n') {
+        if !in_hunk {
+            // ファイルの作成と削除、--- と +++ の側を入れ替える
+            if let Some(mode) = line.strip_prefix(b"new file mode ") {
+                inverted.extend_from_slice(b"deleted file mode ");
+                inverted.extend_from_slice(mode);
+                inverted.push(b'\n');
+                continue;
+            }
+            if let Some(mode) = line.strip_prefix(b"deleted file mode ") {
+                inverted.extend_from_slice(b"new file mode ");
+                inverted.extend_from_slice(mode);
+                inverted.push(b'\n');
+                continue;
+            }
+            if let Some(label) = line.strip_prefix(b"--- ") {
+                old_label = label;
+                continue;
+            }
+            if let Some(new_label) = line.strip_prefix(b"+++ ") {
+                inverted.extend_from_slice(b"--- ");
+                inverted.extend_from_slice(&swap_side(new_label, b"a/"));
+                inverted.extend_from_slice(b"\n+++ ");
+                inverted.extend_from_slice(&swap_side(old_label, b"b/"));
+                inverted.push(b'\n');
+                continue;
+            }
+        }
+        if line.starts_with(b"@@ -") {
+            in_hunk = true;
+            let header = String::from_utf8_lossy(line);
+            let swapped = header
+                .strip_prefix("@@ -")
+                .and_then(|rest| rest.split_once(" +"))
+                .and_then(|(old_range, rest)| {
+                    let (new_range, _) = rest.split_once(" @@")?;
+                    Some(format!("@@ -{new_range} +{old_range} @@"))
+                })
+                .unwrap_or_else(|| header.to_string());
+            inverted.extend_from_slice(swapped.as_bytes());
+        } else if in_hunk && line.first() == Some(&b'+') {
+            inverted.push(b'-');
+            inverted.extend_from_slice(&line[1..]);
+        } else if in_hunk && line.first() == Some(&b'-') {
+            inverted.push(b'+');
+            inverted.extend_from_slice(&line[1..]);
+        } else {
+            inverted.extend_from_slice(line);
+        }
+        inverted.push(b'\n');
+    }
+    // split で増えた末尾の改行を落とす
+    inverted.pop();
+    inverted
+}
+
+/// "a/<path>"・"b/<path>" の接頭辞を prefix に付け替える（/dev/null はそのまま）
+fn swap_side(label: &[u8], prefix: &[u8]) -> Vec<u8> {
+    match label
+        .strip_prefix(b"a/")
+        .or_else(|| label.strip_prefix(b"b/"))
+    {
+        Some(path) => [prefix, path].concat(),
+        None => label.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1,4 +1,4 @@
+ one
+-two
+-three
++TWO
++THREE
+ four
+@@ -10,2 +10,3 @@ fn tail
+ ten
+ eleven
++twelve
+";
+
+    fn patch_text(patch: Option<Vec<u8>>) -> String {
+        String::from_utf8(patch.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn build_patch_keeps_selected_lines_forward() {
+        let hunks = parse_raw_hunks(DIFF.as_bytes()).unwrap();
+        assert_eq!(hunks.len(), 2);
+
+        // "two" の削除と "TWO" の追加だけを選ぶ
+        let selection = [
+            DiffLineRef {
+                old_line: Some(2),
+                new_line: None,
+            },
+            DiffLineRef {
+                old_line: None,
+                new_line: Some(2),
+            },
+        ];
+        let patch = build_patch("a.txt", &FileHeader::default(), &hunks, false, |_, line| {
+            selection.contains(line)
+        });
+        assert_eq!(
+            patch_text(patch),
+            "diff --git a/a.txt b/a.txt\n--- a/a.txt\n+++ b/a.txt\n@@ -1,4 +1,4 @@\n one\n-two\n+TWO\n three\n four\n"
+        );
+    }
+
+    #[test]
+    fn build_patch_reverses_hunk_and_shifts_following_hunks() {
+        let hunks = parse_raw_hunks(DIFF.as_bytes()).unwrap();
+        let patch = build_patch("a.txt", &FileHeader::default(), &hunks, true, |index, _| {
+            index == 1
+        });
+        assert_eq!(
+            patch_text(patch),
+            "diff --git a/a.txt b/a.txt\n--- a/a.txt\n+++ b/a.txt\n@@ -10,3 +10,2 @@\n ten\n eleven\n-twelve\n"
+        );
+
+        // 選ばなかった追加行は文脈として残る
+        let only_first = [DiffLineRef {
+            old_line: None,
+            new_line: Some(3),
+        }];
+        let patch = build_patch("a.txt", &FileHeader::default(), &hunks, true, |_, line| {
+            only_first.contains(line)
+        });
+        assert_eq!(
+            patch_text(patch),
+            "diff --git a/a.txt b/a.txt\n--- a/a.txt\n+++ b/a.txt\n@@ -1,4 +1,3 @@\n one\n TWO\n-THREE\n four\n"
+        );
+        assert!(
+            build_patch("a.txt", &FileHeader::default(), &hunks, false, |_, _| false).is_none()
+        );
+    }
+
+    #[test]
+    fn build_patch_creates_and_deletes_whole_files() {
+        let diff = "diff --git a/n.txt b/n.txt\nnew file mode 100644\nindex 0000000..1111111\n--- /dev/null\n+++ b/n.txt\n@@ -0,0 +1,2 @@\n+one\n+two\n";
+        let header = parse_file_header(diff.as_bytes());
+        let hunks = parse_raw_hunks(diff.as_bytes()).unwrap();
+        let first = [DiffLineRef {
+            old_line: None,
+            new_line: Some(1),
+        }];
+
+        // インデックスに無いファイルへは作成のパッチにする
+        let patch = build_patch("n.txt", &header, &hunks, false, |_, line| {
+            first.contains(line)
+        });
+        assert_eq!(
+            patch_text(patch),
+            "diff --git a/n.txt b/n.txt\nnew file mode 100644\n--- /dev/null\n+++ b/n.txt\n@@ -0,0 +1,1 @@\n+one\n"
+        );
+
+        // 全行を取り消すと削除、一部なら変更のパッチになる
+        let patch = build_patch("n.txt", &header, &hunks, true, |_, _| true);
+        assert_eq!(
+            patch_text(patch),
+            "diff --git a/n.txt b/n.txt\ndeleted file mode 100644\n--- a/n.txt\n+++ /dev/null\n@@ -1,2 +0,0 @@\n-one\n-two\n"
+        );
+        let patch = build_patch("n.txt", &header, &hunks, true, |_, line| {
+            first.contains(line)
+        });
+        assert_eq!(
+            patch_text(patch),
+            "diff --git a/n.txt b/n.txt\n--- a/n.txt\n+++ b/n.txt\n@@ -1,2 +1,1 @@\n-one\n two\n"
+        );
+    }
+
+    #[test]
+    fn invert_patch_swaps_file_creation_and_deletion() {
+        let patch = "diff --git a/n.txt b/n.txt\ndeleted file mode 100644\n--- a/n.txt\n+++ /dev/null\n@@ -1,1 +0,0 @@\n-one\n";
+        assert_eq!(
+            String::from_utf8(invert_patch(patch.as_bytes())).unwrap(),
+            "diff --git a/n.txt b/n.txt\nnew file mode 100644\n--- /dev/null\n+++ b/n.txt\n@@ -0,0 +1,1 @@\n+one\n"
+        );
+    }
+
+    #[test]
+    fn invert_patch_swaps_sides() {
+        let patch = "diff --git a/a.txt b/a.txt\n--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,3 @@\n one\n-two\n+TWO\n+extra\n";
+        assert_eq!(
+            String::from_utf8(invert_patch(patch.as_bytes())).unwrap(),
+            "diff --git a/a.txt b/a.txt\n--- a/a.txt\n+++ b/a.txt\n@@ -1,3 +1,2 @@\n one\n+two\n-TWO\n-extra\n"
+        );
+    }
+
+    #[test]
+    fn parse_raw_hunks_rejects_binary() {
+        let diff =
+            b"diff --git a/logo.png b/logo.png\nBinary files a/logo.png and b/logo.png differ\n";
+        assert!(matches!(
+            parse_raw_hunks(diff),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+    }
+}
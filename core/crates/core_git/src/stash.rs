@@ -0,0 +1,244 @@
+use std::path::Path;
+
+use core_types::{CoreError, DiffFile, StashApplyResult, StashCreateOptions, StashEntry};
+
+use crate::{
+    history::signature, parse_unified_diff, resolve_repo_root, with_backend, with_backend_mut,
+    GitBackend,
+};
+
+/// `git stash list` の --format（--date=raw と組み合わせる）
+pub(crate) const STASH_FORMAT: &str = "--format=%H%x1f%cn%x1f%ce%x1f%cd%x1f%s%x1e";
+
+/// stash の一覧を新しい順に返す
+pub fn list_stashes(root_path: &str) -> Result<Vec<StashEntry>, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+
+    with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        backend.stashes(&repo_root)
+    })
+}
+
+/// 作業ツリーの変更を stash し、作成した stash（stash@{0}）を返す
+///
+/// stash する変更が無ければ NoChanges を返す。
+pub fn create_stash(
+    root_path: &str,
+    options: &StashCreateOptions,
+) -> Result<StashEntry, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+    if options
+        .message
+        .as_deref()
+        .is_some_and(|message| message.trim().is_empty())
+    {
+        return Err(CoreError::invalid_argument("message が空です"));
+    }
+    if options.include_untracked && options.staged_only {
+        return Err(CoreError::invalid_argument(
+            "include_untracked と staged_only は併用できません",
+        ));
+    }
+
+    let repo_root = with_backend(|backend| resolve_repo_root(backend, root_path))?;
+    let created = with_backend_mut(|backend| {
+        backend.create_stash(
+            &repo_root,
+            options.message.as_deref(),
+            options.include_untracked,
+            options.staged_only,
+        )
+    })?;
+    if !created {
+        return Err(CoreError::NoChanges {
+            path: root_path.to_string(),
+        });
+    }
+    with_backend(|backend| backend.stashes(&repo_root))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| CoreError::internal("作成した stash が見つかりません"))
+}
+
+/// stash を作業ツリーに適用する（stash は残す）
+///
+/// マージでコンフリクトしたファイルは結果の conflicts に入り、作業ツリーには
+/// コンフリクトマーカーが残る。ローカルの変更が上書きされる場合は何も変更せず
+/// StashConflict を返す。
+pub fn apply_stash(root_path: &str, index: u32) -> Result<StashApplyResult, CoreError> {
+    restore_stash(root_path, index, false)
+}
+
+/// stash を適用して削除する（コンフリクトした場合は stash を残す）
+pub fn pop_stash(root_path: &str, index: u32) -> Result<StashApplyResult, CoreError> {
+    restore_stash(root_path, index, true)
+}
+
+/// stash を削除する
+pub fn drop_stash(root_path: &str, index: u32) -> Result<(), CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+
+    let repo_root = with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        check_stash_index(backend, &repo_root, index)?;
+        Ok(repo_root)
+    })?;
+    with_backend_mut(|backend| backend.drop_stash(&repo_root, index as usize))
+}
+
+/// stash の内容を stash したときの HEAD からの差分として返す（未追跡ファイルは追加扱い）
+pub fn stash_diff(root_path: &str, index: u32) -> Result<Vec<DiffFile>, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+
+    with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        check_stash_index(backend, &repo_root, index)?;
+        let diff_text = backend.stash_diff(&repo_root, index as usize)?;
+        Ok(parse_unified_diff(&diff_text))
+    })
+}
+
+fn restore_stash(root_path: &str, index: u32, pop: bool) -> Result<StashApplyResult, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+
+    let repo_root = with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        check_stash_index(backend, &repo_root, index)?;
+        let status = backend.status(&repo_root)?;
+        if status.entries.iter().any(|entry| entry.conflict.is_some()) {
+            return Err(CoreError::invalid_argument(
+                "コンフリクトが解消されていないファイルがあります",
+            ));
+        }
+        Ok(repo_root)
+    })?;
+    with_backend_mut(|backend| backend.apply_stash(&repo_root, index as usize, pop))
+}
+
+fn check_stash_index(
+    backend: &dyn GitBackend,
+    repo_root: &Path,
+    index: u32,
+) -> Result<(), CoreError> {
+    if index as usize >= backend.stashes(repo_root)?.len() {
+        return Err(CoreError::invalid_argument(format!(
+            "stash が見つかりません: stash@{{{index}}}"
+        )));
+    }
+    Ok(())
+}
+
+/// stash の件名 "WIP on <branch>: ..." / "On <branch>: ..." をブランチとメッセージに分ける
+pub(crate) fn parse_stash_subject(subject: &str) -> (Option<String>, String) {
+    let Some((branch, message)) = subject
+        .strip_prefix("WIP on ")
+        .or_else(|| subject.strip_prefix("On "))
+        .and_then(|rest| rest.split_once(": "))
+    else {
+        return (None, subject.to_string());
+    };
+    let branch = (branch != "(no branch)").then(|| branch.to_string());
+    (branch, message.to_string())
+}
+
+/// `git stash list --date=raw <STASH_FORMAT>` の出力をパースする
+pub(crate) fn parse_stash_list(output: &str) -> Result<Vec<StashEntry>, CoreError> {
+    let mut stashes = Vec::new();
+    for record in output.split('\x1e') {
+        let record = record.trim_start_matches('\n');
+        if record.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = record.splitn(5, '\x1f').collect();
+        let [sha, name, email, date, subject] = fields.as_slice() else {
+            return Err(CoreError::internal(format!(
+                "git stash list の出力を解釈できません: {record}"
+            )));
+        };
+        let (branch, message) = parse_stash_subject(subject);
+        stashes.push(StashEntry {
+            index: stashes.len() as u32,
+            sha: sha.to_string(),
+            message,
+            branch,
+            committer: signature(name, email, date)?,
+        });
+    }
+    Ok(stashes)
+}
+
+/// `git stash apply` が拒否したときに上書きされるとされたパス
+/// （未追跡ファイルの復元先が既にある場合の "<path> already exists, no checkout" も含む）
+pub(crate) fn parse_stash_conflict_paths(stderr: &str) -> Vec<String> {
+    let mut paths: Vec<String> = stderr
+        .lines()
+        .filter_map(|line| {
+            line.strip_prefix('\t')
+                .or_else(|| line.strip_suffix(" already exists, no checkout"))
+        })
+        .map(|path| path.trim().to_string())
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_stash_subject_splits_branch() {
+        assert_eq!(
+            parse_stash_subject("WIP on main: 1234567 initial"),
+            (Some("main".to_string()), "1234567 initial".to_string())
+        );
+        assert_eq!(
+            parse_stash_subject("On feature/x: half-done parser"),
+            (
+                Some("feature/x".to_string()),
+                "half-done parser".to_string()
+            )
+        );
+        assert_eq!(
+            parse_stash_subject("WIP on (no branch): 1234567 initial"),
+            (None, "1234567 initial".to_string())
+        );
+        assert_eq!(
+            parse_stash_subject("autostash"),
+            (None, "autostash".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_stash_list_numbers_entries() {
+        let output = format!(
+            "{sha}\x1fAlice\x1falice@example.com\x1f1700000000 +0900\x1fOn main: second\x1e\n{sha}\x1fAlice\x1falice@example.com\x1f1690000000 -0130\x1fWIP on topic: 1234567 first\x1e\n",
+            sha = "a".repeat(40)
+        );
+        let stashes = parse_stash_list(&output).unwrap();
+        assert_eq!(stashes.len(), 2);
+        assert_eq!(stashes[0].index, 0);
+        assert_eq!(stashes[0].message, "second");
+        assert_eq!(stashes[1].index, 1);
+        assert_eq!(stashes[1].branch.as_deref(), Some("topic"));
+        assert_eq!(stashes[1].committer.offset_minutes, -90);
+        assert!(parse_stash_list("").unwrap().is_empty());
+
+        let stderr = "error: Your local changes to the following files would be overwritten by merge:\n\tsample.swift\nPlease commit your changes or stash them before you merge.\nAborting\nnew.swift already exists, no checkout\n";
+        assert_eq!(
+            parse_stash_conflict_paths(stderr),
+            vec!["new.swift", "sample.swift"]
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use std::path::Path;
+
+use core_types::{
+    CoreError, GitBranchStatus, GitConflictKind, GitFileState, GitRepositoryStatus, GitStatus,
+    GitStatusEntry, GitStatusItem, GitSubmoduleState,
+};
+
+use crate::{resolve_repo_root, with_backend};
+
+/// リポジトリの状態（ブランチ・上流との差分・型付きの変更エントリ）を1回で返す
+pub fn repository_status(root_path: &str) -> Result<GitRepositoryStatus, CoreError> {
+    if root_path.trim().is_empty() {
+        return Err(CoreError::invalid_argument("root_path が空です"));
+    }
+
+    with_backend(|backend| {
+        let repo_root = resolve_repo_root(backend, root_path)?;
+        backend.status(&repo_root)
+    })
+}
+
+/// 型付きの状態を XY 文字列の staged / unstaged / untracked に分類する
+pub(crate) fn classify_status(status: &GitRepositoryStatus) -> GitStatus {
+    let mut classified = GitStatus {
+        staged: Vec::new(),
+        unstaged: Vec::new(),
+        untracked: Vec::new(),
+    };
+    for item in &status.entries {
+        let entry = GitStatusEntry {
+            path: item.path.clone(),
+            status: format!("{}{}", state_code(item.index), state_code(item.worktree)),
+        };
+        if item.index == GitFileState::Untracked {
+            classified.untracked.push(entry);
+            continue;
+        }
+        if item.index != GitFileState::Unmodified {
+            classified.staged.push(entry.clone());
+        }
+        if item.worktree != GitFileState::Unmodified {
+            classified.unstaged.push(entry);
+        }
+    }
+    classified
+}
+
+/// porcelain v1 の XY の1文字
+fn state_code(state: GitFileState) -> char {
+    match state {
+        GitFileState::Unmodified => ' ',
+        GitFileState::Modified => 'M',
+        GitFileState::TypeChanged => 'T',
+        GitFileState::Added => 'A',
+        GitFileState::Deleted => 'D',
+        GitFileState::Renamed => 'R',
+        GitFileState::Copied => 'C',
+        GitFileState::Unmerged => 'U',
+        GitFileState::Untracked => '?',
+    }
+}
+
+fn parse_state(code: u8) -> GitFileState {
+    match code {
+        b'M' => GitFileState::Modified,
+        b'T' => GitFileState::TypeChanged,
+        b'A' => GitFileState::Added,
+        b'D' => GitFileState::Deleted,
+        b'R' => GitFileState::Renamed,
+        b'C' => GitFileState::Copied,
+        b'U' => GitFileState::Unmerged,
+        b'?' => GitFileState::Untracked,
+        _ => GitFileState::Unmodified,
+    }
+}
+
+/// コンフリクトの種類に対応する XY の状態
+pub(crate) fn conflict_states(kind: GitConflictKind) -> (GitFileState, GitFileState) {
+    use GitFileState::{Added, Deleted, Unmerged};
+    match kind {
+        GitConflictKind::BothDeleted => (Deleted, Deleted),
+        GitConflictKind::AddedByUs => (Added, Unmerged),
+        GitConflictKind::DeletedByThem => (Unmerged, Deleted),
+        GitConflictKind::AddedByThem => (Unmerged, Added),
+        GitConflictKind::DeletedByUs => (Deleted, Unmerged),
+        GitConflictKind::BothAdded => (Added, Added),
+        GitConflictKind::BothModified => (Unmerged, Unmerged),
+    }
+}
+
+fn parse_conflict(xy: &[u8]) -> GitConflictKind {
+    match xy {
+        b"DD" => GitConflictKind::BothDeleted,
+        b"AU" => GitConflictKind::AddedByUs,
+        b"UD" => GitConflictKind::DeletedByThem,
+        b"UA" => GitConflictKind::AddedByThem,
+        b"DU" => GitConflictKind::DeletedByUs,
+        b"AA" => GitConflictKind::BothAdded,
+        _ => GitConflictKind::BothModified,
+    }
+}
+
+/// porcelain v2 の <sub> 欄（"N..." はサブモジュールではない）
+fn parse_submodule(field: &[u8]) -> Option<GitSubmoduleState> {
+    match field {
+        [b'S', c, m, u] => Some(GitSubmoduleState {
+            commit_changed: *c == b'C',
+            has_tracked_changes: *m == b'M',
+            has_untracked_changes: *u == b'U',
+        }),
+        _ => None,
+    }
+}
+
+/// リポジトリ相対パス（バイト列）を絶対パスの文字列にする
+pub(crate) fn absolute_status_path(repo_root: &Path, relative_path: &[u8]) -> String {
+    repo_root
+        .join(String::from_utf8_lossy(relative_path).as_ref())
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// `git status --porcelain=v2 -z --branch` の出力をパースする
+///
+/// -z ではパスが引用符なしのまま NUL 区切りで出るため、バイト列のまま分割する。
+/// リネーム・コピー（種別 2）は続く NUL 区切りの欄が元のパス。
+pub(crate) fn parse_status_v2(
+    output: &[u8],
+    repo_root: &Path,
+) -> Result<GitRepositoryStatus, CoreError> {
+    let mut branch = GitBranchStatus {
+        head: None,
+        oid: None,
+        upstream: None,
+        ahead: None,
+        behind: None,
+    };
+    let mut entries = Vec::new();
+    let mut records = output.split(|b| *b == 0).filter(|r| !r.is_empty());
+
+    while let Some(record) = records.next() {
+        let malformed = || {
+            CoreError::internal(format!(
+                "git status の出力を解釈できません: {}",
+                String::from_utf8_lossy(record)
+            ))
+        };
+        let Some((&kind, rest)) = record.split_first() else {
+            continue;
+        };
+        let rest = rest.strip_prefix(b" ").ok_or_else(malformed)?;
+
+        match kind {
+            b'#' => parse_branch_header(&String::from_utf8_lossy(rest), &mut branch),
+            b'1' | b'2' | b'u' => {
+                // パスの前の欄の数（パスは空白を含み得るので残りをまとめて取る）
+                let field_count = match kind {
+                    b'1' => 7,
+                    b'2' => 8,
+                    _ => 9,
+                };
+                let mut fields = rest.splitn(field_count + 1, |b| *b == b' ');
+                let xy = fields
+                    .next()
+                    .filter(|xy| xy.len() == 2)
+                    .ok_or_else(malformed)?;
+                let submodule = parse_submodule(fields.next().ok_or_else(malformed)?);
+                let path = fields.nth(field_count - 2).ok_or_else(malformed)?;
+
+                let original_path = if kind == b'2' {
+                    let original = records.next().ok_or_else(malformed)?;
+                    Some(absolute_status_path(repo_root, original))
+                } else {
+                    None
+                };
+                let conflict = (kind == b'u').then(|| parse_conflict(xy));
+                entries.push(GitStatusItem {
+                    path: absolute_status_path(repo_root, path),
+                    original_path,
+                    index: parse_state(xy[0]),
+                    worktree: parse_state(xy[1]),
+                    conflict,
+                    submodule,
+                });
+            }
+            b'?' => entries.push(GitStatusItem {
+                path: absolute_status_path(repo_root, rest),
+                original_path: None,
+                index: GitFileState::Untracked,
+                worktree: GitFileState::Untracked,
+                conflict: None,
+                submodule: None,
+            }),
+            // 無視ファイル（--ignored 指定時のみ）は扱わない
+            b'!' => {}
+            _ => return Err(malformed()),
+        }
+    }
+
+    Ok(GitRepositoryStatus { branch, entries })
+}
+
+/// "# branch.xxx <value>" のヘッダー行
+fn parse_branch_header(header: &str, branch: &mut GitBranchStatus) {
+    let Some((key, value)) = header.split_once(' ') else {
+        return;
+    };
+    match key {
+        "branch.oid" if value != "(initial)" => branch.oid = Some(value.to_string()),
+        "branch.head" if value != "(detached)" => branch.head = Some(value.to_string()),
+        "branch.upstream" => branch.upstream = Some(value.to_string()),
+        "branch.ab" => {
+            // "+<ahead> -<behind>"
+            let mut counts = value.split(' ');
+            branch.ahead = counts
+                .next()
+                .and_then(|ahead| ahead.strip_prefix('+')?.parse().ok());
+            branch.behind = counts
+                .next()
+                .and_then(|behind| behind.strip_prefix('-')?.parse().ok());
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_status_v2_reads_branch_and_typed_entries() {
+        let repo_root = Path::new("/tmp/blink-repo");
+        let output = concat!(
+            "# branch.oid 1234567890abcdef1234567890abcdef12345678\0",
+            "# branch.head feature/x\0",
+            "# branch.upstream origin/feature/x\0",
+            "# branch.ab +2 -1\0",
+            "1 .M N... 100644 100644 100644 aaaaaaa aaaaaaa src/with space.swift\0",
+            "2 R. N... 100644 100644 100644 bbbbbbb bbbbbbb R100 src/new\nname.swift\0src/old.swift\0",
+            "1 .M SC.U 160000 160000 160000 ccccccc ccccccc vendor/lib\0",
+            "u AA N... 000000 100644 100644 100644 ddddddd eeeeeee fffffff both.swift\0",
+            "? untracked.swift\0",
+        );
+
+        let status = parse_status_v2(output.as_bytes(), repo_root).unwrap();
+        assert_eq!(status.branch.head.as_deref(), Some("feature/x"));
+        assert_eq!(status.branch.upstream.as_deref(), Some("origin/feature/x"));
+        assert_eq!(
+            (status.branch.ahead, status.branch.behind),
+            (Some(2), Some(1))
+        );
+        assert_eq!(status.entries.len(), 5);
+
+        let modified = &status.entries[0];
+        assert_eq!(modified.path, "/tmp/blink-repo/src/with space.swift");
+        assert_eq!(modified.index, GitFileState::Unmodified);
+        assert_eq!(modified.worktree, GitFileState::Modified);
+        assert_eq!(modified.submodule, None);
+
+        // -z では改行を含むパスも引用符なしで出る
+        let renamed = &status.entries[1];
+        assert_eq!(renamed.path, "/tmp/blink-repo/src/new\nname.swift");
+        assert_eq!(
+            renamed.original_path.as_deref(),
+            Some("/tmp/blink-repo/src/old.swift")
+        );
+        assert_eq!(renamed.index, GitFileState::Renamed);
+
+        let submodule = status.entries[2].submodule.as_ref().unwrap();
+        assert!(submodule.commit_changed);
+        assert!(!submodule.has_tracked_changes);
+        assert!(submodule.has_untracked_changes);
+
+        assert_eq!(status.entries[3].conflict, Some(GitConflictKind::BothAdded));
+        assert_eq!(status.entries[4].index, GitFileState::Untracked);
+    }
+
+    #[test]
+    fn parse_status_v2_handles_detached_and_initial_heads() {
+        let repo_root = Path::new("/tmp/blink-repo");
+        let status =
+            parse_status_v2(b"# branch.oid (initial)\0# branch.head main\0", repo_root).unwrap();
+        assert_eq!(status.branch.oid, None);
+        assert_eq!(status.branch.head.as_deref(), Some("main"));
+        assert!(status.entries.is_empty());
+
+        let status = parse_status_v2(
+            b"# branch.oid 1234567890abcdef1234567890abcdef12345678\0# branch.head (detached)\0",
+            repo_root,
+        )
+        .unwrap();
+        assert_eq!(status.branch.head, None);
+        assert_eq!(status.branch.ahead, None);
+        assert!(status.branch.oid.is_some());
+    }
+
+    #[test]
+    fn classify_status_splits_by_side() {
+        let repo_root = Path::new("/tmp/blink-repo");
+        let output = concat!(
+            "1 M. N... 100644 100644 100644 a a staged.swift\0",
+            "1 MM N... 100644 100644 100644 a a both.swift\0",
+            "u UU N... 100644 100644 100644 100644 a b c conflict.swift\0",
+            "? new.swift\0",
+        );
+        let status = parse_status_v2(output.as_bytes(), repo_root).unwrap();
+        let classified = classify_status(&status);
+
+        let codes = |entries: &[GitStatusEntry]| -> Vec<String> {
+            entries.iter().map(|e| e.status.clone()).collect()
+        };
+        assert_eq!(codes(&classified.staged), vec!["M ", "MM", "UU"]);
+        assert_eq!(codes(&classified.unstaged), vec!["MM", "UU"]);
+        assert_eq!(codes(&classified.untracked), vec!["??"]);
+        assert_eq!(
+            conflict_states(GitConflictKind::DeletedByUs),
+            (GitFileState::Deleted, GitFileState::Unmerged)
+        );
+    }
+}
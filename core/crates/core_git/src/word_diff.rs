@@ -0,0 +1,278 @@
+use core_types::{DiffHunk, DiffLineOrigin, MatchRange};
+
+/// 行内差分を計算するトークン数の上限（これを超える行は行全体の強調のみ）
+const MAX_TOKENS: usize = 300;
+/// 変わっていない部分がこの割合未満なら、行内の強調はかえって読みにくいので付けない
+const MIN_SIMILARITY: f64 = 0.4;
+
+/// ハンク内の連続する削除行と追加行を先頭から順に対にし、行内の変更範囲を付ける
+pub(crate) fn annotate_word_changes(hunk: &mut DiffHunk) {
+    let mut index = 0;
+    while index < hunk.lines.len() {
+        let removed_start = index;
+        while index < hunk.lines.len() && hunk.lines[index].origin == DiffLineOrigin::Removed {
+            index += 1;
+        }
+        let added_start = index;
+        while index < hunk.lines.len() && hunk.lines[index].origin == DiffLineOrigin::Added {
+            index += 1;
+        }
+        if removed_start == index {
+            index += 1;
+            continue;
+        }
+
+        let pairs = (added_start - removed_start).min(index - added_start);
+        for offset in 0..pairs {
+            let removed = removed_start + offset;
+            let added = added_start + offset;
+            if let Some((old_ranges, new_ranges)) =
+                word_changes(&hunk.lines[removed].content, &hunk.lines[added].content)
+            {
+                hunk.lines[removed].word_changes = old_ranges;
+                hunk.lines[added].word_changes = new_ranges;
+            }
+        }
+    }
+}
+
+/// 2行を単語単位で比較し、それぞれの行で変わった部分の byte 範囲を返す
+/// 似ていない行・長すぎる行は None
+pub(crate) fn word_changes(old: &str, new: &str) -> Option<(Vec<MatchRange>, Vec<MatchRange>)> {
+    let old_tokens = tokenize(old);
+    let new_tokens = tokenize(new);
+    if old_tokens.len() > MAX_TOKENS || new_tokens.len() > MAX_TOKENS {
+        return None;
+    }
+
+    let old_words: Vec<&str> = old_tokens.iter().map(|&(s, e)| &old[s..e]).collect();
+    let new_words: Vec<&str> = new_tokens.iter().map(|&(s, e)| &new[s..e]).collect();
+    let (old_changed, new_changed) = myers_changes(&old_words, &new_words)?;
+
+    let unchanged_bytes: usize = old_tokens
+        .iter()
+        .zip(&old_changed)
+        .filter(|(_, &changed)| !changed)
+        .map(|(&(s, e), _)| e - s)
+        .sum();
+    let total = old.len() + new.len();
+    if total > 0 && (unchanged_bytes * 2) as f64 / (total as f64) < MIN_SIMILARITY {
+        return None;
+    }
+
+    Some((
+        changed_ranges(&old_tokens, &old_changed),
+        changed_ranges(&new_tokens, &new_changed),
+    ))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CharClass {
+    Word,
+    Whitespace,
+    Other,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else if c.is_whitespace() {
+        CharClass::Whitespace
+    } else {
+        CharClass::Other
+    }
+}
+
+/// 識別子・数値の連続、空白の連続、記号1文字ずつに分ける（構文ハイライトのトークン境界に近い）
+fn tokenize(text: &str) -> Vec<(usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut current: Option<CharClass> = None;
+
+    for (index, c) in text.char_indices() {
+        let class = char_class(c);
+        match current {
+            Some(previous) if previous == class && class != CharClass::Other => {}
+            Some(_) => {
+                tokens.push((start, index));
+                start = index;
+            }
+            None => start = index,
+        }
+        current = Some(class);
+    }
+    if current.is_some() {
+        tokens.push((start, text.len()));
+    }
+    tokens
+}
+
+/// Myers の差分アルゴリズムで、各トークンが変更（削除・追加）されたかを返す
+///
+/// 変更トークン数が全体の (1 - MIN_SIMILARITY) を超えた時点で似ていない行として打ち切る
+/// （探索の記録は打ち切り距離の2乗に比例するので、長く違う行でもメモリを使い切らない）。
+fn myers_changes(old: &[&str], new: &[&str]) -> Option<(Vec<bool>, Vec<bool>)> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = ((n + m) as f64 * (1.0 - MIN_SIMILARITY)) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut found = false;
+    'search: for d in 0..=max as isize {
+        trace.push(v.clone());
+        let mut k = -d;
+        while k <= d {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                found = true;
+                break 'search;
+            }
+            k += 2;
+        }
+    }
+    if !found {
+        return None;
+    }
+
+    let mut old_changed = vec![true; old.len()];
+    let mut new_changed = vec![true; new.len()];
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let index = (k + offset) as usize;
+        let previous_k = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = v[(previous_k + offset) as usize];
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            old_changed[x as usize] = false;
+            new_changed[y as usize] = false;
+        }
+        if d > 0 {
+            x = previous_x;
+            y = previous_y;
+        }
+    }
+
+    Some((old_changed, new_changed))
+}
+
+/// 変更トークンの範囲を隣接するもの同士まとめる
+fn changed_ranges(tokens: &[(usize, usize)], changed: &[bool]) -> Vec<MatchRange> {
+    let mut ranges: Vec<MatchRange> = Vec::new();
+    for (&(start, end), _) in tokens.iter().zip(changed).filter(|(_, &c)| c) {
+        match ranges.last_mut() {
+            Some(last) if last.end_col as usize == start => last.end_col = end as u32,
+            _ => ranges.push(MatchRange {
+                start_col: start as u32,
+                end_col: end as u32,
+            }),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_types::DiffLine;
+
+    fn range(start_col: u32, end_col: u32) -> MatchRange {
+        MatchRange { start_col, end_col }
+    }
+
+    fn line(origin: DiffLineOrigin, content: &str) -> DiffLine {
+        DiffLine {
+            origin,
+            old_line: None,
+            new_line: None,
+            content: content.into(),
+            no_newline_at_eof: false,
+            word_changes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn word_changes_marks_replaced_identifier() {
+        let (old, new) =
+            word_changes("let total = price * qty;", "let total = cost * qty;").unwrap();
+        assert_eq!(old, vec![range(12, 17)]);
+        assert_eq!(new, vec![range(12, 16)]);
+    }
+
+    #[test]
+    fn word_changes_marks_insertion_only_on_new_side() {
+        let (old, new) = word_changes("call(a, b)", "call(a, b, c)").unwrap();
+        assert!(old.is_empty());
+        assert_eq!(new, vec![range(9, 12)]);
+    }
+
+    #[test]
+    fn word_changes_skips_dissimilar_lines() {
+        assert!(word_changes("fn alpha() {}", "struct Beta;").is_none());
+    }
+
+    #[test]
+    fn myers_changes_gives_up_once_too_many_tokens_changed() {
+        let old = vec!["a"; MAX_TOKENS];
+        let new = vec!["b"; MAX_TOKENS];
+        assert!(myers_changes(&old, &new).is_none());
+
+        let mut edited = old.clone();
+        edited[MAX_TOKENS / 2] = "b";
+        let (old_changed, new_changed) = myers_changes(&old, &edited).unwrap();
+        assert_eq!(old_changed.iter().filter(|&&c| c).count(), 1);
+        assert_eq!(new_changed.iter().filter(|&&c| c).count(), 1);
+    }
+
+    #[test]
+    fn word_changes_uses_byte_columns_for_multibyte_text() {
+        let (old, new) = word_changes("let 名前 = 1", "let 名前 = 2").unwrap();
+        assert_eq!(old, vec![range(13, 14)]);
+        assert_eq!(new, vec![range(13, 14)]);
+    }
+
+    #[test]
+    fn annotate_pairs_removed_and_added_runs() {
+        let mut hunk = DiffHunk {
+            header: "@@ -1,3 +1,3 @@".into(),
+            old_start: 1,
+            old_lines: 3,
+            new_start: 1,
+            new_lines: 3,
+            lines: vec![
+                line(DiffLineOrigin::Removed, "let a = 1;"),
+                line(DiffLineOrigin::Removed, "let b = 2;"),
+                line(DiffLineOrigin::Added, "let a = 10;"),
+                line(DiffLineOrigin::Context, "let c = 3;"),
+                line(DiffLineOrigin::Added, "let d = 4;"),
+            ],
+        };
+        annotate_word_changes(&mut hunk);
+
+        assert_eq!(hunk.lines[0].word_changes, vec![range(8, 9)]);
+        assert_eq!(hunk.lines[2].word_changes, vec![range(8, 10)]);
+        // 対になる相手が無い行は空のまま
+        assert!(hunk.lines[1].word_changes.is_empty());
+        assert!(hunk.lines[4].word_changes.is_empty());
+    }
+}
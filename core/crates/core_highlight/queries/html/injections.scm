@@ -0,0 +1,19 @@
+; Blink 独自の injection クエリ（文法同梱の injections.scm より優先される）
+
+; <script type="text/typescript"> / lang="ts"
+((script_element
+  (start_tag
+    (attribute
+      (attribute_name) @_attr
+      (quoted_attribute_value (attribute_value) @_lang)))
+  (raw_text) @injection.content)
+ (#any-of? @_attr "type" "lang")
+ (#any-of? @_lang "ts" "typescript" "text/typescript")
+ (#set! injection.language "typescript"))
+
+; イベントハンドラ属性: onclick="..."
+((attribute
+  (attribute_name) @_attr
+  (quoted_attribute_value (attribute_value) @injection.content))
+ (#match? @_attr "^on[a-z]+$")
+ (#set! injection.language "javascript"))
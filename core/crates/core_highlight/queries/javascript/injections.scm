@@ -0,0 +1,14 @@
+; Blink 独自の injection クエリ（文法同梱の injections.scm より優先される）
+
+; styled-components: styled.div`...` / styled(Button)`...`
+(call_expression
+  function: [
+    (member_expression
+      object: (identifier) @_tag)
+    (call_expression
+      function: (identifier) @_tag)
+  ]
+  arguments: (template_string (string_fragment) @injection.content)
+  (#eq? @_tag "styled")
+  (#set! injection.language "css")
+  (#set! injection.combined))
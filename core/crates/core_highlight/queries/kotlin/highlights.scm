@@ -0,0 +1,110 @@
+; tree-sitter-kotlin-ng は highlights.scm を同梱していないため Blink で定義する
+
+; Declarations
+
+(class_declaration
+  name: (identifier) @type)
+
+(object_declaration
+  (identifier) @type)
+
+(type_alias
+  (identifier) @type)
+
+(enum_entry
+  (identifier) @constant)
+
+(function_declaration
+  name: (identifier) @function)
+
+(parameter
+  (identifier) @variable.parameter)
+
+(class_parameter
+  (identifier) @property)
+
+(user_type
+  (identifier) @type)
+
+(package_header
+  (qualified_identifier) @module)
+
+(import
+  (qualified_identifier) @module)
+
+(annotation
+  "@" @attribute
+  (type) @attribute)
+
+(annotation
+  "@" @attribute
+  (constructor_invocation (type) @attribute))
+
+(label) @label
+
+(this_expression) @variable.builtin
+(super_expression) @variable.builtin
+
+; Calls
+
+(call_expression
+  (identifier) @function.call)
+
+(call_expression
+  (navigation_expression
+    (identifier) @function.method .))
+
+; Literals
+
+(number_literal) @number
+(float_literal) @number
+(character_literal) @character
+(string_literal) @string
+(multiline_string_literal) @string
+(escape_sequence) @string.escape
+
+(interpolation
+  "${" @punctuation.special
+  "}" @punctuation.special) @embedded
+
+(interpolation
+  "$" @punctuation.special) @embedded
+
+(line_comment) @comment
+(block_comment) @comment
+(shebang) @comment
+
+; Keywords
+
+[
+  "abstract" "actual" "annotation" "as" "as?" "by" "catch" "class" "companion"
+  "const" "constructor" "crossinline" "data" "do" "else" "enum" "expect"
+  "external" "final" "finally" "for" "fun" "get" "if" "import" "in" "infix"
+  "init" "inline" "inner" "interface" "internal" "is" "lateinit" "noinline"
+  "object" "open" "operator" "out" "override" "package" "private" "protected"
+  "public" "return" "return@" "sealed" "set" "super" "super@"
+  "suspend" "tailrec" "this" "this@" "throw" "try" "typealias" "val" "value"
+  "var" "vararg" "when" "where" "while"
+] @keyword
+
+; Operators and punctuation
+
+[
+  "!" "!!" "!=" "!==" "!in" "!is" "%" "%=" "&&" "*" "*=" "+" "++" "+=" "-"
+  "--" "-=" "->" ".." "..<" "/" "/=" "<" "<=" "=" "==" "===" ">" ">=" "?:"
+  "||" "::"
+] @operator
+
+["(" ")" "[" "]" "{" "}"] @punctuation.bracket
+
+["," "." "?." ";" ":"] @punctuation.delimiter
+
+; Identifiers（先に書いたパターンが優先されるため汎用的なものは最後に置く）
+
+((identifier) @constant.builtin
+  (#any-of? @constant.builtin "true" "false" "null"))
+
+((identifier) @constant
+  (#match? @constant "^[A-Z][A-Z0-9_]+$"))
+
+(identifier) @variable
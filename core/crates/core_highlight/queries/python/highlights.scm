@@ -0,0 +1,17 @@
+; Blink 独自の上書きクエリ（tree-sitter-python の highlights.scm より優先される）
+
+(call
+  function: (attribute
+    attribute: (identifier) @function.method))
+
+(call
+  function: (identifier) @function.call)
+
+(decorator
+  (identifier) @function)
+
+(type
+  (identifier) @type)
+
+((identifier) @variable.builtin
+  (#any-of? @variable.builtin "self" "cls"))
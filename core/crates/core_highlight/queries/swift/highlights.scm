@@ -0,0 +1,15 @@
+; Blink 独自の上書きクエリ（tree-sitter-swift の highlights.scm より優先される）
+
+(call_expression
+  (simple_identifier) @function.call)
+
+(call_expression
+  (navigation_expression
+    suffix: (navigation_suffix
+      suffix: (simple_identifier) @function.method)))
+
+((simple_identifier) @variable.builtin
+  (#eq? @variable.builtin "self"))
+
+(boolean_literal) @boolean
+"nil" @constant.builtin
@@ -0,0 +1,469 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use core_types::{LanguageDetection, LanguageSource};
+use globset::GlobBuilder;
+
+/// モードラインを探す行数（Vim と同じく先頭・末尾それぞれ5行）
+const MODELINE_SEARCH_LINES: usize = 5;
+
+/// 拡張子から言語名を判定する
+pub fn detect_language(path: &str) -> Option<&'static str> {
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    language_for_extension(&ext)
+}
+
+/// パスと先頭の内容から言語候補を確度の高い順に返す
+///
+/// 判定に使う情報（優先順）:
+/// 1. `.gitattributes` の `linguist-language`
+/// 2. Vim / Emacs のモードライン
+/// 3. shebang 行
+/// 4. 既知のファイル名（Dockerfile, Makefile, .bashrc など）
+/// 5. 拡張子
+pub fn detect_language_candidates(path: &str, content_head: &str) -> Vec<LanguageDetection> {
+    let mut candidates: Vec<LanguageDetection> = Vec::new();
+    let mut push = |language: Option<&'static str>, source: LanguageSource, confidence: f32| {
+        if let Some(language) = language {
+            candidates.push(LanguageDetection {
+                language: language.to_string(),
+                source,
+                confidence,
+            });
+        }
+    };
+
+    push(
+        gitattributes_language(Path::new(path)),
+        LanguageSource::GitAttributes,
+        1.0,
+    );
+    push(
+        modeline_language(content_head),
+        LanguageSource::Modeline,
+        0.95,
+    );
+    push(shebang_language(content_head), LanguageSource::Shebang, 0.9);
+    push(filename_language(path), LanguageSource::Filename, 0.85);
+    push(detect_language(path), LanguageSource::Extension, 0.7);
+
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    candidates
+}
+
+/// パスと先頭の内容から最も確度の高い言語を返す
+pub fn detect_language_with_content(path: &str, content_head: &str) -> Option<LanguageDetection> {
+    detect_language_candidates(path, content_head)
+        .into_iter()
+        .next()
+}
+
+fn language_for_extension(ext: &str) -> Option<&'static str> {
+    match ext {
+        "ts" | "tsx" => Some("typescript"),
+        "js" | "jsx" | "mjs" | "cjs" => Some("javascript"),
+        "json" => Some("json"),
+        "yaml" | "yml" => Some("yaml"),
+        "swift" => Some("swift"),
+        "rs" => Some("rust"),
+        "dart" => Some("dart"),
+        "html" | "htm" => Some("html"),
+        "css" => Some("css"),
+        "py" => Some("python"),
+        #[cfg(feature = "go")]
+        "go" => Some("go"),
+        #[cfg(feature = "java")]
+        "java" => Some("java"),
+        #[cfg(feature = "kotlin")]
+        "kt" | "kts" => Some("kotlin"),
+        #[cfg(feature = "c")]
+        "c" | "h" => Some("c"),
+        #[cfg(feature = "cpp")]
+        "cc" | "cpp" | "cxx" | "c++" | "hh" | "hpp" | "hxx" | "h++" => Some("cpp"),
+        #[cfg(feature = "ruby")]
+        "rb" | "rake" | "gemspec" => Some("ruby"),
+        #[cfg(feature = "bash")]
+        "sh" | "bash" | "zsh" => Some("bash"),
+        #[cfg(feature = "toml")]
+        "toml" => Some("toml"),
+        #[cfg(feature = "markdown")]
+        "md" | "markdown" => Some("markdown"),
+        #[cfg(feature = "sql")]
+        "sql" => Some("sql"),
+        #[cfg(feature = "make")]
+        "mk" | "mak" => Some("make"),
+        #[cfg(feature = "dockerfile")]
+        "dockerfile" | "containerfile" => Some("dockerfile"),
+        _ => None,
+    }
+}
+
+/// 言語名・別名（`Python`, `js`, `C++`, `shell` など）を内部の言語名に解決する
+/// .gitattributes・モードライン・injection の言語指定で共通に使う
+pub(crate) fn language_from_name(name: &str) -> Option<&'static str> {
+    let name = name.trim().to_ascii_lowercase();
+    if name.is_empty() {
+        return None;
+    }
+    if let Some(language) = language_for_extension(&name) {
+        return Some(language);
+    }
+    match name.as_str() {
+        "typescript" => Some("typescript"),
+        "javascript" | "node" => Some("javascript"),
+        "python" | "python3" => Some("python"),
+        "rust" => Some("rust"),
+        "swift" => Some("swift"),
+        "dart" => Some("dart"),
+        #[cfg(feature = "go")]
+        "golang" => Some("go"),
+        #[cfg(feature = "kotlin")]
+        "kotlin" => Some("kotlin"),
+        #[cfg(feature = "ruby")]
+        "ruby" => Some("ruby"),
+        #[cfg(feature = "bash")]
+        "shell" | "console" | "shell-script" => Some("bash"),
+        #[cfg(feature = "markdown")]
+        "markdown_inline" => Some("markdown_inline"),
+        #[cfg(feature = "sql")]
+        "postgresql" | "mysql" | "sqlite" => Some("sql"),
+        #[cfg(feature = "make")]
+        "make" | "makefile" => Some("make"),
+        _ => None,
+    }
+}
+
+/// Dockerfile / Makefile のように拡張子を持たない既知のファイル名から判定する
+fn filename_language(path: &str) -> Option<&'static str> {
+    let name = Path::new(path).file_name()?.to_str()?;
+    match name {
+        #[cfg(feature = "dockerfile")]
+        "Dockerfile" | "Containerfile" => Some("dockerfile"),
+        #[cfg(feature = "dockerfile")]
+        _ if name.starts_with("Dockerfile.") => Some("dockerfile"),
+        #[cfg(feature = "make")]
+        "Makefile" | "makefile" | "GNUmakefile" => Some("make"),
+        #[cfg(feature = "ruby")]
+        "Podfile" | "Gemfile" | "Rakefile" | "Fastfile" | "Appfile" | "Brewfile"
+        | "Vagrantfile" | "Dangerfile" => Some("ruby"),
+        #[cfg(feature = "bash")]
+        ".bashrc" | ".bash_profile" | ".bash_aliases" | ".bash_logout" | ".profile" | ".zshrc"
+        | ".zprofile" | ".zshenv" | "PKGBUILD" => Some("bash"),
+        #[cfg(feature = "toml")]
+        "Cargo.lock" | "Pipfile" | "poetry.lock" => Some("toml"),
+        ".babelrc" | ".eslintrc" | ".prettierrc" => Some("json"),
+        _ => None,
+    }
+}
+
+/// `#!/usr/bin/env python3` のような shebang 行からインタプリタを判定する
+fn shebang_language(content_head: &str) -> Option<&'static str> {
+    let first_line = content_head.lines().next()?;
+    let command = first_line.strip_prefix("#!")?.trim();
+
+    let mut parts = command.split_whitespace();
+    let mut interpreter = basename(parts.next()?);
+    if interpreter == "env" {
+        // `env -S node --flag` のようなオプションは読み飛ばす
+        interpreter = parts.find(|p| !p.starts_with('-') && !p.contains('='))?;
+        interpreter = basename(interpreter);
+    }
+
+    // python3.11 → python, ruby2.7 → ruby
+    let name = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    match name {
+        "python" | "pypy" => Some("python"),
+        "node" | "nodejs" | "deno" | "bun" => Some("javascript"),
+        "ts-node" | "tsx" => Some("typescript"),
+        "swift" => Some("swift"),
+        #[cfg(feature = "bash")]
+        "sh" | "bash" | "zsh" | "dash" | "ksh" => Some("bash"),
+        #[cfg(feature = "ruby")]
+        "ruby" => Some("ruby"),
+        #[cfg(feature = "kotlin")]
+        "kotlin" | "kscript" => Some("kotlin"),
+        #[cfg(feature = "make")]
+        "make" => Some("make"),
+        _ => None,
+    }
+}
+
+fn basename(command: &str) -> &str {
+    command.rsplit('/').next().unwrap_or(command)
+}
+
+/// 先頭・末尾の数行から Vim / Emacs のモードラインを探す
+fn modeline_language(content_head: &str) -> Option<&'static str> {
+    let lines: Vec<&str> = content_head.lines().collect();
+    let head = lines.iter().take(MODELINE_SEARCH_LINES);
+    let tail = lines
+        .iter()
+        .skip(MODELINE_SEARCH_LINES)
+        .rev()
+        .take(MODELINE_SEARCH_LINES);
+
+    head.chain(tail)
+        .find_map(|line| emacs_modeline(line).or_else(|| vim_modeline(line)))
+        .and_then(language_from_name)
+}
+
+/// `-*- mode: python -*-` / `-*- python -*-`
+fn emacs_modeline(line: &str) -> Option<&str> {
+    let start = line.find("-*-")? + 3;
+    let rest = &line[start..];
+    let body = rest[..rest.find("-*-")?].trim();
+
+    if !body.contains(':') {
+        return Some(body);
+    }
+    body.split(';').find_map(|entry| {
+        let (key, value) = entry.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case("mode")
+            .then(|| value.trim())
+    })
+}
+
+/// `vim: set ft=python:` / `vi: filetype=sh` / `ex: syntax=ruby`
+/// Vim と同じく、マーカーは行頭か空白の直後にあるものだけを見る（`regex:` などを除く）
+fn vim_modeline(line: &str) -> Option<&str> {
+    let marker = ["vim:", "vi:", "ex:"]
+        .iter()
+        .filter_map(|m| {
+            line.match_indices(m)
+                .find(|&(i, _)| {
+                    line[..i]
+                        .chars()
+                        .next_back()
+                        .is_none_or(char::is_whitespace)
+                })
+                .map(|(i, _)| i + m.len())
+        })
+        .min()?;
+    line[marker..]
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .find_map(|option| {
+            let (key, value) = option.split_once('=')?;
+            matches!(key, "ft" | "filetype" | "syn" | "syntax").then_some(value)
+        })
+}
+
+/// パスの祖先ディレクトリ（リポジトリのルートまで）にある .gitattributes から linguist-language を探す
+/// 深い階層・後に書かれた行ほど優先される。リポジトリ外のファイルには適用しない
+fn gitattributes_language(path: &Path) -> Option<&'static str> {
+    let absolute = fs::canonicalize(path).ok()?;
+    let mut directories: Vec<PathBuf> = Vec::new();
+    for dir in absolute.ancestors().skip(1) {
+        directories.push(dir.to_path_buf());
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    if !directories
+        .last()
+        .is_some_and(|root| root.join(".git").exists())
+    {
+        return None;
+    }
+
+    directories.iter().find_map(|dir| {
+        let content = fs::read_to_string(dir.join(".gitattributes")).ok()?;
+        let relative = absolute
+            .strip_prefix(dir)
+            .ok()?
+            .to_string_lossy()
+            .replace('\\', "/");
+        linguist_language_for(&content, &relative)
+    })
+}
+
+/// .gitattributes の内容から relative_path に一致する linguist-language を返す
+fn linguist_language_for(content: &str, relative_path: &str) -> Option<&'static str> {
+    let file_name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+
+    content.lines().rev().find_map(|line| {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let mut fields = line.split_whitespace();
+        let pattern = fields.next()?;
+        let language = fields.find_map(|attr| attr.strip_prefix("linguist-language="))?;
+
+        // スラッシュを含まないパターンはどの階層のファイル名にも一致する
+        let (glob, target) = match pattern.strip_prefix('/') {
+            Some(anchored) => (anchored, relative_path),
+            None if pattern.contains('/') => (pattern, relative_path),
+            None => (pattern, file_name),
+        };
+        let matcher = GlobBuilder::new(glob)
+            .literal_separator(true)
+            .build()
+            .ok()?
+            .compile_matcher();
+        if matcher.is_match(target) {
+            language_from_name(language)
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn detect_language_uses_file_name_extension_only() {
+        assert_eq!(detect_language("dir.v2/README"), None);
+        assert_eq!(detect_language("dir.v2/main.py"), Some("python"));
+        assert_eq!(detect_language(".bashrc"), None);
+    }
+
+    #[test]
+    fn shebang_detects_interpreter() {
+        assert_eq!(
+            shebang_language("#!/usr/bin/env python3\nprint(1)"),
+            Some("python")
+        );
+        assert_eq!(shebang_language("#!/usr/bin/python3.11"), Some("python"));
+        assert_eq!(
+            shebang_language("#!/usr/bin/env -S node --no-warnings"),
+            Some("javascript")
+        );
+        assert_eq!(shebang_language("print(1)"), None);
+    }
+
+    #[cfg(feature = "bash")]
+    #[test]
+    fn shebang_detects_shell() {
+        assert_eq!(shebang_language("#!/bin/sh\necho hi"), Some("bash"));
+    }
+
+    #[test]
+    fn modeline_detects_vim_and_emacs() {
+        assert_eq!(
+            modeline_language("# -*- mode: python; coding: utf-8 -*-\n"),
+            Some("python")
+        );
+        assert_eq!(
+            modeline_language("// -*- javascript -*-\n"),
+            Some("javascript")
+        );
+        assert_eq!(
+            modeline_language("line\n# vim: set ft=rust ts=4:\n"),
+            Some("rust")
+        );
+        assert_eq!(modeline_language("no modeline here"), None);
+        assert_eq!(modeline_language("vi: ft=css"), Some("css"));
+        // 単語の途中の "vi:" / "ex:" はモードラインではない
+        assert_eq!(modeline_language("let regex: ft=python"), None);
+        assert_eq!(modeline_language("// kiwi: syntax=rust"), None);
+    }
+
+    #[test]
+    fn modeline_checks_last_lines() {
+        let mut content = "x\n".repeat(20);
+        content.push_str("/* vim: syntax=css */\n");
+        assert_eq!(modeline_language(&content), Some("css"));
+    }
+
+    #[test]
+    fn linguist_language_matches_patterns() {
+        let attributes = "\
+# comment
+*.tmpl linguist-language=HTML
+/scripts/* linguist-language=Python
+config/*.conf linguist-language=YAML
+";
+        assert_eq!(
+            linguist_language_for(attributes, "a/b/page.tmpl"),
+            Some("html")
+        );
+        assert_eq!(
+            linguist_language_for(attributes, "scripts/deploy"),
+            Some("python")
+        );
+        assert_eq!(linguist_language_for(attributes, "a/scripts/deploy"), None);
+        assert_eq!(
+            linguist_language_for(attributes, "config/app.conf"),
+            Some("yaml")
+        );
+        assert_eq!(linguist_language_for(attributes, "other.conf"), None);
+    }
+
+    #[test]
+    fn gitattributes_is_ignored_outside_repository() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join(".gitattributes"),
+            "*.js linguist-language=TypeScript\n",
+        )
+        .unwrap();
+        let file = tmp.path().join("tool.js");
+        fs::write(&file, "").unwrap();
+        assert_eq!(gitattributes_language(&file), None);
+
+        fs::create_dir(tmp.path().join(".git")).unwrap();
+        assert_eq!(gitattributes_language(&file), Some("typescript"));
+    }
+
+    #[test]
+    fn candidates_are_ranked_by_confidence() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir(tmp.path().join(".git")).unwrap();
+        fs::write(
+            tmp.path().join(".gitattributes"),
+            "*.js linguist-language=TypeScript\n",
+        )
+        .unwrap();
+        let file = tmp.path().join("tool.js");
+        let content = "#!/usr/bin/env python3\n";
+        fs::write(&file, content).unwrap();
+
+        let candidates = detect_language_candidates(file.to_str().unwrap(), content);
+        let ranked: Vec<(&str, LanguageSource)> = candidates
+            .iter()
+            .map(|c| (c.language.as_str(), c.source))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![
+                ("typescript", LanguageSource::GitAttributes),
+                ("python", LanguageSource::Shebang),
+                ("javascript", LanguageSource::Extension),
+            ]
+        );
+    }
+
+    #[test]
+    fn detect_language_with_content_prefers_shebang_for_extensionless_scripts() {
+        let detection =
+            detect_language_with_content("/nonexistent/bin/deploy", "#!/usr/bin/env python3\n")
+                .unwrap();
+        assert_eq!(detection.language, "python");
+        assert_eq!(detection.source, LanguageSource::Shebang);
+    }
+
+    #[cfg(all(
+        feature = "dockerfile",
+        feature = "make",
+        feature = "ruby",
+        feature = "bash"
+    ))]
+    #[test]
+    fn filename_detects_well_known_files() {
+        assert_eq!(filename_language("/repo/Dockerfile"), Some("dockerfile"));
+        assert_eq!(
+            filename_language("/repo/Dockerfile.dev"),
+            Some("dockerfile")
+        );
+        assert_eq!(filename_language("/repo/Makefile"), Some("make"));
+        assert_eq!(filename_language("/repo/ios/Podfile"), Some("ruby"));
+        assert_eq!(filename_language("/home/me/.bashrc"), Some("bash"));
+        assert_eq!(filename_language("/repo/README"), None);
+    }
+}
@@ -0,0 +1,421 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use core_types::{CoreError, HighlightDelta, TextEdit, TokenSpan};
+use tree_sitter::{InputEdit, Parser, Point, Tree};
+
+use crate::highlights::{self, HighlightConfig, InjectionTrees};
+use crate::{new_parser, parse_failed};
+
+/// 構文木を保持し、編集ごとにインクリメンタルに再パースするドキュメント
+pub struct Document {
+    language: String,
+    config: Arc<HighlightConfig>,
+    parser: Parser,
+    tree: Tree,
+    text: String,
+    /// スクロールのたびに埋め込み範囲を再パースしないよう、編集されるまで使い回す
+    injections: Mutex<InjectionTrees>,
+}
+
+impl Document {
+    /// テキスト全体をパースしてドキュメントを生成する
+    pub fn new(text: &str, language: &str) -> Result<Self, CoreError> {
+        let config = highlights::config_for(language)?;
+        let mut parser = new_parser(&config, language)?;
+        let tree = parser
+            .parse(text, None)
+            .ok_or_else(|| parse_failed(language))?;
+
+        Ok(Self {
+            language: language.to_string(),
+            config,
+            parser,
+            tree,
+            text: text.to_string(),
+            injections: Mutex::default(),
+        })
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// 保持している構文木から指定行範囲（1始まり、両端含む）のトークンを返す
+    /// 再パースは行わない（埋め込み範囲も初回にパースした木を使い回す）
+    pub fn highlight_range(&self, start_line: u32, end_line: u32) -> Vec<TokenSpan> {
+        if start_line > end_line || end_line == 0 {
+            return Vec::new();
+        }
+        let start_row = start_line.saturating_sub(1) as usize;
+        let end_row = end_line.saturating_sub(1) as usize;
+
+        // キャッシュは木を入れ替える途中で panic しないので、poison されていてもそのまま使える
+        let mut injections = self
+            .injections
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        highlights::highlight_tree(
+            &self.tree,
+            self.text.as_bytes(),
+            &self.config,
+            &mut injections,
+            start_row,
+            end_row,
+        )
+    }
+
+    /// テキスト全体を置き換えて再パースする（ファイル再読み込み時など）
+    pub fn set_text(&mut self, text: &str) -> Result<(), CoreError> {
+        let tree = self
+            .parser
+            .parse(text, None)
+            .ok_or_else(|| parse_failed(&self.language))?;
+        self.tree = tree;
+        self.text = text.to_string();
+        self.injections_mut().clear();
+        Ok(())
+    }
+
+    /// 編集を適用してインクリメンタルに再パースし、変化した行範囲のトークンを返す
+    pub fn apply_edit(&mut self, edit: &TextEdit) -> Result<Vec<HighlightDelta>, CoreError> {
+        let start_byte = edit.start_byte as usize;
+        let old_end_byte = edit.old_end_byte as usize;
+
+        if start_byte > old_end_byte || old_end_byte > self.text.len() {
+            return Err(CoreError::invalid_argument(format!(
+                "編集範囲が不正です: start_byte={start_byte}, old_end_byte={old_end_byte}, len={}",
+                self.text.len()
+            )));
+        }
+        if !self.text.is_char_boundary(start_byte) || !self.text.is_char_boundary(old_end_byte) {
+            return Err(CoreError::invalid_argument(format!(
+                "編集範囲が文字境界ではありません: start_byte={start_byte}, old_end_byte={old_end_byte}"
+            )));
+        }
+
+        // 行・列は byte オフセットから求め直し、呼び出し側の値と食い違う編集は適用しない
+        let start_position = point_at(&self.text, start_byte);
+        let old_end_position = point_at(&self.text, old_end_byte);
+        let given_start = Point::new(edit.start_row as usize, edit.start_column as usize);
+        let given_old_end = Point::new(edit.old_end_row as usize, edit.old_end_column as usize);
+        if given_start != start_position || given_old_end != old_end_position {
+            return Err(CoreError::invalid_argument(format!(
+                "編集位置が byte オフセットと一致しません: start={given_start} (期待値 {start_position}), old_end={given_old_end} (期待値 {old_end_position})"
+            )));
+        }
+        let new_end_position = end_point_after(start_position, &edit.new_text);
+        let new_end_byte = start_byte + edit.new_text.len();
+
+        self.text
+            .replace_range(start_byte..old_end_byte, &edit.new_text);
+        let input_edit = InputEdit {
+            start_byte,
+            old_end_byte,
+            new_end_byte,
+            start_position,
+            old_end_position,
+            new_end_position,
+        };
+        self.tree.edit(&input_edit);
+
+        let new_tree = self
+            .parser
+            .parse(&self.text, Some(&self.tree))
+            .ok_or_else(|| parse_failed(&self.language))?;
+
+        // 構文構造が変わった範囲に加え、編集された行そのものも必ず再計算する
+        let mut row_ranges: Vec<(usize, usize)> = self
+            .tree
+            .changed_ranges(&new_tree)
+            .map(|r| (r.start_point.row, r.end_point.row))
+            .collect();
+        row_ranges.push((start_position.row, new_end_position.row));
+        // 埋め込み側の変化はホストの changed_ranges に現れないため、編集を含む埋め込み全体を加える
+        row_ranges.extend(highlights::injection_rows_at(
+            &new_tree,
+            self.text.as_bytes(),
+            &self.config,
+            &input_edit,
+        ));
+        self.tree = new_tree;
+        self.injections_mut().invalidate_from(start_byte);
+
+        Ok(merge_row_ranges(row_ranges)
+            .into_iter()
+            .map(|(start_row, end_row)| {
+                let start_line = start_row as u32 + 1;
+                let end_line = end_row as u32 + 1;
+                HighlightDelta {
+                    start_line,
+                    end_line,
+                    tokens: self.highlight_range(start_line, end_line),
+                }
+            })
+            .collect())
+    }
+
+    fn injections_mut(&mut self) -> &mut InjectionTrees {
+        self.injections
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// text 内の byte オフセットに対応する行・列（列は byte 単位）を返す
+fn point_at(text: &str, byte: usize) -> Point {
+    let before = &text[..byte];
+    match before.rfind('\n') {
+        Some(last_newline) => Point::new(before.matches('\n').count(), byte - last_newline - 1),
+        None => Point::new(0, byte),
+    }
+}
+
+/// start から new_text を挿入した後の終端位置を計算する
+fn end_point_after(start: Point, new_text: &str) -> Point {
+    match new_text.rfind('\n') {
+        Some(last_newline) => Point::new(
+            start.row + new_text.matches('\n').count(),
+            new_text.len() - last_newline - 1,
+        ),
+        None => Point::new(start.row, start.column + new_text.len()),
+    }
+}
+
+/// 重複・隣接する行範囲をまとめる
+fn merge_row_ranges(mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_types::TokenType;
+
+    fn insert_edit(start_byte: u32, row: u32, column: u32, text: &str) -> TextEdit {
+        TextEdit {
+            start_byte,
+            old_end_byte: start_byte,
+            start_row: row,
+            start_column: column,
+            old_end_row: row,
+            old_end_column: column,
+            new_text: text.to_string(),
+        }
+    }
+
+    /// 各差分のトークンが、編集後のテキストを丸ごと tokenize した結果の同じ行範囲と一致することを確かめる
+    fn assert_deltas_match_tokenize(doc: &Document, deltas: &[HighlightDelta]) {
+        let fresh = crate::tokenize(doc.text(), doc.language()).unwrap();
+        for delta in deltas {
+            let expected: Vec<&TokenSpan> = fresh
+                .iter()
+                .filter(|t| t.line >= delta.start_line && t.line <= delta.end_line)
+                .collect();
+            let actual: Vec<&TokenSpan> = delta.tokens.iter().collect();
+            assert_eq!(
+                actual, expected,
+                "lines {}..={}",
+                delta.start_line, delta.end_line
+            );
+        }
+    }
+
+    #[test]
+    fn highlight_range_matches_tokenize() {
+        let code = "const a = 1;\nconst b = 2;\nconst c = 3;";
+        let doc = Document::new(code, "javascript").unwrap();
+
+        let expected: Vec<TokenSpan> = crate::tokenize(code, "javascript")
+            .unwrap()
+            .into_iter()
+            .filter(|t| t.line == 2)
+            .collect();
+        assert_eq!(doc.highlight_range(2, 2), expected);
+    }
+
+    #[test]
+    fn apply_edit_returns_only_changed_lines() {
+        let code = "let a = 1;\nlet b = 2;\nlet c = 3;\n";
+        let mut doc = Document::new(code, "javascript").unwrap();
+
+        // 2行目の "2" を "42" に変更
+        let deltas = doc.apply_edit(&insert_edit(19, 1, 8, "4")).unwrap();
+
+        assert_eq!(doc.text(), "let a = 1;\nlet b = 42;\nlet c = 3;\n");
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].start_line, 2);
+        assert_eq!(deltas[0].end_line, 2);
+        let number = deltas[0]
+            .tokens
+            .iter()
+            .find(|t| t.token_type == TokenType::Number)
+            .unwrap();
+        assert_eq!((number.start_col, number.end_col), (8, 10));
+    }
+
+    #[test]
+    fn apply_edit_multiline_insert_matches_full_parse() {
+        let code = "fn main() {\n}\n";
+        let mut doc = Document::new(code, "rust").unwrap();
+
+        let deltas = doc
+            .apply_edit(&insert_edit(12, 1, 0, "    let x = 1;\n"))
+            .unwrap();
+
+        assert_eq!(doc.text(), "fn main() {\n    let x = 1;\n}\n");
+        assert!(deltas.iter().any(|d| d.start_line <= 2 && d.end_line >= 2));
+
+        let expected: Vec<TokenSpan> = crate::tokenize(doc.text(), "rust")
+            .unwrap()
+            .into_iter()
+            .filter(|t| t.line == 2)
+            .collect();
+        assert_eq!(doc.highlight_range(2, 2), expected);
+    }
+
+    #[test]
+    fn highlight_range_reuses_injection_trees_until_edited() {
+        let code = "<script>\nconst x = 1;\n</script>\n<style>\n.a { color: red; }\n</style>\n";
+        let mut doc = Document::new(code, "html").unwrap();
+
+        let first = doc.highlight_range(1, 6);
+        assert_eq!(doc.injections_mut().len(), 2);
+        assert_eq!(doc.highlight_range(1, 6), first);
+        // 表示範囲外の埋め込みはパースしない
+        doc.injections_mut().clear();
+        doc.highlight_range(2, 2);
+        assert_eq!(doc.injections_mut().len(), 1);
+
+        // <style> 内の編集では、それより前の <script> の木は残して <style> だけパースし直す
+        let deltas = doc.apply_edit(&insert_edit(40, 4, 0, "b ")).unwrap();
+        assert_eq!(doc.injections_mut().len(), 2);
+        let expected: Vec<TokenSpan> = crate::tokenize(doc.text(), "html")
+            .unwrap()
+            .into_iter()
+            .filter(|t| t.line == 5)
+            .collect();
+        let delta_tokens: Vec<TokenSpan> = deltas
+            .into_iter()
+            .flat_map(|delta| delta.tokens)
+            .filter(|t| t.line == 5)
+            .collect();
+        assert_eq!(delta_tokens, expected);
+        assert_eq!(doc.highlight_range(1, 6).len(), first.len() + 1);
+    }
+
+    #[test]
+    fn apply_edit_in_script_rehighlights_following_lines() {
+        let code = "<script>\nconst a = 1;\nconst b = 2; // */\n</script>\n";
+        let mut doc = Document::new(code, "html").unwrap();
+        doc.highlight_range(1, 4);
+
+        // 2行目の先頭に "/*" を入力すると、3行目の "*/" までがコメントになる
+        let deltas = doc.apply_edit(&insert_edit(9, 1, 0, "/*")).unwrap();
+
+        assert!(deltas.iter().any(|d| d.start_line <= 3 && d.end_line >= 3));
+        let expected: Vec<TokenSpan> = crate::tokenize(doc.text(), "html")
+            .unwrap()
+            .into_iter()
+            .filter(|t| t.line == 3)
+            .collect();
+        let delta_tokens: Vec<TokenSpan> = deltas
+            .into_iter()
+            .flat_map(|delta| delta.tokens)
+            .filter(|t| t.line == 3)
+            .collect();
+        assert_eq!(delta_tokens, expected);
+        assert!(expected.iter().any(|t| t.token_type == TokenType::Comment));
+    }
+
+    #[test]
+    fn apply_edit_in_html_script_and_style_matches_tokenize() {
+        let code = "<script>\nconst x = 1;\n</script>\n<style>\n.a { color: red; }\n</style>\n";
+        let mut doc = Document::new(code, "html").unwrap();
+        doc.highlight_range(1, 6);
+
+        // <script> 内の "1" の前に "4" を挿入する
+        let deltas = doc.apply_edit(&insert_edit(19, 1, 10, "4")).unwrap();
+        assert!(deltas.iter().any(|d| d.start_line <= 2 && d.end_line >= 2));
+        assert_deltas_match_tokenize(&doc, &deltas);
+
+        // <style> 内の宣言の前に別の宣言を挿入する
+        let deltas = doc
+            .apply_edit(&insert_edit(46, 4, 5, "margin: 0; "))
+            .unwrap();
+        assert_eq!(
+            doc.text(),
+            "<script>\nconst x = 41;\n</script>\n<style>\n.a { margin: 0; color: red; }\n</style>\n"
+        );
+        assert!(deltas.iter().any(|d| d.start_line <= 5 && d.end_line >= 5));
+        assert_deltas_match_tokenize(&doc, &deltas);
+    }
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn apply_edit_in_markdown_fence_matches_tokenize() {
+        let code = "# Title\n\n```rust\nfn main() {}\n```\n";
+        let mut doc = Document::new(code, "markdown").unwrap();
+        doc.highlight_range(1, 5);
+
+        // フェンス内の関数本体に文を挿入する
+        let deltas = doc
+            .apply_edit(&insert_edit(28, 3, 11, " let x = 1; "))
+            .unwrap();
+        assert_eq!(
+            doc.text(),
+            "# Title\n\n```rust\nfn main() { let x = 1; }\n```\n"
+        );
+        assert!(deltas.iter().any(|d| d.start_line <= 4 && d.end_line >= 4));
+        assert_deltas_match_tokenize(&doc, &deltas);
+    }
+
+    #[test]
+    fn apply_edit_rejects_position_mismatching_bytes() {
+        let mut doc = Document::new("let a = 1;\nlet b = 2;\n", "javascript").unwrap();
+
+        // byte 11 は2行目の先頭だが、1行目の末尾として渡す
+        let result = doc.apply_edit(&insert_edit(11, 0, 11, "x"));
+
+        assert!(matches!(result, Err(CoreError::InvalidArgument { .. })));
+        assert_eq!(doc.text(), "let a = 1;\nlet b = 2;\n");
+    }
+
+    #[test]
+    fn apply_edit_out_of_range_returns_error() {
+        let mut doc = Document::new("let a = 1;", "javascript").unwrap();
+        let edit = TextEdit {
+            start_byte: 5,
+            old_end_byte: 100,
+            start_row: 0,
+            start_column: 5,
+            old_end_row: 0,
+            old_end_column: 100,
+            new_text: String::new(),
+        };
+        assert!(matches!(
+            doc.apply_edit(&edit),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+        assert_eq!(doc.text(), "let a = 1;");
+    }
+
+    #[test]
+    fn merge_row_ranges_merges_adjacent() {
+        assert_eq!(
+            merge_row_ranges(vec![(5, 6), (0, 1), (2, 2), (9, 9)]),
+            vec![(0, 2), (5, 6), (9, 9)]
+        );
+    }
+}
@@ -0,0 +1,656 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use core_types::{CoreError, TokenSpan, TokenType};
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{InputEdit, Language, Node, Parser, Point, Query, QueryCursor, Range, Tree};
+
+use crate::{detect::language_from_name, LanguageSpec};
+
+/// injection の入れ子の上限（Rust のマクロ内マクロなどで無限に潜らないようにする）
+const MAX_INJECTION_DEPTH: usize = 3;
+
+/// 言語ごとのハイライト設定（コンパイル済みクエリと capture → TokenType 対応表）
+pub(crate) struct HighlightConfig {
+    pub(crate) language: Language,
+    query: Query,
+    capture_types: Vec<Option<TokenType>>,
+    injection_query: Option<Query>,
+}
+
+/// ハイライト対象の capture（byte 範囲）
+struct Capture {
+    start: usize,
+    end: usize,
+    depth: usize,
+    pattern_index: usize,
+    token_type: TokenType,
+}
+
+/// 別言語としてパースする埋め込み範囲
+struct Injection {
+    language: &'static str,
+    ranges: Vec<Range>,
+}
+
+/// 埋め込み範囲ごとの構文木（言語と byte 範囲が同じなら再パースしない）
+/// Document はハイライト要求をまたいで保持し、編集された位置以降に掛かる木だけを捨てる
+#[derive(Default)]
+pub(crate) struct InjectionTrees {
+    trees: HashMap<(&'static str, Vec<std::ops::Range<usize>>), Tree>,
+}
+
+impl InjectionTrees {
+    /// byte 以降に掛かる埋め込みの木を捨てる（それより前の範囲は内容も位置も変わらない）
+    pub(crate) fn invalidate_from(&mut self, byte: usize) {
+        self.trees
+            .retain(|(_, ranges), _| ranges.iter().all(|range| range.end <= byte));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.trees.clear();
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.trees.len()
+    }
+
+    /// 埋め込み範囲を対応する言語でパースする（パース済みならその木を返す）
+    fn parse(
+        &mut self,
+        source: &[u8],
+        injection: &Injection,
+        config: &HighlightConfig,
+    ) -> Option<Tree> {
+        let key = (
+            injection.language,
+            injection
+                .ranges
+                .iter()
+                .map(|range| range.start_byte..range.end_byte)
+                .collect::<Vec<_>>(),
+        );
+        if let Some(tree) = self.trees.get(&key) {
+            return Some(tree.clone());
+        }
+
+        let mut parser = Parser::new();
+        if parser.set_language(&config.language).is_err()
+            || parser.set_included_ranges(&injection.ranges).is_err()
+        {
+            return None;
+        }
+        let tree = parser.parse(source, None)?;
+        self.trees.insert(key, tree.clone());
+        Some(tree)
+    }
+}
+
+static CONFIG_CACHE: OnceLock<Mutex<HashMap<String, Arc<HighlightConfig>>>> = OnceLock::new();
+
+fn config_cache() -> &'static Mutex<HashMap<String, Arc<HighlightConfig>>> {
+    CONFIG_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+impl HighlightConfig {
+    /// クエリソースを連結してコンパイルする
+    /// tree-sitter は同一ノードに複数パターンが一致した場合に先頭のパターンを優先するため、
+    /// 上書き用クエリは sources の先頭に置く
+    fn new(spec: LanguageSpec) -> Result<Self, String> {
+        let language = spec.language;
+        let query =
+            Query::new(&language, &spec.highlights.join("\n")).map_err(|e| format!("{e}"))?;
+        let capture_types = query
+            .capture_names()
+            .iter()
+            .map(|name| token_type_for_capture(name))
+            .collect();
+        let injection_query = if spec.injections.is_empty() {
+            None
+        } else {
+            Some(
+                Query::new(&language, &spec.injections.join("\n"))
+                    .map_err(|e| format!("injections: {e}"))?,
+            )
+        };
+
+        Ok(Self {
+            language,
+            query,
+            capture_types,
+            injection_query,
+        })
+    }
+}
+
+/// 言語名に対応するハイライト設定を返す（初回のみクエリをコンパイルしてキャッシュする）
+pub(crate) fn config_for(language: &str) -> Result<Arc<HighlightConfig>, CoreError> {
+    if let Some(config) = config_cache()
+        .lock()
+        .map_err(|e| CoreError::internal(format!("highlight config lock 失敗: {e}")))?
+        .get(language)
+        .cloned()
+    {
+        return Ok(config);
+    }
+
+    let spec = crate::language_spec(language).ok_or_else(|| CoreError::UnsupportedLanguage {
+        language: language.to_string(),
+    })?;
+    let config = Arc::new(HighlightConfig::new(spec).map_err(|e| {
+        CoreError::internal(format!(
+            "{language} ハイライトクエリのコンパイルエラー: {e}"
+        ))
+    })?);
+
+    config_cache()
+        .lock()
+        .map_err(|e| CoreError::internal(format!("highlight config lock 失敗: {e}")))?
+        .insert(language.to_string(), config.clone());
+    Ok(config)
+}
+
+/// capture 名（例: `function.method`, `constant.builtin`）を TokenType に対応付ける
+/// 色付けに使わない補助的な capture は None を返す
+fn token_type_for_capture(name: &str) -> Option<TokenType> {
+    match name {
+        "constant.builtin" | "boolean" => return Some(TokenType::Keyword),
+        "constant.numeric" => return Some(TokenType::Number),
+        "string.special.key" => return Some(TokenType::Variable),
+        // Markdown
+        "text.title" => return Some(TokenType::Keyword),
+        "text.literal" | "text.uri" => return Some(TokenType::String),
+        "text.reference" => return Some(TokenType::Variable),
+        _ => {}
+    }
+
+    let head = name.split('.').next().unwrap_or(name);
+    match head {
+        "keyword" | "conditional" | "repeat" | "include" | "exception" | "storageclass" => {
+            Some(TokenType::Keyword)
+        }
+        "string" | "escape" | "character" => Some(TokenType::String),
+        "comment" => Some(TokenType::Comment),
+        "type" | "constructor" | "tag" | "namespace" | "module" => Some(TokenType::Type),
+        "function" | "method" => Some(TokenType::Function),
+        "number" | "float" => Some(TokenType::Number),
+        "operator" => Some(TokenType::Operator),
+        "punctuation" | "delimiter" => Some(TokenType::Punctuation),
+        "variable" | "property" | "parameter" | "attribute" | "label" | "constant" | "field"
+        | "symbol" => Some(TokenType::Variable),
+        // 埋め込み式（テンプレート文字列の `${}` など）は外側の色を打ち消す
+        "embedded" | "none" => Some(TokenType::Plain),
+        _ => None,
+    }
+}
+
+/// 構文木に対してハイライトクエリを実行し、start_row..=end_row（0始まり）の TokenSpan を返す
+/// injections.scm で指定された埋め込み範囲は対応する言語でパースし（injections に無ければ）、結果を重ねる
+pub(crate) fn highlight_tree(
+    tree: &Tree,
+    source: &[u8],
+    config: &HighlightConfig,
+    injections: &mut InjectionTrees,
+    start_row: usize,
+    end_row: usize,
+) -> Vec<TokenSpan> {
+    let point_range = Point::new(start_row, 0)..Point::new(end_row.saturating_add(1), 0);
+
+    let mut captures: Vec<Capture> = Vec::new();
+    collect_captures(
+        tree,
+        source,
+        config,
+        injections,
+        &point_range,
+        0,
+        &mut captures,
+    );
+
+    // 外側のノードを先に、同一範囲は埋め込み側 → 先に定義されたパターンの順で優先する
+    captures.sort_by(|a, b| {
+        a.start
+            .cmp(&b.start)
+            .then(b.end.cmp(&a.end))
+            .then(b.depth.cmp(&a.depth))
+            .then(a.pattern_index.cmp(&b.pattern_index))
+    });
+    captures.dedup_by(|later, earlier| later.start == earlier.start && later.end == earlier.end);
+
+    let segments = flatten_captures(&captures);
+    let line_starts = line_starts(source);
+
+    let mut tokens = Vec::new();
+    for (start, end, token_type) in segments {
+        push_spans(source, &line_starts, start, end, token_type, &mut tokens);
+    }
+    tokens.retain(|t| {
+        let row = t.line as usize - 1;
+        row >= start_row && row <= end_row
+    });
+    tokens
+}
+
+/// 編集後の範囲（edit の start..new_end）に掛かる埋め込みの行範囲（0始まり、両端含む）を返す
+/// 埋め込み側のトークンは編集位置より後の行でも変わりうるため（`/*` の入力など）、埋め込み全体を対象にする
+pub(crate) fn injection_rows_at(
+    tree: &Tree,
+    source: &[u8],
+    config: &HighlightConfig,
+    edit: &InputEdit,
+) -> Vec<(usize, usize)> {
+    let Some(injection_query) = &config.injection_query else {
+        return Vec::new();
+    };
+    let point_range = Point::new(edit.start_position.row, 0)
+        ..Point::new(edit.new_end_position.row.saturating_add(1), 0);
+
+    find_injections(tree, source, injection_query, &point_range)
+        .into_iter()
+        .filter(|injection| {
+            injection.ranges.iter().any(|range| {
+                range.start_byte <= edit.new_end_byte && edit.start_byte <= range.end_byte
+            })
+        })
+        .filter_map(|injection| {
+            let first = injection.ranges.first()?;
+            let last = injection.ranges.last()?;
+            Some((first.start_point.row, last.end_point.row))
+        })
+        .collect()
+}
+
+/// ハイライトクエリの capture を集め、埋め込み言語があれば再帰的に処理する
+fn collect_captures(
+    tree: &Tree,
+    source: &[u8],
+    config: &HighlightConfig,
+    injections: &mut InjectionTrees,
+    point_range: &std::ops::Range<Point>,
+    depth: usize,
+    captures: &mut Vec<Capture>,
+) {
+    let mut cursor = QueryCursor::new();
+    cursor.set_point_range(point_range.clone());
+
+    let mut query_captures = cursor.captures(&config.query, tree.root_node(), source);
+    while let Some((query_match, capture_index)) = query_captures.next() {
+        let capture = query_match.captures[*capture_index];
+        let Some(token_type) = config.capture_types[capture.index as usize] else {
+            continue;
+        };
+        let node = capture.node;
+        if node.start_byte() >= node.end_byte() {
+            continue;
+        }
+        captures.push(Capture {
+            start: node.start_byte(),
+            end: node.end_byte(),
+            depth,
+            pattern_index: query_match.pattern_index,
+            token_type,
+        });
+    }
+
+    if depth >= MAX_INJECTION_DEPTH {
+        return;
+    }
+    let Some(injection_query) = &config.injection_query else {
+        return;
+    };
+
+    for injection in find_injections(tree, source, injection_query, point_range) {
+        // 埋め込み先が未対応の言語・パース失敗の場合はホスト側の色のままにする
+        let Ok(injected_config) = config_for(injection.language) else {
+            continue;
+        };
+        let Some(injected_tree) = injections.parse(source, &injection, &injected_config) else {
+            continue;
+        };
+        collect_captures(
+            &injected_tree,
+            source,
+            &injected_config,
+            injections,
+            point_range,
+            depth + 1,
+            captures,
+        );
+    }
+}
+
+/// injections.scm を実行して埋め込み範囲を求める
+/// 同じノードに複数パターンが一致した場合は、言語を解決できた最初のパターンを採用する
+fn find_injections(
+    tree: &Tree,
+    source: &[u8],
+    query: &Query,
+    point_range: &std::ops::Range<Point>,
+) -> Vec<Injection> {
+    let content_index = query.capture_index_for_name("injection.content");
+    let language_index = query.capture_index_for_name("injection.language");
+
+    // (pattern_index, node_id, language, combined, ranges)
+    let mut candidates: Vec<(usize, usize, &'static str, bool, Vec<Range>)> = Vec::new();
+
+    let mut cursor = QueryCursor::new();
+    cursor.set_point_range(point_range.clone());
+    let mut matches = cursor.matches(query, tree.root_node(), source);
+    while let Some(query_match) = matches.next() {
+        let properties = query.property_settings(query_match.pattern_index);
+        let has_property = |key: &str| properties.iter().any(|p| &*p.key == key);
+
+        let language_name = properties
+            .iter()
+            .find(|p| &*p.key == "injection.language")
+            .and_then(|p| p.value.as_deref())
+            .or_else(|| {
+                query_match
+                    .captures
+                    .iter()
+                    .find(|c| Some(c.index) == language_index)
+                    .and_then(|c| c.node.utf8_text(source).ok())
+            });
+        let Some(language) = language_name.and_then(language_from_name) else {
+            continue;
+        };
+        let include_children = has_property("injection.include-children");
+        let combined = has_property("injection.combined");
+
+        for capture in query_match
+            .captures
+            .iter()
+            .filter(|c| Some(c.index) == content_index)
+        {
+            candidates.push((
+                query_match.pattern_index,
+                capture.node.id(),
+                language,
+                combined,
+                content_ranges(capture.node, include_children),
+            ));
+        }
+    }
+
+    candidates.sort_by_key(|c| c.0);
+    let mut seen_nodes: HashSet<usize> = HashSet::new();
+    let mut injections: Vec<Injection> = Vec::new();
+    let mut combined: Vec<((usize, &'static str), Vec<Range>)> = Vec::new();
+
+    for (pattern_index, node_id, language, is_combined, ranges) in candidates {
+        if !seen_nodes.insert(node_id) || ranges.is_empty() {
+            continue;
+        }
+        if !is_combined {
+            injections.push(Injection { language, ranges });
+            continue;
+        }
+        match combined
+            .iter_mut()
+            .find(|(key, _)| *key == (pattern_index, language))
+        {
+            Some((_, group)) => group.extend(ranges),
+            None => combined.push(((pattern_index, language), ranges)),
+        }
+    }
+
+    // combined は複数ノードを1つの文書としてパースする（範囲は昇順・非重複である必要がある）
+    for ((_, language), mut ranges) in combined {
+        ranges.sort_by_key(|r| r.start_byte);
+        ranges.dedup_by(|later, earlier| later.start_byte < earlier.end_byte);
+        injections.push(Injection { language, ranges });
+    }
+
+    injections
+}
+
+/// 埋め込み対象ノードの範囲を返す
+/// include-children が無い場合は名前付き子ノードの範囲を除外する
+/// （Markdown のコードブロックなどは記号を匿名の子ノードとして持つため、それらは含める）
+fn content_ranges(node: Node, include_children: bool) -> Vec<Range> {
+    let range = node.range();
+    if include_children {
+        return vec![range];
+    }
+
+    let mut ranges = Vec::new();
+    let mut start_byte = range.start_byte;
+    let mut start_point = range.start_point;
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let child_range = child.range();
+        if child_range.start_byte > start_byte {
+            ranges.push(Range {
+                start_byte,
+                end_byte: child_range.start_byte,
+                start_point,
+                end_point: child_range.start_point,
+            });
+        }
+        start_byte = child_range.end_byte;
+        start_point = child_range.end_point;
+    }
+    if range.end_byte > start_byte {
+        ranges.push(Range {
+            start_byte,
+            end_byte: range.end_byte,
+            start_point,
+            end_point: range.end_point,
+        });
+    }
+    ranges
+}
+
+/// 入れ子になった capture 範囲を、内側を優先した重なりのない区間列に変換する
+fn flatten_captures(captures: &[Capture]) -> Vec<(usize, usize, TokenType)> {
+    let mut segments: Vec<(usize, usize, TokenType)> = Vec::new();
+    let mut stack: Vec<(usize, TokenType)> = Vec::new();
+    let mut pos = 0;
+
+    for capture in captures {
+        let (start, end, token_type) = (capture.start, capture.end, capture.token_type);
+        while let Some(&(parent_end, parent_type)) = stack.last() {
+            if parent_end > start {
+                break;
+            }
+            push_segment(&mut segments, pos, parent_end, parent_type);
+            pos = pos.max(parent_end);
+            stack.pop();
+        }
+
+        if let Some(&(_, parent_type)) = stack.last() {
+            push_segment(&mut segments, pos, start, parent_type);
+        }
+        pos = pos.max(start);
+
+        let end = stack
+            .last()
+            .map(|&(parent_end, _)| end.min(parent_end))
+            .unwrap_or(end);
+        stack.push((end, token_type));
+    }
+
+    while let Some((end, token_type)) = stack.pop() {
+        push_segment(&mut segments, pos, end, token_type);
+        pos = pos.max(end);
+    }
+
+    segments
+}
+
+fn push_segment(
+    segments: &mut Vec<(usize, usize, TokenType)>,
+    start: usize,
+    end: usize,
+    token_type: TokenType,
+) {
+    if start < end {
+        segments.push((start, end, token_type));
+    }
+}
+
+/// 各行の先頭 byte オフセットを返す
+fn line_starts(source: &[u8]) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(
+            source
+                .iter()
+                .enumerate()
+                .filter(|(_, &b)| b == b'\n')
+                .map(|(i, _)| i + 1),
+        )
+        .collect()
+}
+
+/// byte 範囲を行ごとの TokenSpan に分割して追加する
+fn push_spans(
+    source: &[u8],
+    line_starts: &[usize],
+    start: usize,
+    end: usize,
+    token_type: TokenType,
+    tokens: &mut Vec<TokenSpan>,
+) {
+    let mut row = line_starts.partition_point(|&s| s <= start) - 1;
+    let mut cursor = start;
+
+    while cursor < end {
+        let line_start = line_starts[row];
+        let line_end = line_starts
+            .get(row + 1)
+            .map(|&next| next - 1)
+            .unwrap_or(source.len());
+        let span_end = end.min(line_end);
+
+        if span_end > cursor {
+            tokens.push(TokenSpan {
+                line: row as u32 + 1,
+                start_col: (cursor - line_start) as u32,
+                end_col: (span_end - line_start) as u32,
+                token_type,
+            });
+        }
+
+        row += 1;
+        match line_starts.get(row) {
+            Some(&next) => cursor = next,
+            None => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_type_for_capture_uses_specific_then_prefix() {
+        assert_eq!(
+            token_type_for_capture("function.method"),
+            Some(TokenType::Function)
+        );
+        assert_eq!(
+            token_type_for_capture("constant.builtin"),
+            Some(TokenType::Keyword)
+        );
+        assert_eq!(
+            token_type_for_capture("constant"),
+            Some(TokenType::Variable)
+        );
+        assert_eq!(
+            token_type_for_capture("punctuation.bracket"),
+            Some(TokenType::Punctuation)
+        );
+        assert_eq!(token_type_for_capture("spell"), None);
+    }
+
+    #[test]
+    fn flatten_captures_inner_overrides_outer() {
+        // "abc${x}def" のように外側 String の中に Plain がある
+        let capture = |start, end, token_type| Capture {
+            start,
+            end,
+            depth: 0,
+            pattern_index: 0,
+            token_type,
+        };
+        let captures = vec![
+            capture(0, 10, TokenType::String),
+            capture(3, 7, TokenType::Plain),
+            capture(5, 6, TokenType::Variable),
+        ];
+        assert_eq!(
+            flatten_captures(&captures),
+            vec![
+                (0, 3, TokenType::String),
+                (3, 5, TokenType::Plain),
+                (5, 6, TokenType::Variable),
+                (6, 7, TokenType::Plain),
+                (7, 10, TokenType::String),
+            ]
+        );
+    }
+
+    #[test]
+    fn push_spans_splits_multiline_ranges() {
+        let source = b"ab\ncdef\ng";
+        let starts = line_starts(source);
+        let mut tokens = Vec::new();
+        push_spans(source, &starts, 1, 9, TokenType::Comment, &mut tokens);
+
+        let ranges: Vec<(u32, u32, u32)> = tokens
+            .iter()
+            .map(|t| (t.line, t.start_col, t.end_col))
+            .collect();
+        assert_eq!(ranges, vec![(1, 1, 2), (2, 0, 4), (3, 0, 1)]);
+    }
+
+    #[test]
+    fn all_language_queries_compile() {
+        for language in [
+            "typescript",
+            "javascript",
+            "json",
+            "yaml",
+            "swift",
+            "rust",
+            "dart",
+            "html",
+            "css",
+            "python",
+            #[cfg(feature = "go")]
+            "go",
+            #[cfg(feature = "java")]
+            "java",
+            #[cfg(feature = "kotlin")]
+            "kotlin",
+            #[cfg(feature = "c")]
+            "c",
+            #[cfg(feature = "cpp")]
+            "cpp",
+            #[cfg(feature = "ruby")]
+            "ruby",
+            #[cfg(feature = "bash")]
+            "bash",
+            #[cfg(feature = "toml")]
+            "toml",
+            #[cfg(feature = "markdown")]
+            "markdown",
+            #[cfg(feature = "markdown")]
+            "markdown_inline",
+            #[cfg(feature = "sql")]
+            "sql",
+            #[cfg(feature = "make")]
+            "make",
+            #[cfg(feature = "dockerfile")]
+            "dockerfile",
+        ] {
+            if let Err(e) = config_for(language) {
+                panic!("{e}");
+            }
+        }
+    }
+}
@@ -1,432 +1,228 @@
-use core_types::{TokenSpan, TokenType};
-use tree_sitter::{Node, Parser};
-
-/// 拡張子から言語名を判定する
-pub fn detect_language(path: &str) -> Option<&'static str> {
-    let ext = path.rsplit('.').next()?.to_ascii_lowercase();
-    match ext.as_str() {
-        "ts" | "tsx" => Some("typescript"),
-        "js" | "jsx" | "mjs" | "cjs" => Some("javascript"),
-        "json" => Some("json"),
-        "yaml" | "yml" => Some("yaml"),
-        "swift" => Some("swift"),
-        "rs" => Some("rust"),
-        "dart" => Some("dart"),
-        "html" | "htm" => Some("html"),
-        "css" => Some("css"),
-        "py" => Some("python"),
-        _ => None,
-    }
-}
+use core_types::{CoreError, TokenSpan};
+use tree_sitter::{Language, Parser};
 
-/// テキストをトークン化して TokenSpan のリストを返す
-pub fn tokenize(text: &str, language: &str) -> Result<Vec<TokenSpan>, String> {
-    let mut parser = Parser::new();
+mod detect;
+mod document;
+mod highlights;
 
-    match language {
-        "typescript" => parser
-            .set_language(&tree_sitter_typescript::LANGUAGE_TSX.into())
-            .map_err(|e| format!("TypeScript パーサー設定エラー: {e}"))?,
-        "javascript" => parser
-            .set_language(&tree_sitter_javascript::LANGUAGE.into())
-            .map_err(|e| format!("JavaScript パーサー設定エラー: {e}"))?,
-        "json" => parser
-            .set_language(&tree_sitter_json::LANGUAGE.into())
-            .map_err(|e| format!("JSON パーサー設定エラー: {e}"))?,
-        "yaml" => parser
-            .set_language(&tree_sitter_yaml::LANGUAGE.into())
-            .map_err(|e| format!("YAML パーサー設定エラー: {e}"))?,
-        "swift" => parser
-            .set_language(&tree_sitter_swift::LANGUAGE.into())
-            .map_err(|e| format!("Swift パーサー設定エラー: {e}"))?,
-        "rust" => parser
-            .set_language(&tree_sitter_rust::LANGUAGE.into())
-            .map_err(|e| format!("Rust パーサー設定エラー: {e}"))?,
-        "dart" => parser
-            .set_language(&tree_sitter_dart::language())
-            .map_err(|e| format!("Dart パーサー設定エラー: {e}"))?,
-        "html" => parser
-            .set_language(&tree_sitter_html::LANGUAGE.into())
-            .map_err(|e| format!("HTML パーサー設定エラー: {e}"))?,
-        "css" => parser
-            .set_language(&tree_sitter_css::LANGUAGE.into())
-            .map_err(|e| format!("CSS パーサー設定エラー: {e}"))?,
-        "python" => parser
-            .set_language(&tree_sitter_python::LANGUAGE.into())
-            .map_err(|e| format!("Python パーサー設定エラー: {e}"))?,
-        _ => return Err(format!("未対応の言語: {language}")),
-    }
+use highlights::{HighlightConfig, InjectionTrees};
 
-    let tree = parser
-        .parse(text, None)
-        .ok_or_else(|| "パースに失敗しました".to_string())?;
+pub use detect::{detect_language, detect_language_candidates, detect_language_with_content};
+pub use document::Document;
 
-    let root_node = tree.root_node();
-    let mut tokens = Vec::new();
-    let source = text.as_bytes();
-    collect_tokens(root_node, source, &mut tokens);
-
-    Ok(tokens)
-}
-
-/// AST ノードを再帰的に走査し、葉ノードを TokenSpan に変換する
-fn collect_tokens(node: Node, source: &[u8], tokens: &mut Vec<TokenSpan>) {
-    if node.child_count() == 0 {
-        let start = node.start_position();
-        let end = node.end_position();
-
-        if start.row != end.row {
-            let text = node.utf8_text(source).unwrap_or("").to_string();
-            let token_type = classify_node(node);
-            for (i, line) in text.split('\n').enumerate() {
-                if line.is_empty() {
-                    continue;
-                }
-                let line_num = start.row as u32 + i as u32 + 1;
-                let start_col = if i == 0 { start.column as u32 } else { 0 };
-                let end_col = start_col + line.len() as u32;
-                tokens.push(TokenSpan {
-                    line: line_num,
-                    start_col,
-                    end_col,
-                    token_type,
-                });
-            }
-        } else {
-            let token_type = classify_node(node);
-            tokens.push(TokenSpan {
-                line: start.row as u32 + 1,
-                start_col: start.column as u32,
-                end_col: end.column as u32,
-                token_type,
-            });
-        }
-        return;
-    }
-
-    // call_expression の関数名部分を特別扱い
-    if node.kind() == "call_expression" {
-        if let Some(func_node) = node.child_by_field_name("function") {
-            if func_node.child_count() == 0 {
-                let start = func_node.start_position();
-                let end = func_node.end_position();
-                tokens.push(TokenSpan {
-                    line: start.row as u32 + 1,
-                    start_col: start.column as u32,
-                    end_col: end.column as u32,
-                    token_type: TokenType::Function,
-                });
-
-                let mut cursor = node.walk();
-                for child in node.children(&mut cursor) {
-                    if child.id() != func_node.id() {
-                        collect_tokens(child, source, tokens);
-                    }
-                }
-                return;
-            }
-        }
-    }
-
-    let mut cursor = node.walk();
-    for child in node.children(&mut cursor) {
-        collect_tokens(child, source, tokens);
-    }
-}
-
-/// ノード種別を TokenType にマッピング
-fn classify_node(node: Node) -> TokenType {
-    let kind = node.kind();
-    let parent_kind = node.parent().map(|p| p.kind()).unwrap_or("");
-
-    if is_comment_kind(kind) {
-        return TokenType::Comment;
-    }
-    if is_string_kind(kind) {
-        return TokenType::String;
-    }
-    if is_number_kind(kind) {
-        return TokenType::Number;
-    }
-    if is_keyword_kind(kind) {
-        return TokenType::Keyword;
-    }
-    if is_operator_kind(kind) {
-        return TokenType::Operator;
-    }
-    if is_punctuation_kind(kind) {
-        return TokenType::Punctuation;
-    }
-    if is_type_kind(kind) {
-        return TokenType::Type;
-    }
-    if is_function_kind(kind, parent_kind) {
-        return TokenType::Function;
-    }
-    if is_variable_kind(kind, parent_kind) {
-        return TokenType::Variable;
-    }
-
-    TokenType::Plain
-}
-
-fn is_comment_kind(kind: &str) -> bool {
-    matches!(
-        kind,
-        "comment" | "line_comment" | "block_comment" | "html_comment"
-    )
-}
-
-fn is_string_kind(kind: &str) -> bool {
-    matches!(
-        kind,
-        "string"
-            | "string_fragment"
-            | "template_string"
-            | "template_literal_type"
-            | "interpreted_string_literal"
-            | "raw_string_literal"
-            | "char_literal"
-            | "escape_sequence"
-    )
-}
-
-fn is_number_kind(kind: &str) -> bool {
-    matches!(
-        kind,
-        "number"
-            | "integer"
-            | "float"
-            | "integer_literal"
-            | "float_literal"
-            | "hex_literal"
-            | "binary_literal"
-            | "octal_literal"
-    )
-}
-
-fn is_keyword_kind(kind: &str) -> bool {
-    matches!(
-        kind,
-        "if" | "else"
-            | "for"
-            | "while"
-            | "do"
-            | "switch"
-            | "case"
-            | "default"
-            | "break"
-            | "continue"
-            | "return"
-            | "throw"
-            | "try"
-            | "catch"
-            | "finally"
-            | "new"
-            | "delete"
-            | "typeof"
-            | "instanceof"
-            | "in"
-            | "of"
-            | "void"
-            | "yield"
-            | "await"
-            | "async"
-            | "class"
-            | "extends"
-            | "super"
-            | "import"
-            | "export"
-            | "from"
-            | "as"
-            | "const"
-            | "let"
-            | "var"
-            | "function"
-            | "static"
-            | "get"
-            | "set"
-            | "this"
-            | "with"
-            | "debugger"
-            | "interface"
-            | "type"
-            | "enum"
-            | "implements"
-            | "public"
-            | "private"
-            | "protected"
-            | "readonly"
-            | "abstract"
-            | "declare"
-            | "namespace"
-            | "module"
-            | "keyof"
-            | "infer"
-            | "satisfies"
-            | "fn"
-            | "impl"
-            | "trait"
-            | "struct"
-            | "match"
-            | "mut"
-            | "pub"
-            | "where"
-            | "use"
-            | "mod"
-            | "crate"
-            | "self"
-            | "Self"
-            | "let_statement"
-            | "func"
-            | "protocol"
-            | "guard"
-            | "defer"
-            | "repeat"
-            | "inout"
-            | "operator"
-            | "subscript"
-            | "init"
-            | "deinit"
-            | "associatedtype"
-            | "some"
-            | "any"
-            | "extension"
-            | "enum_declaration"
-            | "class_declaration"
-            | "func_literal"
-            | "def"
-            | "lambda"
-            | "elif"
-            | "except"
-            | "pass"
-            | "raise"
-            | "global"
-            | "nonlocal"
-            | "del"
-            | "assert"
-            | "True"
-            | "False"
-            | "None"
-            | "null"
-            | "undefined"
-            | "true"
-            | "false"
-    )
-}
+/// テキストをトークン化して TokenSpan のリストを返す
+/// 各言語の highlights.scm（と上書き用クエリ）で capture されたノードだけがトークンになる
+pub fn tokenize(text: &str, language: &str) -> Result<Vec<TokenSpan>, CoreError> {
+    let config = highlights::config_for(language)?;
+    let mut parser = new_parser(&config, language)?;
 
-fn is_operator_kind(kind: &str) -> bool {
-    matches!(
-        kind,
-        "+" | "-"
-            | "*"
-            | "/"
-            | "%"
-            | "="
-            | "=="
-            | "==="
-            | "!="
-            | "!=="
-            | "<"
-            | ">"
-            | "<="
-            | ">="
-            | "&&"
-            | "||"
-            | "!"
-            | "&"
-            | "|"
-            | "^"
-            | "~"
-            | "<<"
-            | ">>"
-            | ">>>"
-            | "+="
-            | "-="
-            | "*="
-            | "/="
-            | "%="
-            | "**"
-            | "??"
-            | "?."
-            | "=>"
-            | "..."
-            | "++"
-            | "--"
-            | "?"
-            | ":"
-            | "->"
-            | "::"
-            | "@"
-            | "#"
-    )
+    let tree = parser
+        .parse(text, None)
+        .ok_or_else(|| parse_failed(language))?;
+
+    Ok(highlights::highlight_tree(
+        &tree,
+        text.as_bytes(),
+        &config,
+        &mut InjectionTrees::default(),
+        0,
+        usize::MAX,
+    ))
 }
 
-fn is_punctuation_kind(kind: &str) -> bool {
-    matches!(
-        kind,
-        "(" | ")" | "[" | "]" | "{" | "}" | ";" | "," | "." | "<" | ">" | "/" | "\\"
-    )
+/// ハイライト設定の言語でパーサーを生成する
+fn new_parser(config: &HighlightConfig, language: &str) -> Result<Parser, CoreError> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&config.language)
+        .map_err(|e| CoreError::internal(format!("{language} パーサー設定エラー: {e}")))?;
+    Ok(parser)
 }
 
-fn is_type_kind(kind: &str) -> bool {
-    matches!(
-        kind,
-        "type_identifier"
-            | "predefined_type"
-            | "type_annotation"
-            | "type_alias_declaration"
-            | "primitive_type"
-            | "generic_type"
-            | "enum_variant"
-            | "tag_name"
-    )
+/// tree-sitter がパース結果を返さなかった場合のエラー
+fn parse_failed(language: &str) -> CoreError {
+    CoreError::internal(format!("{language} のパースに失敗しました"))
 }
 
-fn is_function_kind(kind: &str, parent_kind: &str) -> bool {
-    if matches!(
-        kind,
-        "function_item"
-            | "function_declaration"
-            | "function_definition"
-            | "method_definition"
-            | "method_declaration"
-            | "function_name"
-            | "constructor"
-    ) {
-        return true;
-    }
-
-    matches!(
-        (kind, parent_kind),
-        ("identifier", "function_declaration")
-            | ("identifier", "method_definition")
-            | ("identifier", "function_item")
-            | ("identifier", "call_expression")
-            | ("property_identifier", "function_declaration")
-            | ("property_identifier", "method_definition")
-    )
+/// 言語ごとの tree-sitter 文法とクエリソース
+/// クエリは先頭ほど優先されるため、queries/ 配下の上書き用クエリを先に並べる
+struct LanguageSpec {
+    language: Language,
+    highlights: Vec<&'static str>,
+    injections: Vec<&'static str>,
 }
 
-fn is_variable_kind(kind: &str, parent_kind: &str) -> bool {
-    matches!(
-        kind,
-        "identifier"
-            | "property_identifier"
-            | "field_identifier"
-            | "attribute_name"
-            | "property_name"
-            | "variable_name"
-            | "module_identifier"
-    ) || matches!(
-        parent_kind,
-        "pair" | "object_pair" | "assignment_expression" | "lexical_declaration"
-    )
+/// 言語名から tree-sitter の Language とクエリ群を返す
+fn language_spec(language: &str) -> Option<LanguageSpec> {
+    let spec = match language {
+        "typescript" => LanguageSpec {
+            language: tree_sitter_typescript::LANGUAGE_TSX.into(),
+            highlights: vec![
+                tree_sitter_typescript::HIGHLIGHTS_QUERY,
+                tree_sitter_javascript::JSX_HIGHLIGHT_QUERY,
+                tree_sitter_javascript::HIGHLIGHT_QUERY,
+            ],
+            injections: vec![
+                include_str!("../queries/javascript/injections.scm"),
+                tree_sitter_javascript::INJECTIONS_QUERY,
+            ],
+        },
+        "javascript" => LanguageSpec {
+            language: tree_sitter_javascript::LANGUAGE.into(),
+            highlights: vec![
+                tree_sitter_javascript::JSX_HIGHLIGHT_QUERY,
+                tree_sitter_javascript::HIGHLIGHT_QUERY,
+            ],
+            injections: vec![
+                include_str!("../queries/javascript/injections.scm"),
+                tree_sitter_javascript::INJECTIONS_QUERY,
+            ],
+        },
+        "json" => LanguageSpec {
+            language: tree_sitter_json::LANGUAGE.into(),
+            highlights: vec![tree_sitter_json::HIGHLIGHTS_QUERY],
+            injections: vec![],
+        },
+        "yaml" => LanguageSpec {
+            language: tree_sitter_yaml::LANGUAGE.into(),
+            highlights: vec![tree_sitter_yaml::HIGHLIGHTS_QUERY],
+            injections: vec![],
+        },
+        "swift" => LanguageSpec {
+            language: tree_sitter_swift::LANGUAGE.into(),
+            highlights: vec![
+                include_str!("../queries/swift/highlights.scm"),
+                tree_sitter_swift::HIGHLIGHTS_QUERY,
+            ],
+            injections: vec![tree_sitter_swift::INJECTIONS_QUERY],
+        },
+        "rust" => LanguageSpec {
+            language: tree_sitter_rust::LANGUAGE.into(),
+            highlights: vec![tree_sitter_rust::HIGHLIGHTS_QUERY],
+            injections: vec![tree_sitter_rust::INJECTIONS_QUERY],
+        },
+        // tree-sitter-dart は highlights.scm を公開していないため同梱版を使う
+        "dart" => LanguageSpec {
+            language: tree_sitter_dart::language(),
+            highlights: vec![include_str!("../queries/dart/highlights.scm")],
+            injections: vec![],
+        },
+        "html" => LanguageSpec {
+            language: tree_sitter_html::LANGUAGE.into(),
+            highlights: vec![tree_sitter_html::HIGHLIGHTS_QUERY],
+            injections: vec![
+                include_str!("../queries/html/injections.scm"),
+                tree_sitter_html::INJECTIONS_QUERY,
+            ],
+        },
+        "css" => LanguageSpec {
+            language: tree_sitter_css::LANGUAGE.into(),
+            highlights: vec![tree_sitter_css::HIGHLIGHTS_QUERY],
+            injections: vec![],
+        },
+        "python" => LanguageSpec {
+            language: tree_sitter_python::LANGUAGE.into(),
+            highlights: vec![
+                include_str!("../queries/python/highlights.scm"),
+                tree_sitter_python::HIGHLIGHTS_QUERY,
+            ],
+            injections: vec![],
+        },
+        #[cfg(feature = "go")]
+        "go" => LanguageSpec {
+            language: tree_sitter_go::LANGUAGE.into(),
+            highlights: vec![tree_sitter_go::HIGHLIGHTS_QUERY],
+            injections: vec![],
+        },
+        #[cfg(feature = "java")]
+        "java" => LanguageSpec {
+            language: tree_sitter_java::LANGUAGE.into(),
+            highlights: vec![tree_sitter_java::HIGHLIGHTS_QUERY],
+            injections: vec![],
+        },
+        // tree-sitter-kotlin-ng はクエリを同梱していないため Blink 側で定義する
+        #[cfg(feature = "kotlin")]
+        "kotlin" => LanguageSpec {
+            language: tree_sitter_kotlin_ng::LANGUAGE.into(),
+            highlights: vec![include_str!("../queries/kotlin/highlights.scm")],
+            injections: vec![],
+        },
+        #[cfg(feature = "c")]
+        "c" => LanguageSpec {
+            language: tree_sitter_c::LANGUAGE.into(),
+            highlights: vec![tree_sitter_c::HIGHLIGHT_QUERY],
+            injections: vec![],
+        },
+        // C++ の highlights.scm は C のクエリを継承する前提で書かれている
+        #[cfg(feature = "cpp")]
+        "cpp" => LanguageSpec {
+            language: tree_sitter_cpp::LANGUAGE.into(),
+            highlights: vec![
+                tree_sitter_cpp::HIGHLIGHT_QUERY,
+                #[cfg(feature = "c")]
+                tree_sitter_c::HIGHLIGHT_QUERY,
+            ],
+            injections: vec![],
+        },
+        #[cfg(feature = "ruby")]
+        "ruby" => LanguageSpec {
+            language: tree_sitter_ruby::LANGUAGE.into(),
+            highlights: vec![tree_sitter_ruby::HIGHLIGHTS_QUERY],
+            injections: vec![],
+        },
+        #[cfg(feature = "bash")]
+        "bash" => LanguageSpec {
+            language: tree_sitter_bash::LANGUAGE.into(),
+            highlights: vec![tree_sitter_bash::HIGHLIGHT_QUERY],
+            injections: vec![],
+        },
+        #[cfg(feature = "toml")]
+        "toml" => LanguageSpec {
+            language: tree_sitter_toml_ng::LANGUAGE.into(),
+            highlights: vec![tree_sitter_toml_ng::HIGHLIGHTS_QUERY],
+            injections: vec![],
+        },
+        // Markdown はブロック構造とインライン要素で文法が分かれており、
+        // インライン部分は injection で markdown_inline として処理する
+        #[cfg(feature = "markdown")]
+        "markdown" => LanguageSpec {
+            language: tree_sitter_md::LANGUAGE.into(),
+            highlights: vec![tree_sitter_md::HIGHLIGHT_QUERY_BLOCK],
+            injections: vec![tree_sitter_md::INJECTION_QUERY_BLOCK],
+        },
+        #[cfg(feature = "markdown")]
+        "markdown_inline" => LanguageSpec {
+            language: tree_sitter_md::INLINE_LANGUAGE.into(),
+            highlights: vec![tree_sitter_md::HIGHLIGHT_QUERY_INLINE],
+            injections: vec![tree_sitter_md::INJECTION_QUERY_INLINE],
+        },
+        #[cfg(feature = "sql")]
+        "sql" => LanguageSpec {
+            language: tree_sitter_sequel::LANGUAGE.into(),
+            highlights: vec![tree_sitter_sequel::HIGHLIGHTS_QUERY],
+            injections: vec![],
+        },
+        #[cfg(feature = "make")]
+        "make" => LanguageSpec {
+            language: tree_sitter_make::LANGUAGE.into(),
+            highlights: vec![tree_sitter_make::HIGHLIGHTS_QUERY],
+            injections: vec![],
+        },
+        #[cfg(feature = "dockerfile")]
+        "dockerfile" => LanguageSpec {
+            language: tree_sitter_containerfile::LANGUAGE.into(),
+            highlights: vec![tree_sitter_containerfile::HIGHLIGHTS_QUERY],
+            injections: vec![tree_sitter_containerfile::INJECTIONS_QUERY],
+        },
+        _ => return None,
+    };
+    Some(spec)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use core_types::TokenType;
 
     #[test]
     fn detect_language_returns_correct_language() {
@@ -446,11 +242,41 @@ mod tests {
         assert_eq!(detect_language("no_extension"), None);
     }
 
+    #[test]
+    fn detect_language_additional_languages() {
+        #[cfg(feature = "go")]
+        assert_eq!(detect_language("main.go"), Some("go"));
+        #[cfg(feature = "java")]
+        assert_eq!(detect_language("Main.java"), Some("java"));
+        #[cfg(feature = "kotlin")]
+        assert_eq!(detect_language("build.gradle.kts"), Some("kotlin"));
+        #[cfg(feature = "c")]
+        assert_eq!(detect_language("util.h"), Some("c"));
+        #[cfg(feature = "cpp")]
+        assert_eq!(detect_language("engine.hpp"), Some("cpp"));
+        #[cfg(feature = "ruby")]
+        assert_eq!(detect_language("app.rb"), Some("ruby"));
+        #[cfg(feature = "bash")]
+        assert_eq!(detect_language("deploy.sh"), Some("bash"));
+        #[cfg(feature = "toml")]
+        assert_eq!(detect_language("Cargo.toml"), Some("toml"));
+        #[cfg(feature = "markdown")]
+        assert_eq!(detect_language("README.md"), Some("markdown"));
+        #[cfg(feature = "sql")]
+        assert_eq!(detect_language("schema.sql"), Some("sql"));
+        #[cfg(feature = "make")]
+        assert_eq!(detect_language("rules.mk"), Some("make"));
+    }
+
     #[test]
     fn tokenize_unsupported_language_returns_error() {
-        let result = tokenize("hello", "kotlin");
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("未対応の言語"));
+        let result = tokenize("hello", "cobol");
+        assert_eq!(
+            result.unwrap_err(),
+            CoreError::UnsupportedLanguage {
+                language: "cobol".into()
+            }
+        );
     }
 
     #[test]
@@ -476,6 +302,112 @@ mod tests {
         assert!(tokens.iter().any(|t| t.token_type == TokenType::String));
     }
 
+    fn token_at(tokens: &[TokenSpan], line: u32, start_col: u32) -> Option<TokenType> {
+        tokens
+            .iter()
+            .find(|t| t.line == line && t.start_col == start_col)
+            .map(|t| t.token_type)
+    }
+
+    #[test]
+    fn tokenize_python_soft_keyword_identifier_is_not_keyword() {
+        let code = "type = 1
+obj.run(type)";
+        let tokens = tokenize(code, "python").unwrap();
+        assert_ne!(token_at(&tokens, 1, 0), Some(TokenType::Keyword));
+        assert_eq!(token_at(&tokens, 2, 4), Some(TokenType::Function));
+    }
+
+    #[test]
+    fn tokenize_python_builtin_constants() {
+        let tokens = tokenize("x = None", "python").unwrap();
+        assert_eq!(token_at(&tokens, 1, 4), Some(TokenType::Keyword));
+    }
+
+    #[test]
+    fn tokenize_swift_method_call() {
+        let code = "let v = items.count()
+print(v)";
+        let tokens = tokenize(code, "swift").unwrap();
+        assert_eq!(token_at(&tokens, 1, 0), Some(TokenType::Keyword));
+        assert_eq!(token_at(&tokens, 1, 14), Some(TokenType::Function));
+        assert_eq!(token_at(&tokens, 2, 0), Some(TokenType::Function));
+    }
+
+    #[test]
+    fn tokenize_rust_uses_highlight_queries() {
+        let code = "fn main() { let s: String = greet(); }";
+        let tokens = tokenize(code, "rust").unwrap();
+        assert_eq!(token_at(&tokens, 1, 0), Some(TokenType::Keyword));
+        assert_eq!(token_at(&tokens, 1, 3), Some(TokenType::Function));
+        assert_eq!(token_at(&tokens, 1, 19), Some(TokenType::Type));
+        assert_eq!(token_at(&tokens, 1, 28), Some(TokenType::Function));
+    }
+
+    #[test]
+    fn tokenize_template_substitution_is_not_string() {
+        let code = "const s = `a${b}c`;";
+        let tokens = tokenize(code, "javascript").unwrap();
+        assert_eq!(token_at(&tokens, 1, 10), Some(TokenType::String));
+        assert_eq!(token_at(&tokens, 1, 16), Some(TokenType::String));
+    }
+
+    #[test]
+    fn tokenize_html_injects_script_and_style() {
+        let code = "<script>\nconst x = 1;\n</script>\n<style>\n.a { color: red; }\n</style>";
+        let tokens = tokenize(code, "html").unwrap();
+
+        // <script> 内は JavaScript としてハイライトされる
+        assert_eq!(token_at(&tokens, 2, 0), Some(TokenType::Keyword));
+        assert_eq!(token_at(&tokens, 2, 10), Some(TokenType::Number));
+        // <style> 内は CSS としてハイライトされる
+        assert!(tokens
+            .iter()
+            .any(|t| t.line == 5 && t.token_type == TokenType::Variable));
+        assert!(tokens.iter().filter(|t| t.line == 5).count() > 3);
+    }
+
+    #[test]
+    fn tokenize_html_script_lang_typescript() {
+        let code = "<script lang=\"ts\">\nlet n: number = 1;\n</script>";
+        let tokens = tokenize(code, "html").unwrap();
+        assert_eq!(token_at(&tokens, 2, 7), Some(TokenType::Type));
+    }
+
+    #[test]
+    fn tokenize_css_in_js_template_literal() {
+        let code = "const Button = styled.button`\n  color: red;\n`;\nconst c = css`margin: 0;`;";
+        let tokens = tokenize(code, "javascript").unwrap();
+
+        assert_eq!(token_at(&tokens, 2, 2), Some(TokenType::Variable));
+        assert_ne!(token_at(&tokens, 2, 2), Some(TokenType::String));
+        assert!(tokens
+            .iter()
+            .any(|t| t.line == 4 && t.start_col == 14 && t.token_type == TokenType::Variable));
+    }
+
+    #[test]
+    fn tokenize_unknown_injection_language_keeps_host_tokens() {
+        let code = "const q = zzz`SELECT 1`;";
+        let tokens = tokenize(code, "javascript").unwrap();
+        // 未知の言語名ならテンプレート全体がホストの文字列トークンのまま
+        let template: Vec<_> = tokens
+            .iter()
+            .filter(|t| t.line == 1 && (13..23).contains(&t.start_col))
+            .map(|t| (t.start_col, t.end_col, t.token_type))
+            .collect();
+        assert_eq!(template, vec![(13, 23, TokenType::String)]);
+    }
+
+    #[cfg(feature = "sql")]
+    #[test]
+    fn tokenize_sql_in_js_template_literal() {
+        let code = "const q = sql`SELECT id FROM users`;";
+        let tokens = tokenize(code, "javascript").unwrap();
+        assert_eq!(token_at(&tokens, 1, 14), Some(TokenType::Keyword));
+        assert_eq!(token_at(&tokens, 1, 24), Some(TokenType::Keyword));
+    }
+
     #[test]
     fn tokenize_supported_languages_smoke() {
         let cases = vec![
@@ -490,6 +422,33 @@ mod tests {
             ("html", "<div class=\"app\">hello</div>"),
             ("css", ".app { color: #fff; margin: 4px; }"),
             ("python", "def greet(name):\n    return f\"hi {name}\""),
+            #[cfg(feature = "go")]
+            ("go", "package main\nfunc main() { x := 1 }"),
+            #[cfg(feature = "java")]
+            (
+                "java",
+                "class App { public static void main(String[] a) { int x = 1; } }",
+            ),
+            #[cfg(feature = "kotlin")]
+            ("kotlin", "fun main() { val x = listOf(1, 2) }"),
+            #[cfg(feature = "c")]
+            ("c", "#include <stdio.h>\nint main(void) { return 0; }"),
+            #[cfg(feature = "cpp")]
+            ("cpp", "namespace app { class A { public: int x = 1; }; }"),
+            #[cfg(feature = "ruby")]
+            ("ruby", "def greet(name)\n  puts \"hi #{name}\"\nend"),
+            #[cfg(feature = "bash")]
+            ("bash", "#!/bin/bash\nif [ -f \"$1\" ]; then echo ok; fi"),
+            #[cfg(feature = "toml")]
+            ("toml", "[package]\nname = \"blink\"\nversion = 1"),
+            #[cfg(feature = "markdown")]
+            ("markdown", "# Blink\n\nSome *text* and `code`."),
+            #[cfg(feature = "sql")]
+            ("sql", "SELECT id, name FROM users WHERE id = 1;"),
+            #[cfg(feature = "make")]
+            ("make", "CC = gcc\nall: main.o\n\t$(CC) -o app main.o\n"),
+            #[cfg(feature = "dockerfile")]
+            ("dockerfile", "FROM rust:1.80\nRUN cargo build --release\n"),
         ];
 
         for (lang, src) in cases {
@@ -500,6 +459,26 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "kotlin")]
+    #[test]
+    fn tokenize_kotlin_declarations() {
+        let code = "fun greet(name: String) = println(name)";
+        let tokens = tokenize(code, "kotlin").unwrap();
+        assert_eq!(token_at(&tokens, 1, 0), Some(TokenType::Keyword));
+        assert_eq!(token_at(&tokens, 1, 4), Some(TokenType::Function));
+        assert_eq!(token_at(&tokens, 1, 16), Some(TokenType::Type));
+        assert_eq!(token_at(&tokens, 1, 26), Some(TokenType::Function));
+    }
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn tokenize_markdown_fenced_code_block_is_injected() {
+        let code = "# Title\n\n```rust\nfn main() {}\n```\n";
+        let tokens = tokenize(code, "markdown").unwrap();
+        assert_eq!(token_at(&tokens, 4, 0), Some(TokenType::Keyword));
+        assert_eq!(token_at(&tokens, 4, 3), Some(TokenType::Function));
+    }
+
     #[test]
     fn tokenize_empty_input() {
         let result = tokenize("", "javascript");
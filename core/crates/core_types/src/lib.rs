@@ -41,6 +41,51 @@ pub enum TokenType {
     Plain,
 }
 
+/// 言語判定の根拠
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum LanguageSource {
+    /// .gitattributes の linguist-language 指定
+    GitAttributes,
+    /// Vim / Emacs のモードライン
+    Modeline,
+    /// shebang 行のインタプリタ
+    Shebang,
+    /// Dockerfile / Makefile などの既知のファイル名
+    Filename,
+    /// 拡張子
+    Extension,
+}
+
+/// 言語判定結果（confidence は 0.0〜1.0、大きいほど確度が高い）
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct LanguageDetection {
+    pub language: String,
+    pub source: LanguageSource,
+    pub confidence: f32,
+}
+
+/// ドキュメントへのテキスト編集
+/// byte オフセットと行・列（0始まり、列は byte 単位）で置換範囲を表す
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct TextEdit {
+    pub start_byte: u32,
+    pub old_end_byte: u32,
+    pub start_row: u32,
+    pub start_column: u32,
+    pub old_end_row: u32,
+    pub old_end_column: u32,
+    pub new_text: String,
+}
+
+/// 編集後に再ハイライトが必要になった行範囲とそのトークン
+/// start_line..=end_line（1始まり）のトークンを丸ごと置き換える
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct HighlightDelta {
+    pub start_line: u32,
+    pub end_line: u32,
+    pub tokens: Vec<TokenSpan>,
+}
+
 /// Git Blame 行情報
 #[derive(Debug, Clone, PartialEq, uniffi::Record)]
 pub struct BlameLine {
@@ -49,14 +94,469 @@ pub struct BlameLine {
     pub author_time: i64,
     pub summary: String,
     pub commit: String,
+    /// commit 時点でのファイルパス（リポジトリ相対。リネーム前の名前になりうる）
+    pub original_path: String,
+    /// commit 時点での行番号
+    pub original_line: u32,
+}
+
+/// blame での行の移動・コピー検出（git blame の -M / -C の段階）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum BlameMoveDetection {
+    Off,
+    /// 同じファイル内での移動（-M）
+    WithinFile,
+    /// 同じコミットで変更された他ファイルからの移動・コピー（-C）
+    ModifiedFiles,
+    /// ファイルを作ったコミットの全ファイルから（-C -C）
+    FilesInCreatingCommit,
+    /// すべてのコミットの全ファイルから（-C -C -C。遅い）
+    AnyCommit,
+}
+
+/// blame の実行オプション
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct BlameOptions {
+    /// 空白だけの変更を無視する（-w）
+    #[uniffi(default = false)]
+    pub ignore_whitespace: bool,
+    /// 行の移動・コピー検出（None は Off と同じく検出しない）
+    #[uniffi(default = None)]
+    pub move_detection: Option<BlameMoveDetection>,
+    /// リポジトリ直下の .git-blame-ignore-revs（と blame.ignoreRevsFile）があれば使う
+    #[uniffi(default = true)]
+    pub use_ignore_revs: bool,
+    /// .mailmap で作者名を正規化する
+    #[uniffi(default = true)]
+    pub use_mailmap: bool,
+}
+
+impl Default for BlameOptions {
+    fn default() -> Self {
+        Self {
+            ignore_whitespace: false,
+            move_detection: None,
+            use_ignore_revs: true,
+            use_mailmap: true,
+        }
+    }
+}
+
+/// 「親コミットで blame」した結果（line は親コミットでの対応行）
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct BlameNavigation {
+    /// blame したリビジョン（親コミットの完全なハッシュ）
+    pub revision: String,
+    /// そのリビジョンでのファイルパス（リポジトリ相対）
+    pub path: String,
+    pub line: u32,
+    pub lines: Vec<BlameLine>,
 }
 
 /// Git差分（コミット差分または作業ツリー差分）
+/// files は diff_text をパースしたもの（作業ツリー差分では unstaged → staged の順に並ぶ）
 #[derive(Debug, Clone, PartialEq, uniffi::Record)]
 pub struct GitFileDiff {
     pub commit: String,
     pub path: String,
     pub diff_text: String,
+    pub files: Vec<DiffFile>,
+}
+
+/// 差分行の種別
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum DiffLineOrigin {
+    Context,
+    Added,
+    Removed,
+}
+
+/// 差分の1行
+/// 行番号は 1-based。追加行は old_line、削除行は new_line が None
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct DiffLine {
+    pub origin: DiffLineOrigin,
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
+    /// 先頭の +/-/空白 と改行を除いた内容
+    pub content: String,
+    /// 直後に "\ No newline at end of file" が付いていた行
+    pub no_newline_at_eof: bool,
+    /// 対になる削除行・追加行と比べて変わった部分（content 内の byte 列範囲）
+    /// 対が無い行や大半が書き換わった行では空（行全体を強調する）
+    pub word_changes: Vec<MatchRange>,
+}
+
+/// 差分のハンク
+/// 結合差分（diff --cc）では old_* は第1親に対する範囲
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct DiffHunk {
+    /// "@@ -1,3 +1,4 @@ fn main" の行全体
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+/// 差分ファイルの変更種別
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum DiffFileStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Copied,
+}
+
+/// 差分に含まれる1ファイル分（パスはリポジトリ相対）
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct DiffFile {
+    /// 追加されたファイルでは None
+    pub old_path: Option<String>,
+    /// 削除されたファイルでは None
+    pub new_path: Option<String>,
+    pub status: DiffFileStatus,
+    pub old_mode: Option<String>,
+    pub new_mode: Option<String>,
+    /// リネーム・コピーの類似度（%）
+    pub similarity: Option<u32>,
+    pub is_binary: bool,
+    /// 結合差分（マージコミットの diff --cc）
+    pub is_combined: bool,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// 左右分割表示の片側のセル
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct SplitDiffCell {
+    /// その側のファイルでの行番号（1-based）
+    pub line: u32,
+    pub origin: DiffLineOrigin,
+    pub content: String,
+    pub word_changes: Vec<MatchRange>,
+    pub no_newline_at_eof: bool,
+}
+
+/// 左右分割表示の1行
+#[derive(Debug, Clone, PartialEq, uniffi::Enum)]
+pub enum SplitDiffRow {
+    /// 左（旧）と右（新）の対。片側だけの変更ではもう片側が None（埋め草）
+    Line {
+        left: Option<SplitDiffCell>,
+        right: Option<SplitDiffCell>,
+    },
+    /// ハンクの区切り（"@@ -1,3 +1,4 @@" の行）
+    HunkHeader { header: String },
+    /// 折りたたまれた変更なしの区間。new_start を expanded に渡すと展開される
+    Collapsed {
+        old_start: u32,
+        new_start: u32,
+        line_count: u32,
+    },
+}
+
+/// 左右分割表示の組み立てオプション
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct SplitDiffOptions {
+    /// ハンク内で変更の前後に残すコンテキスト行数（これより長い変更なし区間は折りたたむ）
+    pub context_lines: u32,
+    /// 新しい側のファイル全体。渡すとハンク間・末尾の区間も展開できる
+    pub new_text: Option<String>,
+    /// 展開する折りたたみ区間（Collapsed の new_start）
+    pub expanded: Vec<u32>,
+}
+
+/// エディタのガター用の行変更種別
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum LineChangeKind {
+    Added,
+    Modified,
+    /// この行の直前にあった行が削除された（末尾での削除は最終行 + 1）
+    DeletedAbove,
+}
+
+/// ガターに表示する行ごとの変更マーカー
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct LineChangeMarker {
+    /// バッファ上の行番号（1-based）
+    pub line: u32,
+    pub kind: LineChangeKind,
+    /// インデックスにステージ済みの変更（HEAD との差）。未ステージの変更がある行は false
+    pub staged: bool,
+}
+
+/// コミットの作成者・コミッター（mailmap 適用前の値）
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct GitSignature {
+    pub name: String,
+    pub email: String,
+    /// UNIX 時刻（秒）
+    pub time: i64,
+    /// UTC からのオフセット（分）
+    pub offset_minutes: i32,
+}
+
+/// コミット1件のメタデータ
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct CommitRecord {
+    /// 40桁のコミットハッシュ
+    pub sha: String,
+    pub parents: Vec<String>,
+    pub author: GitSignature,
+    pub committer: GitSignature,
+    /// メッセージの1段落目（改行は空白に置き換え）
+    pub subject: String,
+    /// subject 以降の本文（前後の空白は除く）
+    pub body: String,
+}
+
+/// ファイル履歴の1件（path はそのコミット時点でのリポジトリ相対パス）
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FileHistoryEntry {
+    pub commit: CommitRecord,
+    pub path: String,
+}
+
+/// コミットに付いている参照の種別
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, uniffi::Enum)]
+pub enum RefKind {
+    /// detached HEAD（ブランチを指している HEAD はそのブランチの is_head で表す）
+    Head,
+    LocalBranch,
+    RemoteBranch,
+    Tag,
+}
+
+/// コミットログに表示する参照（name は refs/heads/ などを除いた短縮名）
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct RefDecoration {
+    pub name: String,
+    pub kind: RefKind,
+    pub is_head: bool,
+}
+
+/// コミットグラフで、ある行から次の行へ引く線
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Record)]
+pub struct GraphEdge {
+    pub from_lane: u32,
+    pub to_lane: u32,
+}
+
+/// コミットログの1行（グラフのレーン割り当て付き）
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct CommitGraphRow {
+    pub commit: CommitRecord,
+    /// コミットの点を置く列（0-based）
+    pub lane: u32,
+    /// この行から次の行への線。from_lane == lane の線は親への線、それ以外は通過する線
+    pub edges: Vec<GraphEdge>,
+    /// この行で使っている列数
+    pub width: u32,
+    pub is_merge: bool,
+    /// 子コミットが複数ある（ブランチの分岐点）
+    pub is_branch_point: bool,
+    pub refs: Vec<RefDecoration>,
+}
+
+/// コミットメッセージ末尾のトレーラー（Signed-off-by: ... など）
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct CommitTrailer {
+    pub key: String,
+    pub value: String,
+}
+
+/// コミットで変更されたファイル1件
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct ChangedFile {
+    /// 変更後のパス（削除されたファイルは削除前のパス）
+    pub path: String,
+    /// リネーム・コピー元のパス
+    pub old_path: Option<String>,
+    pub status: DiffFileStatus,
+    /// リネーム・コピーの類似度（%）
+    pub similarity: Option<u32>,
+    pub insertions: u32,
+    pub deletions: u32,
+    pub is_binary: bool,
+}
+
+/// コミット詳細（メタデータ・トレーラー・変更ファイル一覧）
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct CommitDetails {
+    pub commit: CommitRecord,
+    /// コミットメッセージ全文（末尾の空白は除く）
+    pub message: String,
+    pub trailers: Vec<CommitTrailer>,
+    /// 第一親との差分（ルートコミットは全ファイルが追加）
+    pub files: Vec<ChangedFile>,
+}
+
+/// コミットの作成者を上書きするときの名前とメールアドレス
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct CommitAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+/// コミット作成のオプション
+#[derive(Debug, Clone, Default, PartialEq, Eq, uniffi::Record)]
+pub struct CommitOptions {
+    /// HEAD のコミットを作り直す（作成者は上書きしなければ元のまま）
+    pub amend: bool,
+    pub author: Option<CommitAuthor>,
+    /// コミッターの "Signed-off-by:" トレーラーを付ける
+    pub sign_off: bool,
+}
+
+/// 実行したフックの出力（stdout の後に stderr）
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct HookOutput {
+    pub hook: String,
+    pub output: String,
+}
+
+/// コミット作成の結果
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct CommitResult {
+    pub details: CommitDetails,
+    /// 実行順（フックが無ければ空）
+    pub hook_outputs: Vec<HookOutput>,
+}
+
+/// porcelain v2 の XY の片側（インデックス側・作業ツリー側）の状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum GitFileState {
+    Unmodified,
+    Modified,
+    TypeChanged,
+    Added,
+    Deleted,
+    Renamed,
+    Copied,
+    /// コンフリクト中（U）
+    Unmerged,
+    /// 未追跡（両側とも Untracked）
+    Untracked,
+}
+
+/// コンフリクトの種類（porcelain の XY: DD, AU, UD, UA, DU, AA, UU）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum GitConflictKind {
+    BothDeleted,
+    AddedByUs,
+    DeletedByThem,
+    AddedByThem,
+    DeletedByUs,
+    BothAdded,
+    BothModified,
+}
+
+/// サブモジュールの状態（porcelain v2 の S<c><m><u>）
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct GitSubmoduleState {
+    /// チェックアウト中のコミットがインデックスと異なる
+    pub commit_changed: bool,
+    /// 追跡ファイルに変更がある
+    pub has_tracked_changes: bool,
+    /// 未追跡ファイルがある
+    pub has_untracked_changes: bool,
+}
+
+/// `git status --porcelain=v2` の1エントリ
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct GitStatusItem {
+    /// 絶対パス（リネーム・コピーは変更後のパス）
+    pub path: String,
+    /// リネーム・コピー元の絶対パス
+    pub original_path: Option<String>,
+    pub index: GitFileState,
+    pub worktree: GitFileState,
+    pub conflict: Option<GitConflictKind>,
+    /// サブモジュールでなければ None
+    pub submodule: Option<GitSubmoduleState>,
+}
+
+/// `git status --branch` のブランチ情報
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct GitBranchStatus {
+    /// ブランチ名（detached HEAD は None）
+    pub head: Option<String>,
+    /// HEAD のコミット（最初のコミット前は None）
+    pub oid: Option<String>,
+    /// 上流ブランチの短縮名（origin/main など）
+    pub upstream: Option<String>,
+    /// 上流との差分コミット数（上流が無い・参照が消えている場合は None）
+    pub ahead: Option<u32>,
+    pub behind: Option<u32>,
+}
+
+/// リポジトリの状態（ブランチ情報と変更エントリ）
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct GitRepositoryStatus {
+    pub branch: GitBranchStatus,
+    /// 追跡ファイルの変更をパス順に並べた後に未追跡ファイル
+    pub entries: Vec<GitStatusItem>,
+}
+
+/// ブランチ一覧の1件（ローカル・リモート追跡ブランチ）
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct BranchInfo {
+    /// 表示名（main, origin/main など）
+    pub name: String,
+    /// LocalBranch か RemoteBranch
+    pub kind: RefKind,
+    /// チェックアウト中のブランチ
+    pub is_head: bool,
+    /// 上流ブランチの短縮名（ローカルブランチのみ）
+    pub upstream: Option<String>,
+    /// 上流との差分コミット数（上流が無い・参照が消えている場合は None）
+    pub ahead: Option<u32>,
+    pub behind: Option<u32>,
+    /// ブランチ先端のコミット
+    pub last_commit: CommitRecord,
+}
+
+/// stash 一覧の1件
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct StashEntry {
+    /// stash@{index} の index（0 が最新）
+    pub index: u32,
+    pub sha: String,
+    /// "On <branch>: " を除いたメッセージ（自動生成なら "<短縮ハッシュ> <件名>"）
+    pub message: String,
+    /// stash したときのブランチ（detached HEAD では None）
+    pub branch: Option<String>,
+    /// stash した日時
+    pub committer: GitSignature,
+}
+
+/// stash 作成のオプション
+#[derive(Debug, Clone, Default, PartialEq, Eq, uniffi::Record)]
+pub struct StashCreateOptions {
+    pub message: Option<String>,
+    /// 未追跡ファイルも含める
+    pub include_untracked: bool,
+    /// ステージ済みの変更だけを stash する（include_untracked とは併用できない）
+    pub staged_only: bool,
+}
+
+/// stash の apply / pop の結果
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct StashApplyResult {
+    /// マージでコンフリクトしたファイルの絶対パス（空なら問題なく適用できた）
+    pub conflicts: Vec<String>,
+    /// pop で stash を削除したか（コンフリクト時は残る）
+    pub dropped: bool,
+}
+
+/// 部分的なステージ・ステージ解除・破棄で選ぶ差分行
+/// DiffLine の old_line / new_line をそのまま渡す（追加行は new_line、削除行は old_line だけ）
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct DiffLineRef {
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
 }
 
 /// Git status の1エントリ
@@ -74,6 +574,251 @@ pub struct GitStatus {
     pub untracked: Vec<GitStatusEntry>,
 }
 
+/// core_git が使う Git 実装の選択
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum GitBackendKind {
+    /// libgit2 を使い、未対応の操作や失敗時は git CLI にフォールバックする
+    Auto,
+    /// libgit2 のみ（git バイナリ不要）
+    Native,
+    /// git CLI のみ
+    Cli,
+}
+
+/// ツリー表示用の Git 状態
+/// ディレクトリには配下で最も優先度の高い状態（Conflicted > Modified > Untracked）が付く
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, uniffi::Enum)]
+pub enum GitDecoration {
+    Modified,
+    Added,
+    Deleted,
+    Renamed,
+    Untracked,
+    Conflicted,
+}
+
+/// プロジェクトツリーの1ノード
+/// id はルートからの相対パス（ルート自身は空文字）、parent_id はルート直下なら空文字
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct ProjectTreeNode {
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub path: String,
+    pub name: String,
+    pub kind: NodeKind,
+    /// ルート直下を 0 とする階層の深さ
+    pub depth: u32,
+    pub has_children: bool,
+    pub is_expanded: bool,
+    /// ファイルサイズ（byte、ディレクトリは 0）
+    pub size: u64,
+    /// 最終更新時刻（UNIX 秒）
+    pub modified_at: i64,
+    pub symlink_target: Option<String>,
+    pub git_decoration: Option<GitDecoration>,
+}
+
+/// ディレクトリを再読み込みしたときの子ノードの差分
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct ProjectTreeDiff {
+    pub parent_id: String,
+    pub inserted: Vec<ProjectTreeNode>,
+    /// 削除された子ノードの id（配下のノードも合わせて破棄すること）
+    pub removed_ids: Vec<String>,
+    pub updated: Vec<ProjectTreeNode>,
+    /// 再読み込み後の子ノードの並び順
+    pub child_ids: Vec<String>,
+}
+
+/// ファイルシステム変更イベントの種別
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum FsEventKind {
+    Created,
+    Modified,
+    Removed,
+    /// old_path から path への名前変更
+    Renamed,
+    /// イベント取りこぼしや .gitignore 変更により、path 配下を再読み込みする必要がある
+    Rescan,
+}
+
+/// ファイルシステム変更イベント（path は絶対パス）
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct FsEvent {
+    pub kind: FsEventKind,
+    pub path: String,
+    pub old_path: Option<String>,
+    pub is_dir: bool,
+}
+
+/// プロジェクト全体検索の条件
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct SearchOptions {
+    pub query: String,
+    /// true の場合 query を正規表現として扱う
+    pub is_regex: bool,
+    pub case_sensitive: bool,
+    /// 単語境界に一致する場合のみヒットさせる
+    pub whole_word: bool,
+    /// ルートからの相対パスに対する glob（空なら全ファイル）
+    pub include_globs: Vec<String>,
+    pub exclude_globs: Vec<String>,
+    /// ヒット行数の上限（0 は無制限）
+    pub max_results: u32,
+}
+
+/// 行内の一致範囲（列は行頭からの byte 単位、end は含まない）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Record)]
+pub struct MatchRange {
+    pub start_col: u32,
+    pub end_col: u32,
+}
+
+/// 検索でヒットした1行
+/// 長い行の preview は最初の一致付近だけを切り出し、その開始列を preview_start_col に入れる
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct SearchMatch {
+    pub path: String,
+    pub line: u32,
+    pub ranges: Vec<MatchRange>,
+    pub preview: String,
+    pub preview_start_col: u32,
+}
+
+/// 検索完了時の集計
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct SearchSummary {
+    pub searched_files: u32,
+    pub matched_files: u32,
+    pub match_count: u32,
+    /// max_results に達して打ち切った場合 true
+    pub truncated: bool,
+}
+
+/// Go to File のあいまい検索結果
+/// positions は relative_path 内で一致した文字の位置（0始まり、文字単位）
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct FuzzyFileMatch {
+    pub node: FileNode,
+    pub relative_path: String,
+    pub score: i32,
+    pub positions: Vec<u32>,
+}
+
+/// FFI 境界で返す共通エラー
+/// Swift 側はバリアントで分岐し、ログやサポート用途には code() の安定した番号を使う
+#[derive(Debug, Clone, PartialEq, thiserror::Error, uniffi::Error)]
+pub enum CoreError {
+    #[error("パスが存在しません: {path}")]
+    NotFound { path: String },
+    #[error("パスがディレクトリではありません: {path}")]
+    NotADirectory { path: String },
+    #[error("パスがファイルではありません: {path}")]
+    NotAFile { path: String },
+    #[error("アクセス権限がありません: {path}")]
+    PermissionDenied { path: String },
+    #[error("入出力エラー: {path}: {reason}")]
+    Io { path: String, reason: String },
+    #[error("UTF-8 として読み込めません: {path}: {reason}")]
+    Encoding { path: String, reason: String },
+    #[error("引数が不正です: {reason}")]
+    InvalidArgument { reason: String },
+    #[error("Git リポジトリではありません: {path}")]
+    NotAGitRepo { path: String },
+    #[error("git コマンドが見つかりません: {reason}")]
+    GitNotFound { reason: String },
+    #[error("{command} 失敗: {stderr}")]
+    GitFailed {
+        command: String,
+        stderr: String,
+        exit_code: Option<i32>,
+    },
+    #[error("差分が見つかりませんでした: {path}")]
+    NoChanges { path: String },
+    #[error("ブランチが見つかりません: {name}")]
+    BranchNotFound { name: String },
+    #[error("ブランチは既に存在します: {name}")]
+    BranchExists { name: String },
+    #[error("ブランチがマージされていません: {name}")]
+    BranchNotMerged { name: String },
+    #[error("チェックアウトするとローカルの変更が上書きされます: {branch}")]
+    CheckoutConflict { branch: String, paths: Vec<String> },
+    #[error("{hook} フックが失敗しました: {output}")]
+    HookFailed {
+        hook: String,
+        output: String,
+        exit_code: Option<i32>,
+    },
+    #[error("stash を適用するとローカルの変更が上書きされます: {stash}")]
+    StashConflict { stash: String, paths: Vec<String> },
+    #[error("{backend} 実装では対応していない操作です: {reason}")]
+    UnsupportedByBackend { backend: String, reason: String },
+    #[error("未対応の言語: {language}")]
+    UnsupportedLanguage { language: String },
+    #[error("処理がキャンセルされました")]
+    Cancelled,
+    #[error("内部エラー: {reason}")]
+    Internal { reason: String },
+}
+
+impl CoreError {
+    /// バリアントごとの安定したエラーコード（既存の番号は変更しない）
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::NotFound { .. } => 1001,
+            Self::NotADirectory { .. } => 1002,
+            Self::NotAFile { .. } => 1003,
+            Self::PermissionDenied { .. } => 1004,
+            Self::Io { .. } => 1005,
+            Self::Encoding { .. } => 1006,
+            Self::InvalidArgument { .. } => 1007,
+            Self::NotAGitRepo { .. } => 2001,
+            Self::GitNotFound { .. } => 2002,
+            Self::GitFailed { .. } => 2003,
+            Self::NoChanges { .. } => 2004,
+            Self::BranchNotFound { .. } => 2005,
+            Self::BranchExists { .. } => 2006,
+            Self::BranchNotMerged { .. } => 2007,
+            Self::CheckoutConflict { .. } => 2008,
+            Self::HookFailed { .. } => 2009,
+            Self::StashConflict { .. } => 2010,
+            Self::UnsupportedByBackend { .. } => 2011,
+            Self::UnsupportedLanguage { .. } => 3001,
+            Self::Cancelled => 9001,
+            Self::Internal { .. } => 9999,
+        }
+    }
+
+    /// io::Error を対象パス付きの CoreError に変換する
+    pub fn from_io(path: impl Into<String>, error: &std::io::Error) -> Self {
+        let path = path.into();
+        match error.kind() {
+            std::io::ErrorKind::NotFound => Self::NotFound { path },
+            std::io::ErrorKind::PermissionDenied => Self::PermissionDenied { path },
+            std::io::ErrorKind::InvalidData => Self::Encoding {
+                path,
+                reason: error.to_string(),
+            },
+            _ => Self::Io {
+                path,
+                reason: error.to_string(),
+            },
+        }
+    }
+
+    pub fn invalid_argument(reason: impl Into<String>) -> Self {
+        Self::InvalidArgument {
+            reason: reason.into(),
+        }
+    }
+
+    pub fn internal(reason: impl Into<String>) -> Self {
+        Self::Internal {
+            reason: reason.into(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -120,9 +865,24 @@ mod tests {
             author_time: 1700000000,
             summary: "fix: resolve null pointer".into(),
             commit: "abc1234".into(),
+            original_path: "src/old.rs".into(),
+            original_line: 40,
         };
         assert_eq!(blame.line, 42);
         assert_eq!(blame.author, "Alice");
+        assert_eq!(blame.original_line, 40);
+
+        let options = BlameOptions::default();
+        assert!(options.use_ignore_revs && options.use_mailmap);
+        assert_eq!(options.move_detection, None);
+
+        let navigation = BlameNavigation {
+            revision: "b".repeat(40),
+            path: "src/old.rs".into(),
+            line: 39,
+            lines: vec![blame],
+        };
+        assert_eq!(navigation.lines.len(), 1);
     }
 
     #[test]
@@ -131,6 +891,7 @@ mod tests {
             commit: "abc1234".into(),
             path: "/tmp/file.rs".into(),
             diff_text: "@@ -1 +1 @@\n-old\n+new\n".into(),
+            files: vec![],
         };
         assert_eq!(diff.commit, "abc1234");
         assert!(diff.diff_text.contains("+new"));
@@ -157,4 +918,84 @@ mod tests {
         assert_eq!(status.untracked.len(), 1);
         assert_eq!(status.untracked[0].status, "??");
     }
+
+    #[test]
+    fn core_error_codes_are_stable() {
+        assert_eq!(CoreError::NotFound { path: "/a".into() }.code(), 1001);
+        assert_eq!(CoreError::NotAGitRepo { path: "/a".into() }.code(), 2001);
+        assert_eq!(
+            CoreError::GitFailed {
+                command: "git blame".into(),
+                stderr: "fatal".into(),
+                exit_code: Some(128),
+            }
+            .code(),
+            2003
+        );
+        assert_eq!(
+            CoreError::CheckoutConflict {
+                branch: "main".into(),
+                paths: vec!["a.txt".into()],
+            }
+            .code(),
+            2008
+        );
+        assert_eq!(
+            CoreError::HookFailed {
+                hook: "pre-commit".into(),
+                output: "lint failed".into(),
+                exit_code: Some(1),
+            }
+            .code(),
+            2009
+        );
+        assert_eq!(
+            CoreError::StashConflict {
+                stash: "stash@{0}".into(),
+                paths: vec!["a.txt".into()],
+            }
+            .code(),
+            2010
+        );
+        assert_eq!(
+            CoreError::UnsupportedByBackend {
+                backend: "native".into(),
+                reason: "gpg 署名".into(),
+            }
+            .code(),
+            2011
+        );
+        assert_eq!(CoreError::Cancelled.code(), 9001);
+    }
+
+    #[test]
+    fn core_error_from_io_maps_kind() {
+        let not_found = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert_eq!(
+            CoreError::from_io("/a", &not_found),
+            CoreError::NotFound { path: "/a".into() }
+        );
+
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            CoreError::from_io("/a", &denied),
+            CoreError::PermissionDenied { path: "/a".into() }
+        );
+
+        let invalid = std::io::Error::new(std::io::ErrorKind::InvalidData, "bad utf-8");
+        assert!(matches!(
+            CoreError::from_io("/a", &invalid),
+            CoreError::Encoding { .. }
+        ));
+    }
+
+    #[test]
+    fn git_failed_message_includes_command() {
+        let error = CoreError::GitFailed {
+            command: "git blame".into(),
+            stderr: "fatal: no such path".into(),
+            exit_code: Some(128),
+        };
+        assert_eq!(error.to_string(), "git blame 失敗: fatal: no such path");
+    }
 }